@@ -0,0 +1,58 @@
+//! throughput of the different parse paths over a generated blocklist
+//!
+//! run with `cargo bench`. there is no criterion here, just the best of a
+//! few runs per path, which is plenty to spot a regression in the hot loop
+
+use hosts_digger::{parse_borrowed, HostsFile, HostsReader};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const LINES: usize = 200_000;
+const RUNS: usize = 5;
+
+/// something shaped like the StevenBlack unified list
+fn blocklist() -> String {
+    let mut out =
+        String::from("# Title: generated blocklist\n#\n127.0.0.1 localhost\n::1 localhost\n\n");
+    for i in 0..LINES {
+        if i % 1000 == 0 {
+            out.push_str("\n# section\n");
+        }
+        out.push_str(&format!("0.0.0.0 ads{i}.tracker-{}.example.com\n", i % 97));
+    }
+    out
+}
+
+fn bench<T>(name: &str, input: &str, f: impl Fn(&str) -> T) {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        black_box(f(black_box(input)));
+        best = best.min(start.elapsed());
+    }
+    let mib = input.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{name:<16} {:>8.2} ms {:>8.1} MiB/s {:>10.0} lines/s",
+        best.as_secs_f64() * 1000.0,
+        mib / best.as_secs_f64(),
+        LINES as f64 / best.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let input = blocklist();
+
+    bench("hosts_file", &input, |s| s.parse::<HostsFile>().unwrap());
+    #[cfg(feature = "parallel")]
+    bench("parallel", &input, |s| {
+        HostsFile::parse_parallel(s).unwrap()
+    });
+    bench("reader", &input, |s| {
+        HostsReader::new(s.as_bytes())
+            .filter_map(Result::ok)
+            .count()
+    });
+    bench("borrowed", &input, |s| {
+        parse_borrowed(s).filter_map(Result::ok).count()
+    });
+}
@@ -0,0 +1,29 @@
+//! nothing a blocklist download throws at the parser should make it
+//! panic, and whatever parses has to come back out byte for byte
+//!
+//! `cargo +nightly fuzz run parse_str`
+
+#![no_main]
+
+use hosts_digger::{parse_borrowed, parse_str, HostsDocument, ParseOptions, ParseReport};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // raw bytes go through the same bom sniffing and decoding as a file
+    if let Ok(doc) = HostsDocument::parse_reader(data) {
+        assert_eq!(doc.to_bytes(), data);
+    }
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = parse_str(text);
+    let _ = parse_borrowed(text).count();
+    let _ = hosts_digger::line::entries(text).count();
+    let strict = ParseOptions {
+        strict: true,
+        lowercase_names: true,
+        coalesce_lines: true,
+        ..Default::default()
+    };
+    let _ = ParseReport::parse(text, &strict);
+});
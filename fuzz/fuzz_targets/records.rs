@@ -0,0 +1,44 @@
+//! records built straight from fuzzer input have to survive being
+//! written out and parsed back in
+//!
+//! `cargo +nightly fuzz run records`
+
+#![no_main]
+
+use std::net::IpAddr;
+
+use hosts_digger::{parse_str, Record};
+use libfuzzer_sys::arbitrary::{self, Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+
+/// Record is foreign to this crate, so `Arbitrary` goes on a wrapper.
+/// anything that isnt a valid record gets thrown away
+#[derive(Debug)]
+struct ArbitraryRecord(Record);
+
+impl<'a> Arbitrary<'a> for ArbitraryRecord {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        let addr: IpAddr = u.arbitrary()?;
+        let names: Vec<String> = u.arbitrary()?;
+        let mut record = match u.arbitrary::<Option<String>>()? {
+            Some(scope) => Record::scoped(addr, scope, names),
+            None => Record::new(addr, names),
+        }
+        .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        if let Some(comment) = u.arbitrary::<Option<String>>()? {
+            // comments lose their outer whitespace on the way back in, so
+            // only generate ones that survive that
+            record
+                .set_comment(Some(comment.trim().to_string()))
+                .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        }
+        Ok(ArbitraryRecord(record))
+    }
+}
+
+fuzz_target!(|records: Vec<ArbitraryRecord>| {
+    let records: Vec<Record> = records.into_iter().map(|r| r.0).collect();
+    let text: String = records.iter().map(|r| format!("{r}\n")).collect();
+    let parsed = parse_str(&text).expect("written records parse");
+    assert_eq!(parsed.records(), records);
+});
@@ -0,0 +1,31 @@
+//! whatever parses, once written back out by the serializer, has to parse
+//! to the same records again
+//!
+//! `cargo +nightly fuzz run round_trip`
+
+#![no_main]
+
+use hosts_digger::{parse_str, Alignment, FormatOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|text: &str| {
+    let Ok(hosts) = parse_str(text) else {
+        return;
+    };
+    assert_eq!(parse_str(&hosts.to_string()).unwrap(), hosts);
+    for align in [Alignment::Tab, Alignment::Tabs, Alignment::Spaces(24)] {
+        let written = hosts.to_string_aligned(align);
+        assert_eq!(parse_str(&written).unwrap().records(), hosts.records());
+    }
+
+    let mut formatted = hosts.clone();
+    formatted.format(&FormatOptions::default());
+    let reparsed = parse_str(&formatted.to_string()).unwrap();
+    let names = |h: &hosts_digger::HostsFile| {
+        h.records()
+            .iter()
+            .flat_map(|r| r.names().iter().map(move |n| (n.clone(), r.addr())))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&reparsed), names(&formatted));
+});
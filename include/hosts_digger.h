@@ -0,0 +1,82 @@
+/* written by hand to match src/ffi.rs, change the two together */
+
+#ifndef HOSTS_DIGGER_H
+#define HOSTS_DIGGER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct HostsFile HostsFile;
+
+/**
+ * open and parse the hosts file at `path`, null on failure
+ */
+HostsFile *hosts_parse_file(const char *path);
+
+/**
+ * parse hosts file contents out of a string, null on failure
+ */
+HostsFile *hosts_parse_str(const char *text);
+
+/**
+ * look `name` up and write the address it resolves to into `buf` as a
+ * nul terminated string, the way snprintf does. gives back how long the
+ * address is without the nul, so anything at or past `len` means `buf`
+ * was too small and nothing was written. -1 when the name isnt there or
+ * an argument is bad
+ */
+int hosts_lookup(const HostsFile *hosts, const char *name, char *buf, size_t len);
+
+/**
+ * point `name` at `addr`, see `HostsFile::set`. 0 on success, -1 on
+ * failure
+ */
+int hosts_set(HostsFile *hosts, const char *name, const char *addr);
+
+/**
+ * parse `line` as a record and add it to the end of the file. 0 on
+ * success, -1 on failure
+ */
+int hosts_add(HostsFile *hosts, const char *line);
+
+/**
+ * take `name` off every line its on. 1 when it was there, 0 when it
+ * wasnt, -1 on a bad argument
+ */
+int hosts_remove(HostsFile *hosts, const char *name);
+
+/**
+ * write the file out to `path` atomically. 0 on success, -1 on failure
+ */
+int hosts_write(const HostsFile *hosts, const char *path);
+
+/**
+ * the whole file as text, free it with `hosts_string_free`. null when
+ * the text has a nul byte in it
+ */
+char *hosts_to_string(const HostsFile *hosts);
+
+/**
+ * free a string from `hosts_to_string`, null is fine
+ */
+void hosts_string_free(char *s);
+
+/**
+ * the message from the last call on this thread that failed, null when
+ * nothing has. good until the next failing call on the same thread
+ */
+const char *hosts_last_error(void);
+
+/**
+ * free a `HostsFile *`, null is fine
+ */
+void hosts_free(HostsFile *hosts);
+
+#ifdef __cplusplus
+}  /* extern "C" */
+#endif
+
+#endif  /* HOSTS_DIGGER_H */
@@ -0,0 +1,159 @@
+//! turn adblock plus / adguard filter lists into hosts records
+//!
+//! only plain domain rules like `||ads.example.com^` mean anything to a
+//! hosts file. exceptions, cosmetic rules and anything with a path,
+//! wildcard or `$option` get skipped and reported so you can see what the
+//! conversion left behind
+
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::blocklist::BLOCK_SINK;
+use crate::hostname::fold_name;
+use crate::record::{Record, RecordError};
+
+#[derive(Debug)]
+pub enum SkipReason {
+    /// `@@` rules that unblock something, hosts files cant allow
+    Exception,
+    /// element hiding and scriptlet rules like `example.com##.ad`
+    Cosmetic,
+    /// paths, wildcards, regexes and `$options`
+    Unsupported,
+    /// looked like a domain rule but the name didnt pass validation
+    InvalidName(RecordError),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Exception => write!(f, "exception rule"),
+            SkipReason::Cosmetic => write!(f, "cosmetic rule"),
+            SkipReason::Unsupported => write!(f, "not a plain domain rule"),
+            SkipReason::InvalidName(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+/// a rule that didnt make it into the output
+#[derive(Debug)]
+pub struct SkippedRule {
+    /// 1-based line number in the filter list
+    pub line: usize,
+    pub rule: String,
+    pub reason: SkipReason,
+}
+
+/// what came out of a filter list
+#[derive(Debug, Default)]
+pub struct AbpImport {
+    /// one record per blocked domain, in the order they showed up
+    pub records: Vec<Record>,
+    pub skipped: Vec<SkippedRule>,
+}
+
+/// convert `text` pointing everything at 0.0.0.0
+pub fn import(text: &str) -> AbpImport {
+    import_to(text, BLOCK_SINK)
+}
+
+/// convert `text` pointing everything at `sink`. comments (`!`) and
+/// `[Adblock Plus 2.0]` style headers are dropped without a mention
+pub fn import_to(text: &str, sink: IpAddr) -> AbpImport {
+    let mut out = AbpImport::default();
+    let mut seen = HashSet::new();
+
+    for (i, rule) in text.lines().enumerate() {
+        let rule = rule.trim();
+        if rule.is_empty() || rule.starts_with('!') || rule.starts_with('[') {
+            continue;
+        }
+        let skip = |reason| SkippedRule {
+            line: i + 1,
+            rule: rule.to_string(),
+            reason,
+        };
+        match domain(rule) {
+            Ok(name) => {
+                if !seen.insert(fold_name(name).into_owned()) {
+                    continue;
+                }
+                match Record::block_to(name, sink) {
+                    Ok(record) => out.records.push(record),
+                    Err(e) => out.skipped.push(skip(SkipReason::InvalidName(e))),
+                }
+            }
+            Err(reason) => out.skipped.push(skip(reason)),
+        }
+    }
+    out
+}
+
+/// pull the domain out of `||domain^`
+fn domain(rule: &str) -> Result<&str, SkipReason> {
+    if rule.starts_with("@@") {
+        return Err(SkipReason::Exception);
+    }
+    if ["##", "#@#", "#?#", "#$#", "#%#"]
+        .iter()
+        .any(|sep| rule.contains(sep))
+    {
+        return Err(SkipReason::Cosmetic);
+    }
+    let name = rule
+        .strip_prefix("||")
+        .and_then(|r| r.strip_suffix('^').or_else(|| r.strip_suffix("^|")))
+        .ok_or(SkipReason::Unsupported)?;
+    if name.is_empty() || name.contains(['*', '/', '^', '$', '|', ':', '?']) {
+        return Err(SkipReason::Unsupported);
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "[Adblock Plus 2.0]\n\
+        ! Title: test list\n\
+        ||ads.example.com^\n\
+        ||ADS.example.com^\n\
+        ||tracker.example.net^|\n\
+        @@||good.example.com^\n\
+        example.com##.banner\n\
+        ||cdn.example.com/ads/*\n\
+        ||pixel.example.org^$third-party\n\
+        ||bad_^name!.com^\n";
+
+    #[test]
+    fn imports_domain_rules() {
+        let out = import(LIST);
+        let names: Vec<_> = out.records.iter().map(|r| r.names()[0].as_str()).collect();
+        assert_eq!(names, ["ads.example.com", "tracker.example.net"]);
+        assert!(out.records.iter().all(|r| r.addr() == BLOCK_SINK));
+
+        let skipped: Vec<_> = out
+            .skipped
+            .iter()
+            .map(|s| (s.line, s.reason.to_string()))
+            .collect();
+        assert_eq!(
+            skipped,
+            [
+                (6, "exception rule".to_string()),
+                (7, "cosmetic rule".to_string()),
+                (8, "not a plain domain rule".to_string()),
+                (9, "not a plain domain rule".to_string()),
+                (10, "not a plain domain rule".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_names_are_reported() {
+        let out = import_to("||bad!name.com^\n", "127.0.0.1".parse().unwrap());
+        assert!(out.records.is_empty());
+        assert!(matches!(out.skipped[0].reason, SkipReason::InvalidName(_)));
+    }
+}
@@ -0,0 +1,495 @@
+//! an append-only log of every change made to a hosts file, for when
+//! someone has to answer who pointed that name somewhere else and when
+//!
+//! each write through `HostsFile::write_audited` adds one json object per
+//! line to `audit.jsonl` in the state directory, holding who made the
+//! change, when, and which names were added, removed or moved:
+//!
+//! `{"time":1791000000,"user":"nat","path":"/etc/hosts","added":[{"name":"db","addr":"10.0.0.5"}],"removed":[],"changed":[]}`
+//!
+//! `suspicious_entries` is the other kind of audit, it looks at what is in
+//! the file for the overrides malware leaves behind
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::diff::{Change, HostsDiff, Mapping};
+use crate::hosts_file::HostsFile;
+use crate::parser::{ParseOptions, ParserError};
+use crate::snapshot::SnapshotStore;
+use crate::writer::WriteOptions;
+
+pub use crate::hijack::{suspicious_entries, Category, Finding};
+
+#[derive(Error, Debug)]
+pub enum AuditError {
+    #[error("audit log line {line} is broken: {reason}")]
+    Malformed { line: usize, reason: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParserError),
+}
+
+/// one change to a hosts file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditEvent {
+    /// when it was written, to the second
+    pub time: SystemTime,
+    /// the user behind it, under sudo thats whoever ran sudo
+    pub user: String,
+    /// the file that changed
+    pub path: PathBuf,
+    pub changes: HostsDiff,
+}
+
+/// the log file itself
+#[derive(Clone, Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl Default for AuditLog {
+    /// `audit.jsonl` in `SnapshotStore::default_dir`
+    fn default() -> Self {
+        Self::new(SnapshotStore::default_dir().join("audit.jsonl"))
+    }
+}
+
+impl AuditLog {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        AuditLog {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// add `event` to the end of the log
+    pub fn append(&self, event: &AuditEvent) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        // one write per line so concurrent writers dont interleave
+        let mut line = event_json(event);
+        line.push('\n');
+        file.write_all(line.as_bytes())?;
+        file.sync_data()
+    }
+
+    /// every event in the log, oldest first. a log that doesnt exist yet
+    /// is just empty
+    pub fn history(&self) -> Result<Vec<AuditEvent>, AuditError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                parse_event(line).map_err(|reason| AuditError::Malformed {
+                    line: i + 1,
+                    reason,
+                })
+            })
+            .collect()
+    }
+}
+
+/// every event in the default log
+pub fn history() -> Result<Vec<AuditEvent>, AuditError> {
+    AuditLog::default().history()
+}
+
+/// whoever is making the change. under sudo `$USER` is root, so the
+/// person who ran sudo wins
+pub fn current_user() -> String {
+    ["SUDO_USER", "USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|user| !user.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl HostsFile {
+    /// `write_to_with`, then log what changed compared to what was on
+    /// disk before. nothing gets logged when the write fails or nothing
+    /// changed, the event is handed back either way
+    pub fn write_audited<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+        log: &AuditLog,
+    ) -> Result<AuditEvent, AuditError> {
+        let path = path.as_ref();
+        let before = match HostsFile::open_with(path, &ParseOptions::default()) {
+            Ok((before, _)) => before,
+            Err(ParserError::CouldNotOpen(e)) if e.kind() == io::ErrorKind::NotFound => {
+                HostsFile::default()
+            }
+            Err(e) => return Err(e.into()),
+        };
+        self.write_to_with(path, options)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let event = AuditEvent {
+            time: UNIX_EPOCH + Duration::from_secs(now.as_secs()),
+            user: current_user(),
+            path: path.to_path_buf(),
+            changes: before.diff(self),
+        };
+        if !event.changes.is_empty() {
+            log.append(&event)?;
+        }
+        Ok(event)
+    }
+}
+
+fn event_json(event: &AuditEvent) -> String {
+    let secs = event
+        .time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let mut out = format!("{{\"time\":{secs},\"user\":");
+    json_string(&mut out, &event.user);
+    out.push_str(",\"path\":");
+    json_string(&mut out, &event.path.to_string_lossy());
+    for (key, mappings) in [
+        ("added", &event.changes.added),
+        ("removed", &event.changes.removed),
+    ] {
+        out.push_str(&format!(",\"{key}\":["));
+        for (i, mapping) in mappings.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"name\":");
+            json_string(&mut out, &mapping.name);
+            out.push_str(&format!(",\"addr\":\"{}\"}}", mapping.addr));
+        }
+        out.push(']');
+    }
+    out.push_str(",\"changed\":[");
+    for (i, change) in event.changes.changed.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        json_string(&mut out, &change.name);
+        out.push_str(&format!(
+            ",\"old\":\"{}\",\"new\":\"{}\"}}",
+            change.old, change.new
+        ));
+    }
+    out.push_str("]}");
+    out
+}
+
+fn parse_event(line: &str) -> Result<AuditEvent, String> {
+    let json = Json::parse(line)?;
+    let time = json
+        .get("time")
+        .and_then(Json::as_number)
+        .and_then(|n| n.parse::<u64>().ok())
+        .ok_or("no time")?;
+    let string = |json: &Json, key: &str| -> Result<String, String> {
+        json.get(key)
+            .and_then(Json::as_str)
+            .map(str::to_string)
+            .ok_or(format!("no {key}"))
+    };
+    let addr = |json: &Json, key: &str| -> Result<IpAddr, String> {
+        string(json, key)?
+            .parse()
+            .map_err(|e| format!("bad {key}: {e}"))
+    };
+    let list = |key: &str| json.get(key).and_then(Json::as_array).unwrap_or(&[]);
+
+    let mut changes = HostsDiff::default();
+    for item in list("added") {
+        changes.added.push(Mapping {
+            name: string(item, "name")?,
+            addr: addr(item, "addr")?,
+        });
+    }
+    for item in list("removed") {
+        changes.removed.push(Mapping {
+            name: string(item, "name")?,
+            addr: addr(item, "addr")?,
+        });
+    }
+    for item in list("changed") {
+        changes.changed.push(Change {
+            name: string(item, "name")?,
+            old: addr(item, "old")?,
+            new: addr(item, "new")?,
+        });
+    }
+    Ok(AuditEvent {
+        time: UNIX_EPOCH + Duration::from_secs(time),
+        user: string(&json, "user")?,
+        path: PathBuf::from(string(&json, "path")?),
+        changes,
+    })
+}
+
+/// just enough json to read back what we wrote. numbers are kept as
+/// their text
+#[derive(Debug, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn parse(s: &str) -> Result<Json, String> {
+        let mut chars = s.chars().peekable();
+        let value = parse_value(&mut chars)?;
+        skip_ws(&mut chars);
+        match chars.next() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected {c:?} after the value")),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_number(&self) -> Option<&str> {
+        match self {
+            Json::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn skip_ws(chars: &mut Chars) {
+    while chars.next_if(|c| c.is_ascii_whitespace()).is_some() {}
+}
+
+fn expect(chars: &mut Chars, want: char) -> Result<(), String> {
+    skip_ws(chars);
+    match chars.next() {
+        Some(c) if c == want => Ok(()),
+        Some(c) => Err(format!("expected {want:?}, got {c:?}")),
+        None => Err(format!("expected {want:?}, got the end of the line")),
+    }
+}
+
+fn parse_value(chars: &mut Chars) -> Result<Json, String> {
+    skip_ws(chars);
+    match chars.peek().copied() {
+        Some('{') => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_ws(chars);
+            if chars.next_if_eq(&'}').is_some() {
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                skip_ws(chars);
+                let key = parse_string(chars)?;
+                expect(chars, ':')?;
+                fields.push((key, parse_value(chars)?));
+                skip_ws(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(fields)),
+                    _ => return Err("unterminated object".to_string()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_ws(chars);
+            if chars.next_if_eq(&']').is_some() {
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars)?);
+                skip_ws(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(items)),
+                    _ => return Err("unterminated array".to_string()),
+                }
+            }
+        }
+        Some('"') => parse_string(chars).map(Json::String),
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut n = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+                n.push(c);
+            }
+            Ok(Json::Number(n))
+        }
+        Some(c) if c.is_ascii_alphabetic() => {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_alphabetic) {
+                word.push(c);
+            }
+            match word.as_str() {
+                "null" => Ok(Json::Null),
+                "true" => Ok(Json::Bool(true)),
+                "false" => Ok(Json::Bool(false)),
+                _ => Err(format!("unexpected {word:?}")),
+            }
+        }
+        Some(c) => Err(format!("unexpected {c:?}")),
+        None => Err("unexpected end of the line".to_string()),
+    }
+}
+
+fn parse_string(chars: &mut Chars) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut out = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(out),
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'u' => {
+                    let mut unit = parse_hex4(chars)?;
+                    if (0xd800..0xdc00).contains(&unit) {
+                        // the high half of a surrogate pair, the low half
+                        // has to follow right away
+                        expect(chars, '\\')?;
+                        expect(chars, 'u')?;
+                        let low = parse_hex4(chars)?;
+                        unit =
+                            0x10000 + ((unit - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+                    }
+                    out.push(char::from_u32(unit).ok_or("bad \\u escape")?);
+                }
+                c => out.push(c),
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Chars) -> Result<u32, String> {
+    let hex: String = chars.take(4).collect();
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("bad \\u escape {hex:?}"))
+}
+
+/// a quoted and escaped json string
+fn json_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audited_writes() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-audit-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        let log = AuditLog::new(dir.join("state").join("audit.jsonl"));
+        assert!(log.history().unwrap().is_empty());
+
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n10.0.0.5 db\n".parse().unwrap();
+        let first = hosts
+            .write_audited(&path, &WriteOptions::default(), &log)
+            .unwrap();
+        assert_eq!(first.changes.added.len(), 2);
+
+        hosts.set("db", "10.0.0.6".parse().unwrap()).unwrap();
+        hosts.remove_by_name("localhost");
+        hosts.set("cache", "10.0.0.7".parse().unwrap()).unwrap();
+        let second = hosts
+            .write_audited(&path, &WriteOptions::default(), &log)
+            .unwrap();
+        // nothing changed this time around, so nothing is logged
+        hosts
+            .write_audited(&path, &WriteOptions::default(), &log)
+            .unwrap();
+
+        let history = log.history().unwrap();
+        assert_eq!(history, [first, second.clone()]);
+        assert_eq!(second.changes.removed[0].name, "localhost");
+        assert_eq!(second.changes.changed[0].name, "db");
+        assert_eq!(second.changes.added[0].name, "cache");
+
+        fs::write(log.path(), "{\"time\":1,\"user\":\"x\"\n").unwrap();
+        assert!(matches!(
+            log.history(),
+            Err(AuditError::Malformed { line: 1, .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = Json::parse(r#" {"a": [1, -2.5e3, true, null], "b": "q\"é😀\n"} "#).unwrap();
+        assert_eq!(json.get("a").and_then(Json::as_array).unwrap().len(), 4);
+        assert_eq!(json.get("b").and_then(Json::as_str), Some("q\"é😀\n"));
+
+        let mut quoted = String::new();
+        json_string(&mut quoted, "tab\there \u{1} \"x\"");
+        assert_eq!(
+            Json::parse(&quoted).unwrap(),
+            Json::String("tab\there \u{1} \"x\"".to_string())
+        );
+        assert!(Json::parse("{\"a\":1,}").is_err());
+    }
+}
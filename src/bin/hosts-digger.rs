@@ -0,0 +1,163 @@
+//! a small hosts file editor built on the library, see USAGE below
+
+use std::env;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use hosts_digger::diagnostics::{self, Verdict};
+use hosts_digger::lint::Severity;
+use hosts_digger::nsswitch::HostsLookup;
+use hosts_digger::{
+    Escalation, FormatOptions, HostsDocument, HostsFile, InvalidUtf8, ParseOptions, Record,
+    Strictness, WriteOptions,
+};
+
+const USAGE: &str = "usage: hosts-digger [--file PATH] [--backup] [--sudo] <command>
+
+commands:
+  list                  print every record
+  get <name>            print the address a name resolves to
+  add <ip> <names...>   add a record
+  remove <name>         take a name out of the file
+  check                 lint the file
+  stats [--prometheus]  count up what is in the file
+  fingerprint           sha-256 of the records, formatting left out
+  fmt [--write]         line the file up, print it or write it back
+  doctor                check whether the resolver reads the hosts file
+  trace <name>          walk a name through the nsswitch order, dns left out
+  serve [addr]          answer dns for the file, 127.0.0.1:5353 by default";
+
+struct Args {
+    file: PathBuf,
+    write: WriteOptions,
+    command: Vec<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut file = None;
+    let mut write = WriteOptions::default();
+    let mut command = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" | "-f" => file = Some(args.next().ok_or("--file needs a path")?.into()),
+            "--backup" => write.backup = true,
+            "--sudo" => write.escalation = Escalation::Sudo,
+            "--help" | "-h" => return Err(String::new()),
+            _ => command.push(arg),
+        }
+    }
+    Ok(Args {
+        file: file.unwrap_or_else(HostsFile::system_path),
+        write,
+        command,
+    })
+}
+
+fn run(args: Args) -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let path = &args.file;
+    let command: Vec<&str> = args.command.iter().map(String::as_str).collect();
+    match command.as_slice() {
+        ["list"] => {
+            for record in HostsFile::open(path)?.records() {
+                println!("{record}");
+            }
+        }
+        ["get", name] => match HostsFile::open(path)?.lookup(name) {
+            Some(addr) => println!("{addr}"),
+            None => {
+                eprintln!("{name} is not in {}", path.display());
+                return Ok(ExitCode::FAILURE);
+            }
+        },
+        ["add", addr, names @ ..] if !names.is_empty() => {
+            let addr: IpAddr = addr.parse()?;
+            let record = Record::new(addr, names.iter().map(|n| n.to_string()).collect())?;
+            let mut hosts = HostsFile::open(path)?;
+            hosts.add_record(record);
+            hosts.write_to_with(path, &args.write)?;
+        }
+        ["remove", name] => {
+            let mut hosts = HostsFile::open(path)?;
+            if !hosts.remove_by_name(name) {
+                eprintln!("{name} is not in {}", path.display());
+                return Ok(ExitCode::FAILURE);
+            }
+            hosts.write_to_with(path, &args.write)?;
+        }
+        ["check"] => {
+            let options = ParseOptions {
+                hostnames: Strictness::Off,
+                invalid_utf8: InvalidUtf8::Lossy,
+                ..Default::default()
+            };
+            let (doc, _) = HostsDocument::open_with(path, &options)?;
+            let diagnostics = doc.lint();
+            for diagnostic in &diagnostics {
+                println!("{}: {diagnostic}", path.display());
+            }
+            if diagnostics.iter().any(|d| d.severity > Severity::Info) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        ["stats"] => print!("{}", HostsFile::open(path)?.stats()),
+        ["fingerprint"] => println!("{}", HostsFile::open(path)?.fingerprint()),
+        #[cfg(feature = "prometheus")]
+        ["stats", "--prometheus"] => print!("{}", hosts_digger::prometheus::render_file(path)?),
+        ["doctor"] => {
+            let diagnosis = diagnostics::check();
+            print!("{diagnosis}");
+            if diagnosis.verdict == Verdict::NotConsulted {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        #[cfg(feature = "dns-server")]
+        ["serve", rest @ ..] if rest.len() <= 1 => {
+            use hosts_digger::dns::{DnsServer, DEFAULT_PORT};
+            let addr = match rest.first() {
+                Some(addr) => addr.to_string(),
+                None => format!("127.0.0.1:{DEFAULT_PORT}"),
+            };
+            DnsServer::new(HostsFile::open(path)?).serve(addr)?;
+        }
+        ["trace", name] => {
+            let hosts = HostsFile::open(path)?;
+            print!("{}", HostsLookup::system()?.simulate(name, &hosts, None));
+        }
+        ["fmt", rest @ ..] if rest.is_empty() || rest == ["--write"] => {
+            let mut hosts = HostsFile::open(path)?;
+            hosts.format(&FormatOptions::default());
+            if rest.is_empty() {
+                print!("{hosts}");
+            } else {
+                hosts.write_to_with(path, &args.write)?;
+            }
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            return Ok(ExitCode::from(2));
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            if !e.is_empty() {
+                eprintln!("hosts-digger: {e}");
+            }
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(args) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("hosts-digger: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! helpers for ad-block style hosts files, where names get pointed at an
+//! address that goes nowhere instead of at a real machine
+
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::document::{Line, RecordLine};
+use crate::hostname::{fold_name, names_eq};
+use crate::hosts_file::HostsFile;
+use crate::query::name_matches;
+use crate::record::{Record, RecordError};
+
+/// where blocked names go unless you say otherwise. 0.0.0.0 fails fast
+/// instead of waiting on a connection to localhost to get refused
+pub const BLOCK_SINK: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// true for the addresses blocklists point things at, 0.0.0.0, :: and
+/// loopback
+pub fn is_sink(addr: IpAddr) -> bool {
+    addr.is_unspecified() || addr.is_loopback()
+}
+
+/// the names every system already maps to loopback, these are the machine
+/// talking to itself and not something being blocked
+fn is_local_name(name: &str) -> bool {
+    let name = fold_name(name);
+    let name = name.trim_end_matches('.');
+    name == "localhost"
+        || name == "broadcasthost"
+        || name.starts_with("localhost.")
+        || name.starts_with("ip6-")
+        || name.ends_with(".localhost")
+        || name.ends_with(".localdomain")
+}
+
+impl Record {
+    /// `0.0.0.0 name`
+    pub fn block(name: &str) -> Result<Self, RecordError> {
+        Self::block_to(name, BLOCK_SINK)
+    }
+
+    /// `sink name`, for lists that would rather use 127.0.0.1 or ::
+    pub fn block_to(name: &str, sink: IpAddr) -> Result<Self, RecordError> {
+        Self::new(sink, vec![name.to_string()])
+    }
+
+    /// true when this line is blocking names rather than mapping them.
+    /// that means a sink address, and for loopback none of the usual
+    /// localhost names, since `127.0.0.1 localhost` is a real mapping
+    pub fn is_block_entry(&self) -> bool {
+        match self.addr() {
+            addr if addr.is_unspecified() => true,
+            addr if addr.is_loopback() => !self.names().iter().any(|n| is_local_name(n)),
+            _ => false,
+        }
+    }
+}
+
+impl HostsFile {
+    /// block every name in `names` by pointing it at 0.0.0.0, see
+    /// `add_blocked_to`
+    pub fn add_blocked<I, S>(&mut self, names: I) -> Result<usize, RecordError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.add_blocked_to(names, BLOCK_SINK)
+    }
+
+    /// point every name in `names` at `sink`. names that are already
+    /// blocked are left alone, names mapped somewhere real get moved over.
+    /// hands back how many names changed
+    pub fn add_blocked_to<I, S>(&mut self, names: I, sink: IpAddr) -> Result<usize, RecordError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        // everything is checked up front and the file edited in one pass,
+        // going through `set` per name is quadratic on a real blocklist
+        let mut wanted: HashMap<String, Record> = HashMap::new();
+        let mut order = Vec::new();
+        for name in names {
+            let name = name.as_ref();
+            let key = fold_name(name).into_owned();
+            if wanted.contains_key(&key) || self.lookup(name).is_some_and(is_sink) {
+                continue;
+            }
+            let record = Record::block_to(name, sink)?;
+            if let Some(policy) = self.policy() {
+                policy.check(&record)?;
+            }
+            order.push(key.clone());
+            wanted.insert(key, record);
+        }
+        if order.is_empty() {
+            return Ok(0);
+        }
+
+        // the names to take off other lines, `wanted` empties out as the
+        // records find a home
+        let keys: HashSet<String> = order.iter().cloned().collect();
+        let changed = order.len();
+        self.edit(|doc| {
+            doc.lines_mut().retain_mut(|line| {
+                let Line::Record(line) = line else {
+                    return true;
+                };
+                let blocked = |n: &String| keys.contains(fold_name(n).as_ref());
+                if !line.record().names().iter().any(blocked) {
+                    return true;
+                }
+                // a line with just the name gets the sink where it sits,
+                // like `set` does
+                if let [only] = line.record().names() {
+                    if let Some(record) = wanted.remove(fold_name(only).as_ref()) {
+                        line.set_record(record);
+                        return true;
+                    }
+                }
+                let mut record = line.record().clone();
+                record.names.retain(|n| !blocked(n));
+                if record.names.is_empty() {
+                    return false;
+                }
+                line.set_record(record);
+                true
+            });
+            for key in order {
+                if let Some(record) = wanted.remove(&key) {
+                    doc.push(Line::Record(RecordLine::new(record)));
+                }
+            }
+        });
+        Ok(changed)
+    }
+
+    /// take every name in `allowlist` out of the file, for punching holes
+    /// in an aggregated blocklist. entries with a `*` or `?` in them are
+    /// globs like in `find_names`. only the matching names come off a
+    /// line, the line itself goes away once it has none left. hands back
+    /// how many names were removed
+    pub fn subtract(&mut self, allowlist: &[String]) -> usize {
+        let allowed = |name: &str| {
+            allowlist
+                .iter()
+                .any(|entry| match entry.contains(['*', '?']) {
+                    true => name_matches(entry, name),
+                    false => names_eq(entry, name),
+                })
+        };
+        let mut removed = 0;
+        self.edit(|doc| {
+            doc.lines_mut().retain_mut(|line| {
+                let Line::Record(line) = line else {
+                    return true;
+                };
+                let before = line.record().names().len();
+                let mut record = line.record().clone();
+                record.names.retain(|name| !allowed(name));
+                if record.names.len() == before {
+                    return true;
+                }
+                removed += before - record.names.len();
+                if record.names.is_empty() {
+                    return false;
+                }
+                line.set_record(record);
+                true
+            });
+        });
+        removed
+    }
+
+    /// just the lines that block something
+    pub fn blocked(&self) -> impl Iterator<Item = &Record> {
+        self.records().iter().filter(|r| r.is_block_entry())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_block_entries() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            ::1 ip6-localhost ip6-loopback\n\
+            0.0.0.0 ads.example.com\n\
+            127.0.0.1 tracker.example.com\n\
+            10.0.0.5 db\n"
+            .parse()
+            .unwrap();
+        let blocked: Vec<_> = hosts.blocked().map(|r| r.names()[0].as_str()).collect();
+        assert_eq!(blocked, ["ads.example.com", "tracker.example.com"]);
+    }
+
+    #[test]
+    fn add_blocked_names() {
+        let mut hosts: HostsFile =
+            "127.0.0.1 localhost\n0.0.0.0 ads.example.com\n10.0.0.9 cdn.example.com\n"
+                .parse()
+                .unwrap();
+        let added = hosts
+            .add_blocked(["ads.example.com", "cdn.example.com", "tracker.example.com"])
+            .unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(hosts.lookup("cdn.example.com"), Some(BLOCK_SINK));
+        assert_eq!(hosts.lookup("tracker.example.com"), Some(BLOCK_SINK));
+        assert_eq!(hosts.blocked().count(), 3);
+
+        let sink = "127.0.0.1".parse().unwrap();
+        assert_eq!(
+            Record::block_to("x.example.com", sink).unwrap().addr(),
+            sink
+        );
+        assert!(Record::block("bad name!").is_err());
+    }
+
+    #[test]
+    fn block_a_big_list() {
+        // this took minutes when every name went through `set`
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n\
+            10.0.0.9 ads0.example.com www.example.com\n\
+            10.0.0.8 ads1.example.com\n"
+            .parse()
+            .unwrap();
+        let names: Vec<String> = (0..20_000).map(|i| format!("ads{i}.example.com")).collect();
+        assert_eq!(hosts.add_blocked(&names).unwrap(), 20_000);
+        assert_eq!(hosts.add_blocked(&names).unwrap(), 0);
+        assert_eq!(hosts.records().len(), 20_002);
+        assert!(names.iter().all(|n| hosts.lookup(n) == Some(BLOCK_SINK)));
+        assert_eq!(hosts.lookup("www.example.com"), "10.0.0.9".parse().ok());
+        // the line with only the name on it is swapped where it sits
+        assert_eq!(hosts.records()[2].to_string(), "0.0.0.0\tads1.example.com");
+    }
+
+    #[test]
+    fn subtract_allowlist() {
+        let mut hosts: HostsFile = "# aggregated\n\
+            0.0.0.0 ads.example.com\n\
+            0.0.0.0 cdn.example.com img.cdn.example.com tracker.example.com\n\
+            0.0.0.0 Static.CDN.example.com\n\
+            10.0.0.5 db\n"
+            .parse()
+            .unwrap();
+        let allow = [
+            "*.cdn.example.com".to_string(),
+            "CDN.example.com".to_string(),
+        ];
+        assert_eq!(hosts.subtract(&allow), 3);
+        assert_eq!(
+            hosts.to_string(),
+            "# aggregated\n\
+             0.0.0.0 ads.example.com\n\
+             0.0.0.0\ttracker.example.com\n\
+             10.0.0.5 db\n"
+        );
+        assert_eq!(hosts.lookup("img.cdn.example.com"), None);
+        assert_eq!(hosts.subtract(&allow), 0);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::hostname::Strictness;
+use crate::line;
+use crate::parser::ParserError;
+use crate::record::{check_names, check_scope, Record};
+use std::net::IpAddr;
+use std::str::SplitWhitespace;
+
+/// RecordRef is a record that borrows its names straight out of the text it
+/// was parsed from
+///
+/// parsing one doesnt allocate, which adds up over the couple hundred
+/// thousand lines of a big blocklist. use `to_record` to get an owned
+/// Record once you know you want to keep it
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct RecordRef<'a> {
+    addr: IpAddr,
+    scope: Option<&'a str>,
+    /// the names column, still separated by whatever whitespace was there
+    names: &'a str,
+    comment: Option<&'a str>,
+}
+
+impl<'a> RecordRef<'a> {
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn scope(&self) -> Option<&'a str> {
+        self.scope
+    }
+
+    /// all of the names on this record, primary name first
+    pub fn names(&self) -> SplitWhitespace<'a> {
+        self.names.split_whitespace()
+    }
+
+    pub fn primary_name(&self) -> &'a str {
+        // parse_line_ref wont hand out a record without a name
+        self.names().next().unwrap_or_default()
+    }
+
+    pub fn comment(&self) -> Option<&'a str> {
+        self.comment
+    }
+
+    /// copy everything out into an owned Record
+    pub fn to_record(&self) -> Record {
+        Record {
+            addr: self.addr,
+            scope: self.scope.map(str::to_string),
+            names: self.names().map(str::to_string).collect(),
+            comment: self.comment.map(str::to_string),
+            source: None,
+        }
+    }
+}
+
+/// every record in `text`, borrowed out of it
+///
+/// comments and blank lines are skipped, bad lines come out as errors
+/// tagged with their line number
+pub fn parse_borrowed(text: &str) -> BorrowedRecords<'_> {
+    BorrowedRecords {
+        lines: text.strip_prefix('\u{feff}').unwrap_or(text).lines(),
+        line: 0,
+        strictness: Strictness::default(),
+    }
+}
+
+/// iterator from `parse_borrowed`
+#[derive(Clone, Debug)]
+pub struct BorrowedRecords<'a> {
+    lines: std::str::Lines<'a>,
+    line: usize,
+    strictness: Strictness,
+}
+
+impl BorrowedRecords<'_> {
+    /// change how picky to be about names from here on out
+    pub fn strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+}
+
+impl<'a> Iterator for BorrowedRecords<'a> {
+    type Item = Result<RecordRef<'a>, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            self.line += 1;
+            match parse_line_ref(line, self.strictness) {
+                Ok(None) => continue,
+                Ok(Some(record)) => return Some(Ok(record)),
+                Err(e) => return Some(Err(ParserError::at_line(self.line, line, e))),
+            }
+        }
+        None
+    }
+}
+
+/// parse a single line without copying anything out of it
+///
+/// the first field is always the address, everything after it is a name
+/// for that address. blank lines and comments give back `None`
+pub(crate) fn parse_line_ref(
+    line: &str,
+    strictness: Strictness,
+) -> Result<Option<RecordRef<'_>>, ParserError> {
+    let Some(fields) = line::split(line) else {
+        return Ok(None);
+    };
+    if fields.names.is_empty() {
+        return Err(ParserError::MissingNames(fields.addr.to_string()));
+    }
+    let (ip, scope) = fields.ip().map_err(|source| ParserError::InvalidAddress {
+        token: fields.addr.to_string(),
+        source,
+    })?;
+
+    let names = fields.names;
+    check_names(names.split_whitespace(), strictness)?;
+    if let Some(scope) = scope {
+        check_scope(ip, scope)?;
+    }
+
+    Ok(Some(RecordRef {
+        addr: ip,
+        scope,
+        names,
+        comment: fields.comment,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed_records() {
+        let src =
+            "# blocklist\n0.0.0.0\tads.example  ads2.example # sinkhole\n\nfe80::1%lo0 localhost\n";
+        let records = parse_borrowed(src).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0].names().collect::<Vec<_>>(),
+            ["ads.example", "ads2.example"]
+        );
+        assert_eq!(records[0].comment(), Some("sinkhole"));
+        assert_eq!(records[1].scope(), Some("lo0"));
+        assert_eq!(records[1].primary_name(), "localhost");
+        assert_eq!(
+            records[0].to_record(),
+            "0.0.0.0 ads.example ads2.example # sinkhole"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn borrowed_errors_have_lines() {
+        let mut records = parse_borrowed("0.0.0.0 ok.example\n0.0.0.0\n");
+        assert!(records.next().unwrap().is_ok());
+        assert!(matches!(
+            records.next().unwrap(),
+            Err(ParserError::AtLine { line: 2, .. })
+        ));
+        assert!(records.next().is_none());
+    }
+}
@@ -0,0 +1,158 @@
+//! a fluent way to put records together in automation code,
+//! `Record::builder().addr(ip).name("db").alias("db.internal").build()`
+
+use std::collections::HashSet;
+use std::net::IpAddr;
+
+use crate::hostname::{fold_name, Strictness};
+use crate::record::{check_comment, check_names, check_scope, Record, RecordError};
+
+#[derive(Clone, Debug, Default)]
+pub struct RecordBuilder {
+    addr: Option<IpAddr>,
+    scope: Option<String>,
+    names: Vec<String>,
+    comment: Option<String>,
+    strictness: Strictness,
+}
+
+impl Record {
+    pub fn builder() -> RecordBuilder {
+        RecordBuilder::default()
+    }
+}
+
+impl RecordBuilder {
+    pub fn addr(mut self, addr: IpAddr) -> Self {
+        self.addr = Some(addr);
+        self
+    }
+
+    /// zone id for a link-local ipv6 address
+    pub fn scope<S: Into<String>>(mut self, scope: S) -> Self {
+        self.scope = Some(scope.into());
+        self
+    }
+
+    /// the canonical name, always ends up first on the line no matter
+    /// when it gets called
+    pub fn name<S: Into<String>>(mut self, name: S) -> Self {
+        self.names.insert(0, name.into());
+        self
+    }
+
+    pub fn alias<S: Into<String>>(mut self, alias: S) -> Self {
+        self.names.push(alias.into());
+        self
+    }
+
+    pub fn aliases<I, S>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.names.extend(aliases.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn comment<S: Into<String>>(mut self, comment: S) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// how picky to be about the names, lenient by default
+    pub fn strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// check everything and hand back the record. it needs an address, at
+    /// least one name and no name more than once (ignoring case), and the
+    /// comment has to fit on the line
+    pub fn build(self) -> Result<Record, RecordError> {
+        let addr = self.addr.ok_or(RecordError::MissingAddress)?;
+        if self.names.is_empty() {
+            return Err(RecordError::MissingNames);
+        }
+        let mut seen = HashSet::new();
+        if let Some(dupe) = self
+            .names
+            .iter()
+            .find(|name| !seen.insert(fold_name(name).into_owned()))
+        {
+            return Err(RecordError::DuplicateName(dupe.clone()));
+        }
+        check_names(self.names.iter().map(String::as_str), self.strictness)?;
+        if let Some(scope) = &self.scope {
+            check_scope(addr, scope)?;
+        }
+        if let Some(comment) = &self.comment {
+            check_comment(comment)?;
+        }
+        Ok(Record {
+            addr,
+            scope: self.scope,
+            names: self.names,
+            comment: self.comment,
+            source: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_records() {
+        let record = Record::builder()
+            .alias("db.internal")
+            .addr("fe80::5".parse().unwrap())
+            .scope("eth0")
+            .name("db")
+            .aliases(["db1", "db-primary"])
+            .build()
+            .unwrap();
+        assert_eq!(record.names(), ["db", "db.internal", "db1", "db-primary"]);
+        assert_eq!(
+            record.to_string(),
+            "fe80::5%eth0\tdb db.internal db1 db-primary"
+        );
+    }
+
+    #[test]
+    fn enforces_invariants() {
+        let addr: IpAddr = "10.0.0.5".parse().unwrap();
+        let err = |b: RecordBuilder| b.build().unwrap_err();
+        assert!(matches!(
+            err(Record::builder().name("db")),
+            RecordError::MissingAddress
+        ));
+        assert!(matches!(
+            err(Record::builder().addr(addr)),
+            RecordError::MissingNames
+        ));
+        assert!(matches!(
+            err(Record::builder().addr(addr).name("db").alias("DB")),
+            RecordError::DuplicateName(name) if name == "DB"
+        ));
+        assert!(matches!(
+            err(Record::builder().addr(addr).name("db").scope("eth0")),
+            RecordError::InvalidScope(..)
+        ));
+        assert!(matches!(
+            err(Record::builder()
+                .addr(addr)
+                .name("d_b")
+                .strictness(Strictness::Strict)),
+            RecordError::InvalidHostname { .. }
+        ));
+        assert!(matches!(
+            err(Record::builder()
+                .addr(addr)
+                .name("db")
+                .comment("ok\n0.0.0.0 accounts.google.com")),
+            RecordError::InvalidComment(_)
+        ));
+    }
+}
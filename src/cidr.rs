@@ -0,0 +1,134 @@
+//! ip networks in `10.0.0.0/8` notation
+
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum CidrError {
+    #[error("{0:?} is not an ip address")]
+    InvalidAddress(String),
+    #[error("{0:?} is not a valid prefix length")]
+    InvalidPrefix(String),
+}
+
+/// a network, the address always has its host bits cleared
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, CidrError> {
+        if prefix > max_prefix(addr) {
+            return Err(CidrError::InvalidPrefix(prefix.to_string()));
+        }
+        Ok(Cidr {
+            addr: mask(addr, prefix),
+            prefix,
+        })
+    }
+
+    /// the network address
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// true when `addr` is inside this network. v4 networks never contain
+    /// v6 addresses and the other way around
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        addr.is_ipv4() == self.addr.is_ipv4() && mask(addr, self.prefix) == self.addr
+    }
+}
+
+fn max_prefix(addr: IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn mask(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let bits = match prefix {
+                0 => 0,
+                p => u32::MAX << (32 - p as u32),
+            };
+            IpAddr::V4((u32::from(v4) & bits).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = match prefix {
+                0 => 0,
+                p => u128::MAX << (128 - p as u32),
+            };
+            IpAddr::V6((u128::from(v6) & bits).into())
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = CidrError;
+
+    /// `10.0.0.0/8`, or a bare address for a network of one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| CidrError::InvalidAddress(addr.to_string()))?;
+        let prefix = match prefix {
+            Some(p) => p
+                .parse()
+                .map_err(|_| CidrError::InvalidPrefix(p.to_string()))?,
+            None => max_prefix(addr),
+        };
+        Cidr::new(addr, prefix)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_contain() {
+        let net: Cidr = "10.1.2.3/16".parse().unwrap();
+        assert_eq!(net.to_string(), "10.1.0.0/16");
+        assert!(net.contains("10.1.255.4".parse().unwrap()));
+        assert!(!net.contains("10.2.0.1".parse().unwrap()));
+        assert!(!net.contains("::1".parse().unwrap()));
+
+        let v6: Cidr = "fd00::/8".parse().unwrap();
+        assert!(v6.contains("fd12::1".parse().unwrap()));
+        assert!("0.0.0.0/0"
+            .parse::<Cidr>()
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert_eq!("10.0.0.5".parse::<Cidr>().unwrap().prefix(), 32);
+
+        assert!(matches!(
+            "10.0.0.0/33".parse::<Cidr>(),
+            Err(CidrError::InvalidPrefix(_))
+        ));
+        assert!(matches!(
+            "nope/8".parse::<Cidr>(),
+            Err(CidrError::InvalidAddress(_))
+        ));
+    }
+}
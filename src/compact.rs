@@ -0,0 +1,266 @@
+//! a read only hosts file that packs every name into one buffer
+//!
+//! a HostsFile keeps a `String` per name plus the document it came from,
+//! which gets heavy on blocklists with a million entries. CompactHostsFile
+//! keeps the names back to back in a single `String` with a `u32` offset
+//! for each, and looks names up through a sorted index instead of a hash
+//! map. comments and formatting are dropped, so this is for looking
+//! things up and not for editing and writing back
+
+use std::net::IpAddr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use crate::borrowed::{parse_borrowed, RecordRef};
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+use crate::record::Record;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Slot {
+    addr: IpAddr,
+    /// index of the first name in `ends`
+    first: u32,
+    count: u32,
+    /// scope ids are rare enough to not bother packing
+    scope: Option<Box<str>>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompactHostsFile {
+    /// every name, back to back
+    names: String,
+    /// where each name in `names` ends, it starts where the one before
+    /// it ends
+    ends: Vec<u32>,
+    records: Vec<Slot>,
+    /// name indices sorted by folded name, ties in file order
+    sorted: Vec<u32>,
+}
+
+/// a record out of a CompactHostsFile
+#[derive(Clone, Copy, Debug)]
+pub struct CompactRecord<'a> {
+    file: &'a CompactHostsFile,
+    slot: &'a Slot,
+}
+
+impl<'a> CompactRecord<'a> {
+    pub fn addr(&self) -> IpAddr {
+        self.slot.addr
+    }
+
+    pub fn scope(&self) -> Option<&'a str> {
+        self.slot.scope.as_deref()
+    }
+
+    /// all of the names on this record, primary name first
+    pub fn names(&self) -> impl ExactSizeIterator<Item = &'a str> + 'a {
+        let file = self.file;
+        let first = self.slot.first as usize;
+        (first..first + self.slot.count as usize).map(move |i| file.name(i))
+    }
+
+    pub fn primary_name(&self) -> &'a str {
+        self.file.name(self.slot.first as usize)
+    }
+
+    /// copy everything out into an owned Record
+    pub fn to_record(&self) -> Record {
+        Record {
+            addr: self.slot.addr,
+            scope: self.slot.scope.as_deref().map(str::to_string),
+            names: self.names().map(str::to_string).collect(),
+            comment: None,
+            source: None,
+        }
+    }
+}
+
+impl CompactHostsFile {
+    /// parse `s` straight into the packed form, lines that dont parse
+    /// are skipped the same way the system resolver skips them
+    pub fn parse_str(s: &str) -> Self {
+        Self::from_refs(parse_borrowed(s).filter_map(Result::ok))
+    }
+
+    /// `parse_str`, but the first bad line is an error
+    pub fn parse_str_strict(s: &str) -> Result<Self, ParserError> {
+        let mut out = CompactHostsFile::default();
+        for record in parse_borrowed(s) {
+            out.push(record?);
+        }
+        out.index();
+        Ok(out)
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        Ok(Self::from(&HostsFile::open(path)?))
+    }
+
+    fn from_refs<'a>(records: impl IntoIterator<Item = RecordRef<'a>>) -> Self {
+        let mut out = CompactHostsFile::default();
+        for record in records {
+            out.push(record);
+        }
+        out.index();
+        out
+    }
+
+    fn push(&mut self, record: RecordRef<'_>) {
+        self.push_parts(record.addr(), record.scope(), record.names());
+    }
+
+    fn push_parts<'a>(
+        &mut self,
+        addr: IpAddr,
+        scope: Option<&str>,
+        names: impl IntoIterator<Item = &'a str>,
+    ) {
+        let first = self.ends.len() as u32;
+        for name in names {
+            self.names.push_str(name);
+            self.ends.push(self.names.len() as u32);
+        }
+        self.records.push(Slot {
+            addr,
+            first,
+            count: self.ends.len() as u32 - first,
+            scope: scope.map(Into::into),
+        });
+    }
+
+    fn index(&mut self) {
+        let mut sorted = (0..self.ends.len() as u32).collect::<Vec<_>>();
+        // stable, so the first record with a name stays first
+        sorted.sort_by(|&a, &b| {
+            fold_name(self.name(a as usize)).cmp(&fold_name(self.name(b as usize)))
+        });
+        self.sorted = sorted;
+        self.names.shrink_to_fit();
+        self.ends.shrink_to_fit();
+        self.records.shrink_to_fit();
+    }
+
+    fn name(&self, i: usize) -> &str {
+        let start = match i {
+            0 => 0,
+            i => self.ends[i - 1] as usize,
+        };
+        &self.names[start..self.ends[i] as usize]
+    }
+
+    /// the record the name at `i` in `ends` belongs to
+    fn record_of(&self, i: usize) -> &Slot {
+        let at = self
+            .records
+            .partition_point(|slot| slot.first as usize <= i);
+        &self.records[at - 1]
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// how many names there are across every record
+    pub fn name_count(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn get(&self, i: usize) -> Option<CompactRecord<'_>> {
+        self.records
+            .get(i)
+            .map(|slot| CompactRecord { file: self, slot })
+    }
+
+    /// every record, in the order they showed up
+    pub fn records(&self) -> impl ExactSizeIterator<Item = CompactRecord<'_>> {
+        self.records
+            .iter()
+            .map(move |slot| CompactRecord { file: self, slot })
+    }
+
+    /// resolve `name` the same way `HostsFile::lookup` does, the first
+    /// record carrying it wins and case doesnt matter
+    pub fn lookup(&self, name: &str) -> Option<IpAddr> {
+        let name = fold_name(name);
+        let at = self
+            .sorted
+            .partition_point(|&i| fold_name(self.name(i as usize)) < name);
+        let &i = self.sorted.get(at)?;
+        (fold_name(self.name(i as usize)) == name).then(|| self.record_of(i as usize).addr)
+    }
+
+    pub fn contains_name(&self, name: &str) -> bool {
+        self.lookup(name).is_some()
+    }
+}
+
+impl From<&HostsFile> for CompactHostsFile {
+    fn from(hosts: &HostsFile) -> Self {
+        let mut out = CompactHostsFile::default();
+        for record in hosts.records() {
+            out.push_parts(
+                record.addr(),
+                record.scope(),
+                record.names().iter().map(String::as_str),
+            );
+        }
+        out.index();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_and_looks_up() {
+        let src = "127.0.0.1 localhost\n\
+                   0.0.0.0 ads.example Ads2.example # sinkhole\n\
+                   nope x\n\
+                   fe80::1%lo0 router\n\
+                   10.0.0.5 ads.example\n";
+        let compact = CompactHostsFile::parse_str(src);
+        assert_eq!(compact.len(), 4);
+        assert_eq!(compact.name_count(), 5);
+        assert_eq!(
+            compact.names,
+            "localhostads.exampleAds2.examplerouterads.example"
+        );
+
+        let ads = compact.get(1).unwrap();
+        assert_eq!(
+            ads.names().collect::<Vec<_>>(),
+            ["ads.example", "Ads2.example"]
+        );
+        assert_eq!(compact.get(2).unwrap().scope(), Some("lo0"));
+        assert_eq!(compact.lookup("ADS2.example"), Some([0, 0, 0, 0].into()));
+        assert_eq!(compact.lookup("ads.example"), Some([0, 0, 0, 0].into()));
+        assert_eq!(compact.lookup("router"), Some("fe80::1".parse().unwrap()));
+        assert_eq!(compact.lookup("missing"), None);
+
+        assert!(CompactHostsFile::parse_str_strict(src).is_err());
+    }
+
+    #[test]
+    fn same_as_hosts_file() {
+        let src = "127.0.0.1 localhost\n10.0.0.5 db db.lan # primary\n10.0.0.6 cache\n";
+        let hosts = HostsFile::parse_str(src).unwrap();
+        let compact = CompactHostsFile::from(&hosts);
+        for (a, b) in hosts.records().iter().zip(compact.records()) {
+            assert_eq!(a.addr(), b.addr());
+            assert!(a.names().iter().eq(b.names()));
+        }
+        for name in ["localhost", "db.lan", "cache", "nope"] {
+            assert_eq!(compact.lookup(name), hosts.lookup(name));
+        }
+    }
+}
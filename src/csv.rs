@@ -0,0 +1,257 @@
+//! spreadsheet friendly `addr,name,aliases,comment` rows, one per record.
+//! aliases share a single column separated by spaces. fields with the
+//! delimiter, quotes or leading/trailing spaces in them get double quoted
+//! the usual csv way
+
+use std::io;
+use std::net::IpAddr;
+
+use thiserror::Error;
+
+use crate::record::{Record, RecordError};
+
+const HEADER: [&str; 4] = ["addr", "name", "aliases", "comment"];
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Delimiter {
+    #[default]
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    fn as_char(&self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+/// whats wrong with a row, `row` is the 1-based line in the input
+#[derive(Error, Debug)]
+pub enum CsvError {
+    #[error("row {row}: expected 2 to 4 columns, found {found}")]
+    Columns { row: usize, found: usize },
+    #[error("row {row}: a quoted field never ends")]
+    UnterminatedQuote { row: usize },
+    #[error("row {row}: {token:?} is not an ip address")]
+    InvalidAddress {
+        row: usize,
+        token: String,
+        #[source]
+        source: std::net::AddrParseError,
+    },
+    #[error("row {row}: {source}")]
+    InvalidRecord {
+        row: usize,
+        #[source]
+        source: RecordError,
+    },
+}
+
+/// what came out of an import, the good rows and whatever went wrong
+/// with the rest
+#[derive(Debug, Default)]
+pub struct CsvImport {
+    pub records: Vec<Record>,
+    pub errors: Vec<CsvError>,
+}
+
+/// the records as a table with a header row
+pub fn to_string<'a, I>(records: I, delimiter: Delimiter) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write(&mut out, records, delimiter).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `to_string` but straight into a writer
+pub fn write<'a, W, I>(mut w: W, records: I, delimiter: Delimiter) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    write_row(&mut w, &HEADER, delimiter)?;
+    for record in records {
+        let (name, aliases) = match record.names().split_first() {
+            Some((name, aliases)) => (name.as_str(), aliases.join(" ")),
+            None => ("", String::new()),
+        };
+        let addr = record.addr_field();
+        let comment = record.comment().unwrap_or_default();
+        write_row(&mut w, &[&addr, name, &aliases, comment], delimiter)?;
+    }
+    Ok(())
+}
+
+fn write_row<W: io::Write>(w: &mut W, fields: &[&str], delimiter: Delimiter) -> io::Result<()> {
+    let d = delimiter.as_char();
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(w, "{d}")?;
+        }
+        let quote = field.contains([d, '"', '\n', '\r']) || field.trim() != *field;
+        if quote {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            write!(w, "{field}")?;
+        }
+    }
+    writeln!(w)
+}
+
+/// read rows back into records. a header row is skipped if there is one,
+/// blank rows are ignored and bad rows land in `errors` instead of
+/// stopping the import
+pub fn import(text: &str, delimiter: Delimiter) -> CsvImport {
+    let mut out = CsvImport::default();
+    for (i, line) in text.lines().enumerate() {
+        let row = i + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = match split_row(line, delimiter.as_char()) {
+            Some(fields) => fields,
+            None => {
+                out.errors.push(CsvError::UnterminatedQuote { row });
+                continue;
+            }
+        };
+        if row == 1 && fields[0].trim().eq_ignore_ascii_case(HEADER[0]) {
+            continue;
+        }
+        match record_from(row, &fields) {
+            Ok(record) => out.records.push(record),
+            Err(e) => out.errors.push(e),
+        }
+    }
+    out
+}
+
+fn record_from(row: usize, fields: &[String]) -> Result<Record, CsvError> {
+    if !(2..=4).contains(&fields.len()) {
+        return Err(CsvError::Columns {
+            row,
+            found: fields.len(),
+        });
+    }
+    let token = fields[0].trim();
+    let (addr, scope) = match token.split_once('%') {
+        Some((addr, scope)) => (addr, Some(scope)),
+        None => (token, None),
+    };
+    let addr: IpAddr = addr.parse().map_err(|source| CsvError::InvalidAddress {
+        row,
+        token: token.to_string(),
+        source,
+    })?;
+
+    let mut names = vec![fields[1].trim().to_string()];
+    if let Some(aliases) = fields.get(2) {
+        names.extend(aliases.split_whitespace().map(String::from));
+    }
+    let invalid = |source| CsvError::InvalidRecord { row, source };
+    let mut record = match scope {
+        Some(scope) => Record::scoped(addr, scope.to_string(), names),
+        None => Record::new(addr, names),
+    }
+    .map_err(invalid)?;
+    if let Some(comment) = fields.get(3).filter(|c| !c.is_empty()) {
+        record.set_comment(Some(comment.clone())).map_err(invalid)?;
+    }
+    Ok(record)
+}
+
+/// split one row on `d`, honoring double quotes. `None` when a quote is
+/// left open
+fn split_row(line: &str, d: char) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            c if c == d && !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts_file::HostsFile;
+    use crate::testing::{arbitrary_record, Rng};
+
+    const HOSTS: &str = "10.0.0.5 db db.lab cache # primary, do not touch\n\
+        fe80::1%eth0 router\n\
+        127.0.0.1 localhost # say \"hi\"\n";
+
+    #[test]
+    fn round_trip() {
+        let hosts: HostsFile = HOSTS.parse().unwrap();
+        let csv = to_string(hosts.records(), Delimiter::Comma);
+        assert_eq!(
+            csv,
+            "addr,name,aliases,comment\n\
+            10.0.0.5,db,db.lab cache,\"primary, do not touch\"\n\
+            fe80::1%eth0,router,,\n\
+            127.0.0.1,localhost,,\"say \"\"hi\"\"\"\n"
+        );
+        let back = import(&csv, Delimiter::Comma);
+        assert!(back.errors.is_empty());
+        assert_eq!(back.records, hosts.records());
+
+        let tsv = to_string(hosts.records(), Delimiter::Tab);
+        assert!(tsv.starts_with("addr\tname\taliases\tcomment\n10.0.0.5\tdb\t"));
+        assert_eq!(import(&tsv, Delimiter::Tab).records, hosts.records());
+    }
+
+    #[test]
+    fn row_errors() {
+        let out = import(
+            "10.0.0.5,db\nnope,web\n10.0.0.6,bad name!\n10.0.0.7\n10.0.0.8,\"open\n\n10.0.0.9,ok,,\n",
+            Delimiter::Comma,
+        );
+        assert_eq!(out.records.len(), 2);
+        let errors: Vec<_> = out.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors.len(), 4);
+        assert!(errors[0].starts_with("row 2: \"nope\""));
+        assert!(errors[1].starts_with("row 3: "));
+        assert_eq!(errors[2], "row 4: expected 2 to 4 columns, found 1");
+        assert_eq!(errors[3], "row 5: a quoted field never ends");
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..200 {
+            let records: Vec<Record> = (0..rng.below(8))
+                .map(|_| arbitrary_record(&mut rng))
+                .collect();
+            for delimiter in [Delimiter::Comma, Delimiter::Tab] {
+                let out = to_string(&records, delimiter);
+                let imported = import(&out, delimiter);
+                assert!(imported.errors.is_empty(), "{out}");
+                assert_eq!(imported.records, records, "{out}");
+            }
+        }
+    }
+}
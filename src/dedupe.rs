@@ -0,0 +1,258 @@
+use crate::document::{HostsDocument, Line};
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+/// a hostname that points at more than one address
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicateName {
+    pub name: String,
+    /// every distinct address the name shows up with, first one wins
+    pub addrs: Vec<IpAddr>,
+    /// indexes into `HostsFile::records` of every record with the name
+    pub records: Vec<usize>,
+}
+
+/// an address that is listed on more than one line
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DuplicateAddr {
+    pub addr: IpAddr,
+    /// indexes into `HostsFile::records` of every record for the addr
+    pub records: Vec<usize>,
+}
+
+/// everything `find_duplicates` turned up
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Duplicates {
+    pub names: Vec<DuplicateName>,
+    pub addrs: Vec<DuplicateAddr>,
+}
+
+impl Duplicates {
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.addrs.is_empty()
+    }
+}
+
+/// which copy survives when `dedupe` finds the same name more than once
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum DedupePolicy {
+    /// keep the first line with a name, same as the resolver would use
+    #[default]
+    KeepFirst,
+    /// keep the last line with a name
+    KeepLast,
+    /// fold every line for an address onto the first one for it
+    MergeNames,
+}
+
+impl HostsFile {
+    /// report names that map to more than one address and addresses that
+    /// are spread over more than one line
+    pub fn find_duplicates(&self) -> Duplicates {
+        let mut names: Vec<DuplicateName> = Vec::new();
+        let mut by_name: HashMap<String, usize> = HashMap::new();
+        let mut addrs: Vec<DuplicateAddr> = Vec::new();
+        let mut by_addr: HashMap<IpAddr, usize> = HashMap::new();
+
+        for (i, record) in self.records().iter().enumerate() {
+            for name in record.names() {
+                let idx = *by_name
+                    .entry(fold_name(name).into_owned())
+                    .or_insert_with(|| {
+                        names.push(DuplicateName {
+                            name: name.clone(),
+                            addrs: Vec::new(),
+                            records: Vec::new(),
+                        });
+                        names.len() - 1
+                    });
+                let dup = &mut names[idx];
+                if !dup.addrs.contains(&record.addr()) {
+                    dup.addrs.push(record.addr());
+                }
+                if dup.records.last() != Some(&i) {
+                    dup.records.push(i);
+                }
+            }
+
+            let idx = *by_addr.entry(record.addr()).or_insert_with(|| {
+                addrs.push(DuplicateAddr {
+                    addr: record.addr(),
+                    records: Vec::new(),
+                });
+                addrs.len() - 1
+            });
+            addrs[idx].records.push(i);
+        }
+
+        names.retain(|dup| dup.addrs.len() > 1);
+        addrs.retain(|dup| dup.records.len() > 1);
+        Duplicates { names, addrs }
+    }
+
+    /// clean up repeated names and addresses according to `policy`, gives
+    /// back how many lines were dropped along the way
+    pub fn dedupe(&mut self, policy: DedupePolicy) -> usize {
+        self.edit(|doc| match policy {
+            DedupePolicy::KeepFirst => keep_one(doc, false),
+            DedupePolicy::KeepLast => keep_one(doc, true),
+            DedupePolicy::MergeNames => merge_names(doc),
+        })
+    }
+}
+
+/// every name gets to live on exactly one line, the first or the last
+fn keep_one(doc: &mut HostsDocument, keep_last: bool) -> usize {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut emptied: HashSet<usize> = HashSet::new();
+
+    let lines = doc.lines_mut();
+    let mut order = (0..lines.len()).collect::<Vec<usize>>();
+    if keep_last {
+        order.reverse();
+    }
+    for i in order {
+        let Line::Record(line) = &mut lines[i] else {
+            continue;
+        };
+        let mut record = line.record().clone();
+        // walk a line back to front too so KeepLast keeps the last copy
+        // of a name even when it shows up twice on the same line
+        let mut names = std::mem::take(&mut record.names);
+        if keep_last {
+            names.reverse();
+        }
+        names.retain(|name| seen.insert(fold_name(name).into_owned()));
+        if keep_last {
+            names.reverse();
+        }
+        if names.len() == line.record().names().len() {
+            continue;
+        }
+        if names.is_empty() {
+            emptied.insert(i);
+            continue;
+        }
+        record.names = names;
+        line.set_record(record);
+    }
+
+    drop_lines(lines, &emptied)
+}
+
+/// every address ends up on one line holding all of its names
+fn merge_names(doc: &mut HostsDocument) -> usize {
+    let mut first: HashMap<IpAddr, usize> = HashMap::new();
+    let mut moved: HashMap<usize, Vec<String>> = HashMap::new();
+    let mut emptied: HashSet<usize> = HashSet::new();
+
+    let lines = doc.lines_mut();
+    for (i, line) in lines.iter().enumerate() {
+        let Line::Record(line) = line else {
+            continue;
+        };
+        let addr = line.record().addr();
+        match first.get(&addr) {
+            Some(&keep) => {
+                moved
+                    .entry(keep)
+                    .or_default()
+                    .extend(line.record().names().iter().cloned());
+                emptied.insert(i);
+            }
+            None => {
+                first.insert(addr, i);
+            }
+        }
+    }
+
+    for (i, line) in lines.iter_mut().enumerate() {
+        let Line::Record(line) = line else {
+            continue;
+        };
+        if emptied.contains(&i) {
+            continue;
+        }
+        let mut record = line.record().clone();
+        record.names.extend(moved.remove(&i).unwrap_or_default());
+        let mut seen = HashSet::new();
+        record
+            .names
+            .retain(|name| seen.insert(fold_name(name).into_owned()));
+        if &record != line.record() {
+            line.set_record(record);
+        }
+    }
+
+    drop_lines(lines, &emptied)
+}
+
+fn drop_lines(lines: &mut Vec<Line>, drop: &HashSet<usize>) -> usize {
+    let mut i = 0;
+    lines.retain(|_| {
+        i += 1;
+        !drop.contains(&(i - 1))
+    });
+    drop.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUPES: &str = "127.0.0.1 localhost\n\
+                         10.0.0.5 db\n\
+                         10.0.0.6 db cache\n\
+                         127.0.0.1 runsc\n\
+                         10.0.0.7 cache cache\n";
+
+    #[test]
+    fn find_duplicates() {
+        let hosts: HostsFile = DUPES.parse().unwrap();
+        let dups = hosts.find_duplicates();
+        let names = dups
+            .names
+            .iter()
+            .map(|d| d.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["db", "cache"]);
+        assert_eq!(dups.names[0].records, [1, 2]);
+        assert_eq!(dups.addrs.len(), 1);
+        assert_eq!(dups.addrs[0].addr, "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(dups.addrs[0].records, [0, 3]);
+    }
+
+    #[test]
+    fn dedupe_keep_first() {
+        let mut hosts: HostsFile = DUPES.parse().unwrap();
+        assert_eq!(hosts.dedupe(DedupePolicy::KeepFirst), 1);
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.6\tcache\n127.0.0.1 runsc\n"
+        );
+        assert!(hosts.find_duplicates().names.is_empty());
+    }
+
+    #[test]
+    fn dedupe_keep_last() {
+        let mut hosts: HostsFile = DUPES.parse().unwrap();
+        assert_eq!(hosts.dedupe(DedupePolicy::KeepLast), 1);
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n10.0.0.6\tdb\n127.0.0.1 runsc\n10.0.0.7\tcache\n"
+        );
+    }
+
+    #[test]
+    fn dedupe_merge_names() {
+        let mut hosts: HostsFile = DUPES.parse().unwrap();
+        assert_eq!(hosts.dedupe(DedupePolicy::MergeNames), 1);
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1\tlocalhost runsc\n10.0.0.5 db\n10.0.0.6 db cache\n10.0.0.7\tcache\n"
+        );
+        assert!(hosts.find_duplicates().addrs.is_empty());
+    }
+}
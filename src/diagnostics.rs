@@ -0,0 +1,282 @@
+//! working out whether the resolver on this machine is going to look at
+//! the hosts file at all, for the "i edited /etc/hosts and nothing
+//! changed" kind of question
+//!
+//! on linux that comes down to the `hosts:` line in /etc/nsswitch.conf and
+//! whether systemd-resolved is in the picture. macOS and windows always
+//! read the file, they just cache it
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::nsswitch::{Action, HostsLookup, GLIBC_DEFAULT};
+
+/// the short answer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// lookups will see what is in the hosts file
+    Consulted,
+    /// lookups go somewhere else and never get to the file
+    NotConsulted,
+}
+
+/// what we found out about systemd-resolved
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Resolved {
+    #[default]
+    NotRunning,
+    /// running, and it reads the hosts file itself
+    Running,
+    /// running with `ReadEtcHosts=no`
+    IgnoringHosts,
+}
+
+/// the long answer
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnosis {
+    pub verdict: Verdict,
+    /// the sources on the `hosts:` line in order, actions left out. `None`
+    /// on platforms without nsswitch
+    pub sources: Option<Vec<String>>,
+    pub resolved: Resolved,
+    /// everything worth telling someone, one sentence each
+    pub notes: Vec<String>,
+}
+
+/// the bits of system config the diagnosis is made from, split out so it
+/// can be worked out for files that arent on this machine
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SystemFiles {
+    /// /etc/nsswitch.conf
+    pub nsswitch: Option<String>,
+    /// /etc/resolv.conf
+    pub resolv_conf: Option<String>,
+    /// /etc/systemd/resolved.conf
+    pub resolved_conf: Option<String>,
+    /// whether systemd-resolved has its runtime dir around
+    pub resolved_running: bool,
+}
+
+impl SystemFiles {
+    /// read everything off of this machine, files that arent there are
+    /// left as `None`
+    pub fn read() -> Self {
+        let read = |path: &str| fs::read_to_string(path).ok();
+        SystemFiles {
+            nsswitch: read("/etc/nsswitch.conf"),
+            resolv_conf: read("/etc/resolv.conf"),
+            resolved_conf: read("/etc/systemd/resolved.conf"),
+            resolved_running: Path::new("/run/systemd/resolve").is_dir(),
+        }
+    }
+}
+
+/// diagnose the machine we are running on
+pub fn check() -> Diagnosis {
+    #[cfg(any(windows, target_os = "macos"))]
+    {
+        #[cfg(windows)]
+        let flush = "ipconfig /flushdns";
+        #[cfg(target_os = "macos")]
+        let flush = "sudo dscacheutil -flushcache; sudo killall -HUP mDNSResponder";
+        Diagnosis {
+            verdict: Verdict::Consulted,
+            sources: None,
+            resolved: Resolved::NotRunning,
+            notes: vec![format!(
+                "the hosts file is always read here but answers get cached, run `{flush}` after editing it"
+            )],
+        }
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        diagnose(&SystemFiles::read())
+    }
+}
+
+/// work out whether glibc style resolution set up like `files` would end
+/// up reading the hosts file
+pub fn diagnose(files: &SystemFiles) -> Diagnosis {
+    let mut notes = Vec::new();
+    let lookup = match files.nsswitch.as_deref().map(HostsLookup::from_nsswitch) {
+        Some(Ok(Some(lookup))) => lookup,
+        Some(Err(e)) => {
+            notes.push(format!(
+                "the hosts line in nsswitch.conf doesnt parse ({e}), going with `{GLIBC_DEFAULT}`"
+            ));
+            HostsLookup::default()
+        }
+        _ => {
+            notes.push(format!(
+                "nsswitch.conf has no hosts line so glibc falls back to `{GLIBC_DEFAULT}`"
+            ));
+            HostsLookup::default()
+        }
+    };
+    let entries = lookup
+        .sources
+        .iter()
+        .map(|s| {
+            let returns = s.criteria.iter().any(|c| c.action == Action::Return);
+            (s.name.clone(), returns)
+        })
+        .collect::<Vec<_>>();
+    let sources: Vec<String> = entries.iter().map(|(s, _)| s.clone()).collect();
+
+    let resolved = if !files.resolved_running {
+        Resolved::NotRunning
+    } else if files.resolved_conf.as_deref().is_some_and(ignores_hosts) {
+        Resolved::IgnoringHosts
+    } else {
+        Resolved::Running
+    };
+    let stub = files
+        .resolv_conf
+        .as_deref()
+        .is_some_and(|conf| nameservers(conf).any(|ns| ns == "127.0.0.53"));
+    if stub && resolved != Resolved::NotRunning {
+        notes.push(
+            "resolv.conf points at the systemd-resolved stub, so tools that skip nsswitch go through resolved too"
+                .to_string(),
+        );
+    }
+    if resolved == Resolved::IgnoringHosts {
+        notes.push("systemd-resolved is set to ReadEtcHosts=no".to_string());
+    }
+
+    let mut verdict = Verdict::NotConsulted;
+    for (source, returns) in &entries {
+        match source.as_str() {
+            "files" => {
+                verdict = Verdict::Consulted;
+                break;
+            }
+            // resolved reads the hosts file itself, when its up
+            "resolve" if resolved == Resolved::Running => {
+                notes.push(
+                    "systemd-resolved answers from the hosts file before nsswitch gets to `files`"
+                        .to_string(),
+                );
+                verdict = Verdict::Consulted;
+                break;
+            }
+            "resolve" if resolved == Resolved::NotRunning => continue,
+            other => {
+                if *returns {
+                    notes.push(format!(
+                        "`{other}` comes before `files` and stops the lookup once it answers"
+                    ));
+                    if matches!(other, "dns" | "resolve") {
+                        break;
+                    }
+                } else {
+                    notes.push(format!("`{other}` gets asked before the hosts file"));
+                }
+            }
+        }
+    }
+    if !sources.iter().any(|s| s == "files") && verdict == Verdict::NotConsulted {
+        notes.push("`files` isnt on the hosts line of nsswitch.conf".to_string());
+    }
+
+    Diagnosis {
+        verdict,
+        sources: Some(sources),
+        resolved,
+        notes,
+    }
+}
+
+fn ignores_hosts(resolved_conf: &str) -> bool {
+    resolved_conf.lines().any(|line| {
+        let line = line.trim().replace(' ', "");
+        line.eq_ignore_ascii_case("ReadEtcHosts=no")
+            || line.eq_ignore_ascii_case("ReadEtcHosts=false")
+    })
+}
+
+fn nameservers(resolv_conf: &str) -> impl Iterator<Item = &str> {
+    resolv_conf.lines().filter_map(|line| {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("nameserver") => fields.next(),
+            _ => None,
+        }
+    })
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.verdict {
+            Verdict::Consulted => writeln!(f, "the hosts file is consulted")?,
+            Verdict::NotConsulted => writeln!(f, "the hosts file is NOT consulted")?,
+        }
+        if let Some(sources) = &self.sources {
+            writeln!(f, "  hosts: {}", sources.join(" "))?;
+        }
+        for note in &self.notes {
+            writeln!(f, "  - {note}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(nsswitch: &str) -> SystemFiles {
+        SystemFiles {
+            nsswitch: Some(nsswitch.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nsswitch_lines() {
+        let plain = diagnose(&files("passwd: files\nhosts: files dns # default\n"));
+        assert_eq!(plain.verdict, Verdict::Consulted);
+        assert_eq!(plain.sources.unwrap(), ["files", "dns"]);
+
+        let dns_first = diagnose(&files("hosts: dns [NOTFOUND=return] files\n"));
+        assert_eq!(dns_first.verdict, Verdict::NotConsulted);
+        assert!(dns_first.notes[0].contains("`dns` comes before `files`"));
+
+        let missing = diagnose(&files("hosts: dns\n"));
+        assert_eq!(missing.verdict, Verdict::NotConsulted);
+        assert!(missing
+            .notes
+            .iter()
+            .any(|n| n.contains("isnt on the hosts line")));
+
+        let none = diagnose(&SystemFiles::default());
+        assert_eq!(none.verdict, Verdict::NotConsulted);
+        assert!(none.notes[0].contains("falls back"));
+    }
+
+    #[test]
+    fn systemd_resolved() {
+        // what ubuntu and fedora ship
+        let mut sys = files("hosts: mymachines resolve [!UNAVAIL=return] files myhostname dns\n");
+        sys.resolv_conf = Some("nameserver 127.0.0.53\noptions edns0\n".into());
+
+        // resolved down means resolve is unavailable and we fall through
+        let down = diagnose(&sys);
+        assert_eq!(down.verdict, Verdict::Consulted);
+        assert_eq!(down.resolved, Resolved::NotRunning);
+
+        sys.resolved_running = true;
+        let up = diagnose(&sys);
+        assert_eq!(up.verdict, Verdict::Consulted);
+        assert_eq!(up.resolved, Resolved::Running);
+        assert!(up
+            .to_string()
+            .starts_with("the hosts file is consulted\n  hosts: mymachines"));
+
+        sys.resolved_conf = Some("[Resolve]\nReadEtcHosts=no\n".into());
+        let ignoring = diagnose(&sys);
+        assert_eq!(ignoring.resolved, Resolved::IgnoringHosts);
+        assert_eq!(ignoring.verdict, Verdict::NotConsulted);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// a name and where it resolves to
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Mapping {
+    pub name: String,
+    pub addr: IpAddr,
+}
+
+/// a name that resolves somewhere else than it used to
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Change {
+    pub name: String,
+    pub old: IpAddr,
+    pub new: IpAddr,
+}
+
+/// HostsDiff is what changes in name resolution going from one hosts file
+/// to another
+///
+/// it works on what each name resolves to rather than on lines, so moving
+/// lines around or reformatting them doesnt show up as a change
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HostsDiff {
+    /// names that only resolve in the new file, in new file order
+    pub added: Vec<Mapping>,
+    /// names that only resolve in the old file, in old file order
+    pub removed: Vec<Mapping>,
+    /// names that resolve in both but to different addrs
+    pub changed: Vec<Change>,
+}
+
+impl HostsDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// every name in `hosts` along with where it resolves, in file order
+fn mappings(hosts: &HostsFile) -> Vec<Mapping> {
+    let mut seen = HashSet::new();
+    hosts
+        .records()
+        .iter()
+        .flat_map(|r| r.names().iter().map(move |n| (n, r.addr())))
+        .filter(|(name, _)| seen.insert(fold_name(name).into_owned()))
+        .map(|(name, addr)| Mapping {
+            name: name.clone(),
+            addr,
+        })
+        .collect()
+}
+
+/// work out what changes going from `old` to `new`
+pub fn diff(old: &HostsFile, new: &HostsFile) -> HostsDiff {
+    let mut out = HostsDiff::default();
+
+    for mapping in mappings(old) {
+        match new.lookup(&mapping.name) {
+            None => out.removed.push(mapping),
+            Some(addr) if addr != mapping.addr => out.changed.push(Change {
+                name: mapping.name,
+                old: mapping.addr,
+                new: addr,
+            }),
+            Some(_) => {}
+        }
+    }
+    for mapping in mappings(new) {
+        if old.lookup(&mapping.name).is_none() {
+            out.added.push(mapping);
+        }
+    }
+
+    out
+}
+
+impl HostsFile {
+    /// what changes going from this file to `other`
+    pub fn diff(&self, other: &HostsFile) -> HostsDiff {
+        diff(self, other)
+    }
+}
+
+/// one change per line, `+`/`-` for added and removed names and `~` for
+/// names that moved
+impl fmt::Display for HostsDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in &self.removed {
+            writeln!(f, "- {} {}", m.addr, m.name)?;
+        }
+        for m in &self.added {
+            writeln!(f, "+ {} {}", m.addr, m.name)?;
+        }
+        for c in &self.changed {
+            writeln!(f, "~ {} {} -> {}", c.name, c.old, c.new)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_files() {
+        let old: HostsFile = "127.0.0.1 localhost\n10.0.0.5 db db.internal\n10.0.0.7 cache\n"
+            .parse()
+            .unwrap();
+        let new: HostsFile = "# reformatted\n127.0.0.1\tlocalhost\n10.0.0.6 db\n10.0.0.9 queue\n"
+            .parse()
+            .unwrap();
+        let d = old.diff(&new);
+        assert_eq!(
+            d.removed
+                .iter()
+                .map(|m| m.name.as_str())
+                .collect::<Vec<_>>(),
+            ["db.internal", "cache"]
+        );
+        assert_eq!(
+            d.added,
+            [Mapping {
+                name: "queue".into(),
+                addr: "10.0.0.9".parse().unwrap()
+            }]
+        );
+        assert_eq!(d.changed.len(), 1);
+        assert_eq!(d.changed[0].name, "db");
+        assert_eq!(
+            d.to_string(),
+            "- 10.0.0.5 db.internal\n- 10.0.0.7 cache\n+ 10.0.0.9 queue\n~ db 10.0.0.5 -> 10.0.0.6\n"
+        );
+    }
+
+    #[test]
+    fn shadowed_lines_dont_count() {
+        let old: HostsFile = "10.0.0.5 db\n".parse().unwrap();
+        let new: HostsFile = "10.0.0.5 db\n10.0.0.6 db\n".parse().unwrap();
+        assert!(diff(&old, &new).is_empty());
+    }
+}
@@ -0,0 +1,591 @@
+//! a tiny dns server that answers out of a hosts file, for pointing lab
+//! vms or containers at one without setting up dnsmasq
+//!
+//! it knows A, AAAA and PTR and nothing else. it is authoritative for
+//! the names in the file and refuses everything else, recursion
+//! included, so it can never be used to look names up out on the
+//! internet. udp and tcp both work. an answer too long for 512 bytes over
+//! udp, or for the 64k a tcp message can hold, comes back with as many
+//! records as fit and the truncated bit set, so the client asks again
+//! over tcp
+
+use std::io::{self, Read, Write};
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket,
+};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::hostname::names_eq;
+use crate::hosts_file::HostsFile;
+
+/// the port `serve` is usually pointed at, out of the way of a real
+/// resolver on 53 and no root needed to bind it
+pub const DEFAULT_PORT: u16 = 5353;
+
+/// what answers are handed out with unless `with_ttl` says otherwise
+pub const DEFAULT_TTL: u32 = 60;
+
+/// tcp connections served at once, past this new ones are closed right
+/// away so a flood of them cant eat every thread
+pub const MAX_CONNECTIONS: usize = 64;
+
+const UDP_LIMIT: usize = 512;
+/// what the two byte length in front of a tcp message can say
+const TCP_LIMIT: usize = u16::MAX as usize;
+/// how long to wait before accepting again when out of fds
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+/// how often `serve` looks up from udp to see if tcp went down
+const STOP_CHECK: Duration = Duration::from_millis(200);
+const HEADER: usize = 12;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+
+const FORMERR: u8 = 1;
+const NOTIMP: u8 = 4;
+const REFUSED: u8 = 5;
+
+/// the reply to the dns message `query`, answered out of `hosts`. none
+/// when it is too broken to reply to at all, or is a reply itself
+pub fn answer(hosts: &HostsFile, query: &[u8], ttl: u32) -> Option<Vec<u8>> {
+    reply(hosts, query, ttl, TCP_LIMIT)
+}
+
+fn reply(hosts: &HostsFile, query: &[u8], ttl: u32, limit: usize) -> Option<Vec<u8>> {
+    if query.len() < HEADER || query[2] & 0x80 != 0 {
+        return None;
+    }
+    let opcode = (query[2] >> 3) & 0x0f;
+    if opcode != 0 {
+        return Some(error(query, NOTIMP, None));
+    }
+    let Some((name, question)) = question(query) else {
+        return Some(error(query, FORMERR, None));
+    };
+    let qtype = u16::from_be_bytes([question[question.len() - 4], question[question.len() - 3]]);
+    let qclass = u16::from_be_bytes([question[question.len() - 2], question[question.len() - 1]]);
+    if qclass != CLASS_IN && qclass != CLASS_ANY {
+        return Some(error(query, REFUSED, Some(question)));
+    }
+
+    let mut answers = Vec::new();
+    if let Some(addr) = reverse_name(&name) {
+        let names = hosts.reverse_lookup(addr);
+        if names.is_empty() {
+            return Some(error(query, REFUSED, Some(question)));
+        }
+        if matches!(qtype, TYPE_PTR | TYPE_ANY) {
+            for name in names {
+                let mut rdata = Vec::new();
+                if encode_name(&mut rdata, name).is_some() {
+                    answers.push((TYPE_PTR, rdata));
+                }
+            }
+        }
+    } else {
+        let addrs = addrs_for(hosts, &name);
+        if addrs.is_empty() {
+            return Some(error(query, REFUSED, Some(question)));
+        }
+        for addr in addrs {
+            match addr {
+                IpAddr::V4(v4) if matches!(qtype, TYPE_A | TYPE_ANY) => {
+                    answers.push((TYPE_A, v4.octets().to_vec()))
+                }
+                IpAddr::V6(v6) if matches!(qtype, TYPE_AAAA | TYPE_ANY) => {
+                    answers.push((TYPE_AAAA, v6.octets().to_vec()))
+                }
+                // the name is ours but has nothing of that type, which
+                // is a NOERROR with no answers
+                _ => {}
+            }
+        }
+    }
+
+    let limit = limit.min(TCP_LIMIT);
+    let mut out = header(query, 0, true, 1, 0);
+    out.extend_from_slice(question);
+    let mut count: u16 = 0;
+    for (rtype, rdata) in &answers {
+        // name pointer, type, class, ttl and length come to 12 bytes
+        if out.len() + 12 + rdata.len() > limit {
+            out[2] |= 0x02;
+            break;
+        }
+        // the owner name is always the one in the question
+        out.extend_from_slice(&[0xc0, HEADER as u8]);
+        out.extend_from_slice(&rtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&ttl.to_be_bytes());
+        // names are at most 255 bytes, so this always fits
+        out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        out.extend_from_slice(rdata);
+        // under the limit there is no room for more than u16::MAX
+        count += 1;
+    }
+    out[6..8].copy_from_slice(&count.to_be_bytes());
+    Some(out)
+}
+
+/// every address `name` has across the file, the first of each kept
+fn addrs_for(hosts: &HostsFile, name: &str) -> Vec<IpAddr> {
+    let mut addrs = Vec::new();
+    for record in hosts.records() {
+        if record.names().iter().any(|n| names_eq(n, name)) && !addrs.contains(&record.addr()) {
+            addrs.push(record.addr());
+        }
+    }
+    addrs
+}
+
+/// the name off the one question, and the bytes of the whole question
+/// to echo back
+fn question(query: &[u8]) -> Option<(String, &[u8])> {
+    if u16::from_be_bytes([query[4], query[5]]) != 1 {
+        return None;
+    }
+    let mut name = String::new();
+    let mut i = HEADER;
+    loop {
+        let len = *query.get(i)? as usize;
+        i += 1;
+        if len == 0 {
+            break;
+        }
+        // questions dont get compressed, a pointer here is garbage
+        if len > 63 {
+            return None;
+        }
+        let label = query.get(i..i + len)?;
+        if !name.is_empty() {
+            name.push('.');
+        }
+        name.push_str(std::str::from_utf8(label).ok()?);
+        i += len;
+        if name.len() > 253 {
+            return None;
+        }
+    }
+    let end = i + 4;
+    query.get(end - 4..end)?;
+    Some((name, &query[HEADER..end]))
+}
+
+/// the address in a `4.3.2.1.in-addr.arpa` or nibble `ip6.arpa` name
+fn reverse_name(name: &str) -> Option<IpAddr> {
+    let lower = name.to_ascii_lowercase();
+    if let Some(rest) = lower.strip_suffix(".in-addr.arpa") {
+        let mut octets = rest
+            .split('.')
+            .map(|o| o.parse::<u8>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        if octets.len() != 4 {
+            return None;
+        }
+        octets.reverse();
+        return Some(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]).into());
+    }
+    let rest = lower.strip_suffix(".ip6.arpa")?;
+    let nibbles = rest
+        .split('.')
+        .map(|n| match n.len() {
+            1 => u8::from_str_radix(n, 16).ok(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    if nibbles.len() != 32 {
+        return None;
+    }
+    let mut octets = [0u8; 16];
+    for (i, pair) in nibbles.rchunks(2).enumerate() {
+        octets[i] = pair[1] << 4 | pair[0];
+    }
+    Some(Ipv6Addr::from(octets).into())
+}
+
+fn encode_name(out: &mut Vec<u8>, name: &str) -> Option<()> {
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return None;
+        }
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    Some(())
+}
+
+/// a reply header for `query`, recursion available is always off
+fn header(query: &[u8], rcode: u8, authoritative: bool, questions: u16, answers: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(UDP_LIMIT);
+    out.extend_from_slice(&query[..2]);
+    // QR, the opcode and RD copied over
+    let mut flags = 0x80 | (query[2] & 0x79);
+    if authoritative {
+        flags |= 0x04;
+    }
+    out.push(flags);
+    out.push(rcode & 0x0f);
+    out.extend_from_slice(&questions.to_be_bytes());
+    out.extend_from_slice(&answers.to_be_bytes());
+    out.extend_from_slice(&[0, 0, 0, 0]);
+    out
+}
+
+fn error(query: &[u8], rcode: u8, question: Option<&[u8]>) -> Vec<u8> {
+    let mut out = header(query, rcode, false, question.is_some() as u16, 0);
+    out.extend_from_slice(question.unwrap_or_default());
+    out
+}
+
+/// serves a hosts file over udp and tcp. clones share the file, swap a
+/// new one in through `hosts` while it is running
+#[derive(Clone, Debug)]
+pub struct DnsServer {
+    hosts: Arc<RwLock<HostsFile>>,
+    ttl: u32,
+}
+
+impl DnsServer {
+    pub fn new(hosts: HostsFile) -> Self {
+        DnsServer {
+            hosts: Arc::new(RwLock::new(hosts)),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    /// seconds clients can cache answers for
+    pub fn with_ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// the file being served, write a freshly read one in to reload
+    pub fn hosts(&self) -> &Arc<RwLock<HostsFile>> {
+        &self.hosts
+    }
+
+    fn reply(&self, query: &[u8], limit: usize) -> Option<Vec<u8>> {
+        let hosts = self.hosts.read().unwrap_or_else(PoisonError::into_inner);
+        reply(&hosts, query, self.ttl, limit)
+    }
+
+    /// bind udp and tcp on `addr` and serve both until one of them fails,
+    /// the other is stopped and waited for before the error comes back
+    pub fn serve<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        let socket = UdpSocket::bind(addr)?;
+        let listener = TcpListener::bind(socket.local_addr()?)?;
+        let tcp_addr = listener.local_addr()?;
+        socket.set_read_timeout(Some(STOP_CHECK))?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let tcp = {
+            let server = self.clone();
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let result = server.accept_until(listener, &stop);
+                stop.store(true, Ordering::Relaxed);
+                result
+            })
+        };
+        let udp = self.receive_until(socket, &stop);
+        stop.store(true, Ordering::Relaxed);
+        // accept is blocking, a connection of our own gets it to look at
+        // the flag
+        let mut wake = tcp_addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(match wake {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        let _ = TcpStream::connect_timeout(&wake, STOP_CHECK);
+        let tcp = tcp
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the tcp server panicked")));
+        udp.and(tcp)
+    }
+
+    /// answer queries coming in on `socket`, this only comes back when
+    /// receiving fails
+    pub fn serve_udp(&self, socket: UdpSocket) -> io::Result<()> {
+        self.receive_until(socket, &AtomicBool::new(false))
+    }
+
+    fn receive_until(&self, socket: UdpSocket, stop: &AtomicBool) -> io::Result<()> {
+        let mut buf = [0u8; 4096];
+        while !stop.load(Ordering::Relaxed) {
+            let (len, peer) = match socket.recv_from(&mut buf) {
+                Ok(got) => got,
+                // windows reports an icmp port unreachable from an earlier
+                // reply here, it isnt about this socket
+                Err(e) if e.kind() == io::ErrorKind::ConnectionReset => continue,
+                // the read timeout, set so the flag gets looked at
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(out) = self.reply(&buf[..len], UDP_LIMIT) {
+                // a client that went away isnt our problem
+                let _ = socket.send_to(&out, peer);
+            }
+        }
+        Ok(())
+    }
+
+    /// answer connections on `listener`, each on a thread of its own and
+    /// no more than `MAX_CONNECTIONS` at once. errors about a single
+    /// connection or running out of fds are ridden out, this only comes
+    /// back when the listener itself is broken
+    pub fn serve_tcp(&self, listener: TcpListener) -> io::Result<()> {
+        self.accept_until(listener, &AtomicBool::new(false))
+    }
+
+    fn accept_until(&self, listener: TcpListener, stop: &AtomicBool) -> io::Result<()> {
+        let open = Arc::new(AtomicUsize::new(0));
+        loop {
+            let accepted = listener.accept();
+            if stop.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => match retry_accept_after(&e) {
+                    Some(wait) => {
+                        thread::sleep(wait);
+                        continue;
+                    }
+                    None => return Err(e),
+                },
+            };
+            if open.fetch_add(1, Ordering::AcqRel) >= MAX_CONNECTIONS {
+                // closed on drop, the client can try again later
+                open.fetch_sub(1, Ordering::AcqRel);
+                continue;
+            }
+            let server = self.clone();
+            let open = Arc::clone(&open);
+            thread::spawn(move || {
+                let _ = server.connection(stream);
+                open.fetch_sub(1, Ordering::AcqRel);
+            });
+        }
+    }
+
+    /// queries on one connection, each behind its two byte length
+    fn connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        loop {
+            let mut len = [0u8; 2];
+            match stream.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e),
+            }
+            let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query)?;
+            let Some(out) = self.reply(&query, TCP_LIMIT) else {
+                return Ok(());
+            };
+            stream.write_all(&(out.len() as u16).to_be_bytes())?;
+            stream.write_all(&out)?;
+        }
+    }
+}
+
+/// how long to hold off before accepting again after `e`, none when the
+/// listener itself is broken. a client that gave up before we got to it
+/// is nothing, running out of fds or buffers under load passes once some
+/// connections finish
+fn retry_accept_after(e: &io::Error) -> Option<Duration> {
+    match e.kind() {
+        io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::ConnectionReset
+        | io::ErrorKind::Interrupted
+        | io::ErrorKind::WouldBlock
+        | io::ErrorKind::TimedOut => return Some(Duration::ZERO),
+        io::ErrorKind::OutOfMemory => return Some(ACCEPT_BACKOFF),
+        _ => {}
+    }
+    match e.raw_os_error() {
+        Some(code) if sys::OUT_OF_RESOURCES.contains(&code) => Some(ACCEPT_BACKOFF),
+        _ => None,
+    }
+}
+
+/// the errnos for running out of fds or buffers
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::c_int;
+
+    const ENFILE: c_int = 23;
+    const EMFILE: c_int = 24;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const ENOBUFS: c_int = 105;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const ENOBUFS: c_int = 55;
+
+    pub(super) const OUT_OF_RESOURCES: &[c_int] = &[ENFILE, EMFILE, ENOBUFS];
+}
+
+#[cfg(windows)]
+mod sys {
+    const WSAENOBUFS: i32 = 10055;
+    const WSAEMFILE: i32 = 10024;
+
+    pub(super) const OUT_OF_RESOURCES: &[i32] = &[WSAENOBUFS, WSAEMFILE];
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    pub(super) const OUT_OF_RESOURCES: &[i32] = &[];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(name: &str, qtype: u16) -> Vec<u8> {
+        // id 0xbeef, RD on, one question
+        let mut q = vec![0xbe, 0xef, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        encode_name(&mut q, name).unwrap();
+        q.extend_from_slice(&qtype.to_be_bytes());
+        q.extend_from_slice(&CLASS_IN.to_be_bytes());
+        q
+    }
+
+    fn rcode(reply: &[u8]) -> u8 {
+        reply[3] & 0x0f
+    }
+
+    fn answers(reply: &[u8]) -> u16 {
+        u16::from_be_bytes([reply[6], reply[7]])
+    }
+
+    #[test]
+    fn answers_from_the_file() {
+        let hosts: HostsFile = "10.0.0.5 db db.lan\nfd00::5 db\n".parse().unwrap();
+
+        let reply = answer(&hosts, &query("DB.lan", TYPE_A), 30).unwrap();
+        assert_eq!(&reply[..2], [0xbe, 0xef]);
+        // a reply, authoritative, RD echoed, RA off
+        assert_eq!(reply[2], 0x85);
+        assert_eq!(reply[3], 0x00);
+        assert_eq!(answers(&reply), 1);
+        assert_eq!(&reply[reply.len() - 4..], [10, 0, 0, 5]);
+        assert_eq!(
+            &reply[reply.len() - 10..reply.len() - 6],
+            30u32.to_be_bytes()
+        );
+
+        let reply = answer(&hosts, &query("db", TYPE_AAAA), 30).unwrap();
+        assert_eq!(answers(&reply), 1);
+        let v6: Ipv6Addr = "fd00::5".parse().unwrap();
+        assert_eq!(&reply[reply.len() - 16..], v6.octets());
+        assert_eq!(
+            answers(&answer(&hosts, &query("db", TYPE_ANY), 30).unwrap()),
+            2
+        );
+        // room for the A but not the AAAA after it
+        let truncated = super::reply(&hosts, &query("db", TYPE_ANY), 30, 40).unwrap();
+        assert_eq!((truncated[2] & 0x02, answers(&truncated)), (0x02, 1));
+        assert_eq!(truncated.len(), 36);
+        let mut any_class = query("db", TYPE_A);
+        let end = any_class.len();
+        any_class[end - 2..].copy_from_slice(&CLASS_ANY.to_be_bytes());
+        assert_eq!(answers(&answer(&hosts, &any_class, 30).unwrap()), 1);
+        // ours, just nothing of that type
+        let reply = answer(&hosts, &query("db.lan", TYPE_AAAA), 30).unwrap();
+        assert_eq!((rcode(&reply), answers(&reply)), (0, 0));
+
+        let reply = answer(&hosts, &query("5.0.0.10.in-addr.arpa", TYPE_PTR), 30).unwrap();
+        assert_eq!(answers(&reply), 2);
+        let mut db = Vec::new();
+        encode_name(&mut db, "db.lan").unwrap();
+        assert!(reply.ends_with(&db));
+        let ptr = "5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.d.f.ip6.arpa";
+        assert_eq!(
+            answers(&answer(&hosts, &query(ptr, TYPE_PTR), 30).unwrap()),
+            1
+        );
+
+        // anything else would mean going out and recursing
+        let reply = answer(&hosts, &query("example.com", TYPE_A), 30).unwrap();
+        assert_eq!((rcode(&reply), reply[2] & 0x04), (REFUSED, 0));
+        assert_eq!(
+            rcode(&answer(&hosts, &[0xbe, 0xef, 1, 0, 0, 2, 0, 0, 0, 0, 0, 0], 30).unwrap()),
+            FORMERR
+        );
+        assert!(answer(&hosts, &[0; 5], 30).is_none());
+    }
+
+    #[test]
+    fn capped_to_a_tcp_message() {
+        // 5000 A records is more than 64k
+        let text: String = (0..5000u32)
+            .map(|i| format!("{} big\n", Ipv4Addr::from(0x0a00_0000 + i)))
+            .collect();
+        let hosts: HostsFile = text.parse().unwrap();
+        let reply = answer(&hosts, &query("big", TYPE_A), 30).unwrap();
+        assert!(reply.len() <= TCP_LIMIT);
+        assert_eq!(reply[2] & 0x02, 0x02);
+        assert_eq!(answers(&reply) as usize, (TCP_LIMIT - 12 - 9) / 16);
+    }
+
+    #[test]
+    fn accept_errors() {
+        let kind = |kind: io::ErrorKind| retry_accept_after(&kind.into());
+        assert_eq!(kind(io::ErrorKind::ConnectionAborted), Some(Duration::ZERO));
+        assert_eq!(kind(io::ErrorKind::Interrupted), Some(Duration::ZERO));
+        assert_eq!(kind(io::ErrorKind::InvalidInput), None);
+        #[cfg(unix)]
+        assert_eq!(
+            retry_accept_after(&io::Error::from_raw_os_error(24)),
+            Some(ACCEPT_BACKOFF)
+        );
+    }
+
+    #[test]
+    fn udp_and_tcp() {
+        let server = DnsServer::new("10.0.0.5 db\n".parse().unwrap());
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (udp_addr, tcp_addr) = (socket.local_addr().unwrap(), listener.local_addr().unwrap());
+        let (udp, tcp) = (server.clone(), server.clone());
+        thread::spawn(move || udp.serve_udp(socket));
+        thread::spawn(move || tcp.serve_tcp(listener));
+
+        let client = UdpSocket::bind("127.0.0.1:0").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client.send_to(&query("db", TYPE_A), udp_addr).unwrap();
+        let mut buf = [0u8; 512];
+        let len = client.recv(&mut buf).unwrap();
+        assert_eq!(&buf[len - 4..len], [10, 0, 0, 5]);
+
+        // a reload shows up on the next query
+        *server.hosts().write().unwrap() = "10.0.0.6 db\n".parse().unwrap();
+        let mut stream = TcpStream::connect(tcp_addr).unwrap();
+        let q = query("db", TYPE_A);
+        stream.write_all(&(q.len() as u16).to_be_bytes()).unwrap();
+        stream.write_all(&q).unwrap();
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len).unwrap();
+        let mut reply = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut reply).unwrap();
+        assert_eq!(&reply[reply.len() - 4..], [10, 0, 0, 6]);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::encoding::{self, Encoding, LineEnding};
+use crate::parser::{ParseOptions, ParseWarning, Parser, ParserError, Span};
+use crate::provenance::Source;
+use crate::record::Record;
+use std::fmt;
+use std::io::Read;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+/// a single physical line of a hosts file
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Line {
+    /// a line holding an address and its names
+    Record(RecordLine),
+    /// a line that starts with `#`, kept exactly as written
+    Comment(String),
+    /// an empty line, or one that is only whitespace
+    Blank(String),
+    /// a line that wouldnt parse, kept so it writes back out untouched
+    Invalid(String),
+}
+
+/// a record along with the text it was parsed from
+///
+/// as long as the record is untouched we hand back the original text so
+/// the spacing the user picked survives a round trip
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecordLine {
+    record: Record,
+    raw: Option<String>,
+    span: Option<Span>,
+}
+
+impl RecordLine {
+    pub fn new(record: Record) -> Self {
+        Self {
+            record,
+            raw: None,
+            span: None,
+        }
+    }
+
+    pub(crate) fn parsed(record: Record, raw: String, span: Span) -> Self {
+        Self {
+            record,
+            raw: Some(raw),
+            span: Some(span),
+        }
+    }
+
+    /// a record with the exact text it should be written out as, for when
+    /// we lay the line out ourselves instead of using the default format
+    pub(crate) fn formatted(record: Record, raw: String, span: Option<Span>) -> Self {
+        Self {
+            record,
+            raw: Some(raw),
+            span,
+        }
+    }
+
+    /// fold the record on the next physical line into this one, the text
+    /// of both lines is kept so the pair still writes back out as is
+    pub(crate) fn absorb(&mut self, next: RecordLine, newline: &str) {
+        self.raw = match (self.raw.take(), next.raw) {
+            (Some(raw), Some(next)) => Some(format!("{raw}{newline}{next}")),
+            _ => None,
+        };
+        self.span = match (self.span, next.span) {
+            (Some(span), Some(next)) => Some(Span {
+                end: next.end,
+                ..span
+            }),
+            (span, _) => span,
+        };
+        self.record.names.extend(next.record.names);
+        if self.record.comment.is_none() {
+            self.record.comment = next.record.comment;
+        }
+    }
+
+    /// where the line was in the source it was parsed from, `None` for
+    /// records that were added after parsing
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    pub fn record(&self) -> &Record {
+        &self.record
+    }
+
+    /// tag the record with where it came from, this doesnt count as a
+    /// change so the line still writes back out as it was
+    pub(crate) fn set_source(&mut self, source: Option<Source>) {
+        self.record.source = source;
+    }
+
+    /// swap out the record, the line gets rewritten when serialized
+    pub fn set_record(&mut self, record: Record) {
+        self.record = record;
+        self.raw = None;
+    }
+
+    /// true when this line no longer matches the text it came from
+    pub fn is_modified(&self) -> bool {
+        self.raw.is_none()
+    }
+}
+
+/// HostsDocument is a lossless view of a hosts file
+///
+/// unlike HostsFile this holds on to comments, blank lines and ordering
+/// so an unmodified document writes back out byte for byte
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HostsDocument {
+    lines: Vec<Line>,
+    trailing_newline: bool,
+    line_ending: LineEnding,
+    encoding: Encoding,
+}
+
+impl HostsDocument {
+    #[cfg(feature = "std-fs")]
+    /// open and parse the hosts file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        let mut parser = Parser::default();
+        parser.parse(path.as_ref())?;
+        Ok(parser.into_document())
+    }
+
+    /// parse a hosts file out of anything readable, stdin, a download, ...
+    pub fn parse_reader<R: Read>(reader: R) -> Result<Self, ParserError> {
+        Ok(Self::parse_reader_with(reader, &ParseOptions::default())?.0)
+    }
+
+    pub fn parse_reader_with<R: Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let mut parser = Parser::with_options(options.clone());
+        parser.parse_reader(reader)?;
+        Ok(parser.finish())
+    }
+
+    /// same as `str::parse`, here so it reads nicely next to `parse_reader`
+    pub fn parse_str(s: &str) -> Result<Self, ParserError> {
+        s.parse()
+    }
+
+    #[cfg(feature = "std-fs")]
+    /// like `open` but with control over how broken lines are handled
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let mut parser = Parser::with_options(options.clone());
+        parser.parse(path.as_ref())?;
+        Ok(parser.finish())
+    }
+
+    /// parse `s` with control over how broken lines are handled
+    pub fn parse_with(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let mut parser = Parser::with_options(options.clone());
+        parser.parse_str(s)?;
+        Ok(parser.finish())
+    }
+
+    pub(crate) fn from_lines(
+        lines: Vec<Line>,
+        trailing_newline: bool,
+        line_ending: LineEnding,
+        encoding: Encoding,
+    ) -> Self {
+        Self {
+            lines,
+            trailing_newline,
+            line_ending,
+            encoding,
+        }
+    }
+
+    /// the line terminator the file was read with, new lines get the same
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        self.line_ending = line_ending;
+    }
+
+    /// the encoding the file was read in, `to_bytes` writes it back the
+    /// same way
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// the document as it should land on disk, BOM and all
+    ///
+    /// `to_string` gives back the text without any BOM, this is the one
+    /// that round trips a file byte for byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding::encode(&self.to_string(), self.encoding)
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    pub fn lines_mut(&mut self) -> &mut Vec<Line> {
+        &mut self.lines
+    }
+
+    /// just the records, skipping comments and blanks
+    pub fn records(&self) -> impl Iterator<Item = &Record> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Record(line) => Some(line.record()),
+            _ => None,
+        })
+    }
+
+    /// the record lines, with the span each one was parsed from
+    pub fn record_lines(&self) -> impl Iterator<Item = &RecordLine> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Record(line) => Some(line),
+            _ => None,
+        })
+    }
+
+    pub fn records_mut(&mut self) -> impl Iterator<Item = &mut RecordLine> {
+        self.lines.iter_mut().filter_map(|line| match line {
+            Line::Record(line) => Some(line),
+            _ => None,
+        })
+    }
+
+    pub fn push(&mut self, line: Line) {
+        // a fresh document should end the way every hosts file does
+        if self.lines.is_empty() {
+            self.trailing_newline = true;
+        }
+        self.lines.push(line);
+    }
+}
+
+impl FromStr for HostsDocument {
+    type Err = ParserError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::default();
+        parser.parse_str(s)?;
+        Ok(parser.into_document())
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Record(line) => match &line.raw {
+                Some(raw) => f.write_str(raw),
+                None => write!(f, "{}", line.record),
+            },
+            Line::Comment(raw) | Line::Blank(raw) | Line::Invalid(raw) => f.write_str(raw),
+        }
+    }
+}
+
+impl fmt::Display for HostsDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let newline = self.line_ending.as_str();
+        for (i, line) in self.lines.iter().enumerate() {
+            if i > 0 {
+                f.write_str(newline)?;
+            }
+            write!(f, "{line}")?;
+        }
+        if self.trailing_newline {
+            f.write_str(newline)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "# /etc/hosts: static lookup table\n\
+                         127.0.0.1\tlocalhost\n   \n\
+                         # The following lines are desirable for IPv6 capable hosts\n\
+                         ::1     ip6-localhost ip6-loopback\n\
+                         \n\
+                         192.168.10.42   core.naus   core\n";
+
+    #[test]
+    fn round_trip_unmodified() {
+        let doc: HostsDocument = HOSTS.parse().unwrap();
+        assert_eq!(doc.to_string(), HOSTS);
+        assert_eq!(doc.records().count(), 3);
+    }
+
+    #[test]
+    fn round_trip_no_trailing_newline() {
+        let src = "127.0.0.1 localhost\n\n# end";
+        let doc: HostsDocument = src.parse().unwrap();
+        assert_eq!(doc.to_string(), src);
+        assert_eq!(
+            doc.lines()[1..],
+            [Line::Blank(String::new()), Line::Comment("# end".into())]
+        );
+    }
+
+    #[test]
+    fn record_spans() {
+        let doc: HostsDocument = HOSTS.parse().unwrap();
+        let spans = doc
+            .record_lines()
+            .map(|line| line.span().unwrap())
+            .collect::<Vec<Span>>();
+        assert_eq!(spans.iter().map(|s| s.line).collect::<Vec<_>>(), [2, 5, 7]);
+        for span in spans {
+            let text = &HOSTS[span.range()];
+            assert!(!text.contains('\n'));
+            assert!(text.parse::<HostsDocument>().unwrap().records().count() == 1);
+        }
+    }
+
+    #[test]
+    fn invalid_lines_round_trip() {
+        let src = "127.0.0.1 localhost\nnot-an-addr foo\n";
+        let (doc, warnings) = HostsDocument::parse_with(src, &ParseOptions::default()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(doc.to_string(), src);
+
+        let strict = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        assert!(HostsDocument::parse_with(src, &strict).is_err());
+    }
+
+    #[test]
+    fn odd_bytes_round_trip() {
+        // found by the fuzzer, a crlf file with one lf line used to come
+        // back with a \r added to that line
+        for src in [
+            "127.0.0.1 localhost\r\n10.0.0.5 db\n10.0.0.6 cache\r\n",
+            "0.0.0.0 a\0b\n\0\n\u{feff}\u{feff}\n",
+            "\r\r\n\r",
+        ] {
+            let doc: HostsDocument = src.parse().unwrap();
+            assert_eq!(doc.to_string(), src);
+        }
+        let doc = HostsDocument::parse_reader(&b"\xff\xfe\x00"[..]);
+        assert!(matches!(doc, Err(ParserError::InvalidEncoding(_))));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn windows_crlf_bom() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/windows-crlf-bom.hosts"
+        );
+        let mut doc = HostsDocument::open(path).unwrap();
+        assert_eq!(doc.encoding(), Encoding::Utf8Bom);
+        assert_eq!(doc.line_ending(), LineEnding::CrLf);
+        assert_eq!(doc.to_bytes(), std::fs::read(path).unwrap());
+
+        let names = doc
+            .records()
+            .map(|r| r.names()[0].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["db", "kubernetes.docker.internal"]);
+        assert_eq!(
+            doc.lines()[0],
+            Line::Comment("# Copyright (c) 1993-2009 Microsoft Corp.".into())
+        );
+
+        // lines we add pick up the crlf too
+        let record = Record::new("10.0.0.6".parse().unwrap(), vec!["cache".into()]).unwrap();
+        doc.push(Line::Record(RecordLine::new(record)));
+        assert!(doc
+            .to_string()
+            .ends_with("Docker Desktop\r\n10.0.0.6\tcache\r\n"));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn windows_utf16() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/windows-utf16le.hosts"
+        );
+        let doc = HostsDocument::open(path).unwrap();
+        assert_eq!(doc.encoding(), Encoding::Utf16Le);
+        assert_eq!(doc.line_ending(), LineEnding::CrLf);
+        assert_eq!(doc.records().count(), 2);
+        assert_eq!(doc.to_bytes(), std::fs::read(path).unwrap());
+    }
+
+    #[test]
+    fn modified_record_rewritten() {
+        let mut doc: HostsDocument = HOSTS.parse().unwrap();
+        for line in doc.lines_mut() {
+            if let Line::Record(line) = line {
+                if line.record().primary_name() == Some("core.naus") {
+                    let record = Record::new("10.0.0.2".parse().unwrap(), vec!["core.naus".into()]);
+                    line.set_record(record.unwrap());
+                }
+            }
+        }
+        let out = doc.to_string();
+        assert!(out.contains("10.0.0.2\tcore.naus\n"));
+        assert!(out.starts_with("# /etc/hosts: static lookup table\n127.0.0.1\tlocalhost\n"));
+    }
+}
@@ -0,0 +1,315 @@
+//! how far a hosts file has wandered from the state it is supposed to be
+//! in, and pulling it back, for config management agents that keep
+//! /etc/hosts in line with a manifest
+
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::diff::{diff, Mapping};
+use crate::document::{Line, RecordLine};
+use crate::hostname::fold_name;
+use crate::hosts_file::{strip_names, HostsFile};
+use crate::record::{Record, RecordError};
+
+/// a name the manifest and the file disagree on
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Mismatch {
+    pub name: String,
+    /// where it resolves in the file
+    pub actual: IpAddr,
+    /// where the manifest says it should
+    pub desired: IpAddr,
+}
+
+/// what `HostsFile::drift` found. like HostsDiff this goes by what each
+/// name resolves to, so layout and comments dont count as drift
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DriftReport {
+    /// names the manifest has that dont resolve in the file, in manifest
+    /// order
+    pub missing: Vec<Mapping>,
+    /// names that resolve in the file but arent in the manifest, in file
+    /// order
+    pub extra: Vec<Mapping>,
+    /// names in both that resolve somewhere different
+    pub mismatched: Vec<Mismatch>,
+}
+
+impl DriftReport {
+    /// true when the file already matches the manifest
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl HostsFile {
+    /// compare this file against `desired`, the source of truth. anything
+    /// only in this file is `extra`, so a manifest that only covers some
+    /// names will list the rest. look at `missing` and `mismatched` alone
+    /// when you dont care about those
+    pub fn drift(&self, desired: &HostsFile) -> DriftReport {
+        let d = diff(self, desired);
+        DriftReport {
+            missing: d.added,
+            extra: d.removed,
+            mismatched: d
+                .changed
+                .into_iter()
+                .map(|c| Mismatch {
+                    name: c.name,
+                    actual: c.old,
+                    desired: c.new,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// knobs for `HostsFile::converge`
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConvergeOptions {
+    /// work out the edits without making them
+    pub dry_run: bool,
+    /// take out names the manifest doesnt have. off by default so a
+    /// manifest can cover just the names it cares about
+    pub remove_extra: bool,
+}
+
+/// one change `converge` made, or would make on a dry run
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Edit {
+    Added(Mapping),
+    Changed(Mismatch),
+    Removed(Mapping),
+}
+
+impl HostsFile {
+    /// bring this file in line with `desired` touching as little as
+    /// possible. comments, blank lines and records the manifest doesnt
+    /// mention stay as they are
+    ///
+    /// names that need to move and make up a whole line get that line
+    /// repointed where it sits, the rest are pulled off their lines and
+    /// added at the end grouped the way the manifest groups them. gives
+    /// back every edit, manifest order first and removals last
+    pub fn converge(
+        &mut self,
+        desired: &HostsFile,
+        options: &ConvergeOptions,
+    ) -> Result<Vec<Edit>, RecordError> {
+        if options.dry_run {
+            let options = ConvergeOptions {
+                dry_run: false,
+                ..options.clone()
+            };
+            return self.clone().converge(desired, &options);
+        }
+
+        let mut edits = Vec::new();
+        for record in desired.records() {
+            let mut names = Vec::new();
+            for name in record.names() {
+                // shadowed in the manifest, it doesnt resolve through here
+                if !desired
+                    .record_for(name)
+                    .is_some_and(|r| std::ptr::eq(r, record))
+                {
+                    continue;
+                }
+                let mapping = Mapping {
+                    name: name.clone(),
+                    addr: record.addr(),
+                };
+                match self.lookup(name) {
+                    Some(actual) if actual == record.addr() => continue,
+                    Some(actual) => edits.push(Edit::Changed(Mismatch {
+                        name: name.clone(),
+                        actual,
+                        desired: record.addr(),
+                    })),
+                    None => edits.push(Edit::Added(mapping)),
+                }
+                names.push(name.as_str());
+            }
+            if !names.is_empty() {
+                self.move_names(record, &names)?;
+            }
+        }
+
+        if options.remove_extra {
+            for extra in self.drift(desired).extra {
+                self.remove_by_name(&extra.name);
+                edits.push(Edit::Removed(extra));
+            }
+        }
+        Ok(edits)
+    }
+
+    /// point `names` off `record` at its address, keeping them together
+    fn move_names(&mut self, record: &Record, names: &[&str]) -> Result<(), RecordError> {
+        let mut wanted = record.clone();
+        wanted.names = names.iter().map(|n| n.to_string()).collect();
+        wanted.source = None;
+        if let Some(policy) = self.policy() {
+            policy.check(&wanted)?;
+        }
+
+        let mut folded = names.iter().map(|n| fold_name(n)).collect::<Vec<_>>();
+        folded.sort();
+        let same_names = |r: &Record| {
+            let mut have = r.names().iter().map(|n| fold_name(n)).collect::<Vec<_>>();
+            have.sort();
+            have == folded
+        };
+        self.edit(|doc| {
+            let lines = doc.lines_mut();
+            let in_place = lines
+                .iter()
+                .position(|line| matches!(line, Line::Record(l) if same_names(l.record())));
+            match in_place {
+                Some(i) => {
+                    if let Line::Record(line) = &mut lines[i] {
+                        let mut moved = line.record().clone();
+                        moved.addr = wanted.addr;
+                        moved.scope = wanted.scope;
+                        line.set_record(moved);
+                    }
+                    strip_names(lines, names, Some(i));
+                }
+                None => {
+                    strip_names(lines, names, None);
+                    doc.push(Line::Record(RecordLine::new(wanted)));
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// `+`, `~` and `-` like HostsDiff
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Edit::Added(m) => write!(f, "+ {} {}", m.addr, m.name),
+            Edit::Changed(m) => write!(f, "~ {} {} -> {}", m.name, m.actual, m.desired),
+            Edit::Removed(m) => write!(f, "- {} {}", m.addr, m.name),
+        }
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for m in &self.missing {
+            writeln!(f, "missing {} {}", m.addr, m.name)?;
+        }
+        for m in &self.extra {
+            writeln!(f, "extra {} {}", m.addr, m.name)?;
+        }
+        for m in &self.mismatched {
+            writeln!(
+                f,
+                "mismatched {} is {}, wants {}",
+                m.name, m.actual, m.desired
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drift_from_manifest() {
+        let current: HostsFile = "127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.7 old-cache\n"
+            .parse()
+            .unwrap();
+        let desired: HostsFile = "127.0.0.1 localhost\n10.0.0.6 DB\n10.0.0.8 cache\n"
+            .parse()
+            .unwrap();
+        let report = current.drift(&desired);
+        assert_eq!(
+            report.missing,
+            [Mapping {
+                name: "cache".into(),
+                addr: "10.0.0.8".parse().unwrap()
+            }]
+        );
+        assert_eq!(report.extra[0].name, "old-cache");
+        assert_eq!(
+            report.mismatched,
+            [Mismatch {
+                name: "db".into(),
+                actual: "10.0.0.5".parse().unwrap(),
+                desired: "10.0.0.6".parse().unwrap()
+            }]
+        );
+        assert_eq!(
+            report.to_string(),
+            "missing 10.0.0.8 cache\nextra 10.0.0.7 old-cache\nmismatched db is 10.0.0.5, wants 10.0.0.6\n"
+        );
+        assert!(desired.drift(&desired.clone()).is_empty());
+    }
+
+    #[test]
+    fn converge_to_manifest() {
+        let src = "# managed by hand\n\
+            127.0.0.1 localhost\n\
+            10.0.0.5 db db.lan # primary\n\
+            10.0.0.7 cache old-cache\n\
+            192.168.1.9 printer\n";
+        let desired: HostsFile = "127.0.0.1 localhost\n\
+            10.0.0.6 db db.lan\n\
+            10.0.0.8 cache\n\
+            10.0.0.9 queue queue.lan\n"
+            .parse()
+            .unwrap();
+
+        let mut hosts: HostsFile = src.parse().unwrap();
+        let dry = ConvergeOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let planned = hosts.converge(&desired, &dry).unwrap();
+        assert_eq!(hosts.to_string(), src);
+
+        let edits = hosts
+            .converge(&desired, &ConvergeOptions::default())
+            .unwrap();
+        assert_eq!(edits, planned);
+        assert_eq!(
+            edits.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            [
+                "~ db 10.0.0.5 -> 10.0.0.6",
+                "~ db.lan 10.0.0.5 -> 10.0.0.6",
+                "~ cache 10.0.0.7 -> 10.0.0.8",
+                "+ 10.0.0.9 queue",
+                "+ 10.0.0.9 queue.lan",
+            ]
+        );
+        // db moved in place and kept its comment, cache had to split off
+        assert_eq!(
+            hosts.to_string(),
+            "# managed by hand\n\
+             127.0.0.1 localhost\n\
+             10.0.0.6\tdb db.lan # primary\n\
+             10.0.0.7\told-cache\n\
+             192.168.1.9 printer\n\
+             10.0.0.8\tcache\n\
+             10.0.0.9\tqueue queue.lan\n"
+        );
+        assert!(hosts
+            .converge(&desired, &Default::default())
+            .unwrap()
+            .is_empty());
+
+        let options = ConvergeOptions {
+            remove_extra: true,
+            ..Default::default()
+        };
+        let edits = hosts.converge(&desired, &options).unwrap();
+        assert_eq!(edits.len(), 2);
+        assert!(hosts.drift(&desired).is_empty());
+    }
+}
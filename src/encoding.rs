@@ -0,0 +1,177 @@
+use crate::parser::ParserError;
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16_LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16_BE_BOM: &[u8] = &[0xfe, 0xff];
+
+/// the text encoding a hosts file was read in
+///
+/// notepad on windows likes to add a BOM, and older tools sometimes save
+/// the hosts file as utf-16. we only go by the BOM, a file without one is
+/// always treated as utf-8
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// utf-8 with the `EF BB BF` byte order mark up front
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// the line terminator a hosts file uses
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+/// what to do with lines that arent valid utf-8, say a comment someone
+/// saved in latin-1. utf-16 files still have to decode cleanly
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum InvalidUtf8 {
+    /// fail the whole parse, like it always has
+    #[default]
+    Error,
+    /// swap the bad bytes for U+FFFD and carry on. the line is parsed as
+    /// usual, so a record with a mangled comment still counts
+    Lossy,
+    /// treat the line as broken and warn about it, or fail on it in
+    /// strict mode
+    Skip,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+impl Encoding {
+    /// how many bytes of BOM sit in front of the text
+    pub(crate) fn bom_len(&self) -> usize {
+        match self {
+            Encoding::Utf8 => 0,
+            Encoding::Utf8Bom => UTF8_BOM.len(),
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+        }
+    }
+}
+
+/// the line ending `text` uses, none when it is a single line. a file
+/// only counts as crlf when every line ends that way, otherwise the \r on
+/// the lines that have one stays part of the line and writing the file
+/// back doesnt add any
+pub(crate) fn detect_line_ending(text: &str) -> Option<LineEnding> {
+    let first = text.find('\n')?;
+    let crlf = text[..first].ends_with('\r')
+        && text
+            .match_indices('\n')
+            .all(|(i, _)| text[..i].ends_with('\r'));
+    Some(match crlf {
+        true => LineEnding::CrLf,
+        false => LineEnding::Lf,
+    })
+}
+
+/// turn the raw bytes of a file into text, BOM stripped off
+pub(crate) fn decode(bytes: &[u8]) -> Result<(String, Encoding), ParserError> {
+    if let Some(rest) = bytes.strip_prefix(UTF16_LE_BOM) {
+        return Ok((decode_utf16(rest, u16::from_le_bytes)?, Encoding::Utf16Le));
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16_BE_BOM) {
+        return Ok((decode_utf16(rest, u16::from_be_bytes)?, Encoding::Utf16Be));
+    }
+
+    let (rest, encoding) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(rest) => (rest, Encoding::Utf8Bom),
+        None => (bytes, Encoding::Utf8),
+    };
+    let text = std::str::from_utf8(rest).map_err(|_| ParserError::InvalidEncoding("utf-8"))?;
+    Ok((text.to_string(), encoding))
+}
+
+/// the bytes after a utf-8 BOM, if there is one. none for utf-16 files
+pub(crate) fn utf8_body(bytes: &[u8]) -> Option<(&[u8], Encoding)> {
+    if bytes.starts_with(UTF16_LE_BOM) || bytes.starts_with(UTF16_BE_BOM) {
+        return None;
+    }
+    Some(match bytes.strip_prefix(UTF8_BOM) {
+        Some(rest) => (rest, Encoding::Utf8Bom),
+        None => (bytes, Encoding::Utf8),
+    })
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> Result<String, ParserError> {
+    if !bytes.len().is_multiple_of(2) {
+        return Err(ParserError::InvalidEncoding("utf-16"));
+    }
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|_| ParserError::InvalidEncoding("utf-16"))
+}
+
+/// turn text back into bytes in `encoding`, BOM included
+pub(crate) fn encode(text: &str, encoding: Encoding) -> Vec<u8> {
+    match encoding {
+        Encoding::Utf8 => text.as_bytes().to_vec(),
+        Encoding::Utf8Bom => [UTF8_BOM, text.as_bytes()].concat(),
+        Encoding::Utf16Le => {
+            let mut out = UTF16_LE_BOM.to_vec();
+            out.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            out
+        }
+        Encoding::Utf16Be => {
+            let mut out = UTF16_BE_BOM.to_vec();
+            out.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_detects_bom() {
+        let (text, enc) = decode(b"\xef\xbb\xbf127.0.0.1 localhost").unwrap();
+        assert_eq!(enc, Encoding::Utf8Bom);
+        assert_eq!(text, "127.0.0.1 localhost");
+
+        let (text, enc) = decode(b"127.0.0.1 localhost").unwrap();
+        assert_eq!(enc, Encoding::Utf8);
+        assert_eq!(text, "127.0.0.1 localhost");
+    }
+
+    #[test]
+    fn mixed_endings_are_lf() {
+        assert_eq!(detect_line_ending("a\r\nb\r\n"), Some(LineEnding::CrLf));
+        assert_eq!(detect_line_ending("a\r\nb\nc\r\n"), Some(LineEnding::Lf));
+        assert_eq!(detect_line_ending("a\nb\r\n"), Some(LineEnding::Lf));
+        assert_eq!(detect_line_ending("a\r"), None);
+    }
+
+    #[test]
+    fn utf16_round_trip() {
+        for enc in [Encoding::Utf16Le, Encoding::Utf16Be] {
+            let bytes = encode("::1 localhost\r\n", enc);
+            assert_eq!(
+                decode(&bytes).unwrap(),
+                ("::1 localhost\r\n".to_string(), enc)
+            );
+        }
+    }
+
+    #[test]
+    fn bad_utf16() {
+        assert!(decode(b"\xff\xfe\x31").is_err());
+    }
+}
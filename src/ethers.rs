@@ -0,0 +1,176 @@
+//! /etc/ethers, mac addresses to host names or addresses, the file rarp
+//! and `ether_ntohost(3)` read
+//!
+//! ```text
+//! 8:0:20:1:14:3e   printer
+//! 00:1b:21:3a:4f:10  10.0.0.5  # the nas
+//! ```
+//!
+//! lines split the same way hosts lines do, with the mac where the
+//! address would be
+
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::net::IpAddr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::line;
+
+#[cfg(feature = "std-fs")]
+pub const PATH: &str = "/etc/ethers";
+
+#[derive(Error, Debug)]
+pub enum EthersError {
+    #[error("line {line}: {mac:?} is not a mac address")]
+    InvalidMac { line: usize, mac: String },
+    #[error("line {line}: {mac} has no host")]
+    MissingHost { line: usize, mac: MacAddr },
+    #[cfg(feature = "std-fs")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// a 48 bit ethernet address
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MacAddr(pub [u8; 6]);
+
+/// `8:0:20:1:14:3e` or `08-00-20-01-14-3e`, one or two hex digits a part
+impl FromStr for MacAddr {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let separator = if s.contains('-') { '-' } else { ':' };
+        let mut out = [0u8; 6];
+        let mut count = 0;
+        for part in s.split(separator) {
+            if count == 6 || part.is_empty() || part.len() > 2 {
+                return Err(());
+            }
+            out[count] = u8::from_str_radix(part, 16).map_err(|_| ())?;
+            count += 1;
+        }
+        match count {
+            6 => Ok(MacAddr(out)),
+            _ => Err(()),
+        }
+    }
+}
+
+/// lowercase with two digits a part, `08:00:20:01:14:3e`
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ether {
+    pub mac: MacAddr,
+    /// a host name, or an address written out
+    pub host: String,
+    pub comment: Option<String>,
+}
+
+impl Ether {
+    /// the host when it is an address rather than a name
+    pub fn addr(&self) -> Option<IpAddr> {
+        self.host.parse().ok()
+    }
+}
+
+/// every entry in `text`, in file order
+pub fn parse(text: &str) -> Result<Vec<Ether>, EthersError> {
+    let mut ethers = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let Some(fields) = line::split(raw) else {
+            continue;
+        };
+        let line = i + 1;
+        let mac = fields.addr.parse().map_err(|_| EthersError::InvalidMac {
+            line,
+            mac: fields.addr.to_string(),
+        })?;
+        let Some(host) = fields.names.split_whitespace().next() else {
+            return Err(EthersError::MissingHost { line, mac });
+        };
+        ethers.push(Ether {
+            mac,
+            host: host.to_string(),
+            comment: fields.comment.map(str::to_string),
+        });
+    }
+    Ok(ethers)
+}
+
+#[cfg(feature = "std-fs")]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Vec<Ether>, EthersError> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// the host for `mac`, what `ether_ntohost` gives back
+pub fn host_of(ethers: &[Ether], mac: MacAddr) -> Option<&str> {
+    ethers
+        .iter()
+        .find(|e| e.mac == mac)
+        .map(|e| e.host.as_str())
+}
+
+/// the mac for `host`, what `ether_hostton` gives back. names are
+/// compared ignoring case
+pub fn mac_of(ethers: &[Ether], host: &str) -> Option<MacAddr> {
+    ethers
+        .iter()
+        .find(|e| e.host.eq_ignore_ascii_case(host))
+        .map(|e| e.mac)
+}
+
+impl fmt::Display for Ether {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}", self.mac, self.host)?;
+        if let Some(comment) = &self.comment {
+            write!(f, " # {comment}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ethers_file() {
+        let ethers = parse(
+            "# ethers\n\
+             8:0:20:1:14:3e   printer\n\
+             00-1B-21-3A-4F-10  10.0.0.5  # the nas\n",
+        )
+        .unwrap();
+        let printer = MacAddr([8, 0, 0x20, 1, 0x14, 0x3e]);
+        assert_eq!(ethers[0].mac, printer);
+        assert_eq!(host_of(&ethers, printer), Some("printer"));
+        assert_eq!(mac_of(&ethers, "PRINTER"), Some(printer));
+        assert_eq!(ethers[1].addr(), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(
+            ethers[1].to_string(),
+            "00:1b:21:3a:4f:10\t10.0.0.5 # the nas"
+        );
+
+        assert!(matches!(
+            parse("8:0:20:1:14 printer\n"),
+            Err(EthersError::InvalidMac { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse("8:0:20:1:14:3e\n"),
+            Err(EthersError::MissingHost { line: 1, .. })
+        ));
+        assert!("8:0:20:1:14:3e:7".parse::<MacAddr>().is_err());
+        assert!("8:0:20:1:144:3e".parse::<MacAddr>().is_err());
+    }
+}
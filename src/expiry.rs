@@ -0,0 +1,194 @@
+//! records that go stale on their own, for the temporary overrides during
+//! a migration that nobody remembers to take out again
+//!
+//! ```text
+//! 10.0.0.9 api.example.com # expires: 2025-07-01T00:00:00Z
+//! ```
+//!
+//! the expiry is an rfc 3339 timestamp in a comment tag. a bare date like
+//! `2025-07-01` means midnight utc
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::document::Line;
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+const EXPIRES: &str = "expires";
+
+impl Record {
+    /// when the `expires:` tag says this record stops being good. none
+    /// when there is no tag or it isnt a timestamp we can read
+    pub fn expires(&self) -> Option<SystemTime> {
+        parse_timestamp(self.tag(EXPIRES)?)
+    }
+
+    pub fn set_expires(&mut self, expires: Option<SystemTime>) {
+        let value = expires.map(format_timestamp);
+        // a formatted timestamp is always a valid tag value
+        let _ = self.set_tag(EXPIRES, value.as_deref());
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires().is_some_and(|expires| expires <= now)
+    }
+}
+
+impl HostsFile {
+    /// drop every record that expired at or before `now`, gives back how
+    /// many lines went away. records without an expiry are never touched
+    pub fn purge_expired(&mut self, now: SystemTime) -> usize {
+        self.edit(|doc| {
+            let lines = doc.lines_mut();
+            let before = lines.len();
+            lines.retain(|line| !matches!(line, Line::Record(l) if l.record().is_expired(now)));
+            before - lines.len()
+        })
+    }
+}
+
+/// days since 1970-01-01 for a date in the proleptic gregorian calendar
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// `days_from_civil` backwards
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn number(s: &str, digits: usize) -> Option<i64> {
+    match s.len() == digits && s.bytes().all(|b| b.is_ascii_digit()) {
+        true => s.parse().ok(),
+        false => None,
+    }
+}
+
+/// `2025-07-01`, `2025-07-01T00:00:00Z`, `2025-07-01T02:00:00+02:00`,
+/// fractions of a second are dropped
+fn parse_timestamp(s: &str) -> Option<SystemTime> {
+    let (date, time) = match s.split_once(['T', 't']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (s, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let year = number(parts.next()?, 4)?;
+    let month = number(parts.next()?, 2)?;
+    let day = number(parts.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut secs = days_from_civil(year, month, day) * 86_400;
+
+    if let Some(time) = time {
+        let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+            Some(i) => time.split_at(i),
+            None => return None,
+        };
+        let clock = clock.split('.').next()?;
+        let mut hms = clock.splitn(3, ':');
+        let hour = number(hms.next()?, 2)?;
+        let minute = number(hms.next()?, 2)?;
+        let second = number(hms.next()?, 2)?;
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+        secs += hour * 3600 + minute * 60 + second;
+        if let Some(rest) = offset.strip_prefix(['+', '-']) {
+            let (h, m) = rest.split_once(':')?;
+            let shift = number(h, 2)? * 3600 + number(m, 2)? * 60;
+            secs -= if offset.starts_with('+') {
+                shift
+            } else {
+                -shift
+            };
+        } else if offset.len() != 1 {
+            return None;
+        }
+    }
+
+    match secs >= 0 {
+        true => Some(UNIX_EPOCH + Duration::from_secs(secs as u64)),
+        false => UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs())),
+    }
+}
+
+/// rfc 3339 in utc, to the second
+fn format_timestamp(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let rem = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem / 60 % 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(
+            parse_timestamp("2025-07-01T00:00:00Z"),
+            Some(at(1_751_328_000))
+        );
+        assert_eq!(parse_timestamp("2025-07-01"), Some(at(1_751_328_000)));
+        assert_eq!(
+            parse_timestamp("2025-07-01T02:30:00.5+02:30"),
+            Some(at(1_751_328_000))
+        );
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00-01:00"), Some(at(3600)));
+        assert_eq!(parse_timestamp("2025-13-01"), None);
+        assert_eq!(parse_timestamp("2025-07-01T00:00:00"), None);
+        assert_eq!(parse_timestamp("soon"), None);
+        for secs in [0, 951_782_400, 1_751_328_000, 4_102_444_799] {
+            assert_eq!(parse_timestamp(&format_timestamp(at(secs))), Some(at(secs)));
+        }
+        assert_eq!(format_timestamp(at(951_782_400)), "2000-02-29T00:00:00Z");
+    }
+
+    #[test]
+    fn purge() {
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n\
+            10.0.0.9 api.example.com # migration expires: 2025-07-01T00:00:00Z\n\
+            10.0.0.8 web.example.com # expires: 2030-01-01\n\
+            10.0.0.7 odd # expires: whenever\n"
+            .parse()
+            .unwrap();
+        let now = parse_timestamp("2026-01-01").unwrap();
+        assert!(hosts.records()[1].is_expired(now));
+        assert_eq!(hosts.purge_expired(now), 1);
+        assert_eq!(hosts.purge_expired(now), 0);
+        assert_eq!(hosts.lookup("api.example.com"), None);
+        assert!(hosts.lookup("odd").is_some());
+
+        let mut record = hosts.records()[0].clone();
+        record.set_expires(Some(now));
+        assert_eq!(record.comment(), Some("expires: 2026-01-01T00:00:00Z"));
+        assert_eq!(record.expires(), Some(now));
+        record.set_expires(None);
+        assert_eq!(record.comment(), None);
+    }
+}
@@ -0,0 +1,560 @@
+//! writing records out in the config formats of other resolvers
+
+use std::collections::HashSet;
+use std::io;
+use std::net::IpAddr;
+
+use crate::hostname::fold_name;
+use crate::record::Record;
+
+/// which kinds of address records to write out
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AddrFamily {
+    #[default]
+    Both,
+    /// just the A records
+    V4,
+    /// just the AAAA records
+    V6,
+}
+
+impl AddrFamily {
+    fn includes(&self, addr: IpAddr) -> bool {
+        match self {
+            AddrFamily::Both => true,
+            AddrFamily::V4 => addr.is_ipv4(),
+            AddrFamily::V6 => addr.is_ipv6(),
+        }
+    }
+}
+
+fn rr_type(addr: IpAddr) -> &'static str {
+    match addr {
+        IpAddr::V4(_) => "A",
+        IpAddr::V6(_) => "AAAA",
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct UnboundOptions {
+    pub family: AddrFamily,
+    /// turn blocking entries into `redirect` zones so every subdomain of a
+    /// blocked name gets the sink address too, not just the name itself
+    pub redirect_blocked: bool,
+}
+
+/// dnsmasq `address=/name/ip` lines, one per record with all of its names
+/// packed onto the line. note dnsmasq matches subdomains of these names
+/// too, which is usually what you want for blocking
+pub fn dnsmasq<'a, I>(records: I) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write_dnsmasq(&mut out, records).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `dnsmasq` but straight into a writer
+pub fn write_dnsmasq<'a, W, I>(mut w: W, records: I) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    for record in records {
+        write!(w, "address=/")?;
+        for name in record.names() {
+            write!(w, "{name}/")?;
+        }
+        writeln!(w, "{}", record.addr())?;
+    }
+    Ok(())
+}
+
+/// unbound `local-data` lines, meant to be pulled into unbound.conf with
+/// an `include:` under the `server:` section
+pub fn unbound<'a, I>(records: I, options: &UnboundOptions) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write_unbound(&mut out, records, options).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `unbound` but straight into a writer
+pub fn write_unbound<'a, W, I>(mut w: W, records: I, options: &UnboundOptions) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    for record in records {
+        let addr = record.addr();
+        if !options.family.includes(addr) {
+            continue;
+        }
+        let redirect = options.redirect_blocked && record.is_block_entry();
+        for name in record.names() {
+            let name = name.trim_end_matches('.');
+            if redirect {
+                writeln!(w, "local-zone: \"{name}.\" redirect")?;
+            }
+            writeln!(w, "local-data: \"{name}. {} {addr}\"", rr_type(addr))?;
+        }
+    }
+    Ok(())
+}
+
+/// a BIND style zone file for `origin`, A and AAAA records for every name
+/// that lives in the zone. dotless names are taken to be relative to the
+/// origin, anything outside of it gets left out
+pub fn zonefile<'a, I>(records: I, origin: &str, ttl: u32) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write_zonefile(&mut out, records, origin, ttl).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `zonefile` but straight into a writer
+pub fn write_zonefile<'a, W, I>(mut w: W, records: I, origin: &str, ttl: u32) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    let origin = bare(origin);
+    writeln!(w, "$ORIGIN {origin}.")?;
+    writeln!(w, "$TTL {ttl}")?;
+    // `db` and `db.<origin>` come out as the same owner, and owners dont
+    // care about case, so the same rr could go out more than once
+    let mut written = HashSet::new();
+    for record in records {
+        let addr = record.addr();
+        for name in record.names() {
+            let Some(owner) = relative(name, &origin) else {
+                continue;
+            };
+            if written.insert((fold_name(&owner).into_owned(), addr)) {
+                writeln!(w, "{owner}\tIN\t{}\t{addr}", rr_type(addr))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// PTR records for the reverse zone `origin` (something like
+/// `0.10.in-addr.arpa`), pointing each address back at its first name.
+/// dotless names get `domain` tacked on, blocking entries and addresses
+/// outside of the zone are skipped
+pub fn reverse_zonefile<'a, I>(records: I, origin: &str, domain: &str, ttl: u32) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write_reverse_zonefile(&mut out, records, origin, domain, ttl)
+        .expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `reverse_zonefile` but straight into a writer
+pub fn write_reverse_zonefile<'a, W, I>(
+    mut w: W,
+    records: I,
+    origin: &str,
+    domain: &str,
+    ttl: u32,
+) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    let origin = bare(origin);
+    let domain = bare(domain);
+    writeln!(w, "$ORIGIN {origin}.")?;
+    writeln!(w, "$TTL {ttl}")?;
+    for record in records {
+        if record.is_block_entry() {
+            continue;
+        }
+        let Some(name) = record.primary_name() else {
+            continue;
+        };
+        let Some(owner) = relative(&reverse_name(record.addr()), &origin) else {
+            continue;
+        };
+        let target = bare(name);
+        if target.contains('.') || domain.is_empty() {
+            writeln!(w, "{owner}\tIN\tPTR\t{target}.")?;
+        } else {
+            writeln!(w, "{owner}\tIN\tPTR\t{target}.{domain}.")?;
+        }
+    }
+    Ok(())
+}
+
+/// `~/.ssh/config` blocks, a `Host` line with the primary name and its
+/// aliases and a `HostName` with the address. blocking entries are left
+/// out and so are names an earlier record already took, ssh goes with the
+/// first block that matches the same way lookups go with the first record
+pub fn ssh_config<'a, I>(records: I) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut out = Vec::new();
+    write_ssh_config(&mut out, records).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `ssh_config` but straight into a writer
+pub fn write_ssh_config<'a, W, I>(mut w: W, records: I) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+{
+    let mut seen = HashSet::new();
+    let mut first = true;
+    for record in records {
+        if record.is_block_entry() {
+            continue;
+        }
+        let names = record
+            .names()
+            .iter()
+            .filter(|name| seen.insert(fold_name(name).into_owned()))
+            .map(|name| bare(name))
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            continue;
+        }
+        if !first {
+            writeln!(w)?;
+        }
+        first = false;
+        writeln!(w, "Host {}", names.join(" "))?;
+        match record.scope() {
+            Some(scope) => writeln!(w, "    HostName {}%{scope}", record.addr())?,
+            None => writeln!(w, "    HostName {}", record.addr())?,
+        }
+    }
+    Ok(())
+}
+
+/// the two ways ansible takes a static inventory
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum InventoryFormat {
+    #[default]
+    Ini,
+    Yaml,
+}
+
+/// an ansible inventory with a host per record, named after its primary
+/// name and with `ansible_host` set to the address. `group` picks the
+/// group each record goes in, `None` leaves it ungrouped. `by_domain` and
+/// `by_tag` cover the usual picks
+///
+/// blocking entries and primary names an earlier record already used are
+/// skipped. groups come out in the order they first show up
+pub fn ansible_inventory<'a, I, F>(records: I, format: InventoryFormat, group: F) -> String
+where
+    I: IntoIterator<Item = &'a Record>,
+    F: FnMut(&Record) -> Option<String>,
+{
+    let mut out = Vec::new();
+    write_ansible_inventory(&mut out, records, format, group).expect("writing to a vec cant fail");
+    String::from_utf8(out).expect("records are always utf-8")
+}
+
+/// `ansible_inventory` but straight into a writer
+pub fn write_ansible_inventory<'a, W, I, F>(
+    mut w: W,
+    records: I,
+    format: InventoryFormat,
+    mut group: F,
+) -> io::Result<()>
+where
+    W: io::Write,
+    I: IntoIterator<Item = &'a Record>,
+    F: FnMut(&Record) -> Option<String>,
+{
+    let mut seen = HashSet::new();
+    let mut ungrouped = Vec::new();
+    let mut groups: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for record in records {
+        if record.is_block_entry() {
+            continue;
+        }
+        let Some(name) = record.primary_name() else {
+            continue;
+        };
+        let name = bare(name);
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+        let addr = match record.scope() {
+            Some(scope) => format!("{}%{scope}", record.addr()),
+            None => record.addr().to_string(),
+        };
+        let host = (name, addr);
+        match group(record).map(|g| group_name(&g)) {
+            Some(g) => match groups.iter_mut().find(|(name, _)| *name == g) {
+                Some((_, hosts)) => hosts.push(host),
+                None => groups.push((g, vec![host])),
+            },
+            None => ungrouped.push(host),
+        }
+    }
+
+    match format {
+        InventoryFormat::Ini => {
+            for (name, addr) in &ungrouped {
+                writeln!(w, "{name} ansible_host={addr}")?;
+            }
+            for (i, (group, hosts)) in groups.iter().enumerate() {
+                if i > 0 || !ungrouped.is_empty() {
+                    writeln!(w)?;
+                }
+                writeln!(w, "[{group}]")?;
+                for (name, addr) in hosts {
+                    writeln!(w, "{name} ansible_host={addr}")?;
+                }
+            }
+        }
+        InventoryFormat::Yaml => {
+            writeln!(w, "all:")?;
+            let yaml_hosts = |w: &mut W, hosts: &[(String, String)], indent: &str| {
+                writeln!(w, "{indent}hosts:")?;
+                for (name, addr) in hosts {
+                    writeln!(w, "{indent}  {name}:")?;
+                    // quoted, v6 addresses would trip up yaml otherwise
+                    writeln!(w, "{indent}    ansible_host: \"{addr}\"")?;
+                }
+                Ok::<_, io::Error>(())
+            };
+            if !ungrouped.is_empty() {
+                yaml_hosts(&mut w, &ungrouped, "  ")?;
+            }
+            if !groups.is_empty() {
+                writeln!(w, "  children:")?;
+                for (group, hosts) in &groups {
+                    writeln!(w, "    {group}:")?;
+                    yaml_hosts(&mut w, hosts, "      ")?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// group records by the domain their primary name is in, `db.lab.lan`
+/// goes in `lab_lan`. short names get no group
+pub fn by_domain(record: &Record) -> Option<String> {
+    let name = bare(record.primary_name()?);
+    name.split_once('.').map(|(_, domain)| domain.to_string())
+}
+
+/// group records by a comment tag, `# group: web` with `by_tag("group")`
+pub fn by_tag(key: &str) -> impl Fn(&Record) -> Option<String> + '_ {
+    move |record| record.tag(key).map(str::to_string)
+}
+
+/// ansible wants group names to be identifiers
+fn group_name(group: &str) -> String {
+    let name: String = group
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_lowercase(),
+            false => '_',
+        })
+        .collect();
+    match name.starts_with(|c: char| c.is_ascii_digit()) {
+        true => format!("_{name}"),
+        false => name,
+    }
+}
+
+/// lowercase and without the trailing dot
+fn bare(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// where `name` sits under `origin`, `None` when it isnt in the zone
+fn relative(name: &str, origin: &str) -> Option<String> {
+    let name = bare(name);
+    if name == origin {
+        return Some("@".to_string());
+    }
+    match name.strip_suffix(origin) {
+        Some(prefix) if prefix.ends_with('.') => Some(prefix.trim_end_matches('.').to_string()),
+        _ if !name.contains('.') => Some(name),
+        _ => None,
+    }
+}
+
+/// `5.0.0.10.in-addr.arpa` and the nibble format for ipv6
+fn reverse_name(addr: IpAddr) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(v6) => {
+            let mut out = String::with_capacity(72);
+            for byte in v6.octets().iter().rev() {
+                out.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            out.push_str("ip6.arpa");
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts_file::HostsFile;
+
+    const MIXED: &str = "10.0.0.5 db db.lab.\nfe80::5%eth0 db6\n0.0.0.0 ads.example.com\n";
+
+    #[test]
+    fn dnsmasq_lines() {
+        let hosts: HostsFile =
+            "# lab\n10.0.0.5 db db.lab\n::1 localhost\n0.0.0.0 ads.example.com\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            dnsmasq(hosts.records()),
+            "address=/db/db.lab/10.0.0.5\naddress=/localhost/::1\naddress=/ads.example.com/0.0.0.0\n"
+        );
+    }
+
+    #[test]
+    fn unbound_local_data() {
+        let hosts: HostsFile = MIXED.parse().unwrap();
+        assert_eq!(
+            unbound(hosts.records(), &UnboundOptions::default()),
+            "local-data: \"db. A 10.0.0.5\"\n\
+            local-data: \"db.lab. A 10.0.0.5\"\n\
+            local-data: \"db6. AAAA fe80::5\"\n\
+            local-data: \"ads.example.com. A 0.0.0.0\"\n"
+        );
+    }
+
+    #[test]
+    fn unbound_options() {
+        let hosts: HostsFile = MIXED.parse().unwrap();
+        let v6 = UnboundOptions {
+            family: AddrFamily::V6,
+            ..Default::default()
+        };
+        assert_eq!(
+            unbound(hosts.records(), &v6),
+            "local-data: \"db6. AAAA fe80::5\"\n"
+        );
+
+        let redirect = UnboundOptions {
+            family: AddrFamily::V4,
+            redirect_blocked: true,
+        };
+        assert!(unbound(hosts.records(), &redirect).ends_with(
+            "local-zone: \"ads.example.com.\" redirect\n\
+            local-data: \"ads.example.com. A 0.0.0.0\"\n"
+        ));
+    }
+
+    #[test]
+    fn ssh_host_blocks() {
+        let hosts: HostsFile = "10.0.0.5 db db.lan\n\
+            0.0.0.0 ads.example\n\
+            fe80::1%eth0 router\n\
+            10.0.0.6 DB cache\n\
+            10.0.0.7 db.lan\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            ssh_config(hosts.records()),
+            "Host db db.lan\n    HostName 10.0.0.5\n\n\
+             Host router\n    HostName fe80::1%eth0\n\n\
+             Host cache\n    HostName 10.0.0.6\n"
+        );
+    }
+
+    #[test]
+    fn ansible_inventories() {
+        let hosts: HostsFile = "10.0.0.5 db.lab.lan db # group: data\n\
+            10.0.0.6 web.lab.lan # group: web\n\
+            0.0.0.0 ads.example\n\
+            fe80::1%eth0 router\n\
+            10.0.0.7 db.lab.lan\n\
+            10.0.0.8 cache.lab.lan # group: data\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            ansible_inventory(hosts.records(), InventoryFormat::Ini, by_tag("group")),
+            "router ansible_host=fe80::1%eth0\n\n\
+             [data]\n\
+             db.lab.lan ansible_host=10.0.0.5\n\
+             cache.lab.lan ansible_host=10.0.0.8\n\n\
+             [web]\n\
+             web.lab.lan ansible_host=10.0.0.6\n"
+        );
+        assert_eq!(
+            ansible_inventory(hosts.records(), InventoryFormat::Yaml, by_domain),
+            "all:\n\
+             \x20 hosts:\n\
+             \x20   router:\n\
+             \x20     ansible_host: \"fe80::1%eth0\"\n\
+             \x20 children:\n\
+             \x20   lab_lan:\n\
+             \x20     hosts:\n\
+             \x20       db.lab.lan:\n\
+             \x20         ansible_host: \"10.0.0.5\"\n\
+             \x20       web.lab.lan:\n\
+             \x20         ansible_host: \"10.0.0.6\"\n\
+             \x20       cache.lab.lan:\n\
+             \x20         ansible_host: \"10.0.0.8\"\n"
+        );
+        assert_eq!(group_name("10.lan"), "_10_lan");
+    }
+
+    #[test]
+    fn zonefile_forward() {
+        let hosts: HostsFile = "10.0.0.5 db db.lab.example.com\n\
+            10.0.0.1 lab.example.com\n\
+            10.0.0.5 DB\n\
+            fe80::5 db6.lab.example.com.\n\
+            10.0.0.9 elsewhere.example.org\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            zonefile(hosts.records(), "lab.example.com.", 3600),
+            "$ORIGIN lab.example.com.\n\
+            $TTL 3600\n\
+            db\tIN\tA\t10.0.0.5\n\
+            @\tIN\tA\t10.0.0.1\n\
+            db6\tIN\tAAAA\tfe80::5\n"
+        );
+    }
+
+    #[test]
+    fn zonefile_reverse() {
+        let hosts: HostsFile = "10.0.0.5 db\n\
+            10.1.0.5 other.lab.example.com\n\
+            10.0.3.7 web.example.org\n\
+            0.0.0.0 ads.example.com\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            reverse_zonefile(hosts.records(), "10.in-addr.arpa", "lab.example.com", 300),
+            "$ORIGIN 10.in-addr.arpa.\n\
+            $TTL 300\n\
+            5.0.0\tIN\tPTR\tdb.lab.example.com.\n\
+            5.0.1\tIN\tPTR\tother.lab.example.com.\n\
+            7.3.0\tIN\tPTR\tweb.example.org.\n"
+        );
+        assert_eq!(
+            reverse_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+}
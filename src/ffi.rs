@@ -0,0 +1,383 @@
+//! a C ABI for tools that arent written in rust
+//!
+//! the header lives in `include/hosts_digger.h` and is written by hand,
+//! change it along with anything in here. a `HostsFile *` is opaque on
+//! the C side, get one from `hosts_parse_file` or `hosts_parse_str` and
+//! hand it back to `hosts_free` when done
+//!
+//! functions that can fail leave a message behind for
+//! `hosts_last_error`, one per thread
+//!
+//! the shared library C links against isnt built by default, a cdylib
+//! cant be built without std and would break the no_std build. get one
+//! with `cargo rustc --release --lib --features ffi --crate-type cdylib`
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::ptr;
+
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: impl ToString) {
+    // a message with a nul in it would get cut short anyway
+    let message = message.to_string().replace('\0', " ");
+    LAST_ERROR.with(|e| *e.borrow_mut() = CString::new(message).ok());
+}
+
+/// the utf-8 string behind `ptr`, or an error message on the way out
+unsafe fn arg<'a>(ptr: *const c_char, what: &str) -> Option<&'a str> {
+    if ptr.is_null() {
+        set_error(format!("{what} is null"));
+        return None;
+    }
+    match CStr::from_ptr(ptr).to_str() {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_error(format!("{what} is not valid utf-8"));
+            None
+        }
+    }
+}
+
+/// open and parse the hosts file at `path`, null on failure
+///
+/// # Safety
+///
+/// `path` has to be null or a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hosts_parse_file(path: *const c_char) -> *mut HostsFile {
+    let Some(path) = arg(path, "path") else {
+        return ptr::null_mut();
+    };
+    match HostsFile::open(path) {
+        Ok(hosts) => Box::into_raw(Box::new(hosts)),
+        Err(e) => {
+            set_error(format!("{path}: {e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// parse hosts file contents out of a string, null on failure
+///
+/// # Safety
+///
+/// `text` has to be null or a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hosts_parse_str(text: *const c_char) -> *mut HostsFile {
+    let Some(text) = arg(text, "text") else {
+        return ptr::null_mut();
+    };
+    match HostsFile::parse_str(text) {
+        Ok(hosts) => Box::into_raw(Box::new(hosts)),
+        Err(e) => {
+            set_error(e);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// look `name` up and write the address it resolves to into `buf` as a
+/// nul terminated string, the way snprintf does. gives back how long the
+/// address is without the nul, so anything at or past `len` means `buf`
+/// was too small and nothing was written. -1 when the name isnt there or
+/// an argument is bad
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet, `name`
+/// has to be a nul terminated string, and `buf` has to point at `len`
+/// writable bytes unless `len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn hosts_lookup(
+    hosts: *const HostsFile,
+    name: *const c_char,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    let Some(hosts) = hosts.as_ref() else {
+        set_error("hosts is null");
+        return -1;
+    };
+    let Some(name) = arg(name, "name") else {
+        return -1;
+    };
+    let Some(addr) = hosts.lookup(name) else {
+        set_error(format!("{name} is not in the hosts file"));
+        return -1;
+    };
+    let addr = addr.to_string();
+    if addr.len() < len && !buf.is_null() {
+        ptr::copy_nonoverlapping(addr.as_ptr().cast(), buf, addr.len());
+        *buf.add(addr.len()) = 0;
+    }
+    addr.len() as c_int
+}
+
+/// point `name` at `addr`, see `HostsFile::set`. 0 on success, -1 on
+/// failure
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet, `name`
+/// and `addr` have to be nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn hosts_set(
+    hosts: *mut HostsFile,
+    name: *const c_char,
+    addr: *const c_char,
+) -> c_int {
+    let Some(hosts) = hosts.as_mut() else {
+        set_error("hosts is null");
+        return -1;
+    };
+    let (Some(name), Some(addr)) = (arg(name, "name"), arg(addr, "addr")) else {
+        return -1;
+    };
+    let addr = match addr.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            set_error(format!("bad address {addr:?}: {e}"));
+            return -1;
+        }
+    };
+    match hosts.set(name, addr) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// parse `line` as a record and add it to the end of the file. 0 on
+/// success, -1 on failure
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet, `line`
+/// has to be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hosts_add(hosts: *mut HostsFile, line: *const c_char) -> c_int {
+    let Some(hosts) = hosts.as_mut() else {
+        set_error("hosts is null");
+        return -1;
+    };
+    let Some(line) = arg(line, "line") else {
+        return -1;
+    };
+    match line.parse::<Record>() {
+        Ok(record) => {
+            hosts.add_record(record);
+            0
+        }
+        Err(e) => {
+            set_error(e);
+            -1
+        }
+    }
+}
+
+/// take `name` off every line its on. 1 when it was there, 0 when it
+/// wasnt, -1 on a bad argument
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet, `name`
+/// has to be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hosts_remove(hosts: *mut HostsFile, name: *const c_char) -> c_int {
+    let Some(hosts) = hosts.as_mut() else {
+        set_error("hosts is null");
+        return -1;
+    };
+    let Some(name) = arg(name, "name") else {
+        return -1;
+    };
+    hosts.remove_by_name(name) as c_int
+}
+
+/// write the file out to `path` atomically. 0 on success, -1 on failure
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet, `path`
+/// has to be a nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn hosts_write(hosts: *const HostsFile, path: *const c_char) -> c_int {
+    let Some(hosts) = hosts.as_ref() else {
+        set_error("hosts is null");
+        return -1;
+    };
+    let Some(path) = arg(path, "path") else {
+        return -1;
+    };
+    match hosts.write_to(path) {
+        Ok(()) => 0,
+        Err(e) => {
+            set_error(format!("{path}: {e}"));
+            -1
+        }
+    }
+}
+
+/// null with an error set when `s` has a nul in it, a comment line can
+/// carry one and C would see the text end there
+fn into_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(e) => {
+            set_error(format!(
+                "the text has a nul byte at {}, C strings cant hold it",
+                e.nul_position()
+            ));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// the whole file as text, free it with `hosts_string_free`. null when
+/// the text has a nul byte in it
+///
+/// # Safety
+///
+/// `hosts` has to come from `hosts_parse_*` and not be freed yet
+#[no_mangle]
+pub unsafe extern "C" fn hosts_to_string(hosts: *const HostsFile) -> *mut c_char {
+    match hosts.as_ref() {
+        Some(hosts) => into_c_string(hosts.to_string()),
+        None => {
+            set_error("hosts is null");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// free a string from `hosts_to_string`, null is fine
+///
+/// # Safety
+///
+/// `s` has to be null or come from one of those, and cant be used again
+/// afterwards
+#[no_mangle]
+pub unsafe extern "C" fn hosts_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// the message from the last call on this thread that failed, null when
+/// nothing has. good until the next failing call on the same thread
+#[no_mangle]
+pub extern "C" fn hosts_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// free a `HostsFile *`, null is fine
+///
+/// # Safety
+///
+/// `hosts` has to be null or come from `hosts_parse_*`, and cant be used
+/// again afterwards
+#[no_mangle]
+pub unsafe extern "C" fn hosts_free(hosts: *mut HostsFile) {
+    if !hosts.is_null() {
+        drop(Box::from_raw(hosts));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lookup_free() {
+        let text = CString::new("127.0.0.1 localhost\nfe80::1%lo0 router\n").unwrap();
+        let name = CString::new("router").unwrap();
+        let mut buf = [0 as c_char; 64];
+        unsafe {
+            let hosts = hosts_parse_str(text.as_ptr());
+            assert!(!hosts.is_null());
+            let n = hosts_lookup(hosts, name.as_ptr(), buf.as_mut_ptr(), buf.len());
+            assert_eq!(n, 7);
+            assert_eq!(CStr::from_ptr(buf.as_ptr()).to_str(), Ok("fe80::1"));
+
+            // too small, nothing written but we still learn how big it is
+            let mut tiny = [1 as c_char; 4];
+            assert_eq!(hosts_lookup(hosts, name.as_ptr(), tiny.as_mut_ptr(), 4), 7);
+            assert_eq!(tiny, [1; 4]);
+
+            let missing = CString::new("nope").unwrap();
+            assert_eq!(
+                hosts_lookup(hosts, missing.as_ptr(), buf.as_mut_ptr(), 64),
+                -1
+            );
+            assert_eq!(
+                CStr::from_ptr(hosts_last_error()).to_str(),
+                Ok("nope is not in the hosts file")
+            );
+            hosts_free(hosts);
+            hosts_free(ptr::null_mut());
+
+            // parses fine, but cant go back out as a C string
+            let nul = HostsFile::parse_str("# a\0b\n10.0.0.5 db\n").unwrap();
+            let nul = Box::into_raw(Box::new(nul));
+            assert!(hosts_to_string(nul).is_null());
+            assert_eq!(
+                CStr::from_ptr(hosts_last_error()).to_str(),
+                Ok("the text has a nul byte at 3, C strings cant hold it")
+            );
+            hosts_free(nul);
+        }
+    }
+
+    #[test]
+    fn edits() {
+        let text = CString::new("127.0.0.1 localhost\n10.0.0.5 db\n").unwrap();
+        let cstr = |s: &str| CString::new(s).unwrap();
+        unsafe {
+            let hosts = hosts_parse_str(text.as_ptr());
+            assert_eq!(
+                hosts_set(hosts, cstr("db").as_ptr(), cstr("10.0.0.6").as_ptr()),
+                0
+            );
+            assert_eq!(
+                hosts_set(hosts, cstr("db").as_ptr(), cstr("ten").as_ptr()),
+                -1
+            );
+            assert_eq!(hosts_add(hosts, cstr("10.0.0.7 cache").as_ptr()), 0);
+            assert_eq!(hosts_remove(hosts, cstr("localhost").as_ptr()), 1);
+            assert_eq!(hosts_remove(hosts, cstr("localhost").as_ptr()), 0);
+
+            let out = hosts_to_string(hosts);
+            assert_eq!(
+                CStr::from_ptr(out).to_str(),
+                Ok("10.0.0.6\tdb\n10.0.0.7\tcache\n")
+            );
+            hosts_string_free(out);
+            hosts_free(hosts);
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let path = CString::new("/nonexistent/hosts-digger").unwrap();
+        unsafe {
+            assert!(hosts_parse_file(path.as_ptr()).is_null());
+            assert!(CStr::from_ptr(hosts_last_error())
+                .to_str()
+                .unwrap()
+                .starts_with("/nonexistent/hosts-digger: "));
+            assert!(hosts_parse_str(ptr::null()).is_null());
+            assert_eq!(
+                CStr::from_ptr(hosts_last_error()).to_str(),
+                Ok("text is null")
+            );
+        }
+    }
+}
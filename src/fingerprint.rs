@@ -0,0 +1,160 @@
+//! a sha-256 over what a hosts file says rather than how it says it, for
+//! integrity monitors that shouldnt go off every time someone lines the
+//! columns up or edits a comment
+//!
+//! the hash is taken over `HostsFile::canonicalize`, so every address
+//! counts once with all of its names. each record goes in as a line of
+//! the address, its scope id and the names lowercased and with unicode
+//! labels punycoded, repeats dropped. that folding is done here and not
+//! with the `idna` feature, so the same file gives the same fingerprint
+//! whichever features it was built with. comments, blank lines, lines
+//! that dont parse and all the spacing are left out. order is kept
+//! because the first record for a name is the one that counts
+
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::hosts_file::HostsFile;
+use crate::idna;
+use crate::sha256::Sha256;
+
+/// bumped if the canonical form ever changes, so old fingerprints cant
+/// match new ones by accident
+const VERSION: &[u8] = b"hosts-digger fingerprint v2\n";
+
+#[derive(Error, Clone, Debug, Eq, PartialEq)]
+pub enum FingerprintError {
+    #[error("a fingerprint is 64 hex digits, this has {0} characters")]
+    Length(usize),
+    #[error("{0:?} isnt a hex digit")]
+    NotHex(char),
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl HostsFile {
+    /// sha-256 of the canonical record set, see the module docs for what
+    /// counts
+    pub fn fingerprint(&self) -> Fingerprint {
+        let mut canonical = self.clone();
+        canonical.canonicalize();
+
+        let mut hasher = Sha256::default();
+        hasher.update(VERSION);
+        let mut line = String::new();
+        for record in canonical.records() {
+            line.clear();
+            line.push_str(&record.addr().to_string());
+            if let Some(scope) = record.scope() {
+                line.push('%');
+                line.push_str(scope);
+            }
+            let mut seen = HashSet::new();
+            for name in record.names().iter().map(|n| fold(n)) {
+                if seen.insert(name.clone()) {
+                    line.push(' ');
+                    line.push_str(&name);
+                }
+            }
+            line.push('\n');
+            hasher.update(line.as_bytes());
+        }
+        Fingerprint(hasher.finish())
+    }
+}
+
+/// what `fold_name` gives with the `idna` feature on, but always
+fn fold(name: &str) -> String {
+    if !name.is_ascii() {
+        if let Ok(ascii) = idna::to_ascii(name) {
+            return ascii.to_ascii_lowercase();
+        }
+    }
+    name.to_ascii_lowercase()
+}
+
+/// lowercase hex, the way sha256sum prints it
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// back from the 64 hex digits `Display` gives, for checking against a
+/// fingerprint that was stored earlier. either case is fine
+impl FromStr for Fingerprint {
+    type Err = FingerprintError;
+
+    fn from_str(s: &str) -> Result<Self, FingerprintError> {
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(FingerprintError::NotHex(c));
+        }
+        if s.len() != 64 {
+            return Err(FingerprintError::Length(s.len()));
+        }
+        let mut out = [0u8; 32];
+        for (i, byte) in out.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).expect("checked above");
+        }
+        Ok(Fingerprint(out))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignores_formatting() {
+        let fingerprint = |s: &str| s.parse::<HostsFile>().unwrap().fingerprint();
+        let base = fingerprint("127.0.0.1 localhost\n10.0.0.5 db db.lan\n");
+        assert_eq!(
+            base,
+            fingerprint("# managed\n127.0.0.1\tlocalhost\n\n10.0.0.5    DB db.lan  # primary\n")
+        );
+        assert_ne!(
+            base,
+            fingerprint("127.0.0.1 localhost\n10.0.0.6 db db.lan\n")
+        );
+        assert_ne!(
+            base,
+            fingerprint("10.0.0.5 db db.lan\n127.0.0.1 localhost\n")
+        );
+        // canonicalize folds these onto one line
+        assert_eq!(
+            base,
+            fingerprint("127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.5 db.lan DB\n")
+        );
+        // the same with and without the idna feature
+        assert_eq!(
+            fingerprint("10.0.0.7 bücher xn--bcher-kva\n"),
+            fingerprint("10.0.0.7 xn--bcher-kva\n")
+        );
+
+        let hex = base.to_string();
+        assert_eq!(hex.len(), 64);
+        assert_eq!(hex.parse::<Fingerprint>(), Ok(base));
+        assert_eq!(hex.to_uppercase().parse::<Fingerprint>(), Ok(base));
+        assert_eq!(
+            "abc".parse::<Fingerprint>(),
+            Err(FingerprintError::Length(3))
+        );
+        assert_eq!(
+            "é".repeat(32).parse::<Fingerprint>(),
+            Err(FingerprintError::NotHex('é'))
+        );
+    }
+}
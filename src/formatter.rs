@@ -0,0 +1,196 @@
+//! rustfmt for hosts files. lines up the columns, trims trailing
+//! whitespace and breaks up alias lists that got too long, while leaving
+//! what the file actually says alone
+
+use crate::document::{HostsDocument, Line, RecordLine};
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+use crate::writer::{record_line, widest_addr, Alignment};
+
+/// macOS stops reading a hosts line after this many characters
+const MAX_WIDTH: usize = 255;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FormatOptions {
+    /// how the names get lined up, on a shared tab stop by default
+    pub align: Alignment,
+    /// lines longer than this get their names split over more lines for
+    /// the same address, `None` leaves long lines be
+    pub max_width: Option<usize>,
+    /// most names to put on one line, records with more get split over
+    /// more lines for the same address. for resolvers that stop reading
+    /// aliases after some count. parse with `coalesce_lines` to get the
+    /// one record back
+    pub max_names: Option<usize>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            align: Alignment::default(),
+            max_width: Some(MAX_WIDTH),
+            max_names: None,
+        }
+    }
+}
+
+impl HostsDocument {
+    /// lay every line out the same way. records are rewritten in columns,
+    /// comments and unparseable lines lose their trailing whitespace and
+    /// blank lines are emptied out
+    pub fn format(&mut self, options: &FormatOptions) {
+        let widest = widest_addr(self.records());
+        let lines = std::mem::take(self.lines_mut());
+        for line in lines {
+            match line {
+                Line::Record(line) => {
+                    let mut span = line.span();
+                    for record in wrap(line.record(), options, widest) {
+                        let raw = record_line(&record, options.align, widest);
+                        self.lines_mut().push(Line::Record(RecordLine::formatted(
+                            record,
+                            raw,
+                            span.take(),
+                        )));
+                    }
+                }
+                Line::Comment(text) => self.lines_mut().push(Line::Comment(trim(text))),
+                Line::Invalid(text) => self.lines_mut().push(Line::Invalid(trim(text))),
+                Line::Blank(_) => self.lines_mut().push(Line::Blank(String::new())),
+            }
+        }
+    }
+}
+
+impl HostsFile {
+    /// see `HostsDocument::format`
+    pub fn format(&mut self, options: &FormatOptions) {
+        self.edit(|doc| doc.format(options))
+    }
+}
+
+fn trim(mut text: String) -> String {
+    text.truncate(text.trim_end().len());
+    text
+}
+
+/// split `record` into as few records as it takes to keep each line under
+/// the width and name count. the comment stays with the first one
+fn wrap(record: &Record, options: &FormatOptions, widest: usize) -> Vec<Record> {
+    let too_long = |r: &Record| {
+        options
+            .max_width
+            .is_some_and(|max| record_line(r, options.align, widest).chars().count() > max)
+            || options
+                .max_names
+                .is_some_and(|max| r.names().len() > max.max(1))
+    };
+    if !too_long(record) || record.names().len() < 2 {
+        return vec![record.clone()];
+    }
+
+    let mut out = Vec::new();
+    let mut current = record.clone();
+    current.names.clear();
+    for name in record.names() {
+        current.names.push(name.clone());
+        if current.names.len() > 1 && too_long(&current) {
+            current.names.pop();
+            let mut next = current.clone();
+            next.names = vec![name.clone()];
+            next.comment = None;
+            out.push(std::mem::replace(&mut current, next));
+        }
+    }
+    out.push(current);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ParseOptions;
+
+    #[test]
+    fn lines_up_columns() {
+        let mut doc: HostsDocument = "# hosts   \n\
+            127.0.0.1 localhost\n\
+            \t \n\
+            ::1     ip6-localhost   ip6-loopback\n\
+            192.168.10.42 core   # the box under the desk  \n\
+            not valid at all   \n"
+            .parse()
+            .unwrap();
+        doc.format(&FormatOptions::default());
+        assert_eq!(
+            doc.to_string(),
+            "# hosts\n\
+            127.0.0.1\tlocalhost\n\
+            \n\
+            ::1\t\tip6-localhost ip6-loopback\n\
+            192.168.10.42\tcore # the box under the desk\n\
+            not valid at all\n"
+        );
+
+        let mut spaces = doc.clone();
+        spaces.format(&FormatOptions {
+            align: Alignment::Spaces(16),
+            ..Default::default()
+        });
+        assert!(spaces
+            .to_string()
+            .contains("\n::1             ip6-localhost"));
+    }
+
+    #[test]
+    fn wraps_long_alias_lists() {
+        let names: Vec<String> = (0..30)
+            .map(|i| format!("alias{i:02}.example.com"))
+            .collect();
+        let text = format!("10.0.0.5 {} # lab\n", names.join(" "));
+        let mut hosts: HostsFile = text.parse().unwrap();
+        hosts.format(&FormatOptions::default());
+
+        let out = hosts.to_string();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| l.len() <= MAX_WIDTH));
+        assert!(lines[0].ends_with("# lab"));
+        assert!(lines[1..]
+            .iter()
+            .all(|l| l.starts_with("10.0.0.5\t") && !l.contains('#')));
+
+        let all: Vec<_> = hosts.records().iter().flat_map(|r| r.names()).collect();
+        assert_eq!(all, names.iter().collect::<Vec<_>>());
+        assert_eq!(hosts.document().lines()[1].to_string(), lines[1]);
+        assert!(hosts.lookup("alias29.example.com").is_some());
+    }
+
+    #[test]
+    fn split_by_name_count_and_coalesce() {
+        let mut hosts: HostsFile = "10.0.0.5 a b c d e # five\n127.0.0.1 localhost\n"
+            .parse()
+            .unwrap();
+        hosts.format(&FormatOptions {
+            max_names: Some(2),
+            ..Default::default()
+        });
+        let out = hosts.to_string();
+        assert_eq!(
+            out,
+            "10.0.0.5\ta b # five\n10.0.0.5\tc d\n10.0.0.5\te\n127.0.0.1\tlocalhost\n"
+        );
+
+        let options = ParseOptions {
+            coalesce_lines: true,
+            ..Default::default()
+        };
+        let (coalesced, _) = HostsFile::parse_with(&out, &options).unwrap();
+        assert_eq!(coalesced.records().len(), 2);
+        assert_eq!(coalesced.records()[0].names(), ["a", "b", "c", "d", "e"]);
+        assert_eq!(coalesced.records()[0].comment(), Some("five"));
+        assert_eq!(coalesced.to_string(), out);
+        let span = coalesced.document().record_lines().next().unwrap().span();
+        assert_eq!(span.map(|s| (s.line, s.end)), Some((1, 43)));
+    }
+}
@@ -0,0 +1,153 @@
+//! records tagged with a group, so a dev environment manager can drop
+//! everything it added for a project in one go
+//!
+//! ```text
+//! 192.168.56.10 web.myproject.test # group: myproject
+//! 192.168.56.11 db.myproject.test # group: myproject
+//! ```
+
+use crate::document::{Line, RecordLine};
+use crate::hosts_file::HostsFile;
+use crate::record::{Record, RecordError};
+
+const GROUP: &str = "group";
+
+impl Record {
+    /// the `group:` tag in the trailing comment
+    pub fn group(&self) -> Option<&str> {
+        self.tag(GROUP)
+    }
+
+    pub fn set_group(&mut self, group: Option<&str>) -> Result<(), RecordError> {
+        self.set_tag(GROUP, group)
+    }
+}
+
+fn in_group(line: &Line, group: &str) -> bool {
+    matches!(line, Line::Record(l) if l.record().group() == Some(group))
+}
+
+fn tagged<I>(records: I, group: &str) -> Result<Vec<Line>, RecordError>
+where
+    I: IntoIterator<Item = Record>,
+{
+    records
+        .into_iter()
+        .map(|mut record| {
+            record.set_group(Some(group))?;
+            Ok(Line::Record(RecordLine::new(record)))
+        })
+        .collect()
+}
+
+impl HostsFile {
+    /// every group in the file, in the order they first show up
+    pub fn groups(&self) -> Vec<&str> {
+        let mut groups: Vec<&str> = Vec::new();
+        for group in self.records().iter().filter_map(Record::group) {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        }
+        groups
+    }
+
+    /// the records tagged with `group`, in file order
+    pub fn group(&self, group: &str) -> Vec<&Record> {
+        self.records()
+            .iter()
+            .filter(|r| r.group() == Some(group))
+            .collect()
+    }
+
+    /// tag `records` with `group` and add them at the end
+    pub fn add_to_group<I>(&mut self, group: &str, records: I) -> Result<(), RecordError>
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        let lines = tagged(records, group)?;
+        self.edit(|doc| {
+            for line in lines {
+                doc.push(line);
+            }
+        });
+        Ok(())
+    }
+
+    /// drop every record tagged with `group`, gives back how many lines
+    /// went away
+    pub fn remove_group(&mut self, group: &str) -> usize {
+        self.edit(|doc| {
+            let lines = doc.lines_mut();
+            let before = lines.len();
+            lines.retain(|line| !in_group(line, group));
+            before - lines.len()
+        })
+    }
+
+    /// swap everything in `group` for `records`. they go where the first
+    /// line of the group was, or at the end when the group is new
+    pub fn replace_group<I>(&mut self, group: &str, records: I) -> Result<(), RecordError>
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        let new = tagged(records, group)?;
+        self.edit(|doc| {
+            let lines = doc.lines_mut();
+            match lines.iter().position(|line| in_group(line, group)) {
+                Some(at) => {
+                    lines.retain(|line| !in_group(line, group));
+                    lines.splice(at..at, new);
+                }
+                None => {
+                    for line in new {
+                        doc.push(line);
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(addr: &str, name: &str) -> Record {
+        Record::new(addr.parse().unwrap(), vec![name.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn manage_groups() {
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n\
+            192.168.56.10 web.test # group: myproject\n\
+            10.0.0.5 db # group: other\n\
+            192.168.56.11 db.test # vm group: myproject\n"
+            .parse()
+            .unwrap();
+        assert_eq!(hosts.groups(), ["myproject", "other"]);
+        assert_eq!(hosts.group("myproject").len(), 2);
+
+        hosts
+            .replace_group("myproject", vec![record("192.168.56.20", "app.test")])
+            .unwrap();
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n\
+            192.168.56.20\tapp.test # group: myproject\n\
+            10.0.0.5 db # group: other\n"
+        );
+
+        hosts
+            .add_to_group("myproject", vec![record("192.168.56.21", "cache.test")])
+            .unwrap();
+        assert_eq!(hosts.remove_group("myproject"), 2);
+        assert_eq!(hosts.remove_group("myproject"), 0);
+        assert_eq!(hosts.groups(), ["other"]);
+        assert!(hosts.add_to_group("two words", vec![]).is_ok());
+        assert!(hosts
+            .add_to_group("two words", vec![record("10.0.0.9", "x")])
+            .is_err());
+    }
+}
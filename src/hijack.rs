@@ -0,0 +1,189 @@
+//! hosts entries that point well known domains somewhere they shouldnt
+//! go. malware likes to drop a line for the microsoft login page, an
+//! update server or a certificate revocation endpoint into /etc/hosts
+//! and sit there, so these are worth a look whenever they show up
+//!
+//! pointing one of them at loopback or 0.0.0.0 is blocking and isnt
+//! flagged, pointing it anywhere else is. this is `audit::suspicious_entries`
+
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::blocklist::is_sink;
+use crate::document::Line;
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+
+/// what kind of domain got overridden
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Category {
+    /// sign in pages, someone wants the passwords
+    Identity,
+    /// online banking, the `.bank` and `.insurance` tlds
+    Banking,
+    /// os and browser updates, someone wants to serve their own or none
+    OsUpdate,
+    /// crl and ocsp endpoints, without them revoked certificates pass
+    Revocation,
+    /// antivirus updates and lookups
+    SecurityVendor,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Category::Identity => "identity",
+            Category::Banking => "banking",
+            Category::OsUpdate => "os-update",
+            Category::Revocation => "revocation",
+            Category::SecurityVendor => "security-vendor",
+        })
+    }
+}
+
+/// the domains watched for. each one covers its subdomains too, the
+/// banking entries are whole tlds
+const WATCHED: &[(&str, Category)] = &[
+    ("login.microsoftonline.com", Category::Identity),
+    ("login.microsoft.com", Category::Identity),
+    ("login.live.com", Category::Identity),
+    ("accounts.google.com", Category::Identity),
+    ("appleid.apple.com", Category::Identity),
+    ("idmsa.apple.com", Category::Identity),
+    ("signin.aws.amazon.com", Category::Identity),
+    ("okta.com", Category::Identity),
+    ("github.com", Category::Identity),
+    ("paypal.com", Category::Banking),
+    ("bank", Category::Banking),
+    ("insurance", Category::Banking),
+    ("windowsupdate.com", Category::OsUpdate),
+    ("update.microsoft.com", Category::OsUpdate),
+    ("delivery.mp.microsoft.com", Category::OsUpdate),
+    ("swscan.apple.com", Category::OsUpdate),
+    ("swcdn.apple.com", Category::OsUpdate),
+    ("mesu.apple.com", Category::OsUpdate),
+    ("archive.ubuntu.com", Category::OsUpdate),
+    ("security.ubuntu.com", Category::OsUpdate),
+    ("deb.debian.org", Category::OsUpdate),
+    ("security.debian.org", Category::OsUpdate),
+    ("mirrors.fedoraproject.org", Category::OsUpdate),
+    ("update.googleapis.com", Category::OsUpdate),
+    ("ocsp.digicert.com", Category::Revocation),
+    ("crl3.digicert.com", Category::Revocation),
+    ("crl4.digicert.com", Category::Revocation),
+    ("ocsp.pki.goog", Category::Revocation),
+    ("crl.pki.goog", Category::Revocation),
+    ("ocsp.sectigo.com", Category::Revocation),
+    ("crl.sectigo.com", Category::Revocation),
+    ("ocsp.globalsign.com", Category::Revocation),
+    ("crl.globalsign.com", Category::Revocation),
+    ("lencr.org", Category::Revocation),
+    ("crl.microsoft.com", Category::Revocation),
+    ("ocsp.apple.com", Category::Revocation),
+    ("wdcp.microsoft.com", Category::SecurityVendor),
+    ("definitionupdates.microsoft.com", Category::SecurityVendor),
+    ("symantec.com", Category::SecurityVendor),
+    ("mcafee.com", Category::SecurityVendor),
+    ("kaspersky.com", Category::SecurityVendor),
+    ("kaspersky-labs.com", Category::SecurityVendor),
+    ("eset.com", Category::SecurityVendor),
+    ("avast.com", Category::SecurityVendor),
+    ("malwarebytes.com", Category::SecurityVendor),
+    ("virustotal.com", Category::SecurityVendor),
+];
+
+/// one overridden domain
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Finding {
+    /// 1-based line the entry is on
+    pub line: usize,
+    /// the name as written in the file
+    pub name: String,
+    /// where the file sends it
+    pub addr: IpAddr,
+    /// the watched domain it falls under, `microsoftonline.com` or `bank`
+    pub matched: &'static str,
+    pub category: Category,
+}
+
+/// every entry that sends a watched domain, or anything under one, to an
+/// address that isnt loopback or unspecified. in line order
+pub fn suspicious_entries(hosts: &HostsFile) -> Vec<Finding> {
+    let mut out = Vec::new();
+    for (i, line) in hosts.document().lines().iter().enumerate() {
+        let Line::Record(line) = line else {
+            continue;
+        };
+        let record = line.record();
+        if is_sink(record.addr()) {
+            continue;
+        }
+        for name in record.names() {
+            if let Some(&(matched, category)) = watched(name) {
+                out.push(Finding {
+                    line: i + 1,
+                    name: name.clone(),
+                    addr: record.addr(),
+                    matched,
+                    category,
+                });
+            }
+        }
+    }
+    out
+}
+
+fn watched(name: &str) -> Option<&'static (&'static str, Category)> {
+    let name = fold_name(name.strip_suffix('.').unwrap_or(name));
+    WATCHED.iter().find(|(domain, _)| {
+        name == *domain
+            || name
+                .strip_suffix(domain)
+                .is_some_and(|rest| rest.ends_with('.'))
+    })
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} is sent to {}, overriding the {} domain {}",
+            self.line, self.name, self.addr, self.category, self.matched
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_overrides() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            203.0.113.7 login.microsoftonline.com\n\
+            0.0.0.0 ocsp.digicert.com\n\
+            10.0.0.5 db MyBank.Bank.\n\
+            198.51.100.2 r3.o.lencr.org notlencr.org\n\
+            127.0.0.1 accounts.google.com\n"
+            .parse()
+            .unwrap();
+        let findings = suspicious_entries(&hosts);
+        let found = findings
+            .iter()
+            .map(|f| (f.line, f.name.as_str(), f.category))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                (2, "login.microsoftonline.com", Category::Identity),
+                (4, "MyBank.Bank.", Category::Banking),
+                (5, "r3.o.lencr.org", Category::Revocation),
+            ]
+        );
+        assert_eq!(findings[1].matched, "bank");
+        assert_eq!(
+            findings[0].to_string(),
+            "line 2: login.microsoftonline.com is sent to 203.0.113.7, overriding the identity domain login.microsoftonline.com"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+use thiserror::Error;
+
+/// longest a full name can be, RFC 1123 section 2.1
+const MAX_NAME_LEN: usize = 253;
+/// longest a single label can be, RFC 1035 section 2.3.4
+const MAX_LABEL_LEN: usize = 63;
+
+/// how picky to be about hostnames
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Strictness {
+    /// take any name that fits on the line
+    Off,
+    /// RFC 1123 lengths and label rules, but let through the underscores
+    /// and non-ascii (IDN) names that show up in real hosts files
+    #[default]
+    Lenient,
+    /// RFC 952/1123 to the letter, ascii letters, digits and hyphens only
+    Strict,
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum HostnameError {
+    #[error("name is empty")]
+    Empty,
+
+    #[error("name is {0} bytes, longer than the {MAX_NAME_LEN} allowed")]
+    TooLong(usize),
+
+    #[error("name has an empty label")]
+    EmptyLabel,
+
+    #[error("label {0:?} is longer than the {MAX_LABEL_LEN} bytes allowed")]
+    LabelTooLong(String),
+
+    #[error("label {0:?} starts with a hyphen")]
+    LeadingHyphen(String),
+
+    #[error("label {0:?} ends with a hyphen")]
+    TrailingHyphen(String),
+
+    #[error("{0:?} is not allowed in a hostname")]
+    InvalidChar(char),
+}
+
+/// check `name` against the hostname rules at `strictness`
+pub fn validate_hostname(name: &str, strictness: Strictness) -> Result<(), HostnameError> {
+    if strictness == Strictness::Off {
+        return Ok(());
+    }
+    if name.is_empty() {
+        return Err(HostnameError::Empty);
+    }
+
+    // a single trailing dot just means the name is fully qualified
+    let name = match strictness {
+        Strictness::Lenient => name.strip_suffix('.').unwrap_or(name),
+        _ => name,
+    };
+    if name.len() > MAX_NAME_LEN {
+        return Err(HostnameError::TooLong(name.len()));
+    }
+
+    for label in name.split('.') {
+        if label.is_empty() {
+            return Err(HostnameError::EmptyLabel);
+        }
+        if label.len() > MAX_LABEL_LEN {
+            return Err(HostnameError::LabelTooLong(label.to_string()));
+        }
+        if label.starts_with('-') {
+            return Err(HostnameError::LeadingHyphen(label.to_string()));
+        }
+        if label.ends_with('-') {
+            return Err(HostnameError::TrailingHyphen(label.to_string()));
+        }
+        if let Some(c) = label.chars().find(|&c| !allowed(c, strictness)) {
+            return Err(HostnameError::InvalidChar(c));
+        }
+    }
+
+    Ok(())
+}
+
+/// the form of `name` we compare on, dns names dont care about case
+///
+/// ascii is always folded. with the `idna` feature unicode names get
+/// folded too and encoded as punycode, so every spelling of a name ends up
+/// as the same key
+pub(crate) fn fold_name(name: &str) -> Cow<'_, str> {
+    #[cfg(feature = "idna")]
+    if !name.is_ascii() {
+        if let Ok(ascii) = crate::idna::to_ascii(name) {
+            return Cow::Owned(ascii.to_ascii_lowercase());
+        }
+    }
+    if name.bytes().any(|b| b.is_ascii_uppercase()) {
+        return Cow::Owned(name.to_ascii_lowercase());
+    }
+    Cow::Borrowed(name)
+}
+
+/// true if `a` and `b` are the same name as far as dns is concerned
+pub(crate) fn names_eq(a: &str, b: &str) -> bool {
+    a == b || fold_name(a) == fold_name(b)
+}
+
+/// lowercase `name` for the normalization pass on parse, unicode is only
+/// touched with the `idna` feature
+pub(crate) fn lowercase_name(name: &str) -> String {
+    #[cfg(feature = "idna")]
+    {
+        name.to_lowercase()
+    }
+    #[cfg(not(feature = "idna"))]
+    {
+        name.to_ascii_lowercase()
+    }
+}
+
+fn allowed(c: char, strictness: Strictness) -> bool {
+    match strictness {
+        Strictness::Strict => c.is_ascii_alphanumeric() || c == '-',
+        _ => {
+            c.is_ascii_alphanumeric()
+                || c == '-'
+                || c == '_'
+                || (!c.is_ascii() && c.is_alphanumeric())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_names() {
+        for name in [
+            "localhost",
+            "db.internal",
+            "ip6-localhost",
+            "1password.local",
+            "a",
+        ] {
+            assert_eq!(validate_hostname(name, Strictness::Strict), Ok(()));
+        }
+        for name in ["_dmarc.example.com", "fqdn.example.com.", "bücher.example"] {
+            assert_eq!(validate_hostname(name, Strictness::Lenient), Ok(()));
+            assert!(validate_hostname(name, Strictness::Strict).is_err());
+        }
+    }
+
+    #[test]
+    fn invalid_names() {
+        let cases = [
+            ("", HostnameError::Empty),
+            ("db..internal", HostnameError::EmptyLabel),
+            ("-db.internal", HostnameError::LeadingHyphen("-db".into())),
+            ("db-.internal", HostnameError::TrailingHyphen("db-".into())),
+            ("db!", HostnameError::InvalidChar('!')),
+            ("db/internal", HostnameError::InvalidChar('/')),
+        ];
+        for (name, err) in cases {
+            assert_eq!(validate_hostname(name, Strictness::Lenient), Err(err));
+        }
+    }
+
+    #[test]
+    fn fold_case() {
+        assert_eq!(fold_name("LocalHost"), "localhost");
+        assert!(matches!(fold_name("localhost"), Cow::Borrowed(_)));
+        assert!(names_eq("DB.Internal", "db.internal"));
+        assert!(!names_eq("db", "db.internal"));
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn fold_unicode_case() {
+        assert!(names_eq("BÜCHER.example", "xn--bcher-kva.example"));
+        assert!(names_eq("XN--BCHER-KVA.EXAMPLE", "bücher.example"));
+    }
+
+    #[test]
+    fn length_limits() {
+        let label = "a".repeat(64);
+        assert_eq!(
+            validate_hostname(&label, Strictness::Lenient),
+            Err(HostnameError::LabelTooLong(label.clone()))
+        );
+        let long = vec!["a".repeat(63); 5].join(".");
+        assert_eq!(
+            validate_hostname(&long, Strictness::Lenient),
+            Err(HostnameError::TooLong(319))
+        );
+        assert_eq!(validate_hostname(&long, Strictness::Off), Ok(()));
+    }
+}
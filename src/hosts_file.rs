@@ -0,0 +1,639 @@
+use crate::document::{HostsDocument, Line, RecordLine};
+use crate::fxhash::FxHashMap;
+use crate::hostname::{fold_name, names_eq};
+use crate::parser::{ParseOptions, ParseWarning, ParserError};
+use crate::policy::ValidationPolicy;
+use crate::record::{Record, RecordError};
+#[cfg(feature = "std-fs")]
+use crate::writer::{write_atomic, WriteOptions};
+use crate::writer::{write_records, Alignment};
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::io::Read;
+use std::net::IpAddr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// HostsFile is the parsed contents of a hosts file
+///
+/// it sits on top of a HostsDocument so edits leave the lines you didnt
+/// touch, comments and all, exactly as they were
+#[derive(Clone, Debug, Default)]
+pub struct HostsFile {
+    document: HostsDocument,
+    /// the records out of `document`, kept in sync after every edit
+    records: Vec<Record>,
+    /// built on the first lookup and thrown away on every edit, so a run
+    /// of edits doesnt rebuild it each time
+    index: OnceLock<Index>,
+    /// checked by `set` and before every write
+    policy: Option<ValidationPolicy>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Index {
+    /// name -> index of the first record carrying that name
+    by_name: FxHashMap<String, usize>,
+    /// addr -> index of every record for that addr, in file order
+    by_addr: FxHashMap<IpAddr, Vec<usize>>,
+}
+
+impl Index {
+    fn build(records: &[Record]) -> Self {
+        let mut index = Index::default();
+        for (i, record) in records.iter().enumerate() {
+            // the resolver stops at the first line that matches, so do we
+            for name in record.names() {
+                index
+                    .by_name
+                    .entry(fold_name(name).into_owned())
+                    .or_insert(i);
+            }
+            index.by_addr.entry(record.addr()).or_default().push(i);
+        }
+        index
+    }
+}
+
+// the index is just a cache, whether it has been built yet doesnt make
+// two files any different
+impl PartialEq for HostsFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.document == other.document
+            && self.records == other.records
+            && self.policy == other.policy
+    }
+}
+
+impl Eq for HostsFile {}
+
+impl HostsFile {
+    #[cfg(feature = "std-fs")]
+    /// open and parse the hosts file at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        Ok(Self::from_document(HostsDocument::open(path)?))
+    }
+
+    #[cfg(feature = "std-fs")]
+    /// open and parse this machine's hosts file
+    pub fn system() -> Result<Self, ParserError> {
+        Self::open(Self::system_path())
+    }
+
+    /// where the hosts file lives on this platform
+    ///
+    /// on windows this follows `%SystemRoot%` since windows doesnt have to
+    /// be installed on `C:\`
+    pub fn system_path() -> PathBuf {
+        #[cfg(windows)]
+        {
+            let root = std::env::var_os("SystemRoot").unwrap_or_else(|| r"C:\Windows".into());
+            PathBuf::from(root).join(r"System32\drivers\etc\hosts")
+        }
+        #[cfg(not(windows))]
+        {
+            PathBuf::from("/etc/hosts")
+        }
+    }
+
+    /// parse a hosts file out of anything readable, stdin, a download, ...
+    pub fn parse_reader<R: Read>(reader: R) -> Result<Self, ParserError> {
+        Ok(Self::from_document(HostsDocument::parse_reader(reader)?))
+    }
+
+    pub fn parse_reader_with<R: Read>(
+        reader: R,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let (document, warnings) = HostsDocument::parse_reader_with(reader, options)?;
+        Ok((Self::from_parsed(document, options), warnings))
+    }
+
+    /// same as `str::parse`, here so it reads nicely next to `parse_reader`
+    pub fn parse_str(s: &str) -> Result<Self, ParserError> {
+        s.parse()
+    }
+
+    #[cfg(feature = "std-fs")]
+    /// like `open` but with control over how broken lines are handled
+    pub fn open_with<P: AsRef<Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let (document, warnings) = HostsDocument::open_with(path, options)?;
+        Ok((Self::from_parsed(document, options), warnings))
+    }
+
+    /// parse `s` with control over how broken lines are handled
+    pub fn parse_with(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let (document, warnings) = HostsDocument::parse_with(s, options)?;
+        Ok((Self::from_parsed(document, options), warnings))
+    }
+
+    pub fn from_document(document: HostsDocument) -> Self {
+        let mut hosts = Self {
+            document,
+            ..Default::default()
+        };
+        hosts.reindex();
+        hosts
+    }
+
+    /// a file fresh out of the parser, picking up the policy it was
+    /// parsed with
+    pub(crate) fn from_parsed(document: HostsDocument, options: &ParseOptions) -> Self {
+        let mut hosts = Self::from_document(document);
+        hosts.policy = options.policy.clone();
+        hosts
+    }
+
+    /// the policy edits and writes are held to, if there is one
+    pub fn policy(&self) -> Option<&ValidationPolicy> {
+        self.policy.as_ref()
+    }
+
+    /// swap the policy out, records already in the file arent checked
+    /// until the next write
+    pub fn set_policy(&mut self, policy: Option<ValidationPolicy>) {
+        self.policy = policy;
+    }
+
+    /// the lossless document backing this file
+    pub fn document(&self) -> &HostsDocument {
+        &self.document
+    }
+
+    pub fn into_document(self) -> HostsDocument {
+        self.document
+    }
+
+    /// make changes straight to the document, the lookup maps get rebuilt
+    /// on the next lookup after `f` is done
+    pub(crate) fn edit<T>(&mut self, f: impl FnOnce(&mut HostsDocument) -> T) -> T {
+        let out = f(&mut self.document);
+        self.reindex();
+        out
+    }
+
+    /// rebuild the record list from the document and drop the lookup maps
+    fn reindex(&mut self) {
+        self.records = self.document.records().cloned().collect();
+        self.index = OnceLock::new();
+    }
+
+    fn index(&self) -> &Index {
+        self.index.get_or_init(|| Index::build(&self.records))
+    }
+
+    /// true when some record carries `name`, ignoring case. cheap enough
+    /// to call for every candidate when filtering a big list of domains
+    pub fn contains_name(&self, name: &str) -> bool {
+        self.index().by_name.contains_key(fold_name(name).as_ref())
+    }
+
+    /// true when some record is for `addr`
+    pub fn contains_ip(&self, addr: IpAddr) -> bool {
+        self.index().by_addr.contains_key(&addr)
+    }
+
+    /// resolve `name` to an address the same way the system resolver
+    /// would, the first record carrying the name wins. names are matched
+    /// without caring about case
+    pub fn lookup(&self, name: &str) -> Option<IpAddr> {
+        self.record_for(name).map(Record::addr)
+    }
+
+    /// the record `lookup` would answer `name` from
+    pub(crate) fn record_for(&self, name: &str) -> Option<&Record> {
+        self.index()
+            .by_name
+            .get(fold_name(name).as_ref())
+            .map(|&i| &self.records[i])
+    }
+
+    /// every name that points at `addr`, in the order they show up
+    pub fn reverse_lookup(&self, addr: IpAddr) -> Vec<&str> {
+        self.index()
+            .by_addr
+            .get(&addr)
+            .into_iter()
+            .flatten()
+            .flat_map(|&i| self.records[i].names())
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// the other names on the line `name` resolves through, the primary
+    /// name included when `name` is one of its aliases. use
+    /// `reverse_lookup` for every name across all lines for the address
+    pub fn aliases_of(&self, name: &str) -> Vec<&str> {
+        let Some(&i) = self.index().by_name.get(fold_name(name).as_ref()) else {
+            return Vec::new();
+        };
+        self.records[i]
+            .names()
+            .iter()
+            .filter(|n| !names_eq(n, name))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// every record we pulled out of the file, in the order they showed up
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// append a record to the end of the file
+    ///
+    /// this doesnt look at the policy, a record that breaks it gets caught
+    /// when the file is written
+    pub fn add_record(&mut self, record: Record) {
+        self.document.push(Line::Record(RecordLine::new(record)));
+        self.reindex();
+    }
+
+    /// take `name` off of every record carrying it, lines left with no
+    /// names at all are dropped. gives back true if anything changed
+    pub fn remove_by_name(&mut self, name: &str) -> bool {
+        let removed = strip_name(self.document.lines_mut(), name, None);
+        if removed {
+            self.reindex();
+        }
+        removed
+    }
+
+    /// drop every record for `addr`, gives back how many lines went away
+    pub fn remove_by_ip(&mut self, addr: IpAddr) -> usize {
+        let lines = self.document.lines_mut();
+        let before = lines.len();
+        lines.retain(|line| !matches!(line, Line::Record(l) if l.record().addr() == addr));
+        let removed = before - lines.len();
+        if removed > 0 {
+            self.reindex();
+        }
+        removed
+    }
+
+    /// point `name` at `addr`, adding a record if there isnt one yet
+    ///
+    /// a line that holds nothing but `name` is updated where it sits,
+    /// otherwise the name is pulled off the lines it was on and a new
+    /// record is added at the end of the file
+    pub fn set(&mut self, name: &str, addr: IpAddr) -> Result<(), RecordError> {
+        let record = Record::new(addr, vec![name.to_string()])?;
+        if let Some(policy) = &self.policy {
+            policy.check(&record)?;
+        }
+        if self.lookup(name) == Some(addr) {
+            // any later lines with this name are shadowed, leave them be
+            return Ok(());
+        }
+
+        let lines = self.document.lines_mut();
+        let in_place = lines
+            .iter()
+            .position(|line| matches!(line, Line::Record(l) if is_only_name(l.record(), name)));
+        match in_place {
+            Some(i) => {
+                if let Line::Record(line) = &mut lines[i] {
+                    line.set_record(record);
+                }
+                strip_name(lines, name, Some(i));
+            }
+            None => {
+                strip_name(lines, name, None);
+                self.document.push(Line::Record(RecordLine::new(record)));
+            }
+        }
+
+        self.reindex();
+        Ok(())
+    }
+
+    /// serialize the records back into hosts syntax using `align` to
+    /// separate the address column from the names
+    ///
+    /// this only writes records, comments and blank lines are left out.
+    /// use `to_string` to keep the file the way it was
+    pub fn to_string_aligned(&self, align: Alignment) -> String {
+        let mut out = String::new();
+        // writing into a String cant fail
+        let _ = write_records(&mut out, &self.records, align);
+        out
+    }
+
+    /// write the file out to `path`, replacing whatever was there
+    ///
+    /// the write is atomic, see `write_to_with` for keeping a backup
+    #[cfg(feature = "std-fs")]
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.write_to_with(path, &WriteOptions::default())
+    }
+
+    #[cfg(feature = "std-fs")]
+    /// fails with `InvalidData` without touching the file when a record
+    /// breaks the policy
+    pub fn write_to_with<P: AsRef<Path>>(&self, path: P, options: &WriteOptions) -> io::Result<()> {
+        self.enforce_policy()?;
+        write_atomic(path.as_ref(), &self.document.to_bytes(), options)
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn write_to_aligned<P: AsRef<Path>>(&self, path: P, align: Alignment) -> io::Result<()> {
+        self.enforce_policy()?;
+        let contents = self.to_string_aligned(align);
+        write_atomic(path.as_ref(), contents.as_bytes(), &WriteOptions::default())
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub(crate) fn enforce_policy(&self) -> io::Result<()> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+        match self.check_policy(policy).into_iter().next() {
+            Some(violation) => Err(io::Error::new(io::ErrorKind::InvalidData, violation)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// parse a hosts file out of anything readable
+pub fn parse_reader<R: Read>(reader: R) -> Result<HostsFile, ParserError> {
+    HostsFile::parse_reader(reader)
+}
+
+/// parse a hosts file out of a string
+pub fn parse_str(s: &str) -> Result<HostsFile, ParserError> {
+    HostsFile::parse_str(s)
+}
+
+fn is_only_name(record: &Record, name: &str) -> bool {
+    matches!(record.names(), [only] if names_eq(only, name))
+}
+
+/// take `name` off of every record line except the one at `keep`,
+/// dropping lines that end up without any names
+fn strip_name(lines: &mut Vec<Line>, name: &str, keep: Option<usize>) -> bool {
+    strip_names(lines, &[name], keep)
+}
+
+/// `strip_name` for several names in one pass
+pub(crate) fn strip_names(lines: &mut Vec<Line>, names: &[&str], keep: Option<usize>) -> bool {
+    let mut changed = false;
+    let mut i = 0;
+    let stripped = |n: &String| names.iter().any(|name| names_eq(n, name));
+    lines.retain_mut(|line| {
+        let idx = i;
+        i += 1;
+        let Line::Record(line) = line else {
+            return true;
+        };
+        if Some(idx) == keep || !line.record().names().iter().any(stripped) {
+            return true;
+        }
+
+        changed = true;
+        let mut record = line.record().clone();
+        record.names.retain(|n| !stripped(n));
+        if record.names.is_empty() {
+            return false;
+        }
+        line.set_record(record);
+        true
+    });
+    changed
+}
+
+/// displays the whole file, untouched lines come out exactly as they
+/// were read
+impl fmt::Display for HostsFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.document)
+    }
+}
+
+impl FromStr for HostsFile {
+    type Err = ParserError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from_document(s.parse()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std-fs")]
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn from_str_records() {
+        let hosts = HostsFile::from_str(
+            "# static table lookup for hostnames\n\
+             127.0.0.1\tlocalhost\n\
+             \n\
+             192.168.10.42\tcore.naus core\n",
+        )
+        .unwrap();
+        assert_eq!(hosts.records().len(), 2);
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn write_and_reopen() {
+        let hosts = HostsFile::from_str("127.0.0.1 localhost\n::1 ip6-localhost\n").unwrap();
+        let path = std::env::temp_dir().join(format!("hosts-digger-write-{}", std::process::id()));
+        hosts.write_to(&path).unwrap();
+        let reopened = HostsFile::open(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(hosts, reopened);
+        assert_eq!(
+            hosts.to_string_aligned(Alignment::Tabs),
+            "127.0.0.1\tlocalhost\n::1\t\tip6-localhost\n"
+        );
+    }
+
+    #[test]
+    fn lookup() {
+        let hosts = HostsFile::from_str(
+            "127.0.0.1 localhost\n\
+             10.0.0.5 db db.internal\n\
+             10.0.0.6 db\n\
+             127.0.0.1 runsc\n",
+        )
+        .unwrap();
+        assert_eq!(hosts.lookup("db"), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(
+            hosts.lookup("db.internal"),
+            Some("10.0.0.5".parse().unwrap())
+        );
+        assert_eq!(hosts.lookup("nope"), None);
+        assert_eq!(
+            hosts.reverse_lookup("127.0.0.1".parse().unwrap()),
+            vec!["localhost", "runsc"]
+        );
+        assert!(hosts.reverse_lookup("10.9.9.9".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn contains_after_edits() {
+        let mut hosts = HostsFile::from_str(EDITABLE).unwrap();
+        let clean = hosts.clone();
+        assert!(hosts.contains_name("DB"));
+        assert!(hosts.contains_ip("10.0.0.5".parse().unwrap()));
+        // a built index doesnt make it compare any different
+        assert_eq!(hosts, clean);
+
+        hosts.remove_by_name("db");
+        assert!(!hosts.contains_name("db"));
+        hosts.set("db", "10.0.0.9".parse().unwrap()).unwrap();
+        assert!(hosts.contains_name("db"));
+        assert!(hosts.contains_ip("10.0.0.9".parse().unwrap()));
+        assert!(!hosts.contains_ip("10.9.9.9".parse().unwrap()));
+    }
+
+    #[test]
+    fn aliases_of() {
+        let hosts = HostsFile::from_str(
+            "10.0.0.5 db db.internal postgres
+             10.0.0.5 db-replica
+             10.0.0.6 db.internal cache
+",
+        )
+        .unwrap();
+        assert_eq!(hosts.aliases_of("db"), ["db.internal", "postgres"]);
+        assert_eq!(hosts.aliases_of("POSTGRES"), ["db", "db.internal"]);
+        // the first line with the name wins, same as lookup
+        assert_eq!(hosts.aliases_of("db.internal"), ["db", "postgres"]);
+        assert!(hosts.aliases_of("db-replica").is_empty());
+        assert!(hosts.aliases_of("nope").is_empty());
+    }
+
+    const EDITABLE: &str = "# managed by hand\n\
+                            127.0.0.1\tlocalhost\n\
+                            10.0.0.5  db db.internal\n\
+                            \n\
+                            10.0.0.7  cache\n";
+
+    #[test]
+    fn add_record_appends() {
+        let mut hosts = HostsFile::from_str(EDITABLE).unwrap();
+        let record = Record::new("10.0.0.9".parse().unwrap(), vec!["queue".into()]).unwrap();
+        hosts.add_record(record);
+        assert_eq!(hosts.lookup("queue"), Some("10.0.0.9".parse().unwrap()));
+        assert_eq!(hosts.to_string(), format!("{EDITABLE}10.0.0.9\tqueue\n"));
+    }
+
+    #[test]
+    fn remove_by_name_keeps_other_lines() {
+        let mut hosts = HostsFile::from_str(EDITABLE).unwrap();
+        assert!(hosts.remove_by_name("db"));
+        assert!(!hosts.remove_by_name("db"));
+        assert!(hosts.remove_by_name("cache"));
+        assert_eq!(
+            hosts.to_string(),
+            "# managed by hand\n127.0.0.1\tlocalhost\n10.0.0.5\tdb.internal\n\n"
+        );
+    }
+
+    #[test]
+    fn remove_by_ip() {
+        let mut hosts = HostsFile::from_str(EDITABLE).unwrap();
+        assert_eq!(hosts.remove_by_ip("10.0.0.5".parse().unwrap()), 1);
+        assert_eq!(hosts.lookup("db.internal"), None);
+        assert_eq!(hosts.records().len(), 2);
+    }
+
+    #[test]
+    fn set_upserts() {
+        let mut hosts = HostsFile::from_str(EDITABLE).unwrap();
+        // single name line is updated in place
+        hosts.set("cache", "10.0.0.8".parse().unwrap()).unwrap();
+        // alias gets pulled off its line and added at the end
+        hosts
+            .set("db.internal", "10.0.0.6".parse().unwrap())
+            .unwrap();
+        // brand new name
+        hosts.set("queue", "10.0.0.9".parse().unwrap()).unwrap();
+        // already correct, nothing to do
+        hosts
+            .set("localhost", "127.0.0.1".parse().unwrap())
+            .unwrap();
+        assert_eq!(
+            hosts.to_string(),
+            "# managed by hand\n\
+             127.0.0.1\tlocalhost\n\
+             10.0.0.5\tdb\n\
+             \n\
+             10.0.0.8\tcache\n\
+             10.0.0.6\tdb.internal\n\
+             10.0.0.9\tqueue\n"
+        );
+    }
+
+    #[test]
+    fn system_path() {
+        #[cfg(unix)]
+        assert_eq!(HostsFile::system_path(), Path::new("/etc/hosts"));
+        #[cfg(windows)]
+        assert!(HostsFile::system_path().ends_with(r"System32\drivers\etc\hosts"));
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn lookup_idna() {
+        let hosts =
+            HostsFile::from_str("10.0.0.5 bücher.example\n10.0.0.6 xn--mnchen-3ya.de\n").unwrap();
+        let addr = Some("10.0.0.5".parse().unwrap());
+        assert_eq!(hosts.lookup("xn--bcher-kva.example"), addr);
+        assert_eq!(hosts.lookup("bücher.example"), addr);
+        assert_eq!(
+            hosts.lookup("münchen.de"),
+            Some("10.0.0.6".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn lookup_ignores_case() {
+        let mut hosts = HostsFile::from_str("10.0.0.5 DB.Internal\n10.0.0.6 Cache\n").unwrap();
+        assert_eq!(
+            hosts.lookup("db.internal"),
+            Some("10.0.0.5".parse().unwrap())
+        );
+        assert_eq!(
+            hosts.lookup("DB.INTERNAL"),
+            Some("10.0.0.5".parse().unwrap())
+        );
+        assert!(hosts.remove_by_name("cache"));
+        hosts
+            .set("db.INTERNAL", "10.0.0.7".parse().unwrap())
+            .unwrap();
+        assert_eq!(hosts.to_string(), "10.0.0.7\tdb.INTERNAL\n");
+    }
+
+    #[test]
+    fn parse_from_reader() {
+        let src = "127.0.0.1 localhost\n10.0.0.5 db\n";
+        let hosts = parse_reader(std::io::Cursor::new(src)).unwrap();
+        assert_eq!(hosts, parse_str(src).unwrap());
+        assert_eq!(hosts.records().len(), 2);
+
+        let utf16 = crate::encoding::encode(src, crate::Encoding::Utf16Le);
+        let hosts = HostsFile::parse_reader(&utf16[..]).unwrap();
+        assert_eq!(hosts.lookup("db"), Some("10.0.0.5".parse().unwrap()));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn open_missing_file() {
+        assert!(HostsFile::open("/this/path/does/not/exist").is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! punycode (RFC 3492) for the international names that show up in hosts
+//! files, either as raw utf-8 or already encoded as `xn--` labels
+//!
+//! this is the label encoding only, not the full UTS #46 mapping. unicode
+//! labels get lowercased before encoding which covers what people actually
+//! put in a hosts file
+
+use thiserror::Error;
+
+const BASE: u32 = 36;
+const TMIN: u32 = 1;
+const TMAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+const ACE_PREFIX: &str = "xn--";
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum IdnaError {
+    #[error("punycode label {0:?} is malformed")]
+    Malformed(String),
+
+    #[error("label {0:?} is too long to encode")]
+    Overflow(String),
+}
+
+/// encode every unicode label in `name` as an `xn--` label, ascii labels
+/// are left alone
+pub fn to_ascii(name: &str) -> Result<String, IdnaError> {
+    let labels = name
+        .split('.')
+        .map(|label| {
+            if label.is_ascii() {
+                return Ok(label.to_string());
+            }
+            let lower = label.to_lowercase();
+            let encoded = encode(&lower).ok_or_else(|| IdnaError::Overflow(label.to_string()))?;
+            Ok(format!("{ACE_PREFIX}{encoded}"))
+        })
+        .collect::<Result<Vec<String>, IdnaError>>()?;
+    Ok(labels.join("."))
+}
+
+/// decode every `xn--` label in `name` back to unicode for display
+pub fn to_unicode(name: &str) -> Result<String, IdnaError> {
+    let labels = name
+        .split('.')
+        .map(|label| {
+            let Some(encoded) = strip_ace_prefix(label) else {
+                return Ok(label.to_string());
+            };
+            decode(encoded).ok_or_else(|| IdnaError::Malformed(label.to_string()))
+        })
+        .collect::<Result<Vec<String>, IdnaError>>()?;
+    Ok(labels.join("."))
+}
+
+fn strip_ace_prefix(label: &str) -> Option<&str> {
+    let prefix = label.get(..ACE_PREFIX.len())?;
+    prefix
+        .eq_ignore_ascii_case(ACE_PREFIX)
+        .then(|| &label[ACE_PREFIX.len()..])
+}
+
+fn adapt(mut delta: u32, num_points: u32, first: bool) -> u32 {
+    delta /= if first { DAMP } else { 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + (BASE - TMIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        TMIN
+    } else if k >= bias + TMAX {
+        TMAX
+    } else {
+        k - bias
+    }
+}
+
+fn encode_digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+fn decode_digit(c: char) -> Option<u32> {
+    match c {
+        'a'..='z' => Some(c as u32 - 'a' as u32),
+        'A'..='Z' => Some(c as u32 - 'A' as u32),
+        '0'..='9' => Some(c as u32 - '0' as u32 + 26),
+        _ => None,
+    }
+}
+
+/// RFC 3492 section 6.3
+fn encode(input: &str) -> Option<String> {
+    let input = input.chars().map(|c| c as u32).collect::<Vec<u32>>();
+    let mut output = input
+        .iter()
+        .filter(|&&c| c < 0x80)
+        .map(|&c| char::from_u32(c).unwrap_or_default())
+        .collect::<String>();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic;
+    while (handled as usize) < input.len() {
+        let m = input.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in &input {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = threshold(k, bias);
+                    if q < t {
+                        break;
+                    }
+                    output.push(encode_digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(encode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n = n.checked_add(1)?;
+    }
+
+    Some(output)
+}
+
+/// RFC 3492 section 6.2
+fn decode(input: &str) -> Option<String> {
+    let (basic, extended) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output = basic.chars().collect::<Vec<char>>();
+
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.chars().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut w: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = decode_digit(digits.next()?)?;
+            i = i.checked_add(digit.checked_mul(w)?)?;
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = adapt(i - old_i, len, old_i == 0);
+        n = n.checked_add(i / len)?;
+        i %= len;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+
+    Some(output.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_labels() {
+        assert_eq!(to_ascii("bücher.example").unwrap(), "xn--bcher-kva.example");
+        assert_eq!(to_ascii("MÜNCHEN.de").unwrap(), "xn--mnchen-3ya.de");
+        assert_eq!(to_ascii("españa.test").unwrap(), "xn--espaa-rta.test");
+        assert_eq!(to_ascii("例え.テスト").unwrap(), "xn--r8jz45g.xn--zckzah");
+        assert_eq!(to_ascii("localhost").unwrap(), "localhost");
+    }
+
+    #[test]
+    fn decode_labels() {
+        assert_eq!(
+            to_unicode("xn--bcher-kva.example").unwrap(),
+            "bücher.example"
+        );
+        assert_eq!(to_unicode("XN--mnchen-3ya.de").unwrap(), "münchen.de");
+        assert_eq!(to_unicode("xn--r8jz45g.xn--zckzah").unwrap(), "例え.テスト");
+        assert_eq!(to_unicode("db.internal").unwrap(), "db.internal");
+        assert!(to_unicode("xn--!!").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! what kind of address a record points at. std has most of these checks
+//! but the interesting ones (is_global, the ipv6 ranges) are still
+//! nightly only, so the ranges are spelled out here
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::line::is_link_local;
+use crate::record::Record;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IpClass {
+    /// 127.0.0.0/8 and ::1
+    Loopback,
+    /// rfc 1918 space, 100.64.0.0/10 carrier nat and fc00::/7
+    Private,
+    /// 169.254.0.0/16 and fe80::/10
+    LinkLocal,
+    /// 0.0.0.0 and ::
+    Unspecified,
+    /// the ranges set aside for examples, 192.0.2.0/24, 198.51.100.0/24,
+    /// 203.0.113.0/24 and 2001:db8::/32
+    Documentation,
+    /// 224.0.0.0/4 and ff00::/8
+    Multicast,
+    /// broadcast, benchmarking, the old class E space and anything else
+    /// that shouldnt show up on the internet
+    Reserved,
+    /// everything else, reachable from the internet at large
+    Global,
+}
+
+/// sort `addr` into one of the classes
+pub fn ip_class(addr: IpAddr) -> IpClass {
+    match addr {
+        IpAddr::V4(v4) => v4_class(v4),
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => v4_class(v4),
+            None => v6_class(v6),
+        },
+    }
+}
+
+fn v4_class(addr: Ipv4Addr) -> IpClass {
+    let [a, b, c, _] = addr.octets();
+    if addr.is_unspecified() {
+        IpClass::Unspecified
+    } else if addr.is_loopback() {
+        IpClass::Loopback
+    } else if addr.is_private() || (a == 100 && (b & 0xc0) == 64) {
+        IpClass::Private
+    } else if addr.is_link_local() {
+        IpClass::LinkLocal
+    } else if addr.is_documentation() {
+        IpClass::Documentation
+    } else if addr.is_multicast() {
+        IpClass::Multicast
+    } else if a == 0 || a >= 240 || (a == 198 && (b & 0xfe) == 18) || (a, b, c) == (192, 0, 0) {
+        IpClass::Reserved
+    } else {
+        IpClass::Global
+    }
+}
+
+fn v6_class(addr: Ipv6Addr) -> IpClass {
+    let first = addr.segments()[0];
+    if addr.is_unspecified() {
+        IpClass::Unspecified
+    } else if addr.is_loopback() {
+        IpClass::Loopback
+    } else if (first & 0xfe00) == 0xfc00 {
+        IpClass::Private
+    } else if is_link_local(&addr) {
+        IpClass::LinkLocal
+    } else if first == 0x2001 && addr.segments()[1] == 0x0db8 {
+        IpClass::Documentation
+    } else if addr.is_multicast() {
+        IpClass::Multicast
+    } else if (first & 0xe000) == 0x2000 {
+        // 2000::/3 is the only space handed out for global unicast
+        IpClass::Global
+    } else {
+        IpClass::Reserved
+    }
+}
+
+impl Record {
+    /// what kind of address this record points at
+    pub fn ip_class(&self) -> IpClass {
+        ip_class(self.addr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_addresses() {
+        let cases = [
+            ("127.0.0.53", IpClass::Loopback),
+            ("::1", IpClass::Loopback),
+            ("10.1.2.3", IpClass::Private),
+            ("172.20.0.1", IpClass::Private),
+            ("100.100.0.1", IpClass::Private),
+            ("fd12:3456::1", IpClass::Private),
+            ("169.254.1.1", IpClass::LinkLocal),
+            ("fe80::1", IpClass::LinkLocal),
+            ("0.0.0.0", IpClass::Unspecified),
+            ("::", IpClass::Unspecified),
+            ("203.0.113.9", IpClass::Documentation),
+            ("2001:db8::5", IpClass::Documentation),
+            ("239.255.255.250", IpClass::Multicast),
+            ("ff02::1", IpClass::Multicast),
+            ("255.255.255.255", IpClass::Reserved),
+            ("198.18.0.1", IpClass::Reserved),
+            ("8.8.8.8", IpClass::Global),
+            ("2606:4700::1111", IpClass::Global),
+            ("::ffff:192.168.1.1", IpClass::Private),
+        ];
+        for (addr, class) in cases {
+            assert_eq!(ip_class(addr.parse().unwrap()), class, "{addr}");
+        }
+    }
+
+    #[test]
+    fn filter_global_entries() {
+        let hosts: crate::hosts_file::HostsFile =
+            "127.0.0.1 localhost\n10.0.0.5 db\n1.1.1.1 sneaky\n"
+                .parse()
+                .unwrap();
+        let global: Vec<_> = hosts
+            .records()
+            .iter()
+            .filter(|r| r.ip_class() == IpClass::Global)
+            .map(|r| r.names()[0].as_str())
+            .collect();
+        assert_eq!(global, ["sneaky"]);
+    }
+}
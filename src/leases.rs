@@ -0,0 +1,310 @@
+//! hosts records out of dhcp server leases, so the clients on a home lab
+//! network can be published in a static hosts file
+//!
+//! both dnsmasq's `dnsmasq.leases` and isc dhcpd's `dhcpd.leases` are
+//! read. only leases that are still good at `now` and came with a host
+//! name make it in, everything else is reported as skipped. each record
+//! gets the client's mac in its comment
+
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::expiry::days_from_civil;
+use crate::record::{Record, RecordError};
+
+#[derive(Debug)]
+pub enum SkipReason {
+    /// ran out before `now`
+    Expired,
+    /// isc leases that are `free`, `backup` or anything but `active`
+    Inactive,
+    /// the client never sent a name, dnsmasq writes `*` for those
+    NoHostname,
+    /// the name the client sent isnt a valid host name
+    InvalidName(RecordError),
+    /// a line or lease block we couldnt make sense of
+    Malformed,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Expired => write!(f, "lease expired"),
+            SkipReason::Inactive => write!(f, "lease isnt active"),
+            SkipReason::NoHostname => write!(f, "client sent no host name"),
+            SkipReason::InvalidName(e) => write!(f, "{e}"),
+            SkipReason::Malformed => write!(f, "not a lease"),
+        }
+    }
+}
+
+/// a lease that didnt make it into the output
+#[derive(Debug)]
+pub struct SkippedLease {
+    /// 1-based line the lease starts on
+    pub line: usize,
+    pub addr: Option<IpAddr>,
+    pub reason: SkipReason,
+}
+
+/// what came out of a leases file
+#[derive(Debug, Default)]
+pub struct LeaseImport {
+    /// one record per lease, in file order
+    pub records: Vec<Record>,
+    pub skipped: Vec<SkippedLease>,
+}
+
+struct Lease {
+    line: usize,
+    addr: IpAddr,
+    mac: Option<String>,
+    hostname: Option<String>,
+    /// none for leases that never run out
+    ends: Option<SystemTime>,
+    active: bool,
+}
+
+impl LeaseImport {
+    fn push(&mut self, lease: Lease, now: SystemTime) {
+        let skip = |reason| SkippedLease {
+            line: lease.line,
+            addr: Some(lease.addr),
+            reason,
+        };
+        if !lease.active {
+            return self.skipped.push(skip(SkipReason::Inactive));
+        }
+        if lease.ends.is_some_and(|ends| ends <= now) {
+            return self.skipped.push(skip(SkipReason::Expired));
+        }
+        let Some(hostname) = lease.hostname.clone() else {
+            return self.skipped.push(skip(SkipReason::NoHostname));
+        };
+        let record = Record::new(lease.addr, vec![hostname]).and_then(|record| match &lease.mac {
+            Some(mac) => record.with_comment(format!("dhcp {mac}")),
+            None => Ok(record),
+        });
+        match record {
+            Ok(record) => self.records.push(record),
+            Err(e) => self.skipped.push(skip(SkipReason::InvalidName(e))),
+        }
+    }
+}
+
+/// `dnsmasq.leases`, a lease a line: expiry time, mac, address, host name
+/// and client id. an expiry of 0 never runs out. the `duid` line ahead of
+/// the v6 leases is passed over
+pub fn dnsmasq(text: &str, now: SystemTime) -> LeaseImport {
+    let mut out = LeaseImport::default();
+    for (i, line) in text.lines().enumerate() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.is_empty() || fields[0] == "duid" {
+            continue;
+        }
+        let parsed = match fields.as_slice() {
+            [expiry, mac, addr, hostname, ..] => expiry
+                .parse::<u64>()
+                .ok()
+                .zip(addr.parse::<IpAddr>().ok())
+                .map(|(expiry, addr)| Lease {
+                    line: i + 1,
+                    addr,
+                    // v6 leases have the iaid here
+                    mac: addr.is_ipv4().then(|| mac.to_string()),
+                    hostname: (*hostname != "*").then(|| hostname.to_string()),
+                    ends: (expiry != 0).then(|| UNIX_EPOCH + Duration::from_secs(expiry)),
+                    active: true,
+                }),
+            _ => None,
+        };
+        match parsed {
+            Some(lease) => out.push(lease, now),
+            None => out.skipped.push(SkippedLease {
+                line: i + 1,
+                addr: None,
+                reason: SkipReason::Malformed,
+            }),
+        }
+    }
+    out
+}
+
+/// isc dhcpd's `dhcpd.leases`, `lease <addr> { ... }` blocks. the file is
+/// appended to as leases change, so the last block for an address is the
+/// one that counts
+pub fn isc(text: &str, now: SystemTime) -> LeaseImport {
+    let mut out = LeaseImport::default();
+    let mut leases: Vec<Lease> = Vec::new();
+    let mut current: Option<Lease> = None;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if let Some(lease) = current.as_mut() {
+            if line == "}" {
+                let lease = current.take().expect("inside a lease");
+                match leases.iter_mut().find(|l| l.addr == lease.addr) {
+                    Some(old) => *old = lease,
+                    None => leases.push(lease),
+                }
+                continue;
+            }
+            let statement = line.trim_end_matches(';');
+            let (key, value) = statement.split_once(' ').unwrap_or((statement, ""));
+            match key {
+                "ends" => lease.ends = isc_time(value),
+                "binding" => lease.active = value == "state active",
+                "hardware" => lease.mac = value.strip_prefix("ethernet ").map(str::to_string),
+                "client-hostname" => lease.hostname = Some(value.trim_matches('"').to_string()),
+                _ => {}
+            }
+            continue;
+        }
+        let Some(rest) = line.strip_prefix("lease ") else {
+            continue;
+        };
+        match rest.trim_end_matches('{').trim().parse() {
+            Ok(addr) => {
+                current = Some(Lease {
+                    line: i + 1,
+                    addr,
+                    mac: None,
+                    hostname: None,
+                    ends: None,
+                    // older files dont write a binding state at all
+                    active: true,
+                })
+            }
+            Err(_) => out.skipped.push(SkippedLease {
+                line: i + 1,
+                addr: None,
+                reason: SkipReason::Malformed,
+            }),
+        }
+    }
+    if let Some(lease) = current {
+        out.skipped.push(SkippedLease {
+            line: lease.line,
+            addr: Some(lease.addr),
+            reason: SkipReason::Malformed,
+        });
+    }
+    for lease in leases {
+        out.push(lease, now);
+    }
+    out
+}
+
+/// `4 2025/06/05 22:00:00` in utc, `epoch 1749160800` or `never`. a time
+/// we cant read counts as never, so the lease isnt thrown out over it
+fn isc_time(value: &str) -> Option<SystemTime> {
+    let mut words = value.split_whitespace();
+    let first = words.next()?;
+    if first == "epoch" {
+        let secs = words.next()?.parse().ok()?;
+        return Some(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+    let mut date = words.next()?.split('/').map(|n| n.parse::<i64>().ok());
+    let mut time = words.next()?.split(':').map(|n| n.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(u64::try_from(secs).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn dnsmasq_leases() {
+        let out = dnsmasq(
+            "1749160800 00:11:22:33:44:55 192.168.1.10 laptop 01:00:11:22:33:44:55\n\
+             1749000000 00:11:22:33:44:66 192.168.1.11 phone *\n\
+             0 00:11:22:33:44:77 192.168.1.12 * *\n\
+             0 00:11:22:33:44:88 192.168.1.13 nas *\n\
+             duid 00:01:00:01:2b:2c:2d:2e\n\
+             1749160800 1234 fd00::10 laptop6 00:01\n\
+             garbage\n",
+            at(1_749_100_000),
+        );
+        let records = out
+            .records
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            [
+                "192.168.1.10\tlaptop # dhcp 00:11:22:33:44:55",
+                "192.168.1.13\tnas # dhcp 00:11:22:33:44:88",
+                "fd00::10\tlaptop6",
+            ]
+        );
+        let skipped = out
+            .skipped
+            .iter()
+            .map(|s| (s.line, s.reason.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                (2, "lease expired".to_string()),
+                (3, "client sent no host name".to_string()),
+                (7, "not a lease".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn isc_leases() {
+        let out = isc(
+            "# The format of this file is documented in the dhcpd.leases(5) manual page.\n\
+             lease 192.168.1.10 {\n\
+             \x20 starts 4 2025/06/05 10:00:00;\n\
+             \x20 ends 4 2025/06/05 22:00:00;\n\
+             \x20 binding state active;\n\
+             \x20 hardware ethernet 00:11:22:33:44:55;\n\
+             \x20 client-hostname \"laptop\";\n\
+             }\n\
+             lease 192.168.1.11 {\n\
+             \x20 ends epoch 1749160800; # Thu Jun 05 22:00:00 2025\n\
+             \x20 binding state free;\n\
+             \x20 client-hostname \"phone\";\n\
+             }\n\
+             lease 192.168.1.10 {\n\
+             \x20 ends never;\n\
+             \x20 binding state active;\n\
+             \x20 hardware ethernet 00:11:22:33:44:55;\n\
+             \x20 client-hostname \"laptop-renamed\";\n\
+             }\n\
+             lease 192.168.1.12 {\n\
+             \x20 ends 4 2025/06/05 10:00:00;\n\
+             \x20 client-hostname \"old box\";\n\
+             }\n",
+            at(1_749_120_000),
+        );
+        assert_eq!(out.records.len(), 1);
+        assert_eq!(
+            out.records[0].to_string(),
+            "192.168.1.10\tlaptop-renamed # dhcp 00:11:22:33:44:55"
+        );
+        let skipped = out
+            .skipped
+            .iter()
+            .map(|s| (s.line, s.reason.to_string()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            skipped,
+            [
+                (9, "lease isnt active".to_string()),
+                (20, "lease expired".to_string())
+            ]
+        );
+        assert_eq!(isc_time("4 2025/06/05 22:00:00"), Some(at(1_749_160_800)));
+    }
+}
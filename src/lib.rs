@@ -1,140 +1,164 @@
-/// the etc/hosts file is used to statically define local dns records
-/// the format of this file is quite simple
-///
-/// address \t name, name.domain, name-alias, name-aliai
-/// address \t name
-///
-/// or any combination of the sort
-
-use std::fs::File;
-use std::io::{self, BufRead};
-use std::net::IpAddr;
-use std::path::Path;
-use thiserror::Error;
-
-#[derive(Error, Debug)]
-pub enum RecordError {
-    #[error("Invalid Ipv4Addr: Must be private or global")]
-    InvalidIpAddress(String),
-}
-
-/// Record is a way of representing a single entry in the hosts files
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Record {
-    /// addr for the record
-    addr: IpAddr,
-    /// here we have multiple names for a single record
-    names: Vec<String>,
-}
-impl Record {
-    pub fn new(addr: IpAddr, names: Vec<String>) -> Result<Self, RecordError> {
-        // I would love to use is_global here as well but it is only a nightly feature
-        // may upgrade to nightly later on
-        if addr.is_ipv4() || addr.is_ipv6() {
-            return Ok(Self {
-                addr: addr,
-                names: names,
-            });
-        }
-
-        Err(RecordError::InvalidIpAddress(addr.to_string()))
-    }
-}
-
-#[derive(Error, Debug)]
-pub enum ParserError {
-    #[error(transparent)]
-    CouldNotOpen(#[from] std::io::Error),
-
-    #[error("bad ipv4 addr, not global or loopback")]
-    ParseError(#[from] std::net::AddrParseError),
-
-    #[error("unknown")]
-    Unknown(String),
-}
-/// Parser is a way we can extract Records from the etc/hosts file
-#[derive(Debug)]
-struct Parser {
-    line: i64,
-    records: Vec<Record>,
-}
-
-impl Default for Parser {
-    fn default() -> Parser {
-        let records: Vec<Record> = Vec::new();
-        Parser {
-            line: 0,
-            records: records,
-        }
-    }
-}
-
-impl Parser {
-    pub fn parse(&mut self, file: &Path) -> Result<&Vec<Record>, ParserError> {
-        let file = File::open(file)?;
-        let buff = io::BufReader::new(file).lines();
-
-        for line in buff {
-            if let Ok(a) = line {
-                if a.is_empty() {
-                    continue;
-                }
-                if a.starts_with('#') {
-                    continue;
-                }
-                // dont worry about tabs, gersh darnit
-                let a = a.replace("\t", " ");
-
-                let mut record_info = a
-                    .split(' ')
-                    .filter(|&s| !s.is_empty())
-                    .collect::<Vec<&str>>();
-
-                let name = record_info.remove(0).to_string();
-
-                let addrs = record_info
-                    .iter()
-                    .map(|&s| s.to_string())
-                    .collect::<Vec<String>>();
-
-                if let Ok(record) = Record::new(name.parse()?, addrs) {
-                    self.records.push(record);
-                };
-            }
-        }
-
-        Ok(&self.records)
-    }
+//! the etc/hosts file is used to statically define local dns records
+//! the format of this file is quite simple
+//!
+//! address \t name, name.domain, name-alias, name-aliai
+//! address \t name
+//!
+//! or any combination of the sort
+//!
+//! everything works on std by default. with `default-features = false`
+//! only the `line` module is left, which builds on no_std
+//!
+//! for wasm32-unknown-unknown turn off `std-fs` and keep `std`, which
+//! drops everything that opens or writes files. parsing and writing out
+//! strings still works, `parse_str` in and `to_string` back out
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+/// items that need std, which is everything but the bare line format
+macro_rules! with_std {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "std")]
+            $item
+        )*
+    };
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn create_loopback() {
-        let addr = "127.0.0.1".parse().unwrap();
-        let names: Vec<String> = vec!["localhost".to_string()];
-        let record = Record::new(addr, names);
-        assert!(record.is_ok())
-    }
-
-    #[test]
-    fn create_private() {
-        let addr = "192.168.10.42".parse().unwrap();
-        let names: Vec<String> = vec!["core.naus".to_string()];
-        let record = Record::new(addr, names);
-        assert!(record.is_ok())
-    }
-
-    #[test]
-    fn test_parser() {
-        use std::path::Path;
-        let mut parser: Parser = Default::default();
-        let path = Path::new("/etc/hosts");
-        match parser.parse(path) {
-            Ok(_v) => println!("good to go"),
-            Err(e) => println!("{e:?}"),
-        }
-    }
+pub mod line;
+
+with_std! {
+    #[cfg(feature = "abp")]
+    pub mod abp;
+    #[cfg(feature = "std-fs")]
+    pub mod audit;
+    mod blocklist;
+    mod borrowed;
+    mod builder;
+    mod cidr;
+    mod compact;
+    pub mod csv;
+    mod dedupe;
+    #[cfg(feature = "std-fs")]
+    pub mod diagnostics;
+    pub mod diff;
+    #[cfg(feature = "dns-server")]
+    pub mod dns;
+    mod document;
+    pub mod drift;
+    mod encoding;
+    mod expiry;
+    #[cfg(feature = "ethers")]
+    pub mod ethers;
+    pub mod export;
+    #[cfg(feature = "ffi")]
+    pub mod ffi;
+    mod fingerprint;
+    mod formatter;
+    mod fxhash;
+    mod groups;
+    #[cfg(feature = "std-fs")]
+    mod hijack;
+    mod hostname;
+    mod hosts_file;
+    #[cfg(feature = "idna")]
+    pub mod idna;
+    // fingerprints punycode names whatever the features are
+    #[cfg(not(feature = "idna"))]
+    #[allow(dead_code)]
+    mod idna;
+    mod ip_class;
+    pub mod leases;
+    pub mod lint;
+    #[cfg(all(feature = "if-addrs", unix))]
+    pub mod local;
+    #[cfg(feature = "std-fs")]
+    mod lock;
+    mod managed;
+    mod mdns;
+    mod merge;
+    #[cfg(all(feature = "mmap", unix))]
+    pub mod mmap;
+    #[cfg(feature = "networks")]
+    pub mod networks;
+    #[cfg(feature = "async")]
+    pub mod nonblocking;
+    pub mod nsswitch;
+    #[cfg(feature = "parallel")]
+    mod parallel;
+    mod parser;
+    mod policy;
+    #[cfg(feature = "probe")]
+    pub mod probe;
+    #[cfg(feature = "prometheus")]
+    pub mod prometheus;
+    #[cfg(feature = "psl")]
+    pub mod psl;
+    mod provenance;
+    mod query;
+    mod reader;
+    mod record;
+    #[cfg(feature = "http")]
+    pub mod remote;
+    pub mod resolvconf;
+    mod resolver;
+    mod report;
+    mod sha256;
+    #[cfg(feature = "signing")]
+    pub mod signing;
+    #[cfg(feature = "std-fs")]
+    pub mod snapshot;
+    mod sort;
+    mod stats;
+    mod tags;
+    pub mod tcpwrappers;
+    #[cfg(feature = "std-fs")]
+    mod source_set;
+    mod template;
+    #[cfg(test)]
+    mod testing;
+    #[cfg(feature = "std-fs")]
+    mod transaction;
+    pub mod verify;
+    #[cfg(feature = "watch")]
+    pub mod watch;
+    mod writer;
+
+    pub use blocklist::{is_sink, BLOCK_SINK};
+    pub use borrowed::{parse_borrowed, BorrowedRecords, RecordRef};
+    pub use builder::RecordBuilder;
+    pub use cidr::{Cidr, CidrError};
+    pub use compact::{CompactHostsFile, CompactRecord};
+    pub use dedupe::{DedupePolicy, DuplicateAddr, DuplicateName, Duplicates};
+    pub use diff::HostsDiff;
+    pub use drift::{ConvergeOptions, DriftReport};
+    pub use document::{HostsDocument, Line, RecordLine};
+    pub use encoding::{Encoding, InvalidUtf8, LineEnding};
+    pub use fingerprint::{Fingerprint, FingerprintError};
+    pub use formatter::FormatOptions;
+    pub use hostname::{validate_hostname, HostnameError, Strictness};
+    pub use hosts_file::{parse_reader, parse_str, HostsFile};
+    pub use ip_class::{ip_class, IpClass};
+    #[cfg(feature = "std-fs")]
+    pub use lock::{lock_path, HostsLock};
+    pub use managed::{ManagedBlock, Markers};
+    pub use mdns::MdnsConflict;
+    pub use merge::{MergeError, MergePolicy};
+    pub use parser::{Limit, ParseOptions, ParseWarning, ParserError, Span};
+    pub use policy::{PolicyViolation, ValidationPolicy};
+    pub use provenance::Source;
+    pub use reader::HostsReader;
+    pub use record::{Record, RecordError};
+    pub use resolver::Resolver;
+    pub use report::ParseReport;
+    pub use sort::SortOrder;
+    pub use stats::Stats;
+    #[cfg(feature = "std-fs")]
+    pub use source_set::{SourceSet, SourceSetError};
+    pub use template::{HostsTemplate, TemplateError};
+    #[cfg(feature = "std-fs")]
+    pub use transaction::Transaction;
+    pub use writer::Alignment;
+    #[cfg(feature = "std-fs")]
+    pub use writer::{backup_path, Escalation, WriteOptions};
 }
@@ -0,0 +1,204 @@
+//! the bare line format, pulled out of the rest of the parser so it only
+//! needs `core`
+//!
+//! this is what builds with `default-features = false` for firmware and
+//! other no_std targets. it splits lines into their columns and parses the
+//! address, hostname rules and everything that touches files are left to
+//! the std side of the crate
+
+use core::fmt;
+use core::net::{AddrParseError, IpAddr, Ipv6Addr};
+use core::str::{Lines, SplitWhitespace};
+
+/// a record line cut into its columns, nothing checked yet
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Fields<'a> {
+    /// the address as written, scope id and all
+    pub addr: &'a str,
+    /// the names column, still separated by whatever whitespace was there.
+    /// empty when the line is just an address
+    pub names: &'a str,
+    pub comment: Option<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    /// the address and its scope id. std cant parse zone ids like the
+    /// `lo0` in `fe80::1%lo0` so they get split off first
+    pub fn ip(&self) -> Result<(IpAddr, Option<&'a str>), AddrParseError> {
+        let (ip, scope) = match self.addr.split_once('%') {
+            Some((ip, scope)) => (ip, Some(scope)),
+            None => (self.addr, None),
+        };
+        Ok((ip.parse()?, scope))
+    }
+}
+
+/// cut `line` into its columns. blank lines and comments give back `None`
+pub fn split(line: &str) -> Option<Fields<'_>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    // anything after a # is a comment, even halfway through a line
+    let (line, comment) = match line.split_once('#') {
+        Some((line, comment)) => (line, Some(comment.trim())),
+        None => (line, None),
+    };
+
+    // dont worry about tabs, gersh darnit
+    let line = line.trim_end();
+    let (addr, names) = match line.find(char::is_whitespace) {
+        Some(i) => (&line[..i], line[i..].trim_start()),
+        None => (line, ""),
+    };
+    Some(Fields {
+        addr,
+        names,
+        comment,
+    })
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LineError {
+    /// an address and nothing else
+    MissingNames,
+    InvalidAddress(AddrParseError),
+    /// a scope id on an address that cant have one
+    InvalidScope,
+}
+
+impl fmt::Display for LineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LineError::MissingNames => write!(f, "address has no names"),
+            LineError::InvalidAddress(e) => write!(f, "bad address: {e}"),
+            LineError::InvalidScope => write!(f, "only link-local ipv6 addresses take a scope id"),
+        }
+    }
+}
+
+impl core::error::Error for LineError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            LineError::InvalidAddress(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// a parsed record line, borrowing its names out of the text
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Entry<'a> {
+    pub addr: IpAddr,
+    pub scope: Option<&'a str>,
+    names: &'a str,
+    pub comment: Option<&'a str>,
+}
+
+impl<'a> Entry<'a> {
+    /// primary name first
+    pub fn names(&self) -> SplitWhitespace<'a> {
+        self.names.split_whitespace()
+    }
+}
+
+/// parse a single line. names are taken as they are, there are no
+/// hostname rules down here
+pub fn parse(line: &str) -> Result<Option<Entry<'_>>, LineError> {
+    let Some(fields) = split(line) else {
+        return Ok(None);
+    };
+    if fields.names.is_empty() {
+        return Err(LineError::MissingNames);
+    }
+    let (addr, scope) = fields.ip().map_err(LineError::InvalidAddress)?;
+    if scope.is_some_and(|scope| scope.is_empty() || !scopable(addr)) {
+        return Err(LineError::InvalidScope);
+    }
+    Ok(Some(Entry {
+        addr,
+        scope,
+        names: fields.names,
+        comment: fields.comment,
+    }))
+}
+
+/// every record line in `text` along with its 1-based line number
+pub fn entries(text: &str) -> Entries<'_> {
+    Entries {
+        lines: text.strip_prefix('\u{feff}').unwrap_or(text).lines(),
+        line: 0,
+    }
+}
+
+/// iterator from `entries`
+#[derive(Clone, Debug)]
+pub struct Entries<'a> {
+    lines: Lines<'a>,
+    line: usize,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = (usize, Result<Entry<'a>, LineError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            self.line += 1;
+            match parse(line) {
+                Ok(None) => continue,
+                Ok(Some(entry)) => return Some((self.line, Ok(entry))),
+                Err(e) => return Some((self.line, Err(e))),
+            }
+        }
+        None
+    }
+}
+
+/// true for the addresses a scope id means something on, link-local and
+/// multicast ipv6
+pub fn scopable(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V6(v6) => is_link_local(&v6) || v6.is_multicast(),
+        IpAddr::V4(_) => false,
+    }
+}
+
+/// fe80::/10, Ipv6Addr::is_unicast_link_local is still unstable
+pub(crate) fn is_link_local(addr: &Ipv6Addr) -> bool {
+    (addr.segments()[0] & 0xffc0) == 0xfe80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_columns() {
+        let fields = split("  10.0.0.5\tdb   db.lan # primary ").unwrap();
+        assert_eq!(fields.addr, "10.0.0.5");
+        assert_eq!(fields.names, "db   db.lan");
+        assert_eq!(fields.comment, Some("primary"));
+        assert_eq!(split("fe80::1%lo0 x").unwrap().ip().unwrap().1, Some("lo0"));
+        assert!(split("   # just a comment").is_none());
+        assert!(split("").is_none());
+    }
+
+    #[test]
+    fn parse_entries() {
+        let text = "\u{feff}# head\n0.0.0.0 ads.example ads2.example\n\n10.0.0.5\nnope x\n10.0.0.1%eth0 y\n";
+        let mut entries = entries(text);
+        let (line, entry) = entries.next().unwrap();
+        assert_eq!(line, 2);
+        let entry = entry.unwrap();
+        assert_eq!(entry.addr, IpAddr::from([0, 0, 0, 0]));
+        assert_eq!(entry.names().last(), Some("ads2.example"));
+        assert_eq!(entries.next(), Some((4, Err(LineError::MissingNames))));
+        assert!(matches!(
+            entries.next(),
+            Some((5, Err(LineError::InvalidAddress(_))))
+        ));
+        assert_eq!(entries.next(), Some((6, Err(LineError::InvalidScope))));
+        assert_eq!(entries.next(), None);
+    }
+}
@@ -0,0 +1,366 @@
+//! a linter for hosts files. unlike the parser this never gives up, it
+//! walks every line and hands back everything it thinks is off, each with
+//! a code, how bad it is and what to do about it
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::document::{HostsDocument, Line};
+use crate::hostname::{fold_name, validate_hostname, HostnameError, Strictness};
+use crate::mdns::{is_mdns_name, suggested_name, EXPLANATION};
+use crate::parser::{parse_line_with, ParseOptions, ParserError};
+use crate::record::{Record, RecordError};
+
+/// macOS quietly cuts hosts lines off past this many characters
+const MAX_LINE_LEN: usize = 255;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+    /// worth knowing, nothing is broken
+    Info,
+    /// works but probably not the way you meant
+    Warning,
+    /// the resolver will skip or misread this
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LintCode {
+    /// the first field isnt an ip address
+    InvalidAddress,
+    /// an address with nothing after it
+    MissingNames,
+    /// a scope id on an address that cant have one
+    InvalidScope,
+    /// a name that breaks the hostname rules
+    InvalidHostname,
+    /// a name past the 253 byte dns limit
+    NameTooLong,
+    /// a line long enough that macOS will truncate it
+    LineTooLong,
+    /// a name that shows up for more than one address, only the first wins
+    ConflictingName,
+    /// the same name for the same address on more than one line
+    DuplicateName,
+    /// nothing maps localhost to loopback
+    MissingLocalhost,
+    /// a `.local` name, which mdns gets to first
+    MdnsName,
+}
+
+impl LintCode {
+    /// the stable name of the check, for filtering and config files
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintCode::InvalidAddress => "invalid-address",
+            LintCode::MissingNames => "missing-names",
+            LintCode::InvalidScope => "invalid-scope",
+            LintCode::InvalidHostname => "invalid-hostname",
+            LintCode::NameTooLong => "name-too-long",
+            LintCode::LineTooLong => "line-too-long",
+            LintCode::ConflictingName => "conflicting-name",
+            LintCode::DuplicateName => "duplicate-name",
+            LintCode::MissingLocalhost => "missing-localhost",
+            LintCode::MdnsName => "mdns-name",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintCode::InvalidAddress
+            | LintCode::MissingNames
+            | LintCode::InvalidScope
+            | LintCode::InvalidHostname
+            | LintCode::NameTooLong => Severity::Error,
+            LintCode::LineTooLong
+            | LintCode::ConflictingName
+            | LintCode::MissingLocalhost
+            | LintCode::MdnsName => Severity::Warning,
+            LintCode::DuplicateName => Severity::Info,
+        }
+    }
+}
+
+impl fmt::Display for LintCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// one problem the linter found
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub code: LintCode,
+    pub severity: Severity,
+    /// 1-based line number, `None` for problems with the file as a whole
+    pub line: Option<usize>,
+    pub message: String,
+    /// what would make it go away
+    pub suggestion: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(code: LintCode, line: Option<usize>, message: String) -> Self {
+        Diagnostic {
+            code,
+            severity: code.severity(),
+            line,
+            message,
+            suggestion: None,
+        }
+    }
+
+    fn suggest(mut self, suggestion: impl Into<String>) -> Self {
+        self.suggestion = Some(suggestion.into());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {line}: ")?;
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " ({suggestion})")?;
+        }
+        Ok(())
+    }
+}
+
+/// lint hosts file text. names arent validated during the parse so every
+/// bad name on a line gets reported, not just the first one
+pub fn check(text: &str) -> Vec<Diagnostic> {
+    let options = ParseOptions {
+        hostnames: Strictness::Off,
+        ..Default::default()
+    };
+    match HostsDocument::parse_with(text, &options) {
+        Ok((doc, _)) => doc.lint(),
+        // lenient parsing only fails on things like a bad encoding
+        Err(e) => vec![Diagnostic::new(
+            LintCode::InvalidAddress,
+            None,
+            e.to_string(),
+        )],
+    }
+}
+
+impl HostsDocument {
+    /// everything the linter has to say about this document, in line order
+    /// with the file wide problems at the end
+    pub fn lint(&self) -> Vec<Diagnostic> {
+        let mut out = Vec::new();
+        // first address and line seen for each name, per address family
+        // since a name with one v4 and one v6 address is perfectly normal
+        let mut seen: HashMap<(String, bool), (IpAddr, usize)> = HashMap::new();
+        let mut has_localhost = false;
+
+        for (i, line) in self.lines().iter().enumerate() {
+            let n = i + 1;
+            let record = match line {
+                Line::Record(line) => line.record().clone(),
+                Line::Invalid(raw) => match parse_line_with(raw, Strictness::Off) {
+                    Ok(Some(record)) => record,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        out.push(parse_diagnostic(n, &e));
+                        continue;
+                    }
+                },
+                Line::Comment(_) | Line::Blank(_) => continue,
+            };
+
+            let len = line.to_string().chars().count();
+            if len > MAX_LINE_LEN && record.names().len() > 1 {
+                out.push(
+                    Diagnostic::new(
+                        LintCode::LineTooLong,
+                        Some(n),
+                        format!("line is {len} characters, macOS stops reading at {MAX_LINE_LEN}"),
+                    )
+                    .suggest("split the names over more than one line for the same address"),
+                );
+            }
+
+            for name in record.names() {
+                if let Err(e) = validate_hostname(name, Strictness::Lenient) {
+                    out.push(hostname_diagnostic(n, name, &e));
+                    continue;
+                }
+                if is_mdns_name(name) {
+                    out.push(
+                        Diagnostic::new(
+                            LintCode::MdnsName,
+                            Some(n),
+                            format!("{name}: {EXPLANATION}"),
+                        )
+                        .suggest(format!("rename it to {}", suggested_name(name))),
+                    );
+                }
+                if fold_name(name) == "localhost" && record.addr().is_loopback() {
+                    has_localhost = true;
+                }
+                check_duplicate(&mut out, &mut seen, n, name, &record);
+            }
+        }
+
+        if !has_localhost {
+            out.push(
+                Diagnostic::new(
+                    LintCode::MissingLocalhost,
+                    None,
+                    "nothing maps localhost to a loopback address".to_string(),
+                )
+                .suggest("add `127.0.0.1 localhost` and `::1 localhost`"),
+            );
+        }
+        out
+    }
+}
+
+fn check_duplicate(
+    out: &mut Vec<Diagnostic>,
+    seen: &mut HashMap<(String, bool), (IpAddr, usize)>,
+    n: usize,
+    name: &str,
+    record: &Record,
+) {
+    let addr = record.addr();
+    let key = (fold_name(name).into_owned(), addr.is_ipv4());
+    let Some(&(first, at)) = seen.get(&key) else {
+        seen.insert(key, (addr, n));
+        return;
+    };
+    if at == n {
+        return;
+    }
+    let diagnostic = if first == addr {
+        Diagnostic::new(
+            LintCode::DuplicateName,
+            Some(n),
+            format!("{name} is already mapped to {addr} on line {at}"),
+        )
+        .suggest(format!("remove {name} from this line"))
+    } else {
+        Diagnostic::new(
+            LintCode::ConflictingName,
+            Some(n),
+            format!("{name} resolves to {first} from line {at}, so {addr} here is never used"),
+        )
+        .suggest(format!("remove {name} from one of lines {at} and {n}"))
+    };
+    out.push(diagnostic);
+}
+
+fn parse_diagnostic(n: usize, e: &ParserError) -> Diagnostic {
+    match e {
+        ParserError::InvalidAddress { token, .. } => Diagnostic::new(
+            LintCode::InvalidAddress,
+            Some(n),
+            format!("{token:?} is not an ip address"),
+        )
+        .suggest("start the line with an ipv4 or ipv6 address, or comment it out with #"),
+        ParserError::MissingNames(addr) => Diagnostic::new(
+            LintCode::MissingNames,
+            Some(n),
+            format!("{addr} has no names after it"),
+        )
+        .suggest("add a hostname after the address or remove the line"),
+        ParserError::InvalidRecord(RecordError::InvalidScope(addr, scope)) => Diagnostic::new(
+            LintCode::InvalidScope,
+            Some(n),
+            format!("{addr} cant have a scope id ({scope})"),
+        )
+        .suggest(format!("drop the %{scope}, only link-local ipv6 takes one")),
+        e => Diagnostic::new(LintCode::InvalidAddress, Some(n), e.to_string()),
+    }
+}
+
+fn hostname_diagnostic(n: usize, name: &str, e: &HostnameError) -> Diagnostic {
+    match e {
+        HostnameError::TooLong(len) => Diagnostic::new(
+            LintCode::NameTooLong,
+            Some(n),
+            format!("name is {len} bytes, dns names stop at 253"),
+        )
+        .suggest("shorten the name"),
+        e => Diagnostic::new(
+            LintCode::InvalidHostname,
+            Some(n),
+            format!("{name:?} is not a valid hostname: {e}"),
+        )
+        .suggest("use letters, digits, hyphens and dots only"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(text: &str) -> Vec<(Option<usize>, LintCode)> {
+        check(text).into_iter().map(|d| (d.line, d.code)).collect()
+    }
+
+    #[test]
+    fn clean_file() {
+        assert!(check("127.0.0.1 localhost\n::1 localhost\n10.0.0.5 db\n").is_empty());
+    }
+
+    #[test]
+    fn finds_problems() {
+        let long = format!("10.0.0.9 {}", vec!["alias.example.com"; 20].join(" "));
+        let text = format!(
+            "# lab\n\
+            127.0.0.1 localhost\n\
+            localhost 127.0.0.1\n\
+            10.0.0.5\n\
+            10.0.0.5%eth0 db\n\
+            10.0.0.6 -bad ok also_ok\n\
+            10.0.0.7 db\n\
+            10.0.0.8 web\n\
+            10.0.0.8 WEB\n\
+            {long}\n\
+            10.0.0.10 nas.local\n"
+        );
+        assert_eq!(
+            codes(&text),
+            [
+                (Some(3), LintCode::InvalidAddress),
+                (Some(4), LintCode::MissingNames),
+                (Some(5), LintCode::InvalidScope),
+                (Some(6), LintCode::InvalidHostname),
+                (Some(9), LintCode::DuplicateName),
+                (Some(10), LintCode::LineTooLong),
+                (Some(11), LintCode::MdnsName),
+            ]
+        );
+    }
+
+    #[test]
+    fn conflicts_and_missing_localhost() {
+        let diags = check("10.0.0.5 db\n10.0.0.6 db\n");
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].code, LintCode::ConflictingName);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert_eq!(
+            diags[0].to_string(),
+            "line 2: warning[conflicting-name]: db resolves to 10.0.0.5 from line 1, so \
+             10.0.0.6 here is never used (remove db from one of lines 1 and 2)"
+        );
+        assert_eq!(diags[1].code, LintCode::MissingLocalhost);
+        assert_eq!(diags[1].line, None);
+    }
+}
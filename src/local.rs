@@ -0,0 +1,366 @@
+//! entries for this machine, its name pointed at every address its
+//! interfaces have, the way a provisioning script fills in /etc/hosts on
+//! a fresh box
+//!
+//! the interfaces come from getifaddrs(3) and the name from
+//! gethostname(3), so this is unix only
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+
+use thiserror::Error;
+
+use crate::document::{Line, RecordLine};
+use crate::hostname::names_eq;
+use crate::hosts_file::HostsFile;
+use crate::line::is_link_local;
+use crate::record::{Record, RecordError};
+use crate::resolvconf::{self, ResolvConf};
+
+/// where debian and its children point the machine name, so it resolves
+/// without any network up and doesnt clash with `localhost` on 127.0.0.1
+pub const HOSTNAME_ADDR: Ipv4Addr = Ipv4Addr::new(127, 0, 1, 1);
+
+#[derive(Error, Debug)]
+pub enum LocalError {
+    #[error("the machine name doesnt work in a hosts file: {0}")]
+    InvalidHostname(#[from] RecordError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// an address on one of the interfaces
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Interface {
+    /// `eth0`, `en0`
+    pub name: String,
+    pub addr: IpAddr,
+    pub loopback: bool,
+}
+
+/// what the machine calls itself
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LocalNames {
+    /// the short name, `web1`
+    pub hostname: String,
+    /// `web1.example.com`, when the name has a domain or resolv.conf
+    /// gives one
+    pub fqdn: Option<String>,
+}
+
+impl LocalNames {
+    /// the names in the order they go on a line, fqdn first
+    pub fn names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        names.extend(self.fqdn.clone());
+        names.push(self.hostname.clone());
+        names
+    }
+}
+
+/// the name gethostname gives back, and the fqdn off it or off the
+/// domain in /etc/resolv.conf
+pub fn local_names() -> io::Result<LocalNames> {
+    let name = sys::hostname()?;
+    let names = match name.split_once('.') {
+        Some((short, _)) => LocalNames {
+            hostname: short.to_string(),
+            fqdn: Some(name.clone()),
+        },
+        None => {
+            // a broken or missing resolv.conf just means no domain
+            let domain = ResolvConf::open(resolvconf::PATH)
+                .ok()
+                .and_then(|conf| conf.search_list().first().map(|d| d.to_string()));
+            LocalNames {
+                fqdn: domain.map(|d| format!("{name}.{}", d.trim_end_matches('.'))),
+                hostname: name,
+            }
+        }
+    };
+    Ok(names)
+}
+
+/// every address on every interface that is up
+pub fn interfaces() -> io::Result<Vec<Interface>> {
+    sys::interfaces()
+}
+
+/// a record for each interface address with the machine names on it.
+/// loopback addresses are left out, `HostsFile::ensure_hostname_line`
+/// covers those
+pub fn interface_records() -> Result<Vec<Record>, LocalError> {
+    records_for(&local_names()?, &interfaces()?)
+}
+
+/// `interface_records` for names and interfaces that were worked out
+/// some other way. link-local v6 addresses get the interface as their
+/// scope, they mean nothing without one
+pub fn records_for(
+    names: &LocalNames,
+    interfaces: &[Interface],
+) -> Result<Vec<Record>, LocalError> {
+    let mut records: Vec<Record> = Vec::new();
+    for iface in interfaces {
+        if iface.loopback
+            || iface.addr.is_loopback()
+            || records.iter().any(|r| r.addr() == iface.addr)
+        {
+            continue;
+        }
+        let record = match iface.addr {
+            IpAddr::V6(v6) if is_link_local(&v6) => {
+                Record::scoped(iface.addr, iface.name.clone(), names.names())?
+            }
+            addr => Record::new(addr, names.names())?,
+        };
+        records.push(record);
+    }
+    Ok(records)
+}
+
+impl HostsFile {
+    /// make sure there is a `127.0.1.1 <fqdn> <hostname>` line. one that
+    /// is already there gets the names swapped in, otherwise it goes right
+    /// after the `127.0.0.1` line or at the end. lines elsewhere with the
+    /// machine names stay as they are. gives back whether anything changed
+    pub fn ensure_hostname_line(&mut self, names: &LocalNames) -> Result<bool, RecordError> {
+        let wanted = names.names();
+        let record = Record::new(HOSTNAME_ADDR.into(), wanted.clone())?;
+        if let Some(policy) = self.policy() {
+            policy.check(&record)?;
+        }
+        let addr = IpAddr::from(HOSTNAME_ADDR);
+        let localhost = IpAddr::from(Ipv4Addr::LOCALHOST);
+        let same = |r: &Record| {
+            r.names().len() == wanted.len()
+                && r.names().iter().zip(&wanted).all(|(a, b)| names_eq(a, b))
+        };
+        Ok(self.edit(|doc| {
+            let lines = doc.lines_mut();
+            let existing = lines
+                .iter()
+                .position(|line| matches!(line, Line::Record(l) if l.record().addr() == addr));
+            if let Some(i) = existing {
+                let Line::Record(line) = &mut lines[i] else {
+                    unreachable!("found as a record");
+                };
+                if same(line.record()) {
+                    return false;
+                }
+                let mut updated = line.record().clone();
+                updated.names = record.names.clone();
+                line.set_record(updated);
+                return true;
+            }
+            let after = lines
+                .iter()
+                .position(|line| matches!(line, Line::Record(l) if l.record().addr() == localhost));
+            let line = Line::Record(RecordLine::new(record));
+            match after {
+                Some(i) => lines.insert(i + 1, line),
+                None => doc.push(line),
+            }
+            true
+        }))
+    }
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+))]
+mod sys {
+    use std::ffi::CStr;
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    use std::os::raw::{c_char, c_int, c_uint};
+    use std::ptr;
+
+    use super::Interface;
+
+    const IFF_UP: c_uint = 0x1;
+    const IFF_LOOPBACK: c_uint = 0x8;
+    const AF_INET: u8 = 2;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const AF_INET6: u8 = 10;
+    #[cfg(any(target_os = "macos", target_os = "ios"))]
+    const AF_INET6: u8 = 30;
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    const AF_INET6: u8 = 28;
+    #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+    const AF_INET6: u8 = 24;
+
+    /// the start of `struct ifaddrs`, which is laid out the same on all of
+    /// these as far as the address
+    #[repr(C)]
+    struct IfAddrs {
+        next: *mut IfAddrs,
+        name: *const c_char,
+        flags: c_uint,
+        addr: *const u8,
+    }
+
+    extern "C" {
+        fn getifaddrs(ifap: *mut *mut IfAddrs) -> c_int;
+        fn freeifaddrs(ifa: *mut IfAddrs);
+        fn gethostname(name: *mut c_char, len: usize) -> c_int;
+    }
+
+    /// the family out of a sockaddr, the bsds have a length byte first
+    unsafe fn family(sa: *const u8) -> u8 {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            *sa.cast::<u16>() as u8
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "android")))]
+        {
+            *sa.add(1)
+        }
+    }
+
+    pub(super) fn interfaces() -> io::Result<Vec<Interface>> {
+        let mut head = ptr::null_mut();
+        // SAFETY: on success head is a list we own until freeifaddrs
+        if unsafe { getifaddrs(&mut head) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut out = Vec::new();
+        let mut cur = head;
+        while !cur.is_null() {
+            // SAFETY: every node and what it points at lives until freeifaddrs
+            let ifa = unsafe { &*cur };
+            cur = ifa.next;
+            if ifa.addr.is_null() || ifa.flags & IFF_UP == 0 {
+                continue;
+            }
+            // SAFETY: the address is a sockaddr big enough for its family,
+            // sockaddr_in has the address at 4 and sockaddr_in6 at 8
+            let addr: IpAddr = unsafe {
+                match family(ifa.addr) {
+                    AF_INET => Ipv4Addr::from(*ifa.addr.add(4).cast::<[u8; 4]>()).into(),
+                    AF_INET6 => Ipv6Addr::from(*ifa.addr.add(8).cast::<[u8; 16]>()).into(),
+                    _ => continue,
+                }
+            };
+            // SAFETY: the name is a nul terminated string
+            let name = unsafe { CStr::from_ptr(ifa.name) };
+            out.push(Interface {
+                name: name.to_string_lossy().into_owned(),
+                addr,
+                loopback: ifa.flags & IFF_LOOPBACK != 0,
+            });
+        }
+        // SAFETY: head came from getifaddrs and isnt touched after this
+        unsafe { freeifaddrs(head) };
+        Ok(out)
+    }
+
+    pub(super) fn hostname() -> io::Result<String> {
+        let mut buf = [0 as c_char; 256];
+        // SAFETY: the length leaves room for the nul we put on the end
+        if unsafe { gethostname(buf.as_mut_ptr(), buf.len() - 1) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the last byte is still 0, so there is a nul in there
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+        Ok(name.to_string_lossy().into_owned())
+    }
+}
+
+/// the rest of unix has a different sockaddr layout we dont know
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "openbsd",
+    target_os = "netbsd"
+)))]
+mod sys {
+    use std::io;
+
+    use super::Interface;
+
+    pub(super) fn interfaces() -> io::Result<Vec<Interface>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    pub(super) fn hostname() -> io::Result<String> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names() -> LocalNames {
+        LocalNames {
+            hostname: "web1".into(),
+            fqdn: Some("web1.example.com".into()),
+        }
+    }
+
+    #[test]
+    fn records_and_hostname_line() {
+        let iface = |name: &str, addr: &str| Interface {
+            name: name.into(),
+            addr: addr.parse().unwrap(),
+            loopback: name == "lo",
+        };
+        let records = records_for(
+            &names(),
+            &[
+                iface("lo", "127.0.0.1"),
+                iface("eth0", "10.0.0.5"),
+                iface("eth0", "fe80::1"),
+                iface("eth1", "10.0.0.5"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            records.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            [
+                "10.0.0.5\tweb1.example.com web1",
+                "fe80::1%eth0\tweb1.example.com web1"
+            ]
+        );
+
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n::1 localhost\n".parse().unwrap();
+        assert!(hosts.ensure_hostname_line(&names()).unwrap());
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n127.0.1.1\tweb1.example.com web1\n::1 localhost\n"
+        );
+        assert!(!hosts.ensure_hostname_line(&names()).unwrap());
+        // renamed machine, the line gets the new names where it sits
+        let renamed = LocalNames {
+            hostname: "web2".into(),
+            fqdn: None,
+        };
+        assert!(hosts.ensure_hostname_line(&renamed).unwrap());
+        assert_eq!(hosts.lookup("web2"), Some(HOSTNAME_ADDR.into()));
+        assert_eq!(hosts.lookup("web1"), None);
+    }
+
+    // whatever this machine has, a container can come with no interfaces
+    // up or a name that isnt a valid hostname, so only check the calls work
+    #[test]
+    fn this_machine() {
+        let names = local_names().unwrap();
+        assert!(!names.hostname.is_empty());
+        let interfaces = interfaces().unwrap();
+        if let Ok(records) = records_for(&names, &interfaces) {
+            assert!(records.iter().all(|r| !r.addr().is_loopback()));
+        }
+    }
+}
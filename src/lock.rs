@@ -0,0 +1,375 @@
+//! advisory locking so two tools editing the same hosts file dont stomp
+//! on each other
+//!
+//! the lock is taken on a `<path>.lock` file next to the hosts file and not
+//! on the file itself, since every write renames a fresh file into place
+//! and a lock on the old one would go with it. flock on unix, LockFileEx
+//! on windows. its advisory, so only the tools that ask for it are kept
+//! out
+//!
+//! on unix the lock file is unlinked again on the way out, while it is
+//! still held, so no stray `/etc/hosts.lock` is left lying around. anyone
+//! who was waiting on it then holds a lock on a file that is gone, so
+//! after getting the lock we check the path still points at our file and
+//! go again when it doesnt. windows wont let a file that is open
+//! somewhere else go away cleanly, so there it stays
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+
+/// how often `lock_timeout` tries again
+const RETRY_EVERY: Duration = Duration::from_millis(20);
+
+/// where the lock for `path` goes
+pub fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+/// an exclusive lock on a hosts file, let go of when dropped
+#[derive(Debug)]
+pub struct HostsLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl HostsLock {
+    /// wait for as long as it takes to get the lock on `path`
+    pub fn lock<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let lock = acquire(path.as_ref(), true)?;
+        Ok(lock.expect("a blocking lock always ends up held"))
+    }
+
+    /// take the lock if nobody else has it, `None` when somebody does
+    pub fn try_lock<P: AsRef<Path>>(path: P) -> io::Result<Option<Self>> {
+        acquire(path.as_ref(), false)
+    }
+
+    /// keep trying for up to `timeout`, fails with `TimedOut` after that
+    pub fn lock_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> io::Result<Self> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(lock) = Self::try_lock(path.as_ref())? {
+                return Ok(lock);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("{} is locked by someone else", path.as_ref().display()),
+                ));
+            }
+            thread::sleep(RETRY_EVERY.min(deadline - now));
+        }
+    }
+
+    /// the lock file, not the hosts file. it only exists while somebody
+    /// holds the lock, except on windows
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for HostsLock {
+    fn drop(&mut self) {
+        // gone before the unlock, so nobody new can open it and think they
+        // have the lock once we let go
+        if sys::REMOVE_ON_DROP {
+            let _ = fs::remove_file(&self.path);
+        }
+        // closing the file lets go of the lock too, this is just sooner
+        let _ = sys::unlock(&self.file);
+    }
+}
+
+/// `None` when `block` is off and someone else has the lock
+fn acquire(path: &Path, block: bool) -> io::Result<Option<HostsLock>> {
+    let path = lock_path(path);
+    loop {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match sys::lock(&file, block) {
+            Ok(()) => {}
+            Err(e) if sys::is_contended(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        // whoever had it before may have removed it on their way out
+        if sys::still_at(&file, &path)? {
+            return Ok(Some(HostsLock { file, path }));
+        }
+    }
+}
+
+impl HostsFile {
+    /// read, change and write `path` back while holding its lock, so
+    /// nobody else using `HostsLock` can get in between
+    pub fn update<P, T>(path: P, f: impl FnOnce(&mut HostsFile) -> T) -> Result<T, ParserError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let _lock = HostsLock::lock(path)?;
+        let mut hosts = HostsFile::open(path)?;
+        let out = f(&mut hosts);
+        hosts.write_to(path)?;
+        Ok(out)
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::fs::{self, File};
+    use std::io;
+    use std::os::raw::c_int;
+    use std::os::unix::fs::MetadataExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    pub(super) const REMOVE_ON_DROP: bool = true;
+
+    const LOCK_EX: c_int = 2;
+    const LOCK_NB: c_int = 4;
+    const LOCK_UN: c_int = 8;
+
+    extern "C" {
+        fn flock(fd: c_int, operation: c_int) -> c_int;
+    }
+
+    pub(super) fn lock(file: &File, block: bool) -> io::Result<()> {
+        let op = if block { LOCK_EX } else { LOCK_EX | LOCK_NB };
+        loop {
+            // SAFETY: the fd is open for as long as `file` is borrowed
+            if unsafe { flock(file.as_raw_fd(), op) } == 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+
+    pub(super) fn unlock(file: &File) -> io::Result<()> {
+        // SAFETY: same as above
+        match unsafe { flock(file.as_raw_fd(), LOCK_UN) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    pub(super) fn is_contended(err: &io::Error) -> bool {
+        err.kind() == io::ErrorKind::WouldBlock
+    }
+
+    /// whether `path` is still the file we have open
+    pub(super) fn still_at(file: &File, path: &Path) -> io::Result<bool> {
+        let ours = file.metadata()?;
+        match fs::metadata(path) {
+            Ok(now) => Ok(now.dev() == ours.dev() && now.ino() == ours.ino()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::ffi::c_void;
+    use std::fs::File;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::path::Path;
+
+    pub(super) const REMOVE_ON_DROP: bool = false;
+
+    const LOCKFILE_FAIL_IMMEDIATELY: u32 = 0x1;
+    const LOCKFILE_EXCLUSIVE_LOCK: u32 = 0x2;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    #[repr(C)]
+    struct Overlapped {
+        internal: usize,
+        internal_high: usize,
+        offset: u32,
+        offset_high: u32,
+        event: *mut c_void,
+    }
+
+    impl Overlapped {
+        fn zeroed() -> Self {
+            Overlapped {
+                internal: 0,
+                internal_high: 0,
+                offset: 0,
+                offset_high: 0,
+                event: std::ptr::null_mut(),
+            }
+        }
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn LockFileEx(
+            file: *mut c_void,
+            flags: u32,
+            reserved: u32,
+            bytes_low: u32,
+            bytes_high: u32,
+            overlapped: *mut Overlapped,
+        ) -> i32;
+        fn UnlockFileEx(
+            file: *mut c_void,
+            reserved: u32,
+            bytes_low: u32,
+            bytes_high: u32,
+            overlapped: *mut Overlapped,
+        ) -> i32;
+    }
+
+    pub(super) fn lock(file: &File, block: bool) -> io::Result<()> {
+        let mut flags = LOCKFILE_EXCLUSIVE_LOCK;
+        if !block {
+            flags |= LOCKFILE_FAIL_IMMEDIATELY;
+        }
+        let mut overlapped = Overlapped::zeroed();
+        // SAFETY: the handle is open for as long as `file` is borrowed and
+        // the call is synchronous so `overlapped` outlives it
+        let ok = unsafe {
+            LockFileEx(
+                file.as_raw_handle(),
+                flags,
+                0,
+                u32::MAX,
+                u32::MAX,
+                &mut overlapped,
+            )
+        };
+        match ok {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn unlock(file: &File) -> io::Result<()> {
+        let mut overlapped = Overlapped::zeroed();
+        // SAFETY: same as above
+        let ok =
+            unsafe { UnlockFileEx(file.as_raw_handle(), 0, u32::MAX, u32::MAX, &mut overlapped) };
+        match ok {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    pub(super) fn is_contended(err: &io::Error) -> bool {
+        err.raw_os_error() == Some(ERROR_LOCK_VIOLATION)
+    }
+
+    /// the file is never removed here, so it is always still there
+    pub(super) fn still_at(_: &File, _: &Path) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use std::fs::File;
+    use std::io;
+    use std::path::Path;
+
+    pub(super) const REMOVE_ON_DROP: bool = false;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "file locking isnt supported here",
+        )
+    }
+
+    pub(super) fn lock(_: &File, _: bool) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(super) fn unlock(_: &File) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(super) fn is_contended(_: &io::Error) -> bool {
+        false
+    }
+
+    pub(super) fn still_at(_: &File, _: &Path) -> io::Result<bool> {
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn exclusive_until_dropped() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-lock-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let lock = HostsLock::lock(&path).unwrap();
+        assert_eq!(lock.path(), dir.join("hosts.lock"));
+        assert!(HostsLock::try_lock(&path).unwrap().is_none());
+        let err = HostsLock::lock_timeout(&path, Duration::from_millis(50)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        drop(lock);
+        #[cfg(unix)]
+        assert!(!dir.join("hosts.lock").exists());
+
+        let added = HostsFile::update(&path, |hosts| {
+            assert!(HostsLock::try_lock(&path).unwrap().is_none());
+            hosts.set("db", "10.0.0.5".parse().unwrap()).is_ok()
+        })
+        .unwrap();
+        assert!(added);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.5\tdb\n"
+        );
+        assert!(HostsLock::try_lock(&path).unwrap().is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn waiter_follows_removed_lock_file() {
+        let dir =
+            std::env::temp_dir().join(format!("hosts-digger-lock-wait-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+
+        let first = HostsLock::lock(&path).unwrap();
+        let waiter = {
+            let path = path.clone();
+            thread::spawn(move || HostsLock::lock(path).unwrap())
+        };
+        thread::sleep(Duration::from_millis(50));
+        drop(first);
+        let second = waiter.join().unwrap();
+        // the waiter had the old file open, it had to come back for the new one
+        assert!(second.path().exists());
+        assert!(HostsLock::try_lock(&path).unwrap().is_none());
+        drop(second);
+        assert!(!dir.join("hosts.lock").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,373 @@
+//! fenced off sections that some tool owns, the way docker desktop and
+//! vagrant-hostmanager keep their entries in
+//!
+//! ```text
+//! # BEGIN hosts-digger
+//! 10.0.0.5    db
+//! # END hosts-digger
+//! ```
+//!
+//! replacing a block only ever touches the lines between its markers.
+//! `inject_entries` does the same for sections with other markers, like
+//! the `# Added by Docker Desktop` one
+
+use std::ops::Range;
+
+use crate::document::{HostsDocument, Line, RecordLine};
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+/// a named block of lines between `# BEGIN <name>` and `# END <name>`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ManagedBlock {
+    name: String,
+    records: Vec<Record>,
+    lines: Range<usize>,
+}
+
+impl ManagedBlock {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the records between the markers
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    /// which lines of the document the block covers, markers included
+    pub fn lines(&self) -> Range<usize> {
+        self.lines.clone()
+    }
+}
+
+enum Marker<'a> {
+    Begin(&'a str),
+    End(&'a str),
+}
+
+fn marker(line: &Line) -> Option<Marker<'_>> {
+    let Line::Comment(text) = line else {
+        return None;
+    };
+    let text = text.trim().strip_prefix('#')?.trim_start();
+    if let Some(name) = text.strip_prefix("BEGIN ") {
+        Some(Marker::Begin(name.trim()))
+    } else {
+        text.strip_prefix("END ")
+            .map(|name| Marker::End(name.trim()))
+    }
+}
+
+/// the comment lines around a section `inject_entries` owns, without the
+/// leading `#`
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Markers {
+    pub begin: String,
+    pub end: String,
+}
+
+impl Markers {
+    pub fn new(begin: impl Into<String>, end: impl Into<String>) -> Self {
+        Markers {
+            begin: begin.into(),
+            end: end.into(),
+        }
+    }
+
+    /// `# BEGIN <name>` and `# END <name>`, the same as a managed block
+    pub fn named(name: &str) -> Self {
+        Markers::new(format!("BEGIN {name}"), format!("END {name}"))
+    }
+
+    /// what docker desktop fences its entries off with
+    pub fn docker_desktop() -> Self {
+        Markers::new("Added by Docker Desktop", "End of section")
+    }
+
+    fn is(text: &str, line: &Line) -> bool {
+        matches!(line, Line::Comment(c)
+            if c.trim().strip_prefix('#').is_some_and(|c| c.trim() == text))
+    }
+
+    /// the lines from the first begin marker to the end marker after it
+    fn find(&self, lines: &[Line]) -> Option<Range<usize>> {
+        let start = lines.iter().position(|l| Markers::is(&self.begin, l))?;
+        let end = lines[start + 1..]
+            .iter()
+            .position(|l| Markers::is(&self.end, l))?;
+        Some(start..start + end + 2)
+    }
+}
+
+impl HostsDocument {
+    /// every managed block in the file, in order. a BEGIN without a
+    /// matching END isnt a block and gets left alone
+    pub fn managed_blocks(&self) -> Vec<ManagedBlock> {
+        let lines = self.lines();
+        let mut blocks = Vec::new();
+        let mut i = 0;
+        while i < lines.len() {
+            let Some(Marker::Begin(name)) = marker(&lines[i]) else {
+                i += 1;
+                continue;
+            };
+            let end = lines[i + 1..]
+                .iter()
+                .position(|line| matches!(marker(line), Some(Marker::End(end)) if end == name));
+            match end {
+                Some(offset) => {
+                    let end = i + 1 + offset;
+                    let records = lines[i + 1..end]
+                        .iter()
+                        .filter_map(|line| match line {
+                            Line::Record(line) => Some(line.record().clone()),
+                            _ => None,
+                        })
+                        .collect();
+                    blocks.push(ManagedBlock {
+                        name: name.to_string(),
+                        records,
+                        lines: i..end + 1,
+                    });
+                    i = end + 1;
+                }
+                None => i += 1,
+            }
+        }
+        blocks
+    }
+
+    pub fn managed_block(&self, name: &str) -> Option<ManagedBlock> {
+        self.managed_blocks().into_iter().find(|b| b.name == name)
+    }
+
+    /// make the block called `name` hold exactly `records`. an existing
+    /// block is rewritten in place, otherwise a new one goes at the end
+    pub fn set_managed_block<I>(&mut self, name: &str, records: I)
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        let body = records
+            .into_iter()
+            .map(|record| Line::Record(RecordLine::new(record)));
+        match self.managed_block(name) {
+            Some(block) => {
+                let inner = block.lines.start + 1..block.lines.end - 1;
+                self.lines_mut().splice(inner, body);
+            }
+            None => {
+                self.push(Line::Comment(format!("# BEGIN {name}")));
+                for line in body {
+                    self.push(line);
+                }
+                self.push(Line::Comment(format!("# END {name}")));
+            }
+        }
+    }
+
+    /// take out the block called `name`, markers and all
+    pub fn remove_managed_block(&mut self, name: &str) -> bool {
+        match self.managed_block(name) {
+            Some(block) => {
+                self.lines_mut().drain(block.lines);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl HostsDocument {
+    /// the records between `markers`, none when there is no such section
+    pub fn injected_entries(&self, markers: &Markers) -> Option<Vec<Record>> {
+        let range = markers.find(self.lines())?;
+        let inner = &self.lines()[range.start + 1..range.end - 1];
+        Some(
+            inner
+                .iter()
+                .filter_map(|line| match line {
+                    Line::Record(line) => Some(line.record().clone()),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// make the section between `markers` hold exactly `entries`, adding
+    /// it at the end when it isnt there. running it again with the same
+    /// entries doesnt touch the file, for tools that re-inject every time
+    /// a container starts. gives back whether anything changed
+    pub fn inject_entries<I>(&mut self, entries: I, markers: &Markers) -> bool
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        let entries: Vec<Record> = entries.into_iter().collect();
+        if self.injected_entries(markers).as_ref() == Some(&entries) {
+            return false;
+        }
+        let body = entries
+            .into_iter()
+            .map(|record| Line::Record(RecordLine::new(record)));
+        match markers.find(self.lines()) {
+            Some(range) => {
+                self.lines_mut()
+                    .splice(range.start + 1..range.end - 1, body);
+            }
+            None => {
+                self.push(Line::Comment(format!("# {}", markers.begin)));
+                for line in body {
+                    self.push(line);
+                }
+                self.push(Line::Comment(format!("# {}", markers.end)));
+            }
+        }
+        true
+    }
+
+    /// take out the section between `markers`, markers and all
+    pub fn remove_injected(&mut self, markers: &Markers) -> bool {
+        match markers.find(self.lines()) {
+            Some(range) => {
+                self.lines_mut().drain(range);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl HostsFile {
+    pub fn managed_blocks(&self) -> Vec<ManagedBlock> {
+        self.document().managed_blocks()
+    }
+
+    pub fn managed_block(&self, name: &str) -> Option<ManagedBlock> {
+        self.document().managed_block(name)
+    }
+
+    /// see `HostsDocument::set_managed_block`
+    pub fn set_managed_block<I>(&mut self, name: &str, records: I)
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        self.edit(|doc| doc.set_managed_block(name, records))
+    }
+
+    pub fn remove_managed_block(&mut self, name: &str) -> bool {
+        self.edit(|doc| doc.remove_managed_block(name))
+    }
+
+    pub fn injected_entries(&self, markers: &Markers) -> Option<Vec<Record>> {
+        self.document().injected_entries(markers)
+    }
+
+    /// see `HostsDocument::inject_entries`
+    pub fn inject_entries<I>(&mut self, entries: I, markers: &Markers) -> bool
+    where
+        I: IntoIterator<Item = Record>,
+    {
+        self.edit(|doc| doc.inject_entries(entries, markers))
+    }
+
+    pub fn remove_injected(&mut self, markers: &Markers) -> bool {
+        self.edit(|doc| doc.remove_injected(markers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "127.0.0.1 localhost\n\
+        # BEGIN docker\n\
+        192.168.65.2   host.docker.internal\n\
+        # END docker\n\
+        # BEGIN broken\n\
+        10.0.0.1  web   # hand edited\n";
+
+    fn record(addr: &str, name: &str) -> Record {
+        Record::new(addr.parse().unwrap(), vec![name.to_string()]).unwrap()
+    }
+
+    #[test]
+    fn finds_blocks() {
+        let doc: HostsDocument = HOSTS.parse().unwrap();
+        let blocks = doc.managed_blocks();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].name(), "docker");
+        assert_eq!(blocks[0].lines(), 1..4);
+        assert_eq!(blocks[0].records()[0].names(), ["host.docker.internal"]);
+    }
+
+    #[test]
+    fn replace_only_touches_the_block() {
+        let mut hosts: HostsFile = HOSTS.parse().unwrap();
+        hosts.set_managed_block(
+            "docker",
+            vec![record("192.168.65.3", "gateway.docker.internal")],
+        );
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n\
+            # BEGIN docker\n\
+            192.168.65.3\tgateway.docker.internal\n\
+            # END docker\n\
+            # BEGIN broken\n\
+            10.0.0.1  web   # hand edited\n"
+        );
+        assert_eq!(hosts.lookup("host.docker.internal"), None);
+        assert!(hosts.lookup("gateway.docker.internal").is_some());
+    }
+
+    #[test]
+    fn new_block_goes_at_the_end() {
+        let mut hosts: HostsFile = "127.0.0.1 localhost\n".parse().unwrap();
+        hosts.set_managed_block("hosts-digger", vec![record("10.0.0.5", "db")]);
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n# BEGIN hosts-digger\n10.0.0.5\tdb\n# END hosts-digger\n"
+        );
+
+        assert!(hosts.remove_managed_block("hosts-digger"));
+        assert!(!hosts.remove_managed_block("hosts-digger"));
+        assert_eq!(hosts.to_string(), "127.0.0.1 localhost\n");
+    }
+
+    #[test]
+    fn docker_style_injection() {
+        let src = "127.0.0.1 localhost\n\
+            # Added by Docker Desktop\n\
+            # To allow the same kube context to work on the host and the container:\n\
+            127.0.0.1   kubernetes.docker.internal\n\
+            # End of section\n";
+        let markers = Markers::docker_desktop();
+        let mut hosts: HostsFile = src.parse().unwrap();
+        let existing = hosts.injected_entries(&markers).unwrap();
+        assert!(!hosts.inject_entries(existing, &markers));
+        assert_eq!(hosts.to_string(), src);
+
+        let entries = vec![
+            record("127.0.0.1", "kubernetes.docker.internal"),
+            record("192.168.65.2", "host.docker.internal"),
+        ];
+        assert!(hosts.inject_entries(entries.clone(), &markers));
+        assert!(!hosts.inject_entries(entries, &markers));
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1 localhost\n\
+            # Added by Docker Desktop\n\
+            127.0.0.1\tkubernetes.docker.internal\n\
+            192.168.65.2\thost.docker.internal\n\
+            # End of section\n"
+        );
+
+        assert!(hosts.remove_injected(&markers));
+        assert!(!hosts.remove_injected(&markers));
+        assert_eq!(hosts.to_string(), "127.0.0.1 localhost\n");
+
+        let markers = Markers::named("myapp");
+        hosts.inject_entries(vec![record("10.0.0.5", "db")], &markers);
+        assert_eq!(hosts.managed_block("myapp").unwrap().records().len(), 1);
+    }
+}
@@ -0,0 +1,104 @@
+//! names under `.local`, which belongs to multicast dns. a hosts entry
+//! for one is at best slow and at worst never read: macOS asks mdns first
+//! and waits on it, and linux setups with `mdns4_minimal [NOTFOUND=return]`
+//! ahead of `files` stop before they get to the hosts file
+
+use std::fmt;
+use std::net::IpAddr;
+
+use crate::document::Line;
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+
+/// what to tell someone about a `.local` name
+pub(crate) const EXPLANATION: &str =
+    ".local is reserved for mdns, lookups for it go to mdns first and may never reach the hosts file";
+
+/// a `.local` name in a hosts file
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MdnsConflict {
+    /// 1-based line the name is on
+    pub line: usize,
+    pub name: String,
+    pub addr: IpAddr,
+    /// the same name under `.home.arpa`, which is set aside for exactly
+    /// this by rfc 8375
+    pub suggestion: String,
+}
+
+impl MdnsConflict {
+    /// why this is a problem, the same for every conflict
+    pub fn explanation(&self) -> &'static str {
+        EXPLANATION
+    }
+}
+
+/// true for `.local` and anything under it, trailing dot and case aside
+pub(crate) fn is_mdns_name(name: &str) -> bool {
+    let name = fold_name(name.strip_suffix('.').unwrap_or(name));
+    name == "local" || name.ends_with(".local")
+}
+
+/// `printer.local` to `printer.home.arpa`
+pub(crate) fn suggested_name(name: &str) -> String {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let stem = &name[..name.len() - "local".len()];
+    format!("{stem}home.arpa")
+}
+
+impl HostsFile {
+    /// every `.local` name in the file, in line order
+    pub fn find_mdns_conflicts(&self) -> Vec<MdnsConflict> {
+        let mut out = Vec::new();
+        for (i, line) in self.document().lines().iter().enumerate() {
+            let Line::Record(line) = line else {
+                continue;
+            };
+            let record = line.record();
+            for name in record.names().iter().filter(|n| is_mdns_name(n)) {
+                out.push(MdnsConflict {
+                    line: i + 1,
+                    name: name.clone(),
+                    addr: record.addr(),
+                    suggestion: suggested_name(name),
+                });
+            }
+        }
+        out
+    }
+}
+
+impl fmt::Display for MdnsConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {} ({EXPLANATION}), rename it to {}",
+            self.line, self.name, self.suggestion
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn local_names() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            # lab\n\
+            10.0.0.5 nas nas.LOCAL. printer.local\n\
+            10.0.0.6 local.example.com\n"
+            .parse()
+            .unwrap();
+        let conflicts = hosts.find_mdns_conflicts();
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].line, 3);
+        assert_eq!(conflicts[0].suggestion, "nas.home.arpa");
+        assert_eq!(conflicts[1].name, "printer.local");
+        assert!(conflicts[1]
+            .to_string()
+            .ends_with("rename it to printer.home.arpa"));
+        assert!(!is_mdns_name("localhost"));
+        assert!(!is_mdns_name("notlocal"));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::document::{HostsDocument, Line, RecordLine};
+use crate::hostname::names_eq;
+use crate::hosts_file::HostsFile;
+use crate::provenance::Source;
+use crate::record::Record;
+use std::net::IpAddr;
+use thiserror::Error;
+
+/// what to do when a merged file maps a name somewhere else
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum MergePolicy {
+    /// the file being merged in wins, conflicting names move over to it
+    #[default]
+    Override,
+    /// what is already there wins, new names get tacked onto an existing
+    /// line for the same address when there is one
+    AppendAliases,
+    /// refuse to merge if any name would change address
+    Error,
+}
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum MergeError {
+    #[error(
+        "{name} maps to {existing} but the merged file has it at {incoming}{}",
+        cite(existing_source, incoming_source)
+    )]
+    Conflict {
+        name: String,
+        existing: IpAddr,
+        incoming: IpAddr,
+        /// where the mapping thats already there came from, if tracked
+        existing_source: Option<Source>,
+        /// where the mapping being merged in came from, if tracked
+        incoming_source: Option<Source>,
+    },
+}
+
+/// ` (base:3 vs override:1)` when we know where either side came from
+fn cite(existing: &Option<Source>, incoming: &Option<Source>) -> String {
+    let show = |s: &Option<Source>| s.as_ref().map_or("?".to_string(), Source::to_string);
+    if existing.is_none() && incoming.is_none() {
+        return String::new();
+    }
+    format!(" ({} vs {})", show(existing), show(incoming))
+}
+
+impl HostsFile {
+    /// fold the records from `other` into this file
+    ///
+    /// only records come across, comments and blank lines in `other` are
+    /// left behind. with `MergePolicy::Error` nothing changes if there is
+    /// a conflict
+    pub fn merge(&mut self, other: &HostsFile, policy: MergePolicy) -> Result<(), MergeError> {
+        if policy == MergePolicy::Error {
+            for record in other.records() {
+                for name in record.names() {
+                    match self.lookup(name) {
+                        Some(existing) if existing != record.addr() => {
+                            let existing_source = self
+                                .records()
+                                .iter()
+                                .find(|r| r.names().iter().any(|n| names_eq(n, name)))
+                                .and_then(|r| r.source().cloned());
+                            return Err(MergeError::Conflict {
+                                name: name.clone(),
+                                existing,
+                                incoming: record.addr(),
+                                existing_source,
+                                incoming_source: record.source().cloned(),
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        self.edit(|doc| {
+            for record in other.records() {
+                match policy {
+                    MergePolicy::Override => merge_override(doc, record),
+                    MergePolicy::AppendAliases | MergePolicy::Error => merge_append(doc, record),
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+/// where `name` resolves to in `doc`, first line wins
+fn resolve(doc: &HostsDocument, name: &str) -> Option<IpAddr> {
+    doc.records()
+        .find(|r| r.names().iter().any(|n| names_eq(n, name)))
+        .map(Record::addr)
+}
+
+fn merge_override(doc: &mut HostsDocument, incoming: &Record) {
+    let addr = incoming.addr();
+    let conflicting = |name: &String| resolve(doc, name).is_some_and(|a| a != addr);
+    let moving = incoming
+        .names()
+        .iter()
+        .filter(|n| conflicting(n))
+        .cloned()
+        .collect::<Vec<String>>();
+
+    // pull the moving names off every line that doesnt point at `addr`
+    doc.lines_mut().retain_mut(|line| {
+        let Line::Record(line) = line else {
+            return true;
+        };
+        let is_moving = |n: &String| moving.iter().any(|m| names_eq(m, n));
+        if line.record().addr() == addr || !line.record().names().iter().any(is_moving) {
+            return true;
+        }
+        let mut record = line.record().clone();
+        record.names.retain(|n| !is_moving(n));
+        if record.names.is_empty() {
+            return false;
+        }
+        line.set_record(record);
+        true
+    });
+
+    let mut record = incoming.clone();
+    record.names.retain(|n| resolve(doc, n) != Some(addr));
+    if !record.names.is_empty() {
+        doc.push(Line::Record(RecordLine::new(record)));
+    }
+}
+
+fn merge_append(doc: &mut HostsDocument, incoming: &Record) {
+    let addr = incoming.addr();
+    let new_names = incoming
+        .names()
+        .iter()
+        .filter(|n| resolve(doc, n).is_none())
+        .cloned()
+        .collect::<Vec<String>>();
+    if new_names.is_empty() {
+        return;
+    }
+
+    if let Some(line) = doc.records_mut().find(|line| line.record().addr() == addr) {
+        let mut record = line.record().clone();
+        record.names.extend(new_names);
+        line.set_record(record);
+        return;
+    }
+
+    let mut record = incoming.clone();
+    record.names = new_names;
+    doc.push(Line::Record(RecordLine::new(record)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    const BASE: &str = "# base\n127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.7 cache\n";
+    const STAGING: &str = "10.1.0.5 db\n10.0.0.7 cache redis\n10.1.0.9 queue\n";
+
+    #[test]
+    fn merge_override() {
+        let mut hosts: HostsFile = BASE.parse().unwrap();
+        let staging: HostsFile = STAGING.parse().unwrap();
+        hosts.merge(&staging, MergePolicy::Override).unwrap();
+        assert_eq!(hosts.lookup("db"), Some("10.1.0.5".parse().unwrap()));
+        assert_eq!(hosts.lookup("redis"), Some("10.0.0.7".parse().unwrap()));
+        assert_eq!(
+            hosts.to_string(),
+            "# base\n127.0.0.1 localhost\n10.0.0.7 cache\n\
+             10.1.0.5\tdb\n10.0.0.7\tredis\n10.1.0.9\tqueue\n"
+        );
+    }
+
+    #[test]
+    fn merge_append_aliases() {
+        let mut hosts: HostsFile = BASE.parse().unwrap();
+        let staging: HostsFile = STAGING.parse().unwrap();
+        hosts.merge(&staging, MergePolicy::AppendAliases).unwrap();
+        assert_eq!(hosts.lookup("db"), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(
+            hosts.to_string(),
+            "# base\n127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.7\tcache redis\n10.1.0.9\tqueue\n"
+        );
+    }
+
+    #[test]
+    fn merge_error_on_conflict() {
+        let mut hosts: HostsFile = BASE.parse().unwrap();
+        let staging: HostsFile = STAGING.parse().unwrap();
+        let err = hosts.merge(&staging, MergePolicy::Error).unwrap_err();
+        assert_eq!(
+            err,
+            MergeError::Conflict {
+                name: "db".into(),
+                existing: "10.0.0.5".parse().unwrap(),
+                incoming: "10.1.0.5".parse().unwrap(),
+                existing_source: None,
+                incoming_source: None,
+            }
+        );
+        assert_eq!(hosts.to_string(), BASE);
+
+        let extra: HostsFile = "10.1.0.9 queue\n".parse().unwrap();
+        hosts.merge(&extra, MergePolicy::Error).unwrap();
+        assert_eq!(hosts.lookup("queue"), Some("10.1.0.9".parse().unwrap()));
+    }
+
+    #[test]
+    fn conflicts_cite_sources() {
+        let mut hosts = HostsFile::from_str(BASE).unwrap().with_source("base");
+        let staging = HostsFile::from_str(STAGING).unwrap().with_source("staging");
+        assert_eq!(
+            hosts.records()[1].source(),
+            Some(&Source {
+                path: "base".into(),
+                line: 3,
+            })
+        );
+        // tagging isnt an edit
+        assert_eq!(hosts.to_string(), BASE);
+
+        let err = hosts.merge(&staging, MergePolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "db maps to 10.0.0.5 but the merged file has it at 10.1.0.5 (base:3 vs staging:1)"
+        );
+
+        hosts.merge(&staging, MergePolicy::Override).unwrap();
+        let sources: Vec<_> = hosts
+            .records()
+            .iter()
+            .map(|r| r.source().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            sources,
+            ["base:2", "base:4", "staging:1", "staging:2", "staging:3"]
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! memory mapped hosts files
+//!
+//! mapping the file and parsing borrowed `RecordRef`s out of it skips both
+//! the read into a buffer and the per line allocations, which is what you
+//! want for the StevenBlack sized lists. unix only since it goes straight
+//! to mmap(2)
+
+use crate::borrowed::{parse_borrowed, BorrowedRecords};
+use std::ffi::{c_int, c_void};
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+// the same on every unix
+const PROT_READ: c_int = 1;
+const MAP_PRIVATE: c_int = 2;
+
+/// 32 bits on 32 bit linux unless built with a 64 bit off_t, which plain
+/// `mmap` isnt. 64 everywhere else
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    target_pointer_width = "32"
+))]
+#[allow(non_camel_case_types)]
+type off_t = i32;
+#[cfg(not(all(
+    any(target_os = "linux", target_os = "android"),
+    target_pointer_width = "32"
+)))]
+#[allow(non_camel_case_types)]
+type off_t = i64;
+
+extern "C" {
+    fn mmap(
+        addr: *mut c_void,
+        len: usize,
+        prot: c_int,
+        flags: c_int,
+        fd: c_int,
+        offset: off_t,
+    ) -> *mut c_void;
+    fn munmap(addr: *mut c_void, len: usize) -> c_int;
+}
+
+/// MappedHosts is a hosts file mapped read only into memory
+///
+/// the text is checked to be utf-8 once up front, see `open` for what
+/// that needs from everything else on the machine
+#[derive(Debug)]
+pub struct MappedHosts {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// the mapping is read only and never handed out mutably
+unsafe impl Send for MappedHosts {}
+unsafe impl Sync for MappedHosts {}
+
+impl MappedHosts {
+    /// map the file at `path`
+    ///
+    /// # Safety
+    ///
+    /// nothing can write to or truncate the file until the MappedHosts is
+    /// dropped. the mapping is private but still sees writes made in place
+    /// by anyone else, which could break the utf-8 that `as_str` relies on
+    /// or SIGBUS on a read past a new end. editors and this crate replace
+    /// hosts files by rename, which leaves the mapped file alone
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too big to map"))?;
+        // mmap wont map zero bytes
+        if len == 0 {
+            return Ok(Self {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+
+        // SAFETY: a fresh private read only mapping of a file we have open,
+        // the kernel picks the address
+        let ptr = mmap(
+            std::ptr::null_mut(),
+            len,
+            PROT_READ,
+            MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr as isize == -1 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mapped = Self { ptr, len };
+        std::str::from_utf8(mapped.as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(mapped)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // SAFETY: ptr..ptr+len is mapped for as long as self is around
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: checked in open, and the caller of open promised the
+        // file stays as it was
+        unsafe { std::str::from_utf8_unchecked(self.as_bytes()) }
+    }
+
+    /// every record in the file, borrowed straight out of the mapping
+    pub fn records(&self) -> BorrowedRecords<'_> {
+        parse_borrowed(self.as_str())
+    }
+}
+
+impl Drop for MappedHosts {
+    fn drop(&mut self) {
+        if self.len != 0 {
+            // SAFETY: this is the mapping we made in open
+            unsafe {
+                munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn map_and_parse() {
+        let path = std::env::temp_dir().join(format!("hosts-digger-mmap-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "# list\n0.0.0.0 ads.example\n0.0.0.0 tracker.example\n",
+        )
+        .unwrap();
+        // SAFETY: nothing else knows about this file
+        let mapped = unsafe { MappedHosts::open(&path) }.unwrap();
+        let names = mapped
+            .records()
+            .map(|r| r.unwrap().primary_name())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ads.example", "tracker.example"]);
+        drop(mapped);
+
+        std::fs::write(&path, "").unwrap();
+        let empty = unsafe { MappedHosts::open(&path) }.unwrap();
+        assert_eq!(empty.records().count(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,161 @@
+//! /etc/networks, network names to network numbers
+//!
+//! ```text
+//! loopback    127
+//! link-local  169.254.0.0
+//! lab         10.20       lab-net   # the rack in the back
+//! ```
+//!
+//! lines split the same way hosts lines do, name first this time, then
+//! the number and any aliases. numbers are dotted decimal and can leave
+//! off trailing octets, `10.20` is 10.20.0.0
+
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::net::Ipv4Addr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::line;
+
+#[cfg(feature = "std-fs")]
+pub const PATH: &str = "/etc/networks";
+
+#[derive(Error, Debug)]
+pub enum NetworksError {
+    #[error("line {line}: {name:?} has no network number")]
+    MissingNumber { line: usize, name: String },
+    #[error("line {line}: {number:?} is not a network number")]
+    InvalidNumber { line: usize, number: String },
+    #[cfg(feature = "std-fs")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Network {
+    pub name: String,
+    /// the number with any octets left off filled in as zero
+    pub number: Ipv4Addr,
+    /// how many octets were written out, so it goes back the same way
+    pub octets: u8,
+    pub aliases: Vec<String>,
+    pub comment: Option<String>,
+}
+
+impl Network {
+    /// true when `name` is the name or one of the aliases, ignoring case
+    pub fn is_named(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+            || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+/// every network in `text`, in file order
+pub fn parse(text: &str) -> Result<Vec<Network>, NetworksError> {
+    let mut networks = Vec::new();
+    for (i, raw) in text.lines().enumerate() {
+        let Some(fields) = line::split(raw) else {
+            continue;
+        };
+        let line = i + 1;
+        let mut rest = fields.names.split_whitespace();
+        let Some(number) = rest.next() else {
+            return Err(NetworksError::MissingNumber {
+                line,
+                name: fields.addr.to_string(),
+            });
+        };
+        let (parsed, octets) =
+            parse_number(number).ok_or_else(|| NetworksError::InvalidNumber {
+                line,
+                number: number.to_string(),
+            })?;
+        networks.push(Network {
+            name: fields.addr.to_string(),
+            number: parsed,
+            octets,
+            aliases: rest.map(str::to_string).collect(),
+            comment: fields.comment.map(str::to_string),
+        });
+    }
+    Ok(networks)
+}
+
+#[cfg(feature = "std-fs")]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Vec<Network>, NetworksError> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+/// the first network that goes by `name`
+pub fn lookup<'a>(networks: &'a [Network], name: &str) -> Option<&'a Network> {
+    networks.iter().find(|n| n.is_named(name))
+}
+
+fn parse_number(number: &str) -> Option<(Ipv4Addr, u8)> {
+    let mut octets = [0u8; 4];
+    let mut count = 0;
+    for part in number.split('.') {
+        if count == 4 || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        octets[count] = part.parse().ok()?;
+        count += 1;
+    }
+    Some((octets.into(), count as u8))
+}
+
+/// one line of the file, tab separated like the hosts writer
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let octets = self.number.octets();
+        let number = octets[..self.octets.clamp(1, 4) as usize]
+            .iter()
+            .map(u8::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "{}\t{}", self.name, number.join("."))?;
+        for alias in &self.aliases {
+            write!(f, " {alias}")?;
+        }
+        if let Some(comment) = &self.comment {
+            write!(f, " # {comment}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn networks_file() {
+        let networks = parse(
+            "# networks\n\
+             loopback 127\n\
+             link-local\t169.254.0.0\n\
+             lab 10.20 lab-net # the rack in the back\n",
+        )
+        .unwrap();
+        assert_eq!(networks.len(), 3);
+        assert_eq!(networks[0].number, Ipv4Addr::new(127, 0, 0, 0));
+        assert_eq!(networks[0].octets, 1);
+        let lab = lookup(&networks, "LAB-NET").unwrap();
+        assert_eq!(lab.number, Ipv4Addr::new(10, 20, 0, 0));
+        assert_eq!(lab.to_string(), "lab\t10.20 lab-net # the rack in the back");
+        assert_eq!(networks[1].to_string(), "link-local\t169.254.0.0");
+
+        assert!(matches!(
+            parse("lab\n"),
+            Err(NetworksError::MissingNumber { line: 1, .. })
+        ));
+        assert!(matches!(
+            parse("\nlab 10.300\n"),
+            Err(NetworksError::InvalidNumber { line: 2, .. })
+        ));
+        assert!(parse("lab 1.2.3.4.5\n").is_err());
+    }
+}
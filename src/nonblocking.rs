@@ -0,0 +1,172 @@
+//! async open and write for daemons that cant afford to block their
+//! runtime. the file io runs on its own thread and the future just waits
+//! for it to finish, so this works under tokio, async-std, smol or
+//! whatever else without pulling any of them in
+
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::hosts_file::HostsFile;
+use crate::parser::{ParseOptions, ParseWarning, ParserError};
+use crate::writer::{write_atomic, WriteOptions};
+
+/// a future for some blocking work running on a background thread
+#[derive(Debug)]
+pub struct Blocking<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+#[derive(Debug)]
+struct State<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    fn spawn(f: impl FnOnce() -> T + Send + 'static) -> Self {
+        let state = Arc::new(Mutex::new(State {
+            result: None,
+            waker: None,
+        }));
+        let shared = Arc::clone(&state);
+        thread::spawn(move || {
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+            let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+        Blocking { state }
+    }
+
+    /// a future that is already done, for failing before there is any
+    /// work to hand off
+    fn ready(value: T) -> Self {
+        Blocking {
+            state: Arc::new(Mutex::new(State {
+                result: Some(Ok(value)),
+                waker: None,
+            })),
+        }
+    }
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.result.take() {
+            Some(Ok(value)) => Poll::Ready(value),
+            Some(Err(panic)) => std::panic::resume_unwind(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl HostsFile {
+    /// `open` without blocking the calling task
+    pub fn open_async<P: AsRef<Path>>(path: P) -> Blocking<Result<Self, ParserError>> {
+        let path = path.as_ref().to_path_buf();
+        Blocking::spawn(move || Self::open(path))
+    }
+
+    /// `open_with` without blocking the calling task
+    pub fn open_with_async<P: AsRef<Path>>(
+        path: P,
+        options: &ParseOptions,
+    ) -> Blocking<Result<(Self, Vec<ParseWarning>), ParserError>> {
+        let path = path.as_ref().to_path_buf();
+        let options = options.clone();
+        Blocking::spawn(move || Self::open_with(path, &options))
+    }
+
+    /// `write_to` without blocking the calling task. the file is
+    /// rendered up front so later edits to `self` dont end up in it
+    pub fn write_to_async<P: AsRef<Path>>(&self, path: P) -> Blocking<io::Result<()>> {
+        self.write_to_with_async(path, &WriteOptions::default())
+    }
+
+    /// `write_to_with` without blocking the calling task, the policy is
+    /// checked before anything is handed off
+    pub fn write_to_with_async<P: AsRef<Path>>(
+        &self,
+        path: P,
+        options: &WriteOptions,
+    ) -> Blocking<io::Result<()>> {
+        if let Err(e) = self.enforce_policy() {
+            return Blocking::ready(Err(e));
+        }
+        let path: PathBuf = path.as_ref().to_path_buf();
+        let bytes = self.document().to_bytes();
+        let options = options.clone();
+        Blocking::spawn(move || write_atomic(&path, &bytes, &options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::ValidationPolicy;
+    use std::fs;
+    use std::task::Wake;
+
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = std::pin::pin!(fut);
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn open_and_write_async() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-async-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let mut hosts = block_on(HostsFile::open_async(&path)).unwrap();
+        assert_eq!(
+            hosts.lookup("localhost"),
+            Some("127.0.0.1".parse().unwrap())
+        );
+
+        hosts.set("db", "10.0.0.5".parse().unwrap()).unwrap();
+        block_on(hosts.write_to_async(&path)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.5\tdb\n"
+        );
+
+        assert!(block_on(HostsFile::open_async(dir.join("missing"))).is_err());
+
+        // the policy holds here same as in write_to
+        hosts.set_policy(Some(ValidationPolicy::new().forbid_name("db")));
+        let err = block_on(hosts.write_to_async(&path)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(fs::read_to_string(&path).unwrap().contains("10.0.0.5"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,545 @@
+//! the `hosts:` line out of /etc/nsswitch.conf, which says whether
+//! /etc/hosts gets asked at all and in what order with dns and friends
+//!
+//! ```text
+//! hosts: mymachines mdns4_minimal [NOTFOUND=return] files dns
+//! ```
+//!
+//! with that line a `.local` name that mdns doesnt know stops right there
+//! and never makes it to the hosts file. `HostsLookup::files_reachable`
+//! and `before_files` answer that sort of question
+
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::hostname::names_eq;
+use crate::hosts_file::HostsFile;
+use crate::mdns::is_mdns_name;
+
+#[cfg(feature = "std-fs")]
+pub const PATH: &str = "/etc/nsswitch.conf";
+
+/// what glibc does when nsswitch.conf has no `hosts:` line
+pub(crate) const GLIBC_DEFAULT: &str = "dns [!UNAVAIL=return] files";
+
+#[derive(Error, Debug)]
+pub enum NsSwitchError {
+    #[error("{0:?} is not a lookup status")]
+    UnknownStatus(String),
+    #[error("{0:?} is not a lookup action")]
+    UnknownAction(String),
+    #[error("{0:?} should look like STATUS=action")]
+    InvalidCriterion(String),
+    #[error("`[` without a closing `]`")]
+    UnclosedBracket,
+    #[error("`[...]` has to come after a source")]
+    CriteriaWithoutSource,
+    #[cfg(feature = "std-fs")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// how a source's lookup went
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Status {
+    Success,
+    NotFound,
+    Unavail,
+    TryAgain,
+}
+
+const STATUSES: [Status; 4] = [
+    Status::Success,
+    Status::NotFound,
+    Status::Unavail,
+    Status::TryAgain,
+];
+
+/// what happens after a source gives back a status
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Action {
+    /// stop and go with what there is
+    Return,
+    /// move on to the next source
+    Continue,
+    /// keep going and merge the results, glibc 2.24 and up
+    Merge,
+}
+
+/// `NOTFOUND=return` or `!UNAVAIL=return` in the brackets after a source
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Criterion {
+    /// `!`, the action is for every status but this one
+    pub negated: bool,
+    pub status: Status,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Source {
+    /// `files`, `dns`, `mdns4_minimal`, the nss module name
+    pub name: String,
+    pub criteria: Vec<Criterion>,
+}
+
+impl Source {
+    pub fn is_files(&self) -> bool {
+        self.name == "files"
+    }
+
+    /// what comes after this source gives back `status`. success returns
+    /// and everything else continues unless the criteria say otherwise,
+    /// later criteria win over earlier ones
+    pub fn action(&self, status: Status) -> Action {
+        let mut action = match status {
+            Status::Success => Action::Return,
+            _ => Action::Continue,
+        };
+        for c in &self.criteria {
+            if (c.status == status) != c.negated {
+                action = c.action;
+            }
+        }
+        action
+    }
+
+    /// true when a name this source doesnt know never gets past it,
+    /// because not finding it and failing both return
+    fn stops_everything(&self) -> bool {
+        STATUSES[1..]
+            .iter()
+            .all(|&status| self.action(status) == Action::Return)
+    }
+}
+
+/// the sources on the `hosts:` line, in the order they get asked
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HostsLookup {
+    pub sources: Vec<Source>,
+}
+
+impl HostsLookup {
+    /// pull the `hosts:` line out of a whole nsswitch.conf. none when it
+    /// doesnt have one, glibc falls back to `Default::default` then
+    pub fn from_nsswitch(text: &str) -> Result<Option<Self>, NsSwitchError> {
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if let Some(spec) = line.strip_prefix("hosts:") {
+                return spec.parse().map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// the `hosts:` line of the file at `path`, or the default if it
+    /// doesnt have one
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, NsSwitchError> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::from_nsswitch(&text)?.unwrap_or_default())
+    }
+
+    /// the line on this machine
+    #[cfg(feature = "std-fs")]
+    pub fn system() -> Result<Self, NsSwitchError> {
+        Self::open(PATH)
+    }
+
+    /// where `files` sits in the order
+    pub fn files_position(&self) -> Option<usize> {
+        self.sources.iter().position(Source::is_files)
+    }
+
+    /// true when `files` is on the line and a lookup can actually get to
+    /// it, nothing before it returns whatever happens
+    pub fn files_reachable(&self) -> bool {
+        match self.files_position() {
+            Some(i) => !self.sources[..i].iter().any(Source::stops_everything),
+            None => false,
+        }
+    }
+
+    /// the sources that get asked before the hosts file. any one of them
+    /// that knows a name answers for it and the hosts entry is never read
+    pub fn before_files(&self) -> &[Source] {
+        match self.files_position() {
+            Some(i) => &self.sources[..i],
+            None => &self.sources,
+        }
+    }
+
+    /// `simulate_lookup` with the sources on this line
+    pub fn simulate(&self, name: &str, hosts: &HostsFile, dns: Option<&HostsFile>) -> Simulation {
+        simulate_lookup(name, &self.sources, hosts, dns)
+    }
+}
+
+/// one source getting asked during `simulate_lookup`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Step {
+    pub source: String,
+    pub status: Status,
+    /// what it came back with, empty unless `status` is success
+    pub addrs: Vec<IpAddr>,
+    /// what happened next because of `status`
+    pub action: Action,
+    /// why the source answered the way it did when it isnt obvious
+    pub note: Option<&'static str>,
+}
+
+/// how a lookup went, every source that got asked in order
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Simulation {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+impl Simulation {
+    /// the source the answer came from. the lookup ends with whatever the
+    /// last source asked said, so one that succeeds and continues on to a
+    /// source that doesnt know the name doesnt count
+    pub fn winner(&self) -> Option<&Step> {
+        self.steps.last().filter(|s| s.status == Status::Success)
+    }
+
+    /// the addresses the lookup ends up with
+    pub fn addrs(&self) -> &[IpAddr] {
+        self.winner()
+            .map(|s| s.addrs.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+/// work out how glibc would resolve `name` asking `order` one after the
+/// other, for the "why does this resolve to the wrong address" question
+///
+/// `files` is answered out of `hosts` and `dns` and `resolve` out of the
+/// stub answers in `dns`, give `None` for a dns that is down. `myhostname`
+/// knows `localhost` and the names under it. mdns and the rest of the
+/// nss modules cant be known from here and come back unavailable, same
+/// as the minimal mdns modules really do for names outside `.local`
+pub fn simulate_lookup(
+    name: &str,
+    order: &[Source],
+    hosts: &HostsFile,
+    dns: Option<&HostsFile>,
+) -> Simulation {
+    let mut sim = Simulation {
+        name: name.to_string(),
+        steps: Vec::new(),
+    };
+    for source in order {
+        let (addrs, note) = ask(&source.name, name, hosts, dns);
+        let status = match &addrs {
+            Some(addrs) if !addrs.is_empty() => Status::Success,
+            Some(_) => Status::NotFound,
+            None => Status::Unavail,
+        };
+        // glibc only merges group results, for hosts it just moves on
+        let action = source.action(status);
+        sim.steps.push(Step {
+            source: source.name.clone(),
+            status,
+            addrs: addrs.unwrap_or_default(),
+            action,
+            note,
+        });
+        if action == Action::Return {
+            break;
+        }
+    }
+    sim
+}
+
+/// what one source would come back with, none when it cant be reached
+fn ask(
+    source: &str,
+    name: &str,
+    hosts: &HostsFile,
+    dns: Option<&HostsFile>,
+) -> (Option<Vec<IpAddr>>, Option<&'static str>) {
+    match source {
+        "files" => (Some(hosts.lookup(name).into_iter().collect()), None),
+        "dns" | "resolve" => match dns {
+            Some(dns) => {
+                let mut addrs = Vec::new();
+                for record in dns.records() {
+                    if record.names().iter().any(|n| names_eq(n, name))
+                        && !addrs.contains(&record.addr())
+                    {
+                        addrs.push(record.addr());
+                    }
+                }
+                (Some(addrs), None)
+            }
+            None => (None, Some("no dns answers to go on")),
+        },
+        "myhostname" => {
+            let lower = name.trim_end_matches('.').to_ascii_lowercase();
+            let addrs = match lower == "localhost" || lower.ends_with(".localhost") {
+                true => vec![Ipv4Addr::LOCALHOST.into(), Ipv6Addr::LOCALHOST.into()],
+                false => Vec::new(),
+            };
+            (
+                Some(addrs),
+                Some("only localhost is known, not the machine name"),
+            )
+        }
+        s if s.starts_with("mdns") && s.ends_with("_minimal") && !is_mdns_name(name) => {
+            (None, Some("minimal mdns only looks up .local names"))
+        }
+        s if s.starts_with("mdns") => (None, Some("mdns answers arent known here")),
+        _ => (None, Some("not something that can be simulated")),
+    }
+}
+
+/// what glibc uses with no `hosts:` line, `dns [!UNAVAIL=return] files`
+impl Default for HostsLookup {
+    fn default() -> Self {
+        GLIBC_DEFAULT.parse().expect("the default line parses")
+    }
+}
+
+/// the part after `hosts:`
+impl FromStr for HostsLookup {
+    type Err = NsSwitchError;
+
+    fn from_str(s: &str) -> Result<Self, NsSwitchError> {
+        let mut sources: Vec<Source> = Vec::new();
+        let mut rest = s.trim();
+        while !rest.is_empty() {
+            if let Some(inside) = rest.strip_prefix('[') {
+                let (inside, after) = inside
+                    .split_once(']')
+                    .ok_or(NsSwitchError::UnclosedBracket)?;
+                let source = sources
+                    .last_mut()
+                    .ok_or(NsSwitchError::CriteriaWithoutSource)?;
+                for c in criteria(inside)? {
+                    source.criteria.push(c);
+                }
+                rest = after.trim_start();
+                continue;
+            }
+            let end = rest.find(|c: char| c.is_whitespace() || c == '[');
+            let (name, after) = rest.split_at(end.unwrap_or(rest.len()));
+            sources.push(Source {
+                name: name.to_string(),
+                criteria: Vec::new(),
+            });
+            rest = after.trim_start();
+        }
+        Ok(HostsLookup { sources })
+    }
+}
+
+/// the inside of one `[...]`, spaces around the `=` are fine
+fn criteria(inside: &str) -> Result<Vec<Criterion>, NsSwitchError> {
+    let inside = inside.replace(" =", "=").replace("= ", "=");
+    inside
+        .split_whitespace()
+        .map(|word| {
+            let (status, action) = word
+                .split_once('=')
+                .ok_or_else(|| NsSwitchError::InvalidCriterion(word.to_string()))?;
+            let (negated, status) = match status.strip_prefix('!') {
+                Some(status) => (true, status),
+                None => (false, status),
+            };
+            let status = match status.to_ascii_uppercase().as_str() {
+                "SUCCESS" => Status::Success,
+                "NOTFOUND" => Status::NotFound,
+                "UNAVAIL" => Status::Unavail,
+                "TRYAGAIN" => Status::TryAgain,
+                _ => return Err(NsSwitchError::UnknownStatus(status.to_string())),
+            };
+            let action = match action.to_ascii_lowercase().as_str() {
+                "return" => Action::Return,
+                "continue" => Action::Continue,
+                "merge" => Action::Merge,
+                _ => return Err(NsSwitchError::UnknownAction(action.to_string())),
+            };
+            Ok(Criterion {
+                negated,
+                status,
+                action,
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Status::Success => "SUCCESS",
+            Status::NotFound => "NOTFOUND",
+            Status::Unavail => "UNAVAIL",
+            Status::TryAgain => "TRYAGAIN",
+        })
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Action::Return => "return",
+            Action::Continue => "continue",
+            Action::Merge => "merge",
+        })
+    }
+}
+
+/// a line a step, then what the lookup ended with
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            write!(f, "{}: {}", step.source, step.status)?;
+            for addr in &step.addrs {
+                write!(f, " {addr}")?;
+            }
+            write!(f, " -> {}", step.action)?;
+            if let Some(note) = step.note {
+                write!(f, " ({note})")?;
+            }
+            writeln!(f)?;
+        }
+        match self.winner() {
+            Some(step) => writeln!(f, "{} is answered by {}", self.name, step.source),
+            None => writeln!(f, "{} does not resolve", self.name),
+        }
+    }
+}
+
+/// the line back out without the `hosts:` in front
+impl fmt::Display for HostsLookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, source) in self.sources.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(&source.name)?;
+            if source.criteria.is_empty() {
+                continue;
+            }
+            f.write_str(" [")?;
+            for (j, c) in source.criteria.iter().enumerate() {
+                if j > 0 {
+                    f.write_str(" ")?;
+                }
+                let bang = if c.negated { "!" } else { "" };
+                write!(f, "{bang}{}={}", c.status, c.action)?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hosts_line() {
+        let conf = "# Name Service Switch\n\
+            passwd: files systemd\n\
+            hosts:  mymachines mdns4_minimal [NOTFOUND=return] files myhostname dns # comment\n";
+        let lookup = HostsLookup::from_nsswitch(conf).unwrap().unwrap();
+        let names = lookup
+            .sources
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["mymachines", "mdns4_minimal", "files", "myhostname", "dns"]
+        );
+        let mdns = &lookup.sources[1];
+        assert_eq!(mdns.action(Status::NotFound), Action::Return);
+        assert_eq!(mdns.action(Status::Unavail), Action::Continue);
+        assert_eq!(lookup.files_position(), Some(2));
+        assert_eq!(lookup.before_files().len(), 2);
+        // mdns only stops on NOTFOUND, a missing daemon still falls through
+        assert!(lookup.files_reachable());
+        assert_eq!(
+            lookup.to_string(),
+            "mymachines mdns4_minimal [NOTFOUND=return] files myhostname dns"
+        );
+
+        let blocked: HostsLookup = "dns [NOTFOUND=return UNAVAIL=return tryagain = return] files"
+            .parse()
+            .unwrap();
+        assert!(!blocked.files_reachable());
+        assert!(!"dns".parse::<HostsLookup>().unwrap().files_reachable());
+        assert_eq!(HostsLookup::from_nsswitch("passwd: files\n").unwrap(), None);
+    }
+
+    #[test]
+    fn simulated_lookups() {
+        let hosts: HostsFile = "10.0.0.5 db\n10.0.0.9 printer.local\n".parse().unwrap();
+        let dns: HostsFile = "203.0.113.5 db\n".parse().unwrap();
+
+        // dns answers before the hosts file gets a look in
+        let sim = HostsLookup::default().simulate("db", &hosts, Some(&dns));
+        assert_eq!(sim.winner().unwrap().source, "dns");
+        assert_eq!(sim.addrs(), ["203.0.113.5".parse::<IpAddr>().unwrap()]);
+        assert_eq!(
+            sim.to_string(),
+            "dns: SUCCESS 203.0.113.5 -> return\ndb is answered by dns\n"
+        );
+        // and with it down the file does
+        let sim = HostsLookup::default().simulate("db", &hosts, None);
+        assert_eq!(sim.steps.len(), 2);
+        assert_eq!(sim.winner().unwrap().source, "files");
+
+        // an mdns that cant answer falls through to the file
+        let lookup: HostsLookup = "mdns4_minimal [NOTFOUND=return] files dns".parse().unwrap();
+        let sim = lookup.simulate("printer.local", &hosts, Some(&dns));
+        assert_eq!(sim.steps[0].status, Status::Unavail);
+        assert_eq!(sim.winner().unwrap().source, "files");
+        let sim = lookup.simulate("nothing.example", &hosts, Some(&dns));
+        assert_eq!(sim.steps.len(), 3);
+        assert!(sim.winner().is_none());
+        assert!(sim
+            .to_string()
+            .ends_with("nothing.example does not resolve\n"));
+
+        let lookup: HostsLookup = "files myhostname".parse().unwrap();
+        let sim = lookup.simulate("app.localhost", &hosts, None);
+        assert_eq!(sim.winner().unwrap().addrs.len(), 2);
+    }
+
+    #[test]
+    fn default_and_errors() {
+        let default = HostsLookup::default();
+        let dns = &default.sources[0];
+        // negated, so everything but UNAVAIL returns
+        assert_eq!(dns.action(Status::NotFound), Action::Return);
+        assert_eq!(dns.action(Status::Unavail), Action::Continue);
+        assert!(default.files_reachable());
+
+        assert!(matches!(
+            "[NOTFOUND=return] files".parse::<HostsLookup>(),
+            Err(NsSwitchError::CriteriaWithoutSource)
+        ));
+        assert!(matches!(
+            "dns [NOTFOUND=return".parse::<HostsLookup>(),
+            Err(NsSwitchError::UnclosedBracket)
+        ));
+        assert!(matches!(
+            "dns [LOST=return]".parse::<HostsLookup>(),
+            Err(NsSwitchError::UnknownStatus(_))
+        ));
+        assert!(matches!(
+            "dns [NOTFOUND=panic]".parse::<HostsLookup>(),
+            Err(NsSwitchError::UnknownAction(_))
+        ));
+    }
+}
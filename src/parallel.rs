@@ -0,0 +1,198 @@
+//! parse big files (think multi hundred MB blocklists) across threads.
+//! the text gets cut into chunks at line boundaries, each chunk is parsed
+//! on its own thread and the lines get stitched back together in order,
+//! so you end up with the exact same document the plain parser gives you
+
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::document::HostsDocument;
+use crate::encoding::detect_line_ending;
+use crate::hosts_file::HostsFile;
+use crate::parser::{ParseOptions, ParseWarning, Parser, ParserError};
+
+/// below this it isnt worth spinning up threads
+const MIN_CHUNK: usize = 64 * 1024;
+
+impl HostsFile {
+    /// parse `s` using every core we can get, see `parse_parallel_with`
+    pub fn parse_parallel(s: &str) -> Result<Self, ParserError> {
+        Self::parse_parallel_with(s, &ParseOptions::default()).map(|(hosts, _)| hosts)
+    }
+
+    /// like `parse_with` but splits the work across threads. line numbers,
+    /// spans and warnings come out the same as a single threaded parse
+    pub fn parse_parallel_with(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<ParseWarning>), ParserError> {
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        let (document, warnings) = parse_document(s, options, threads)?;
+        Ok((Self::from_parsed(document, options), warnings))
+    }
+}
+
+pub(crate) fn parse_document(
+    s: &str,
+    options: &ParseOptions,
+    threads: usize,
+) -> Result<(HostsDocument, Vec<ParseWarning>), ParserError> {
+    let mut parser = Parser::with_options(options.clone());
+    parser.check_size(s.len())?;
+    let text = match s.strip_prefix('\u{feff}') {
+        Some(rest) => {
+            parser.skip_bom();
+            rest
+        }
+        None => s,
+    };
+
+    let chunks = chunk_lines(text, threads);
+    if chunks.len() <= 1 {
+        parser.parse_lines(text)?;
+        return Ok(parser.finish());
+    }
+
+    // every chunk has to agree on the line ending, otherwise a crlf file
+    // would get its \r kept on every chunk but the first
+    let line_ending = detect_line_ending(text);
+
+    // figure out where each chunk starts before handing them out
+    let (mut line, mut offset) = parser.position();
+    let mut starts = Vec::with_capacity(chunks.len());
+    for chunk in &chunks {
+        starts.push((line, offset));
+        line += chunk.bytes().filter(|&b| b == b'\n').count();
+        offset += chunk.len();
+    }
+
+    let results: Vec<Result<Parser, ParserError>> = thread::scope(|scope| {
+        let handles: Vec<_> = chunks
+            .iter()
+            .zip(starts)
+            .map(|(chunk, (line, offset))| {
+                let options = parser.options().clone();
+                scope.spawn(move || {
+                    let mut chunk_parser = Parser::resume(options, line, offset, line_ending);
+                    chunk_parser.parse_lines(chunk)?;
+                    Ok(chunk_parser)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("parser thread panicked"))
+            .collect()
+    });
+
+    // in strict mode the first chunk to fail has the earliest bad line
+    for result in results {
+        parser.append(result?)?;
+    }
+    Ok(parser.finish())
+}
+
+/// cut `text` into about `count` pieces, each one ending right after a
+/// newline (except the last)
+fn chunk_lines(text: &str, count: usize) -> Vec<&str> {
+    let size = (text.len() / count.max(1)).max(MIN_CHUNK);
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > size {
+        match rest[size..].find('\n') {
+            Some(i) => {
+                let (chunk, tail) = rest.split_at(size + i + 1);
+                chunks.push(chunk);
+                rest = tail;
+            }
+            None => break,
+        }
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Line;
+
+    fn big_file(ending: &str) -> String {
+        let mut s = String::from("\u{feff}# blocklist");
+        s.push_str(ending);
+        for i in 0..20_000 {
+            if i % 1000 == 0 {
+                s.push_str("not-an-address nope");
+            } else {
+                s.push_str(&format!("0.0.0.0 ads{i}.example.com # entry {i}"));
+            }
+            s.push_str(ending);
+        }
+        s.push_str("127.0.0.1 last");
+        s
+    }
+
+    #[test]
+    fn chunks_end_on_newlines() {
+        let text = big_file("\n");
+        let chunks = chunk_lines(&text, 8);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks.concat(), text);
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert!(chunk.ends_with('\n'));
+        }
+    }
+
+    #[test]
+    fn same_as_single_threaded() {
+        for ending in ["\n", "\r\n"] {
+            let text = big_file(ending);
+            let (serial, serial_warnings) =
+                HostsDocument::parse_with(&text, &ParseOptions::default()).unwrap();
+            let (parallel, warnings) = parse_document(&text, &ParseOptions::default(), 8).unwrap();
+
+            assert_eq!(parallel, serial);
+            assert_eq!(parallel.to_string(), text.trim_start_matches('\u{feff}'));
+            assert_eq!(parallel.line_ending(), serial.line_ending());
+            let lines: Vec<_> = warnings.iter().map(|w| (w.line, w.span)).collect();
+            let serial_lines: Vec<_> = serial_warnings.iter().map(|w| (w.line, w.span)).collect();
+            assert_eq!(lines, serial_lines);
+            assert_eq!(warnings.len(), 20);
+
+            let spans = |doc: &HostsDocument| -> Vec<_> {
+                doc.lines()
+                    .iter()
+                    .filter_map(|line| match line {
+                        Line::Record(rl) => rl.span(),
+                        _ => None,
+                    })
+                    .collect()
+            };
+            assert_eq!(spans(&parallel), spans(&serial));
+        }
+    }
+
+    #[test]
+    fn strict_reports_first_bad_line() {
+        let text = big_file("\n");
+        let options = ParseOptions {
+            strict: true,
+            ..Default::default()
+        };
+        match parse_document(&text, &options, 8) {
+            Err(ParserError::AtLine { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected an error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn small_input_stays_on_one_thread() {
+        let hosts = HostsFile::parse_parallel("127.0.0.1 localhost\n").unwrap();
+        assert_eq!(
+            hosts.lookup("localhost"),
+            Some("127.0.0.1".parse().unwrap())
+        );
+    }
+}
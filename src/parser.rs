@@ -0,0 +1,800 @@
+use crate::borrowed::parse_line_ref;
+use crate::document::{HostsDocument, Line, RecordLine};
+use crate::encoding::{self, Encoding, InvalidUtf8, LineEnding};
+use crate::hostname::{lowercase_name, Strictness};
+use crate::policy::{PolicyViolation, ValidationPolicy};
+use crate::record::{Record, RecordError};
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::fs;
+use std::io::Read;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ParserError {
+    #[error(transparent)]
+    CouldNotOpen(#[from] std::io::Error),
+
+    #[error("bad address {token:?}: {source}")]
+    InvalidAddress {
+        token: String,
+        #[source]
+        source: std::net::AddrParseError,
+    },
+
+    #[error("line has no address")]
+    MissingAddress,
+
+    #[error("address {0} has no names")]
+    MissingNames(String),
+
+    #[error(transparent)]
+    InvalidRecord(#[from] RecordError),
+
+    #[error(transparent)]
+    Policy(#[from] PolicyViolation),
+
+    #[error("line is {length} characters long, more than the {max} allowed")]
+    LineTooLong { length: usize, max: usize },
+
+    #[error("hosts file has more than the {max} {limit} allowed")]
+    LimitExceeded { limit: Limit, max: usize },
+
+    #[error("hosts file is not valid {0}")]
+    InvalidEncoding(&'static str),
+
+    #[error("line {line}: {source}")]
+    AtLine {
+        line: usize,
+        /// the line exactly as it showed up in the file
+        content: String,
+        #[source]
+        source: Box<ParserError>,
+    },
+
+    #[error("unknown")]
+    Unknown(String),
+}
+
+impl ParserError {
+    /// wrap `error` with where it happened
+    pub(crate) fn at_line(line: usize, content: &str, error: ParserError) -> Self {
+        ParserError::AtLine {
+            line,
+            content: content.to_string(),
+            source: Box::new(error),
+        }
+    }
+
+    /// the 1-based line the error is on, when we know it
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            ParserError::AtLine { line, .. } => Some(*line),
+            _ => None,
+        }
+    }
+
+    /// the raw text of the line the error is on, when we know it
+    pub fn content(&self) -> Option<&str> {
+        match self {
+            ParserError::AtLine { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+
+    /// the piece of the line that was wrong, the address that wouldnt
+    /// parse, the hostname that broke the rules and so on
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            ParserError::AtLine { source, .. } => source.token(),
+            ParserError::InvalidAddress { token, .. } => Some(token),
+            ParserError::MissingNames(addr) => Some(addr),
+            ParserError::InvalidRecord(e) => match e {
+                RecordError::InvalidComment(comment) => Some(comment),
+                RecordError::InvalidScope(_, scope) => Some(scope),
+                RecordError::InvalidHostname { name, .. } => Some(name),
+                RecordError::DuplicateName(name) => Some(name),
+                RecordError::InvalidTag(tag) => Some(tag),
+                RecordError::Policy(v) => Some(v.name()),
+                RecordError::MissingAddress | RecordError::MissingNames => None,
+            },
+            ParserError::Policy(v) => Some(v.name()),
+            ParserError::CouldNotOpen(_)
+            | ParserError::MissingAddress
+            | ParserError::LineTooLong { .. }
+            | ParserError::LimitExceeded { .. }
+            | ParserError::InvalidEncoding(_)
+            | ParserError::Unknown(_) => None,
+        }
+    }
+}
+
+/// which of the `ParseOptions` limits a file went over
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Limit {
+    /// `max_file_size`
+    FileSize,
+    /// `max_records`
+    Records,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::FileSize => write!(f, "bytes"),
+            Limit::Records => write!(f, "records"),
+        }
+    }
+}
+
+/// knobs for how forgiving the parser is about broken lines
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParseOptions {
+    /// bail on the first line we cant parse instead of warning about it.
+    /// off by default since the system resolver just skips bad lines too
+    pub strict: bool,
+    /// how picky to be about the names on each line
+    pub hostnames: Strictness,
+    /// lowercase every name as it is parsed. lines that change get
+    /// rewritten when the document is written back out
+    pub lowercase_names: bool,
+    /// records that break the policy are treated like any other broken
+    /// line, and the HostsFile parsed with it keeps enforcing it
+    pub policy: Option<ValidationPolicy>,
+    /// record lines longer than this many characters are treated as
+    /// broken, macOS for one quietly cuts hosts lines off at 255
+    pub max_line_length: Option<usize>,
+    /// fold runs of lines for the same address into one record, for
+    /// files that had a long alias list split over several lines. the
+    /// lines still write back out exactly as they were until the record
+    /// is changed
+    pub coalesce_lines: bool,
+    /// how to deal with utf-8 files that have a few bad bytes in them.
+    /// lossy and skipped lines write back out with U+FFFD where the bad
+    /// bytes were
+    pub invalid_utf8: InvalidUtf8,
+    /// give up with `LimitExceeded` once the file has more records than
+    /// this, even in lenient mode. for parsing hosts files from people
+    /// you dont trust
+    pub max_records: Option<usize>,
+    /// give up with `LimitExceeded` on files bigger than this many bytes.
+    /// readers stop being read one byte past it, so a huge upload never
+    /// ends up in memory
+    pub max_file_size: Option<usize>,
+}
+
+/// where a line sat in the source it was parsed from
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Span {
+    /// 1-based line number
+    pub line: usize,
+    /// byte offset of the start of the line
+    pub start: usize,
+    /// byte offset just past the end of the line, not counting the \n
+    pub end: usize,
+}
+
+impl Span {
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// a line the parser couldnt make sense of in lenient mode
+///
+/// the line itself is kept in the document as `Line::Invalid` so nothing
+/// is lost when the file gets written back out
+#[derive(Debug)]
+pub struct ParseWarning {
+    /// 1-based line number
+    pub line: usize,
+    /// where the line sits in the source
+    pub span: Span,
+    /// the line exactly as it showed up in the file
+    pub content: String,
+    /// why we couldnt parse it
+    pub error: ParserError,
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: {}, skipped {:?}",
+            self.line, self.error, self.content
+        )
+    }
+}
+
+/// Parser is a way we can extract Records from the etc/hosts file
+///
+/// it keeps every line it reads, comments and blanks included, so the
+/// result can be written back out exactly as it came in
+#[derive(Debug)]
+pub(crate) struct Parser {
+    line: usize,
+    offset: usize,
+    lines: Vec<Line>,
+    trailing_newline: bool,
+    /// picked from the first line that has a terminator
+    line_ending: Option<LineEnding>,
+    encoding: Encoding,
+    options: ParseOptions,
+    warnings: Vec<ParseWarning>,
+    /// records pushed so far, for `max_records`
+    records: usize,
+}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::with_options(ParseOptions::default())
+    }
+}
+
+impl Parser {
+    pub fn with_options(options: ParseOptions) -> Parser {
+        let lines: Vec<Line> = Vec::new();
+        Parser {
+            line: 0,
+            offset: 0,
+            lines,
+            trailing_newline: false,
+            line_ending: None,
+            encoding: Encoding::Utf8,
+            options,
+            warnings: Vec::new(),
+            records: 0,
+        }
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn parse(&mut self, file: &Path) -> Result<(), ParserError> {
+        match self.options.max_file_size {
+            Some(_) => self.parse_reader(fs::File::open(file)?),
+            None => self.parse_bytes(&fs::read(file)?),
+        }
+    }
+
+    /// read everything out of `reader` and parse it, the encoding is
+    /// picked up from the BOM just like it is for files
+    pub fn parse_reader<R: Read>(&mut self, mut reader: R) -> Result<(), ParserError> {
+        let mut bytes = Vec::new();
+        match self.options.max_file_size {
+            // one past the limit is enough to know its over
+            Some(max) => reader
+                .by_ref()
+                .take(max as u64 + 1)
+                .read_to_end(&mut bytes)?,
+            None => reader.read_to_end(&mut bytes)?,
+        };
+        self.parse_bytes(&bytes)
+    }
+
+    /// fail when `len` bytes is more than `max_file_size`
+    pub(crate) fn check_size(&self, len: usize) -> Result<(), ParserError> {
+        match self.options.max_file_size {
+            Some(max) if len > max => Err(ParserError::LimitExceeded {
+                limit: Limit::FileSize,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse_bytes(&mut self, bytes: &[u8]) -> Result<(), ParserError> {
+        self.check_size(bytes.len())?;
+        let (text, encoding) = match encoding::decode(bytes) {
+            Ok(decoded) => decoded,
+            Err(e) => match (self.options.invalid_utf8, encoding::utf8_body(bytes)) {
+                (InvalidUtf8::Error, _) | (_, None) => return Err(e),
+                (_, Some((body, encoding))) => {
+                    self.encoding = encoding;
+                    self.offset = encoding.bom_len();
+                    return self.parse_byte_lines(body);
+                }
+            },
+        };
+        self.encoding = encoding;
+        // utf-8 spans point into the file itself, utf-16 ones into the
+        // decoded text since byte offsets dont line up anymore
+        if encoding == Encoding::Utf8Bom {
+            self.offset = encoding.bom_len();
+        }
+        self.parse_lines(&text)
+    }
+
+    pub fn parse_str(&mut self, s: &str) -> Result<(), ParserError> {
+        self.check_size(s.len())?;
+        match s.strip_prefix('\u{feff}') {
+            Some(rest) => {
+                self.encoding = Encoding::Utf8Bom;
+                self.offset = Encoding::Utf8Bom.bom_len();
+                self.parse_lines(rest)
+            }
+            None => self.parse_lines(s),
+        }
+    }
+
+    /// a parser that picks up partway through a document, so chunks of
+    /// it can be handed out to other threads and glued back on with
+    /// `append`
+    #[cfg(feature = "parallel")]
+    pub(crate) fn resume(
+        options: ParseOptions,
+        line: usize,
+        offset: usize,
+        line_ending: Option<LineEnding>,
+    ) -> Parser {
+        Parser {
+            line,
+            offset,
+            line_ending,
+            ..Parser::with_options(options)
+        }
+    }
+
+    /// tack the lines from a parser that picked up where this one stopped
+    /// onto the end of this one
+    #[cfg(feature = "parallel")]
+    pub(crate) fn append(&mut self, chunk: Parser) -> Result<(), ParserError> {
+        self.line = chunk.line;
+        self.offset = chunk.offset;
+        self.trailing_newline = chunk.trailing_newline;
+        if self.line_ending.is_none() {
+            self.line_ending = chunk.line_ending;
+        }
+        let mut lines = chunk.lines.into_iter();
+        if let Some(first) = lines.next() {
+            self.push_line(first)?;
+        }
+        self.records += lines
+            .as_slice()
+            .iter()
+            .filter(|l| matches!(l, Line::Record(_)))
+            .count();
+        self.check_records()?;
+        self.lines.extend(lines);
+        self.warnings.extend(chunk.warnings);
+        Ok(())
+    }
+
+    /// where the next line starts, as (lines so far, byte offset)
+    #[cfg(feature = "parallel")]
+    pub(crate) fn position(&self) -> (usize, usize) {
+        (self.line, self.offset)
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// the text had a utf-8 BOM that the caller already stripped off
+    #[cfg(feature = "parallel")]
+    pub(crate) fn skip_bom(&mut self) {
+        self.encoding = Encoding::Utf8Bom;
+        self.offset = Encoding::Utf8Bom.bom_len();
+    }
+
+    pub(crate) fn parse_lines(&mut self, text: &str) -> Result<(), ParserError> {
+        if self.line_ending.is_none() {
+            self.line_ending = encoding::detect_line_ending(text);
+        }
+        // slicing lines straight out of the text instead of copying each
+        // one into a buffer first
+        for buf in text.split_inclusive('\n') {
+            self.parse_one(buf, buf.len(), false)?;
+        }
+        Ok(())
+    }
+
+    /// `parse_lines` for utf-8 that doesnt decode, each line is checked
+    /// on its own so only the broken ones get the `invalid_utf8` treatment
+    fn parse_byte_lines(&mut self, bytes: &[u8]) -> Result<(), ParserError> {
+        if self.line_ending.is_none() {
+            self.line_ending = encoding::detect_line_ending(&String::from_utf8_lossy(bytes));
+        }
+        for buf in bytes.split_inclusive(|b| *b == b'\n') {
+            match std::str::from_utf8(buf) {
+                Ok(text) => self.parse_one(text, buf.len(), false)?,
+                Err(_) => self.parse_one(&String::from_utf8_lossy(buf), buf.len(), true)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// parse one line with its terminator still on. `len` is how many
+    /// bytes it took up in the source, which is more than `buf` when bad
+    /// utf-8 got replaced
+    fn parse_one(&mut self, buf: &str, len: usize, bad_utf8: bool) -> Result<(), ParserError> {
+        self.line += 1;
+        let start = self.offset;
+        self.offset += len;
+
+        let raw = match buf.strip_suffix('\n') {
+            Some(raw) => {
+                self.trailing_newline = true;
+                // in a crlf file the \r belongs to the terminator, in an
+                // lf file a stray \r is part of the line and stays put
+                match self.line_ending.unwrap_or_default() {
+                    LineEnding::CrLf => raw.strip_suffix('\r').unwrap_or(raw),
+                    LineEnding::Lf => raw,
+                }
+            }
+            None => {
+                self.trailing_newline = false;
+                buf
+            }
+        };
+        let span = Span {
+            line: self.line,
+            start,
+            end: start + len - (buf.len() - raw.len()),
+        };
+
+        let classified = match (bad_utf8, self.options.invalid_utf8) {
+            (true, InvalidUtf8::Skip) => Err(ParserError::InvalidEncoding("utf-8")),
+            _ => classify_line(raw, span, &self.options),
+        };
+        let line = match classified {
+            Ok(line) => line,
+            Err(e) if self.options.strict => return Err(ParserError::at_line(self.line, raw, e)),
+            Err(e) => {
+                self.warnings.push(ParseWarning {
+                    line: self.line,
+                    span,
+                    content: raw.to_string(),
+                    error: e,
+                });
+                Line::Invalid(raw.to_string())
+            }
+        };
+        self.push_line(line)
+    }
+
+    /// add a line, folding it into the record before it when
+    /// `coalesce_lines` is on and they share an address
+    fn push_line(&mut self, line: Line) -> Result<(), ParserError> {
+        if self.options.coalesce_lines {
+            if let (Some(Line::Record(prev)), Line::Record(next)) = (self.lines.last_mut(), &line) {
+                if prev.record().addr() == next.record().addr()
+                    && prev.record().scope() == next.record().scope()
+                {
+                    let newline = self.line_ending.unwrap_or_default().as_str();
+                    if let Line::Record(next) = line {
+                        prev.absorb(next, newline);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        if matches!(line, Line::Record(_)) {
+            self.records += 1;
+            self.check_records()?;
+        }
+        self.lines.push(line);
+        Ok(())
+    }
+
+    fn check_records(&self) -> Result<(), ParserError> {
+        match self.options.max_records {
+            Some(max) if self.records > max => Err(ParserError::LimitExceeded {
+                limit: Limit::Records,
+                max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn into_document(self) -> HostsDocument {
+        self.finish().0
+    }
+
+    /// the parsed document along with any warnings from lenient mode
+    pub(crate) fn finish(self) -> (HostsDocument, Vec<ParseWarning>) {
+        let doc = HostsDocument::from_lines(
+            self.lines,
+            self.trailing_newline,
+            self.line_ending.unwrap_or_default(),
+            self.encoding,
+        );
+        (doc, self.warnings)
+    }
+}
+
+/// sort a raw line into a record, comment or blank
+fn classify_line(raw: &str, span: Span, options: &ParseOptions) -> Result<Line, ParserError> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return Ok(Line::Blank(raw.to_string()));
+    }
+    if trimmed.starts_with('#') {
+        return Ok(Line::Comment(raw.to_string()));
+    }
+
+    if let Some(max) = options.max_line_length {
+        let length = raw.chars().count();
+        if length > max {
+            return Err(ParserError::LineTooLong { length, max });
+        }
+    }
+
+    let Some(record) = parse_line_with(raw, options.hostnames)? else {
+        return Ok(Line::Blank(raw.to_string()));
+    };
+    let mut line = RecordLine::parsed(record, raw.to_string(), span);
+    if options.lowercase_names {
+        let mut lowered = line.record().clone();
+        lowered.names = lowered.names.iter().map(|n| lowercase_name(n)).collect();
+        if &lowered != line.record() {
+            line.set_record(lowered);
+        }
+    }
+    if let Some(policy) = &options.policy {
+        policy.check(line.record())?;
+    }
+    Ok(Line::Record(line))
+}
+
+/// parse a single line of the hosts file
+///
+/// the first field is always the address, everything after it is a name
+/// for that address. blank lines and comments give back `None`
+pub(crate) fn parse_line(line: &str) -> Result<Option<Record>, ParserError> {
+    parse_line_with(line, Strictness::default())
+}
+
+/// `parse_line` with control over how picky to be about names
+pub(crate) fn parse_line_with(
+    line: &str,
+    strictness: Strictness,
+) -> Result<Option<Record>, ParserError> {
+    Ok(parse_line_ref(line, strictness)?.map(|r| r.to_record()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::IpAddr;
+
+    #[test]
+    fn parse_line_address_first() {
+        let record = parse_line("127.0.0.1\tlocalhost").unwrap().unwrap();
+        assert_eq!(record.addr, "127.0.0.1".parse::<IpAddr>().unwrap());
+        assert_eq!(record.names, vec!["localhost".to_string()]);
+    }
+
+    #[test]
+    fn parse_line_aliases() {
+        let record = parse_line("192.168.10.42  core.naus core   naus-alias")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.addr, "192.168.10.42".parse::<IpAddr>().unwrap());
+        assert_eq!(record.names, vec!["core.naus", "core", "naus-alias"]);
+    }
+
+    #[test]
+    fn parse_line_skips_comments_and_blanks() {
+        assert!(parse_line("").unwrap().is_none());
+        assert!(parse_line("   \t ").unwrap().is_none());
+        assert!(parse_line("# The following lines are desirable")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn parse_line_ipv6() {
+        let lines = [
+            ("::1     localhost ip6-localhost ip6-loopback", "::1"),
+            ("fe00::0 ip6-localnet", "fe00::0"),
+            ("ff00::0 ip6-mcastprefix", "ff00::0"),
+            ("ff02::1 ip6-allnodes", "ff02::1"),
+            ("ff02::2 ip6-allrouters", "ff02::2"),
+        ];
+        for (line, addr) in lines {
+            let record = parse_line(line).unwrap().unwrap();
+            assert_eq!(record.addr, addr.parse::<IpAddr>().unwrap());
+            assert!(record.scope.is_none());
+        }
+    }
+
+    #[test]
+    fn parse_line_scoped_ipv6() {
+        let record = parse_line("fe80::1%lo0\tlocalhost").unwrap().unwrap();
+        assert_eq!(record.addr, "fe80::1".parse::<IpAddr>().unwrap());
+        assert_eq!(record.scope.as_deref(), Some("lo0"));
+        assert_eq!(record.names, vec!["localhost"]);
+    }
+
+    #[test]
+    fn parse_line_inline_comment() {
+        let record = parse_line("1.2.3.4 host host.lan # managed by ansible")
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.names, vec!["host", "host.lan"]);
+        assert_eq!(record.comment.as_deref(), Some("managed by ansible"));
+
+        let record = parse_line("1.2.3.4\thost#tight").unwrap().unwrap();
+        assert_eq!(record.names, vec!["host"]);
+        assert_eq!(record.comment.as_deref(), Some("tight"));
+
+        let record = parse_line("1.2.3.4 host").unwrap().unwrap();
+        assert!(record.comment.is_none());
+    }
+
+    #[test]
+    fn parse_line_bad_addr() {
+        match parse_line("localhost 127.0.0.1") {
+            Err(ParserError::InvalidAddress { token, .. }) => assert_eq!(token, "localhost"),
+            r => panic!("expected a bad address, got {r:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_line_no_names() {
+        assert!(matches!(
+            parse_line("10.0.0.1   # nothing here"),
+            Err(ParserError::MissingNames(_))
+        ));
+    }
+
+    #[test]
+    fn lenient_collects_warnings() {
+        let mut parser = Parser::default();
+        parser
+            .parse_str("127.0.0.1 localhost\nlocalhost 127.0.0.1\n10.0.0.5 db\n")
+            .unwrap();
+        let (doc, warnings) = parser.finish();
+        assert_eq!(doc.records().count(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].line, 2);
+        assert_eq!(warnings[0].content, "localhost 127.0.0.1");
+        assert_eq!(warnings[0].span.range(), 20..39);
+        assert_eq!(doc.lines()[1], Line::Invalid("localhost 127.0.0.1".into()));
+    }
+
+    #[test]
+    fn strict_reports_line() {
+        let mut parser = Parser::with_options(ParseOptions {
+            strict: true,
+            ..Default::default()
+        });
+        let err = parser
+            .parse_str("127.0.0.1 localhost\n\n127.0.0.1%lo0 nope\n")
+            .unwrap_err();
+        match err {
+            ParserError::AtLine {
+                line,
+                ref content,
+                ref source,
+            } => {
+                assert_eq!(line, 3);
+                assert_eq!(content, "127.0.0.1%lo0 nope");
+                assert!(matches!(**source, ParserError::InvalidRecord(_)));
+                assert_eq!(err.token(), Some("lo0"));
+                let inner = std::error::Error::source(&err).unwrap();
+                assert_eq!(inner.to_string(), source.to_string());
+            }
+            e => panic!("expected a line number, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn hostname_strictness() {
+        let src = "10.0.0.5 db_primary\n";
+        let (doc, warnings) = HostsDocument::parse_with(src, &ParseOptions::default()).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(doc.records().count(), 1);
+
+        let options = ParseOptions {
+            hostnames: Strictness::Strict,
+            ..Default::default()
+        };
+        let (doc, warnings) = HostsDocument::parse_with(src, &options).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(doc.records().count(), 0);
+    }
+
+    #[test]
+    fn lowercase_names_on_parse() {
+        let options = ParseOptions {
+            lowercase_names: true,
+            ..Default::default()
+        };
+        let src = "127.0.0.1 localhost\n10.0.0.5  DB db.Internal\n";
+        let (doc, _) = HostsDocument::parse_with(src, &options).unwrap();
+        let names = doc.records().flat_map(|r| r.names()).collect::<Vec<_>>();
+        assert_eq!(names, ["localhost", "db", "db.internal"]);
+        assert_eq!(
+            doc.to_string(),
+            "127.0.0.1 localhost\n10.0.0.5\tdb db.internal\n"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_lines() {
+        let src = b"# caf\xe9 list\n10.0.0.5 db # r\xe9plica\n10.0.0.6 cache\n";
+        let parse = |invalid_utf8| {
+            let mut parser = Parser::with_options(ParseOptions {
+                invalid_utf8,
+                ..Default::default()
+            });
+            parser.parse_reader(&src[..]).map(|_| parser.finish())
+        };
+        assert!(matches!(
+            parse(InvalidUtf8::Error),
+            Err(ParserError::InvalidEncoding("utf-8"))
+        ));
+
+        let (doc, warnings) = parse(InvalidUtf8::Lossy).unwrap();
+        assert!(warnings.is_empty());
+        assert_eq!(doc.records().count(), 2);
+        assert_eq!(doc.lines()[0], Line::Comment("# caf\u{fffd} list".into()));
+
+        let (doc, warnings) = parse(InvalidUtf8::Skip).unwrap();
+        assert_eq!(doc.records().count(), 1);
+        assert_eq!(warnings.iter().map(|w| w.line).collect::<Vec<_>>(), [1, 2]);
+        // spans still point at the bytes in the file
+        assert_eq!(warnings[1].span.range(), 12..33);
+    }
+
+    #[test]
+    fn limits() {
+        let src = "127.0.0.1 localhost\n# note\n10.0.0.5 db\n10.0.0.6 cache\n";
+        let parse = |options: ParseOptions| {
+            let mut parser = Parser::with_options(options);
+            parser.parse_reader(src.as_bytes()).map(|_| parser.finish())
+        };
+        let (doc, _) = parse(ParseOptions {
+            max_records: Some(3),
+            max_file_size: Some(src.len()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(doc.records().count(), 3);
+
+        let err = parse(ParseOptions {
+            max_records: Some(2),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::LimitExceeded {
+                limit: Limit::Records,
+                max: 2
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "hosts file has more than the 2 records allowed"
+        );
+
+        let err = parse(ParseOptions {
+            max_file_size: Some(src.len() - 1),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            ParserError::LimitExceeded {
+                limit: Limit::FileSize,
+                ..
+            }
+        ));
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn test_parser() {
+        use std::path::Path;
+        let mut parser: Parser = Default::default();
+        let path = Path::new("/etc/hosts");
+        match parser.parse(path).map(|_| parser.into_document()) {
+            Ok(_v) => println!("good to go"),
+            Err(e) => println!("{e:?}"),
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! rules for what a hosts file is allowed to say, for when the file is
+//! handed out by corporate tooling and people shouldnt be pointing names
+//! at whatever they like
+//!
+//! a policy can go in `ParseOptions` to reject lines while parsing, and a
+//! HostsFile parsed with one keeps it around so `set` refuses names
+//! outside of it and the write functions refuse to put them on disk
+
+use std::net::IpAddr;
+
+use thiserror::Error;
+
+use crate::cidr::Cidr;
+use crate::hostname::{fold_name, names_eq};
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+#[derive(Error, Debug, Clone, Eq, PartialEq)]
+pub enum PolicyViolation {
+    #[error("{name} points at {addr} which is outside every allowed network")]
+    AddressNotAllowed { addr: IpAddr, name: String },
+    #[error("{name} is under .{tld} which is not an allowed tld")]
+    TldNotAllowed { name: String, tld: String },
+    #[error("{name} is a forbidden hostname, it cant point at {addr}")]
+    ForbiddenName { name: String, addr: IpAddr },
+}
+
+impl PolicyViolation {
+    /// the name that broke the rule
+    pub fn name(&self) -> &str {
+        match self {
+            PolicyViolation::AddressNotAllowed { name, .. }
+            | PolicyViolation::TldNotAllowed { name, .. }
+            | PolicyViolation::ForbiddenName { name, .. } => name,
+        }
+    }
+}
+
+/// the rules a record has to follow. an empty list of networks or tlds
+/// allows anything
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ValidationPolicy {
+    allowed_networks: Vec<Cidr>,
+    allowed_tlds: Vec<String>,
+    forbidden_names: Vec<String>,
+}
+
+impl ValidationPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// addresses have to be inside at least one allowed network
+    pub fn allow_network(mut self, network: Cidr) -> Self {
+        self.allowed_networks.push(network);
+        self
+    }
+
+    /// names have to end in one of the allowed tlds, `corp` or `.corp`.
+    /// a name without any dots is its own tld so `localhost` needs to be
+    /// allowed like any other
+    pub fn allow_tld(mut self, tld: &str) -> Self {
+        self.allowed_tlds
+            .push(fold_name(tld.trim_matches('.')).into_owned());
+        self
+    }
+
+    /// a name that can never show up, no matter the address
+    pub fn forbid_name(mut self, name: &str) -> Self {
+        self.forbidden_names.push(name.to_string());
+        self
+    }
+
+    pub fn allowed_networks(&self) -> &[Cidr] {
+        &self.allowed_networks
+    }
+
+    pub fn allowed_tlds(&self) -> &[String] {
+        &self.allowed_tlds
+    }
+
+    pub fn forbidden_names(&self) -> &[String] {
+        &self.forbidden_names
+    }
+
+    /// the first rule `record` breaks
+    pub fn check(&self, record: &Record) -> Result<(), PolicyViolation> {
+        match self.violations(record).into_iter().next() {
+            Some(violation) => Err(violation),
+            None => Ok(()),
+        }
+    }
+
+    /// every rule `record` breaks, the address first and then each name
+    pub fn violations(&self, record: &Record) -> Vec<PolicyViolation> {
+        let addr = record.addr();
+        let mut out = Vec::new();
+        if !self.allowed_networks.is_empty()
+            && !self.allowed_networks.iter().any(|net| net.contains(addr))
+        {
+            out.push(PolicyViolation::AddressNotAllowed {
+                addr,
+                name: record.primary_name().unwrap_or_default().to_string(),
+            });
+        }
+        for name in record.names() {
+            if self.forbidden_names.iter().any(|f| names_eq(f, name)) {
+                out.push(PolicyViolation::ForbiddenName {
+                    name: name.clone(),
+                    addr,
+                });
+                continue;
+            }
+            let bare = name.trim_end_matches('.');
+            let tld = bare.rsplit('.').next().unwrap_or(bare);
+            let tld = fold_name(tld);
+            if !self.allowed_tlds.is_empty() && !self.allowed_tlds.iter().any(|t| *t == tld) {
+                out.push(PolicyViolation::TldNotAllowed {
+                    name: name.clone(),
+                    tld: tld.into_owned(),
+                });
+            }
+        }
+        out
+    }
+}
+
+impl HostsFile {
+    /// every rule broken by every record in the file, in file order
+    pub fn check_policy(&self, policy: &ValidationPolicy) -> Vec<PolicyViolation> {
+        self.records()
+            .iter()
+            .flat_map(|record| policy.violations(record))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ValidationPolicy {
+        ValidationPolicy::new()
+            .allow_network("10.0.0.0/8".parse().unwrap())
+            .allow_network("127.0.0.0/8".parse().unwrap())
+            .allow_tld(".corp")
+            .allow_tld("localhost")
+            .forbid_name("login.corp")
+    }
+
+    #[test]
+    fn record_violations() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            10.0.0.5 db.corp DB.example\n\
+            192.168.1.4 printer.corp\n\
+            10.0.0.9 Login.Corp\n"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            hosts.check_policy(&policy()),
+            [
+                PolicyViolation::TldNotAllowed {
+                    name: "DB.example".into(),
+                    tld: "example".into(),
+                },
+                PolicyViolation::AddressNotAllowed {
+                    addr: "192.168.1.4".parse().unwrap(),
+                    name: "printer.corp".into(),
+                },
+                PolicyViolation::ForbiddenName {
+                    name: "Login.Corp".into(),
+                    addr: "10.0.0.9".parse().unwrap(),
+                },
+            ]
+        );
+        assert!(hosts.check_policy(&ValidationPolicy::new()).is_empty());
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn enforced_on_parse_edit_and_write() {
+        use crate::parser::{ParseOptions, ParserError};
+        use crate::record::RecordError;
+
+        let strict = ParseOptions {
+            strict: true,
+            policy: Some(policy()),
+            ..Default::default()
+        };
+        let err =
+            HostsFile::parse_with("127.0.0.1 localhost\n8.8.8.8 dns.corp\n", &strict).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: dns.corp points at 8.8.8.8 which is outside every allowed network"
+        );
+
+        let lenient = ParseOptions {
+            policy: Some(policy()),
+            ..Default::default()
+        };
+        let (mut hosts, warnings) =
+            HostsFile::parse_with("10.0.0.5 db.corp\n10.0.0.6 db.example\n", &lenient).unwrap();
+        assert!(matches!(warnings[0].error, ParserError::Policy(_)));
+        assert_eq!(hosts.policy(), Some(&policy()));
+
+        assert!(matches!(
+            hosts.set("login.corp", "10.0.0.7".parse().unwrap()),
+            Err(RecordError::Policy(PolicyViolation::ForbiddenName { .. }))
+        ));
+        hosts
+            .set("cache.corp", "10.0.0.8".parse().unwrap())
+            .unwrap();
+
+        // add_record doesnt check, the write is what stops it landing
+        let record = Record::new("1.1.1.1".parse().unwrap(), vec!["dns.corp".into()]).unwrap();
+        hosts.add_record(record);
+        let path = std::env::temp_dir().join(format!("hosts-digger-policy-{}", std::process::id()));
+        let err = hosts.write_to(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(!path.exists());
+
+        hosts.set_policy(None);
+        hosts.write_to(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+//! checking whether the addresses in a hosts file still answer, so the
+//! entries for lab boxes that got pulled months ago can be flagged
+//!
+//! icmp goes through the system `ping` since raw sockets need root, and
+//! when that isnt around or gets no reply we fall back to a tcp connect.
+//! a refused connection still counts as live, something had to send the
+//! reset
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+/// how to check an address
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ProbeMethod {
+    /// ping only
+    Icmp,
+    /// tcp connect to this port only
+    Tcp(u16),
+    /// ping, and tcp connect to this port when ping cant run or gets no
+    /// reply
+    IcmpThenTcp(u16),
+}
+
+impl Default for ProbeMethod {
+    fn default() -> Self {
+        ProbeMethod::IcmpThenTcp(80)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProbeOptions {
+    pub method: ProbeMethod,
+    /// how long to wait on each address
+    pub timeout: Duration,
+}
+
+impl Default for ProbeOptions {
+    fn default() -> Self {
+        ProbeOptions {
+            method: ProbeMethod::default(),
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Liveness {
+    Live,
+    Dead,
+    /// `ProbeMethod::Icmp` on a machine where ping wouldnt run
+    Unknown,
+}
+
+/// probe a single address. unspecified addresses like the 0.0.0.0 sink
+/// on blocking entries are never live, connecting to one ends up on the
+/// local machine
+pub fn probe(addr: IpAddr, options: &ProbeOptions) -> Liveness {
+    if addr.is_unspecified() {
+        return Liveness::Dead;
+    }
+    match options.method {
+        ProbeMethod::Icmp => match ping(addr, options.timeout) {
+            Ok(true) => Liveness::Live,
+            Ok(false) => Liveness::Dead,
+            Err(_) => Liveness::Unknown,
+        },
+        ProbeMethod::Tcp(port) => connect(addr, port, options.timeout),
+        ProbeMethod::IcmpThenTcp(port) => match ping(addr, options.timeout) {
+            Ok(true) => Liveness::Live,
+            _ => connect(addr, port, options.timeout),
+        },
+    }
+}
+
+impl HostsFile {
+    /// every record alongside whether its address answered. each address
+    /// is only probed once no matter how many lines it is on
+    pub fn probe(&self, options: &ProbeOptions) -> Vec<(&Record, Liveness)> {
+        let mut seen: HashMap<IpAddr, Liveness> = HashMap::new();
+        self.records()
+            .iter()
+            .map(|record| {
+                let live = *seen
+                    .entry(record.addr())
+                    .or_insert_with(|| probe(record.addr(), options));
+                (record, live)
+            })
+            .collect()
+    }
+}
+
+fn connect(addr: IpAddr, port: u16, timeout: Duration) -> Liveness {
+    match TcpStream::connect_timeout(&SocketAddr::new(addr, port), timeout) {
+        Ok(_) => Liveness::Live,
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => Liveness::Live,
+        Err(_) => Liveness::Dead,
+    }
+}
+
+/// which command line the system ping wants. only the one for this
+/// machine is used outside the tests
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(not(test), allow(dead_code))]
+enum PingFlavor {
+    /// iputils on linux, `-W` in seconds and `-6` for v6
+    Iputils,
+    /// macos and the bsds, `-W` in milliseconds and v6 goes through a
+    /// separate `ping6` that has no wait flag
+    Bsd,
+    Windows,
+}
+
+impl PingFlavor {
+    #[cfg(windows)]
+    const HOST: PingFlavor = PingFlavor::Windows;
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    ))]
+    const HOST: PingFlavor = PingFlavor::Bsd;
+    #[cfg(not(any(
+        windows,
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "openbsd",
+        target_os = "netbsd"
+    )))]
+    const HOST: PingFlavor = PingFlavor::Iputils;
+
+    /// the program and its arguments for one ping to `addr`
+    fn command_line(self, addr: IpAddr, timeout: Duration) -> (&'static str, Vec<String>) {
+        let millis = timeout.as_millis().max(1).to_string();
+        let (program, mut args) = match self {
+            PingFlavor::Iputils => {
+                let secs = timeout.as_millis().div_ceil(1000).max(1).to_string();
+                let mut args = vec!["-c".into(), "1".into(), "-W".into(), secs];
+                if addr.is_ipv6() {
+                    args.push("-6".into());
+                }
+                ("ping", args)
+            }
+            PingFlavor::Bsd if addr.is_ipv6() => ("ping6", vec!["-c".into(), "1".into()]),
+            PingFlavor::Bsd => ("ping", vec!["-c".into(), "1".into(), "-W".into(), millis]),
+            PingFlavor::Windows => ("ping", vec!["-n".into(), "1".into(), "-w".into(), millis]),
+        };
+        args.push(addr.to_string());
+        (program, args)
+    }
+}
+
+/// true on a reply, an error when ping couldnt be run at all
+fn ping(addr: IpAddr, timeout: Duration) -> io::Result<bool> {
+    let (program, args) = PingFlavor::HOST.command_line(addr, timeout);
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    run_ping(&mut cmd)
+}
+
+fn run_ping(cmd: &mut Command) -> io::Result<bool> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let status = match cmd.stdout(Stdio::null()).stderr(Stdio::null()).status() {
+        Ok(status) => status,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "{program} isnt installed or isnt on PATH, probe with ProbeMethod::Tcp instead"
+                ),
+            ))
+        }
+        Err(e) => return Err(e),
+    };
+    match status.code() {
+        Some(0) => Ok(true),
+        // iputils and bsd ping both exit 2 for "couldnt even try"
+        Some(2) if cfg!(unix) => Err(io::Error::other(format!("{program} failed to run"))),
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn tcp_probe() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let options = ProbeOptions {
+            method: ProbeMethod::Tcp(port),
+            timeout: Duration::from_millis(200),
+        };
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            0.0.0.0 ads.example\n\
+            127.0.0.1 also-local\n"
+            .parse()
+            .unwrap();
+        let live: Vec<_> = hosts
+            .probe(&options)
+            .into_iter()
+            .map(|(record, live)| (record.names()[0].as_str(), live))
+            .collect();
+        assert_eq!(
+            live,
+            [
+                ("localhost", Liveness::Live),
+                ("ads.example", Liveness::Dead),
+                ("also-local", Liveness::Live),
+            ]
+        );
+
+        // nothing listening, but the refusal means the box is up
+        drop(listener);
+        assert_eq!(
+            probe("127.0.0.1".parse().unwrap(), &options),
+            Liveness::Live
+        );
+    }
+
+    #[test]
+    fn ping_command_lines() {
+        let v4: IpAddr = "10.0.0.5".parse().unwrap();
+        let v6: IpAddr = "fd00::5".parse().unwrap();
+        let timeout = Duration::from_millis(1500);
+        let line = |flavor: PingFlavor, addr| {
+            let (program, args) = flavor.command_line(addr, timeout);
+            (program, args.join(" "))
+        };
+        assert_eq!(
+            line(PingFlavor::Iputils, v4),
+            ("ping", "-c 1 -W 2 10.0.0.5".into())
+        );
+        assert_eq!(
+            line(PingFlavor::Iputils, v6),
+            ("ping", "-c 1 -W 2 -6 fd00::5".into())
+        );
+        assert_eq!(
+            line(PingFlavor::Bsd, v4),
+            ("ping", "-c 1 -W 1500 10.0.0.5".into())
+        );
+        assert_eq!(line(PingFlavor::Bsd, v6), ("ping6", "-c 1 fd00::5".into()));
+        assert_eq!(
+            line(PingFlavor::Windows, v6),
+            ("ping", "-n 1 -w 1500 fd00::5".into())
+        );
+
+        let err = run_ping(&mut Command::new("hosts-digger-no-such-ping")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(err
+            .to_string()
+            .starts_with("hosts-digger-no-such-ping isnt installed"));
+    }
+}
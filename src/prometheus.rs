@@ -0,0 +1,164 @@
+//! `stats()` in the prometheus text exposition format, for the node
+//! exporter textfile collector or anything else that scrapes it
+//!
+//! ```text
+//! hosts-digger stats --prometheus > /var/lib/node_exporter/hosts.prom
+//! ```
+
+use std::fmt::Write;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "std-fs")]
+use crate::hosts_file::HostsFile;
+#[cfg(feature = "std-fs")]
+use crate::parser::ParserError;
+use crate::stats::Stats;
+
+/// one value along with the label that sets it apart from the rest of
+/// its metric, on top of the labels every sample gets
+type Sample = (Option<(&'static str, String)>, f64);
+
+/// render `stats` as metrics, with `labels` on every sample. the mtime
+/// metric is only there when `mtime` is
+pub fn render(stats: &Stats, labels: &[(&str, &str)], mtime: Option<SystemTime>) -> String {
+    let mut metrics: Vec<(&str, &str, Vec<Sample>)> = vec![
+        (
+            "hosts_records_total",
+            "records in the hosts file",
+            plain(stats.records),
+        ),
+        (
+            "hosts_records_by_family",
+            "records by address family",
+            vec![
+                (Some(("family", "ipv4".into())), stats.ipv4 as f64),
+                (Some(("family", "ipv6".into())), stats.ipv6 as f64),
+            ],
+        ),
+        (
+            "hosts_records_by_class",
+            "records by class of address",
+            stats
+                .by_class
+                .iter()
+                .map(|(class, count)| {
+                    let class = format!("{class:?}").to_lowercase();
+                    (Some(("class", class)), *count as f64)
+                })
+                .collect(),
+        ),
+        (
+            "hosts_names_total",
+            "names across every record, aliases included",
+            plain(stats.names),
+        ),
+        (
+            "hosts_aliases_total",
+            "names after the first on each record",
+            plain(stats.aliases),
+        ),
+        (
+            "hosts_duplicates_total",
+            "names that show up on more than one record",
+            plain(stats.duplicate_names),
+        ),
+        (
+            "hosts_comment_lines",
+            "comment lines",
+            plain(stats.comments),
+        ),
+        (
+            "hosts_invalid_lines",
+            "lines that dont parse",
+            plain(stats.invalid),
+        ),
+        (
+            "hosts_longest_line_chars",
+            "length of the longest line",
+            plain(stats.longest_line),
+        ),
+    ];
+    if let Some(mtime) = mtime {
+        let secs = mtime.duration_since(UNIX_EPOCH).unwrap_or_default();
+        metrics.push((
+            "hosts_file_mtime_seconds",
+            "when the hosts file was last changed, in unix time",
+            vec![(None, secs.as_secs_f64())],
+        ));
+    }
+
+    let base = labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", escape(v)))
+        .collect::<Vec<_>>();
+    let mut out = String::new();
+    for (name, help, samples) in metrics {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} gauge");
+        for (extra, value) in samples {
+            let mut labels = base.clone();
+            if let Some((k, v)) = extra {
+                labels.push(format!("{k}=\"{}\"", escape(&v)));
+            }
+            let _ = match labels.is_empty() {
+                true => writeln!(out, "{name} {value}"),
+                false => writeln!(out, "{name}{{{}}} {value}", labels.join(",")),
+            };
+        }
+    }
+    out
+}
+
+fn plain(value: usize) -> Vec<Sample> {
+    vec![(None, value as f64)]
+}
+
+/// open the file at `path` and render its metrics with a `path` label
+#[cfg(feature = "std-fs")]
+pub fn render_file<P: AsRef<Path>>(path: P) -> Result<String, ParserError> {
+    let path = path.as_ref();
+    let mtime = std::fs::metadata(path)?.modified().ok();
+    let stats = HostsFile::open(path)?.stats();
+    let label = path.to_string_lossy();
+    Ok(render(&stats, &[("path", &label)], mtime))
+}
+
+/// label values can have anything in them but these three
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hosts_file::HostsFile;
+    use std::time::Duration;
+
+    #[test]
+    fn exposition_format() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n0.0.0.0 ads.example\n0.0.0.0 ads.example\n"
+            .parse()
+            .unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let out = render(&hosts.stats(), &[("path", "C:\\hosts\"")], Some(mtime));
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "# HELP hosts_records_total records in the hosts file"
+        );
+        assert_eq!(lines[1], "# TYPE hosts_records_total gauge");
+        assert_eq!(lines[2], "hosts_records_total{path=\"C:\\\\hosts\\\"\"} 3");
+        assert!(lines
+            .contains(&"hosts_records_by_class{path=\"C:\\\\hosts\\\"\",class=\"unspecified\"} 2"));
+        assert!(lines.contains(&"hosts_duplicates_total{path=\"C:\\\\hosts\\\"\"} 1"));
+        assert_eq!(
+            lines.last(),
+            Some(&"hosts_file_mtime_seconds{path=\"C:\\\\hosts\\\"\"} 1700000000")
+        );
+        assert!(!render(&hosts.stats(), &[], None).contains("mtime"));
+    }
+}
@@ -0,0 +1,54 @@
+//! keeping track of which file every record came out of, for layered
+//! setups where a base file, an override and a blocklist all get merged
+//! and someone wants to know where an entry came from
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::hosts_file::HostsFile;
+#[cfg(feature = "std-fs")]
+use crate::parser::ParserError;
+
+/// the file and line a record was read from
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Source {
+    pub path: PathBuf,
+    /// 1-based line number
+    pub line: usize,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.line)
+    }
+}
+
+impl HostsFile {
+    /// tag every record that came out of the parser with `path` and its
+    /// line number. the tags ride along with the records through `merge`
+    /// so conflicts can say which file said what
+    pub fn with_source<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.set_source(path);
+        self
+    }
+
+    pub fn set_source<P: AsRef<Path>>(&mut self, path: P) {
+        let path = path.as_ref();
+        self.edit(|doc| {
+            for line in doc.records_mut() {
+                if let Some(span) = line.span() {
+                    line.set_source(Some(Source {
+                        path: path.to_path_buf(),
+                        line: span.line,
+                    }));
+                }
+            }
+        })
+    }
+
+    #[cfg(feature = "std-fs")]
+    /// opens `path` and tags every record with where it sits in it
+    pub fn open_tracked<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        Ok(Self::open(path.as_ref())?.with_source(path))
+    }
+}
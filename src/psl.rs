@@ -0,0 +1,182 @@
+//! registrable domains (eTLD+1) off the public suffix list, for telling
+//! how many sites a blocklist covers rather than how many names
+//!
+//! there is no list baked in, load the one your system ships with
+//! `SuffixList::system` or bring your own copy of
+//! https://publicsuffix.org/list/public_suffix_list.dat. with an empty
+//! list every name falls back to the default `*` rule, so `a.b.example`
+//! groups under `b.example`
+
+use std::collections::{BTreeMap, HashSet};
+#[cfg(feature = "std-fs")]
+use std::io;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+
+/// where debian, fedora and arch put the list from the `publicsuffix`
+/// package
+#[cfg(feature = "std-fs")]
+pub const SYSTEM_LIST: &str = "/usr/share/publicsuffix/public_suffix_list.dat";
+
+/// the rules out of a public suffix list file
+#[derive(Clone, Debug, Default)]
+pub struct SuffixList {
+    rules: HashSet<String>,
+    /// `*.ck` is stored as `ck`
+    wildcards: HashSet<String>,
+    /// `!www.ck` is stored as `www.ck`
+    exceptions: HashSet<String>,
+}
+
+impl SuffixList {
+    /// parse the list format, one rule per line with `//` comments. only
+    /// the first word on a line counts, like the spec says
+    pub fn parse(text: &str) -> Self {
+        let mut list = SuffixList::default();
+        for line in text.lines() {
+            let Some(rule) = line.split_whitespace().next() else {
+                continue;
+            };
+            if rule.starts_with("//") {
+                continue;
+            }
+            let rule = fold_name(rule).into_owned();
+            if let Some(rule) = rule.strip_prefix('!') {
+                list.exceptions.insert(rule.to_string());
+            } else if let Some(rule) = rule.strip_prefix("*.") {
+                list.wildcards.insert(rule.to_string());
+            } else {
+                list.rules.insert(rule);
+            }
+        }
+        list
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    /// the list at `SYSTEM_LIST`
+    #[cfg(feature = "std-fs")]
+    pub fn system() -> io::Result<Self> {
+        Self::open(SYSTEM_LIST)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len() + self.wildcards.len() + self.exceptions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// how many labels off the end of `name` make up its public suffix
+    fn suffix_labels(&self, labels: &[&str]) -> usize {
+        let mut longest = 1;
+        for i in (0..labels.len()).rev() {
+            let suffix = labels[i..].join(".");
+            let count = labels.len() - i;
+            if self.exceptions.contains(&suffix) {
+                // an exception rule means the suffix is one label shorter,
+                // and it beats everything else
+                return count - 1;
+            }
+            if self.rules.contains(&suffix) {
+                longest = longest.max(count);
+            }
+            if i > 0 && self.wildcards.contains(&suffix) {
+                longest = longest.max(count + 1);
+            }
+        }
+        longest
+    }
+
+    /// the public suffix of `name`, `co.uk` for `www.example.co.uk`
+    pub fn public_suffix(&self, name: &str) -> String {
+        let name = fold_name(name.strip_suffix('.').unwrap_or(name)).into_owned();
+        let labels = name.split('.').collect::<Vec<_>>();
+        let count = self.suffix_labels(&labels).min(labels.len());
+        labels[labels.len() - count..].join(".")
+    }
+
+    /// the public suffix plus one label, `example.co.uk` for
+    /// `www.example.co.uk`. none when the name is a public suffix itself,
+    /// or has no dots like `localhost`
+    pub fn registrable_domain(&self, name: &str) -> Option<String> {
+        let name = fold_name(name.strip_suffix('.').unwrap_or(name)).into_owned();
+        let labels = name.split('.').collect::<Vec<_>>();
+        if labels.iter().any(|label| label.is_empty()) {
+            return None;
+        }
+        let count = self.suffix_labels(&labels) + 1;
+        (count <= labels.len()).then(|| labels[labels.len() - count..].join("."))
+    }
+}
+
+impl HostsFile {
+    /// every name in the file grouped under its registrable domain, with
+    /// each name showing up once however many lines its on. names that
+    /// dont have one, `localhost` and the like, are left out
+    pub fn group_by_registrable_domain(&self, list: &SuffixList) -> BTreeMap<String, Vec<&str>> {
+        let mut seen = HashSet::new();
+        let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+        for name in self.records().iter().flat_map(|r| r.names()) {
+            if !seen.insert(fold_name(name)) {
+                continue;
+            }
+            if let Some(domain) = list.registrable_domain(name) {
+                groups.entry(domain).or_default().push(name);
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "// a few rules out of the real list\n\
+        com\nuk\nco.uk\n\n// ===BEGIN PRIVATE DOMAINS===\n\
+        github.io\n*.ck\n!www.ck\n";
+
+    #[test]
+    fn registrable_domains() {
+        let list = SuffixList::parse(LIST);
+        assert_eq!(list.len(), 6);
+        let domain = |name| list.registrable_domain(name);
+        assert_eq!(
+            domain("www.example.co.uk").as_deref(),
+            Some("example.co.uk")
+        );
+        assert_eq!(domain("Ads.Example.COM.").as_deref(), Some("example.com"));
+        assert_eq!(domain("me.github.io").as_deref(), Some("me.github.io"));
+        assert_eq!(domain("a.b.ck").as_deref(), Some("a.b.ck"));
+        assert_eq!(domain("www.ck").as_deref(), Some("www.ck"));
+        // unlisted tlds fall back to the default rule
+        assert_eq!(domain("x.tracker.lan").as_deref(), Some("tracker.lan"));
+        assert_eq!(domain("co.uk"), None);
+        assert_eq!(domain("localhost"), None);
+        assert_eq!(list.public_suffix("www.example.co.uk"), "co.uk");
+    }
+
+    #[test]
+    fn groups_by_site() {
+        let hosts: HostsFile = "0.0.0.0 ads.example.com track.example.com\n\
+            0.0.0.0 ADS.example.com pixel.shop.co.uk\n\
+            127.0.0.1 localhost\n"
+            .parse()
+            .unwrap();
+        let groups = hosts.group_by_registrable_domain(&SuffixList::parse(LIST));
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups["example.com"],
+            ["ads.example.com", "track.example.com"]
+        );
+        assert_eq!(groups["shop.co.uk"], ["pixel.shop.co.uk"]);
+    }
+}
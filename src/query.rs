@@ -0,0 +1,173 @@
+//! questions you can ask a hosts file beyond plain lookups
+
+use crate::cidr::Cidr;
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+impl HostsFile {
+    /// every record with an address inside `network`, in file order. handy
+    /// for finding what still points into a subnet thats going away
+    pub fn records_in(&self, network: Cidr) -> Vec<&Record> {
+        self.records()
+            .iter()
+            .filter(|record| network.contains(record.addr()))
+            .collect()
+    }
+
+    /// every name matching the glob `pattern` along with the record its on,
+    /// in file order. `*` matches any run of characters, dots included, and
+    /// `?` matches exactly one, so `*.corp` is everything under `.corp`.
+    /// case doesnt matter
+    pub fn find_names(&self, pattern: &str) -> Vec<(&str, &Record)> {
+        let pattern: Vec<char> = fold_name(pattern).chars().collect();
+        self.records()
+            .iter()
+            .flat_map(|record| {
+                record
+                    .names()
+                    .iter()
+                    .map(move |name| (name.as_str(), record))
+            })
+            .filter(|(name, _)| {
+                let name: Vec<char> = fold_name(name).chars().collect();
+                glob_match(&pattern, &name)
+            })
+            .collect()
+    }
+
+    /// the record for `name` or the closest parent domain that has one,
+    /// so a `0.0.0.0 ads.example.com` line covers
+    /// `tracker.ads.example.com` too. this is how dns proxies that block
+    /// off a hosts file usually treat it. every parent is one hash lookup,
+    /// so its cheap to call for every query that comes through
+    pub fn matches_suffix(&self, name: &str) -> Option<&Record> {
+        let mut name = name.strip_suffix('.').unwrap_or(name);
+        loop {
+            if let Some(record) = self.record_for(name) {
+                return Some(record);
+            }
+            name = name.split_once('.')?.1;
+        }
+    }
+}
+
+/// true when `name` matches the glob `pattern`, ignoring case
+pub(crate) fn name_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = fold_name(pattern).chars().collect();
+    let name: Vec<char> = fold_name(name).chars().collect();
+    glob_match(&pattern, &name)
+}
+
+/// classic wildcard matching, on a mismatch we go back to the last `*`
+/// and let it eat one more character
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((sp, st)) => {
+                    p = sp + 1;
+                    t = st + 1;
+                    star = Some((sp, st + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_in_network() {
+        let hosts: HostsFile = "127.0.0.1 localhost\n\
+            10.1.0.5 db\n\
+            10.2.0.9 cache\n\
+            10.1.7.1 queue\n\
+            fd00::5 db6\n"
+            .parse()
+            .unwrap();
+        let names = |net: &str| {
+            hosts
+                .records_in(net.parse().unwrap())
+                .iter()
+                .map(|r| r.primary_name().unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("10.1.0.0/16"), ["db", "queue"]);
+        assert_eq!(names("10.0.0.0/8"), ["db", "cache", "queue"]);
+        assert_eq!(names("fd00::/8"), ["db6"]);
+        assert!(names("192.168.0.0/16").is_empty());
+    }
+
+    #[test]
+    fn glob_names() {
+        let hosts: HostsFile = "10.0.0.5 db.corp db\n\
+            10.0.0.6 Cache.CORP\n\
+            10.0.0.7 corp web.corp.example\n"
+            .parse()
+            .unwrap();
+        let names = |pattern: &str| {
+            hosts
+                .find_names(pattern)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("*.corp"), ["db.corp", "Cache.CORP"]);
+        assert_eq!(
+            names("*corp*"),
+            ["db.corp", "Cache.CORP", "corp", "web.corp.example"]
+        );
+        assert_eq!(names("d?"), ["db"]);
+        assert_eq!(names("*"), names("**"));
+        assert!(names("*.internal").is_empty());
+        assert_eq!(
+            hosts.find_names("cache.*")[0].1.addr().to_string(),
+            "10.0.0.6"
+        );
+    }
+
+    #[test]
+    fn suffix_matches() {
+        let hosts: HostsFile = "0.0.0.0 ads.example.com
+            0.0.0.0 tracker.ads.example.com track.example.org
+            10.0.0.5 db
+"
+        .parse()
+        .unwrap();
+        let matched = |name: &str| {
+            hosts
+                .matches_suffix(name)
+                .map(|r| r.primary_name().unwrap())
+        };
+        assert_eq!(matched("ads.example.com"), Some("ads.example.com"));
+        assert_eq!(matched("x.y.Ads.Example.com."), Some("ads.example.com"));
+        // the closest parent wins
+        assert_eq!(
+            matched("a.tracker.ads.example.com"),
+            Some("tracker.ads.example.com")
+        );
+        assert_eq!(
+            matched("a.track.example.org"),
+            Some("tracker.ads.example.com")
+        );
+        assert_eq!(matched("db"), Some("db"));
+        assert_eq!(matched("example.com"), None);
+        assert_eq!(matched("notads.example.com"), None);
+        assert_eq!(matched(""), None);
+    }
+}
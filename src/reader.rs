@@ -0,0 +1,110 @@
+use crate::hostname::lowercase_name;
+use crate::parser::{parse_line_with, ParseOptions, ParserError};
+use crate::record::Record;
+use std::io::BufRead;
+
+/// HostsReader streams records out of a hosts file one line at a time
+///
+/// nothing but the current line is kept around, so this is the way to go
+/// for blocklist sized files where you only want to filter or count. bad
+/// lines come out as errors tagged with their line number and reading
+/// carries on after them, comments and blank lines are skipped
+#[derive(Debug)]
+pub struct HostsReader<R> {
+    reader: R,
+    buf: String,
+    line: usize,
+    options: ParseOptions,
+    done: bool,
+}
+
+impl<R: BufRead> HostsReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    /// `strict` doesnt mean anything here, every bad line is handed back
+    /// as an error and its up to the caller whether to keep going
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader,
+            buf: String::new(),
+            line: 0,
+            options,
+            done: false,
+        }
+    }
+
+    /// the 1-based number of the last line read
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl<R: BufRead> Iterator for HostsReader<R> {
+    type Item = Result<Record, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line += 1;
+                    let mut raw = self.buf.as_str();
+                    if self.line == 1 {
+                        raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+                    }
+                    match parse_line_with(raw, self.options.hostnames) {
+                        Ok(None) => continue,
+                        Ok(Some(mut record)) => {
+                            if self.options.lowercase_names {
+                                record.names =
+                                    record.names.iter().map(|n| lowercase_name(n)).collect();
+                            }
+                            return Some(Ok(record));
+                        }
+                        Err(e) => return Some(Err(ParserError::at_line(self.line, raw, e))),
+                    }
+                }
+                Err(e) => {
+                    // a read error means the rest of the stream is suspect
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streams_records() {
+        let src = "\u{feff}# blocklist\r\n0.0.0.0 ads.example\r\n\r\nnope nope\r\n0.0.0.0 tracker.example\r\n";
+        let mut reader = HostsReader::new(src.as_bytes());
+        assert_eq!(reader.next().unwrap().unwrap().names(), ["ads.example"]);
+        match reader.next().unwrap() {
+            Err(ParserError::AtLine { line, .. }) => assert_eq!(line, 4),
+            r => panic!("expected an error on line 4, got {r:?}"),
+        }
+        assert_eq!(reader.next().unwrap().unwrap().names(), ["tracker.example"]);
+        assert!(reader.next().is_none());
+        assert_eq!(reader.line(), 5);
+    }
+
+    #[test]
+    fn early_exit() {
+        let src = "0.0.0.0 a.example\n0.0.0.0 b.example\n0.0.0.0 c.example\n";
+        let mut reader = HostsReader::new(src.as_bytes());
+        let found = reader
+            .by_ref()
+            .filter_map(Result::ok)
+            .find(|r| r.primary_name() == Some("b.example"));
+        assert!(found.is_some());
+        assert_eq!(reader.line(), 2);
+    }
+}
@@ -0,0 +1,491 @@
+use crate::hostname::{fold_name, validate_hostname, HostnameError, Strictness};
+use crate::line;
+use crate::parser::{parse_line, ParserError};
+use crate::policy::PolicyViolation;
+use crate::provenance::Source;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RecordError {
+    #[error("{0:?} cant be a comment, it has to stay on one line")]
+    InvalidComment(String),
+
+    #[error("scope id {1:?} is only valid on link-local and multicast ipv6 addrs, not {0}")]
+    InvalidScope(String, String),
+
+    #[error("invalid hostname {name:?}: {source}")]
+    InvalidHostname {
+        name: String,
+        #[source]
+        source: HostnameError,
+    },
+
+    #[error("record needs an address")]
+    MissingAddress,
+
+    #[error("record needs at least one name")]
+    MissingNames,
+
+    #[error("{0:?} is on the record more than once")]
+    DuplicateName(String),
+
+    #[error("{0:?} cant be a comment tag, it has to be one word without `#`")]
+    InvalidTag(String),
+
+    #[error(transparent)]
+    Policy(#[from] PolicyViolation),
+}
+
+/// Record is a way of representing a single entry in the hosts files
+///
+/// two records are equal when they say the same thing, where they came
+/// from doesnt count
+#[derive(Clone, Debug)]
+pub struct Record {
+    /// addr for the record
+    pub(crate) addr: IpAddr,
+    /// zone/scope id for link-local ipv6 addrs, the `lo0` in `fe80::1%lo0`
+    pub(crate) scope: Option<String>,
+    /// here we have multiple names for a single record
+    pub(crate) names: Vec<String>,
+    /// trailing comment on the line, the text after the `#`
+    pub(crate) comment: Option<String>,
+    /// the file and line this record was read from, when someone asked
+    /// for it to be tracked
+    pub(crate) source: Option<Source>,
+}
+impl Record {
+    /// a record for `addr`, the names are checked at the default
+    /// `Strictness`
+    pub fn new(addr: IpAddr, names: Vec<String>) -> Result<Self, RecordError> {
+        Self::with_strictness(addr, names, Strictness::default())
+    }
+
+    /// like `new` but with control over how picky to be about the names
+    pub fn with_strictness(
+        addr: IpAddr,
+        names: Vec<String>,
+        strictness: Strictness,
+    ) -> Result<Self, RecordError> {
+        // a line with just an address doesnt parse back
+        if names.is_empty() {
+            return Err(RecordError::MissingNames);
+        }
+        check_names(names.iter().map(String::as_str), strictness)?;
+        // every kind of address is fair game in a hosts file, see
+        // `ip_class` for telling them apart
+        Ok(Self {
+            addr,
+            scope: None,
+            names,
+            comment: None,
+            source: None,
+        })
+    }
+
+    /// a record for a scoped ipv6 addr, like the `fe80::1%lo0 localhost`
+    /// entry macOS ships with
+    pub fn scoped(addr: IpAddr, scope: String, names: Vec<String>) -> Result<Self, RecordError> {
+        Self::new(addr, names)?.with_scope(scope)
+    }
+
+    /// tack a scope id onto the record, only link-local ipv6 can have one
+    pub(crate) fn with_scope(mut self, scope: String) -> Result<Self, RecordError> {
+        check_scope(self.addr, &scope)?;
+        self.scope = Some(scope);
+        Ok(self)
+    }
+
+    /// the address every name on this record resolves to
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// the zone id if this is a scoped ipv6 record
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    /// all of the names on this record, primary name first
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// the first name on the line, everything after it is an alias
+    pub fn primary_name(&self) -> Option<&str> {
+        self.names.first().map(String::as_str)
+    }
+
+    /// the trailing comment on the line, like `managed by ansible` in
+    /// `1.2.3.4 host # managed by ansible`
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// a comment with a line break in it would start a line of its own
+    /// when written out, so those are turned down
+    pub fn set_comment(&mut self, comment: Option<String>) -> Result<(), RecordError> {
+        if let Some(comment) = &comment {
+            check_comment(comment)?;
+        }
+        self.comment = comment;
+        Ok(())
+    }
+
+    /// where this record came from, see `HostsFile::with_source`
+    pub fn source(&self) -> Option<&Source> {
+        self.source.as_ref()
+    }
+
+    pub fn set_source(&mut self, source: Option<Source>) {
+        self.source = source;
+    }
+
+    /// like `set_comment`
+    pub fn with_comment<S: Into<String>>(mut self, comment: S) -> Result<Self, RecordError> {
+        self.set_comment(Some(comment.into()))?;
+        Ok(self)
+    }
+
+    /// the address as it gets written in the file, scope id and all
+    pub(crate) fn addr_field(&self) -> String {
+        match &self.scope {
+            Some(scope) => format!("{}%{scope}", self.addr),
+            None => self.addr.to_string(),
+        }
+    }
+
+    /// the names as they get written in the file
+    ///
+    /// with the `idna` feature unicode names are written as punycode so
+    /// resolvers that only speak ascii can still match them
+    pub(crate) fn names_field(&self) -> String {
+        #[cfg(feature = "idna")]
+        {
+            self.names
+                .iter()
+                .map(|name| crate::idna::to_ascii(name).unwrap_or_else(|_| name.clone()))
+                .collect::<Vec<String>>()
+                .join(" ")
+        }
+        #[cfg(not(feature = "idna"))]
+        {
+            self.names.join(" ")
+        }
+    }
+
+    /// the names with any punycode decoded back to unicode, for showing
+    /// to people. names that dont decode come back as they are
+    #[cfg(feature = "idna")]
+    pub fn unicode_names(&self) -> Vec<String> {
+        self.names
+            .iter()
+            .map(|name| crate::idna::to_unicode(name).unwrap_or_else(|_| name.clone()))
+            .collect()
+    }
+
+    /// break the record back up into its address and names
+    pub fn into_parts(self) -> (IpAddr, Vec<String>) {
+        (self.addr, self.names)
+    }
+}
+
+/// a record displays as a single hosts line, `addr\tname alias...`
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\t{}", self.addr_field(), self.names_field())?;
+        if let Some(comment) = &self.comment {
+            write!(f, " # {comment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// records sort by address with every ipv4 addr ahead of every ipv6 one
+/// and addrs compared numerically, then by primary name ignoring case.
+/// anything still tied falls back to scope, the exact names and the
+/// comment so the ordering agrees with `==`
+impl Ord for Record {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.addr
+            .cmp(&other.addr)
+            .then_with(|| {
+                let a = self.primary_name().map(fold_name);
+                let b = other.primary_name().map(fold_name);
+                a.cmp(&b)
+            })
+            .then_with(|| self.scope.cmp(&other.scope))
+            .then_with(|| self.names.cmp(&other.names))
+            .then_with(|| self.comment.cmp(&other.comment))
+    }
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.addr == other.addr
+            && self.scope == other.scope
+            && self.names == other.names
+            && self.comment == other.comment
+    }
+}
+
+impl Eq for Record {}
+
+impl Hash for Record {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.addr.hash(state);
+        self.scope.hash(state);
+        self.names.hash(state);
+        self.comment.hash(state);
+    }
+}
+
+impl PartialOrd for Record {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// parse a single hosts line, `"10.0.0.5\tdb db.internal"`
+impl FromStr for Record {
+    type Err = ParserError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_line(s)?.ok_or(ParserError::MissingAddress)
+    }
+}
+
+impl TryFrom<&str> for Record {
+    type Error = ParserError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+/// make sure every name passes the hostname rules at `strictness`
+pub(crate) fn check_names<'a, I>(names: I, strictness: Strictness) -> Result<(), RecordError>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    for name in names {
+        validate_hostname(name, strictness).map_err(|source| RecordError::InvalidHostname {
+            name: name.to_string(),
+            source,
+        })?;
+    }
+    Ok(())
+}
+
+/// comments go after the names on the same line, so no line breaks
+pub(crate) fn check_comment(comment: &str) -> Result<(), RecordError> {
+    if comment.contains(['\r', '\n']) {
+        return Err(RecordError::InvalidComment(comment.to_string()));
+    }
+    Ok(())
+}
+
+/// only link-local ipv6 addrs can carry a scope id
+pub(crate) fn check_scope(addr: IpAddr, scope: &str) -> Result<(), RecordError> {
+    // anything that would split the address column when written out
+    let breaks_line = |c: char| c.is_whitespace() || c == '#';
+    if !line::scopable(addr) || scope.is_empty() || scope.contains(breaks_line) {
+        return Err(RecordError::InvalidScope(
+            addr.to_string(),
+            scope.to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_loopback() {
+        let addr = "127.0.0.1".parse().unwrap();
+        let names: Vec<String> = vec!["localhost".to_string()];
+        let record = Record::new(addr, names);
+        assert!(record.is_ok())
+    }
+
+    #[test]
+    fn create_private() {
+        let addr = "192.168.10.42".parse().unwrap();
+        let names: Vec<String> = vec!["core.naus".to_string()];
+        let record = Record::new(addr, names);
+        assert!(record.is_ok())
+    }
+
+    #[test]
+    fn create_ipv6() {
+        for addr in ["::1", "fe00::0", "ff00::0", "ff02::1", "ff02::2"] {
+            let names = vec!["ip6-localhost".to_string()];
+            assert!(Record::new(addr.parse().unwrap(), names).is_ok());
+        }
+    }
+
+    #[test]
+    fn create_without_names() {
+        let addr: IpAddr = "10.0.0.5".parse().unwrap();
+        assert!(matches!(
+            Record::new(addr, Vec::new()),
+            Err(RecordError::MissingNames)
+        ));
+        let scoped = Record::scoped("fe80::1".parse().unwrap(), "lo0".into(), Vec::new());
+        assert!(matches!(scoped, Err(RecordError::MissingNames)));
+    }
+
+    #[test]
+    fn create_scoped() {
+        let names = vec!["localhost".to_string()];
+        let record = Record::scoped("fe80::1".parse().unwrap(), "lo0".to_string(), names);
+        assert_eq!(record.unwrap().scope(), Some("lo0"));
+    }
+
+    #[test]
+    fn scope_only_on_link_local() {
+        let names = vec!["localhost".to_string()];
+        let v4 = Record::scoped("127.0.0.1".parse().unwrap(), "lo0".into(), names.clone());
+        assert!(v4.is_err());
+        let global = Record::scoped("2001:db8::1".parse().unwrap(), "en0".into(), names.clone());
+        assert!(global.is_err());
+        let spaced = Record::scoped("fe80::1".parse().unwrap(), "en0 x".into(), names);
+        assert!(spaced.is_err());
+    }
+
+    #[test]
+    fn display() {
+        let names = vec!["localhost".to_string(), "lo".to_string()];
+        let record = Record::scoped("fe80::1".parse().unwrap(), "lo0".into(), names).unwrap();
+        assert_eq!(record.to_string(), "fe80::1%lo0\tlocalhost lo");
+    }
+
+    #[test]
+    fn display_comment() {
+        let names = vec!["db".to_string()];
+        let record = Record::new("10.0.0.5".parse().unwrap(), names).unwrap();
+        let record = record.with_comment("managed by ansible").unwrap();
+        assert_eq!(record.to_string(), "10.0.0.5\tdb # managed by ansible");
+
+        // would write a second line that resolves accounts.google.com
+        let injected = "ok\n0.0.0.0 accounts.google.com";
+        assert!(matches!(
+            record.clone().with_comment(injected),
+            Err(RecordError::InvalidComment(_))
+        ));
+        let mut record = record;
+        assert!(record.set_comment(Some("ok\r".into())).is_err());
+        assert_eq!(record.comment(), Some("managed by ansible"));
+    }
+
+    #[test]
+    fn from_str() {
+        let record: Record = "10.0.0.5\tdb db.internal".parse().unwrap();
+        assert_eq!(record.addr(), "10.0.0.5".parse::<IpAddr>().unwrap());
+        assert_eq!(record.names(), ["db", "db.internal"]);
+        assert_eq!(
+            Record::try_from("10.0.0.5\tdb db.internal").unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn from_str_errors() {
+        assert!(matches!(
+            "# just a comment".parse::<Record>(),
+            Err(ParserError::MissingAddress)
+        ));
+        assert!(matches!(
+            "10.0.0.500 db".parse::<Record>(),
+            Err(ParserError::InvalidAddress { .. })
+        ));
+        assert!(matches!(
+            "10.0.0.5".parse::<Record>(),
+            Err(ParserError::MissingNames(_))
+        ));
+        assert!(matches!(
+            "10.0.0.5%eth0 db".parse::<Record>(),
+            Err(ParserError::InvalidRecord(RecordError::InvalidScope(..)))
+        ));
+        // multicast takes a scope too
+        assert!("ff02::1%eth0 ip6-allnodes".parse::<Record>().is_ok());
+    }
+
+    #[test]
+    fn hostnames_validated() {
+        let addr: IpAddr = "10.0.0.5".parse().unwrap();
+        let err = Record::new(addr, vec!["db".into(), "-db".into()]).unwrap_err();
+        assert!(matches!(err, RecordError::InvalidHostname { name, .. } if name == "-db"));
+
+        let names = vec!["_service.db".to_string()];
+        assert!(Record::new(addr, names.clone()).is_ok());
+        assert!(Record::with_strictness(addr, names, Strictness::Strict).is_err());
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn idna_names() {
+        let names = vec![
+            "bücher.example".to_string(),
+            "xn--mnchen-3ya.de".to_string(),
+        ];
+        let record = Record::new("10.0.0.5".parse().unwrap(), names).unwrap();
+        assert_eq!(
+            record.to_string(),
+            "10.0.0.5\txn--bcher-kva.example xn--mnchen-3ya.de"
+        );
+        assert_eq!(record.unicode_names(), ["bücher.example", "münchen.de"]);
+    }
+
+    #[test]
+    fn accessors() {
+        let addr: IpAddr = "10.0.0.5".parse().unwrap();
+        let names = vec!["db".to_string(), "db.internal".to_string()];
+        let record = Record::new(addr, names.clone()).unwrap();
+        assert_eq!(record.addr(), addr);
+        assert_eq!(record.names(), &names[..]);
+        assert_eq!(record.primary_name(), Some("db"));
+        assert_eq!(record.into_parts(), (addr, names));
+    }
+
+    #[test]
+    fn ordering() {
+        let record = |s: &str| s.parse::<Record>().unwrap();
+        let mut records = [
+            record("::1 localhost"),
+            record("10.0.0.10 web"),
+            record("10.0.0.9 db"),
+            record("10.0.0.9 Cache"),
+            record("127.0.0.1 localhost"),
+            record("10.0.0.9 cache alias"),
+        ];
+        records.sort();
+        let lines: Vec<String> = records.iter().map(|r| r.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "10.0.0.9\tCache",
+                "10.0.0.9\tcache alias",
+                "10.0.0.9\tdb",
+                "10.0.0.10\tweb",
+                "127.0.0.1\tlocalhost",
+                "::1\tlocalhost",
+            ]
+        );
+        assert_eq!(records.binary_search(&record("10.0.0.10 web")), Ok(3));
+
+        let set: std::collections::BTreeSet<_> = records.iter().cloned().collect();
+        assert_eq!(set.len(), records.len());
+        assert_eq!(
+            record("10.0.0.9 db").cmp(&record("10.0.0.9 db")),
+            Ordering::Equal
+        );
+    }
+}
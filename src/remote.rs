@@ -0,0 +1,282 @@
+//! downloading hosts format blocklists, the building block for a pi-hole
+//! style updater
+//!
+//! the download itself is handed to the system `curl` so we dont have to
+//! carry an http and tls stack around. with a `RemoteCache` the last copy
+//! of every list is kept on disk along with its etag and last-modified,
+//! and lists that havent changed come straight out of the cache
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use thiserror::Error;
+
+use crate::parser::{ParseOptions, ParseWarning, ParserError};
+use crate::record::Record;
+use crate::report::ParseReport;
+
+#[derive(Error, Debug)]
+pub enum RemoteError {
+    #[error("fetching {url} failed: {reason}")]
+    Fetch { url: String, reason: String },
+    #[error("{url} doesnt look like a hosts file: {reason}")]
+    Invalid { url: String, reason: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParserError),
+}
+
+/// a downloaded list
+#[derive(Debug)]
+pub struct Fetched {
+    pub records: Vec<Record>,
+    /// lines in the list that didnt parse
+    pub warnings: Vec<ParseWarning>,
+    /// true when the server said nothing changed and the cached copy was
+    /// used
+    pub from_cache: bool,
+}
+
+/// download `url` without caching anything
+pub fn fetch(url: &str) -> Result<Fetched, RemoteError> {
+    // a dir of its own for every call, so two fetches at once dont clean
+    // up each others files
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = loop {
+        let dir = std::env::temp_dir().join(format!(
+            "hosts-digger-fetch-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::create_dir(&dir) {
+            Ok(()) => break dir,
+            // left over from an earlier process that had our pid
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let result = RemoteCache::new(&dir).fetch(url);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+/// a directory of downloaded lists
+#[derive(Clone, Debug)]
+pub struct RemoteCache {
+    dir: PathBuf,
+}
+
+/// the validators we send back on the next fetch
+#[derive(Debug, Default, PartialEq)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl RemoteCache {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        RemoteCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// download `url` unless the cached copy is still current, then parse
+    /// and check it. a list that is empty, html or has nothing but
+    /// broken lines is an error and doesnt replace the cached copy
+    pub fn fetch(&self, url: &str) -> Result<Fetched, RemoteError> {
+        fs::create_dir_all(&self.dir)?;
+        let key = cache_key(url);
+        let body_path = self.dir.join(format!("{key}.hosts"));
+        let meta_path = self.dir.join(format!("{key}.meta"));
+        let tmp_body = self.dir.join(format!(".{key}.body.tmp"));
+        let tmp_headers = self.dir.join(format!(".{key}.headers.tmp"));
+        let _ = fs::remove_file(&tmp_body);
+
+        let cached = match body_path.exists() {
+            // the meta file is written in header syntax
+            true => parse_headers(&fs::read_to_string(&meta_path).unwrap_or_default()),
+            false => Validators::default(),
+        };
+
+        let mut cmd = Command::new("curl");
+        cmd.args(["--silent", "--show-error", "--location", "--fail"])
+            .arg("--dump-header")
+            .arg(&tmp_headers)
+            .arg("--output")
+            .arg(&tmp_body)
+            .args(["--write-out", "%{http_code}"]);
+        if let Some(etag) = &cached.etag {
+            cmd.arg("--header").arg(format!("If-None-Match: {etag}"));
+        }
+        if let Some(date) = &cached.last_modified {
+            cmd.arg("--time-cond").arg(date);
+        }
+        // as an option value so a url that starts with `-` isnt taken for one
+        let output = cmd
+            .arg("--url")
+            .arg(url)
+            .output()
+            .map_err(|e| RemoteError::Fetch {
+                url: url.to_string(),
+                reason: format!("couldnt run curl: {e}"),
+            })?;
+        let headers = fs::read_to_string(&tmp_headers).unwrap_or_default();
+        let _ = fs::remove_file(&tmp_headers);
+        if !output.status.success() {
+            return Err(RemoteError::Fetch {
+                url: url.to_string(),
+                reason: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        // curl skips writing the body at all when a time condition says
+        // nothing changed, which is also how file:// urls report it
+        if status == "304" || !tmp_body.exists() {
+            if !body_path.exists() {
+                return Err(RemoteError::Fetch {
+                    url: url.to_string(),
+                    reason: "nothing came back".to_string(),
+                });
+            }
+            let mut fetched = parse_list(url, &fs::read(&body_path)?)?;
+            fetched.from_cache = true;
+            return Ok(fetched);
+        }
+
+        let body = fs::read(&tmp_body)?;
+        let fetched = match parse_list(url, &body) {
+            Ok(fetched) => fetched,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp_body);
+                return Err(e);
+            }
+        };
+        fs::rename(&tmp_body, &body_path)?;
+        fs::write(&meta_path, format_meta(&parse_headers(&headers)))?;
+        Ok(fetched)
+    }
+}
+
+fn parse_list(url: &str, body: &[u8]) -> Result<Fetched, RemoteError> {
+    let invalid = |reason: &str| RemoteError::Invalid {
+        url: url.to_string(),
+        reason: reason.to_string(),
+    };
+    if body.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'<') {
+        return Err(invalid("got html back"));
+    }
+    let report = ParseReport::parse_reader(body, &ParseOptions::default())?;
+    if report.records.is_empty() {
+        return Err(match report.warnings.is_empty() {
+            true => invalid("there are no records in it"),
+            false => invalid("none of its lines parse"),
+        });
+    }
+    Ok(Fetched {
+        records: report.records,
+        warnings: report.warnings,
+        from_cache: false,
+    })
+}
+
+/// fnv-1a of the url, stable across runs and platforms
+fn cache_key(url: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in url.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{hash:016x}")
+}
+
+/// the validators out of the last response, curl writes one header block
+/// per redirect it follows
+fn parse_headers(raw: &str) -> Validators {
+    let last = raw
+        .rsplit("HTTP/")
+        .next()
+        .filter(|_| raw.contains("HTTP/"))
+        .unwrap_or(raw);
+    let mut out = Validators::default();
+    for line in last.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = Some(value.trim().to_string());
+        match name.trim().to_ascii_lowercase().as_str() {
+            "etag" => out.etag = value,
+            "last-modified" => out.last_modified = value,
+            _ => {}
+        }
+    }
+    out
+}
+
+fn format_meta(validators: &Validators) -> String {
+    let mut out = String::new();
+    if let Some(etag) = &validators.etag {
+        out.push_str(&format!("etag: {etag}\n"));
+    }
+    if let Some(date) = &validators.last_modified {
+        out.push_str(&format!("last-modified: {date}\n"));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headers_from_last_redirect() {
+        let raw = "HTTP/1.1 301 Moved\r\nLocation: /lists/ads\r\nETag: \"old\"\r\n\r\n\
+                   HTTP/2 200\r\netag: \"abc123\"\r\nLast-Modified: Wed, 14 Oct 2026 04:55:06 GMT\r\n\r\n";
+        let validators = parse_headers(raw);
+        assert_eq!(validators.etag.as_deref(), Some("\"abc123\""));
+        assert_eq!(
+            validators.last_modified.as_deref(),
+            Some("Wed, 14 Oct 2026 04:55:06 GMT")
+        );
+        assert_eq!(parse_headers(&format_meta(&validators)), validators);
+        assert_eq!(cache_key("https://example.com/hosts").len(), 16);
+    }
+
+    #[test]
+    fn fetch_and_cache_file_url() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-remote-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let list = dir.join("ads.hosts");
+        fs::write(&list, "0.0.0.0 ads.example\n0.0.0.0 track.example\nbogus\n").unwrap();
+        let url = format!("file://{}", list.display());
+        let cache = RemoteCache::new(dir.join("cache"));
+
+        if let Err(e) = Command::new("curl").arg("--version").output() {
+            fs::remove_dir_all(&dir).unwrap();
+            // no curl on this machine is the only reason to skip
+            assert_eq!(e.kind(), io::ErrorKind::NotFound, "{e}");
+            return;
+        }
+        let first = cache.fetch(&url).unwrap();
+        assert!(!first.from_cache);
+        assert_eq!(first.records.len(), 2);
+        assert_eq!(first.warnings.len(), 1);
+
+        let second = cache.fetch(&url).unwrap();
+        assert!(second.from_cache);
+        assert_eq!(second.records, first.records);
+        assert_eq!(fetch(&url).unwrap().records, first.records);
+
+        fs::write(dir.join("page.html"), "<html>rate limited</html>").unwrap();
+        let html = format!("file://{}", dir.join("page.html").display());
+        assert!(matches!(
+            cache.fetch(&html),
+            Err(RemoteError::Invalid { .. })
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! lenient parsing that owns up to what it dropped. sync tools want the
+//! records and also want to shout about every line that didnt make it
+
+use std::io::Read;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use crate::document::HostsDocument;
+use crate::parser::{ParseOptions, ParseWarning, ParserError};
+use crate::record::Record;
+
+/// the records that parsed along with every line that got skipped
+#[derive(Debug, Default)]
+pub struct ParseReport {
+    pub records: Vec<Record>,
+    /// one per skipped line, in file order. the error on each says why
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl ParseReport {
+    /// parse `s`, skipping broken lines no matter what `options.strict`
+    /// says. an error means nothing could be read at all
+    pub fn parse(s: &str, options: &ParseOptions) -> Result<Self, ParserError> {
+        Ok(Self::from_parsed(HostsDocument::parse_with(
+            s,
+            &lenient(options),
+        )?))
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P, options: &ParseOptions) -> Result<Self, ParserError> {
+        Ok(Self::from_parsed(HostsDocument::open_with(
+            path,
+            &lenient(options),
+        )?))
+    }
+
+    pub fn parse_reader<R: Read>(reader: R, options: &ParseOptions) -> Result<Self, ParserError> {
+        Ok(Self::from_parsed(HostsDocument::parse_reader_with(
+            reader,
+            &lenient(options),
+        )?))
+    }
+
+    /// true when every line made it in
+    pub fn is_clean(&self) -> bool {
+        self.warnings.is_empty()
+    }
+
+    fn from_parsed((document, warnings): (HostsDocument, Vec<ParseWarning>)) -> Self {
+        ParseReport {
+            records: document.records().cloned().collect(),
+            warnings,
+        }
+    }
+}
+
+fn lenient(options: &ParseOptions) -> ParseOptions {
+    ParseOptions {
+        strict: false,
+        ..options.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_skipped_lines() {
+        let src = format!(
+            "127.0.0.1 localhost\n\
+             10.0.0.300 db\n\
+             10.0.0.6\n\
+             10.0.0.7 {}\n\
+             10.0.0.8 cache\n",
+            "alias ".repeat(50).trim_end()
+        );
+        let options = ParseOptions {
+            strict: true,
+            max_line_length: Some(255),
+            ..Default::default()
+        };
+        let report = ParseReport::parse(&src, &options).unwrap();
+        assert!(!report.is_clean());
+        let names: Vec<_> = report
+            .records
+            .iter()
+            .map(|r| r.names()[0].as_str())
+            .collect();
+        assert_eq!(names, ["localhost", "cache"]);
+
+        let reasons: Vec<_> = report.warnings.iter().map(|w| &w.error).collect();
+        assert!(
+            matches!(reasons[0], ParserError::InvalidAddress { token, .. } if token == "10.0.0.300")
+        );
+        assert!(matches!(reasons[1], ParserError::MissingNames(_)));
+        assert!(matches!(
+            reasons[2],
+            ParserError::LineTooLong {
+                length: 308,
+                max: 255
+            }
+        ));
+        assert_eq!(
+            report.warnings[1].to_string(),
+            "line 3: address 10.0.0.6 has no names, skipped \"10.0.0.6\""
+        );
+
+        assert!(ParseReport::parse("10.0.0.5 db\n", &options)
+            .unwrap()
+            .is_clean());
+    }
+}
@@ -0,0 +1,270 @@
+//! /etc/resolv.conf, the resolver config that goes hand in hand with
+//! /etc/hosts
+//!
+//! ```text
+//! # generated by the dhcp client
+//! nameserver 10.0.0.1
+//! nameserver fe80::1%eth0
+//! search lan example.com
+//! options ndots:2 timeout:1 rotate
+//! ```
+//!
+//! the resolver ignores lines it doesnt know and so does this, they get
+//! dropped when the file is written back out. same goes for comments
+
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::net::IpAddr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+#[cfg(feature = "std-fs")]
+pub const PATH: &str = "/etc/resolv.conf";
+
+#[derive(Error, Debug)]
+pub enum ResolvConfError {
+    #[error("line {line}: {value:?} is not a nameserver address")]
+    InvalidNameserver { line: usize, value: String },
+    #[error("line {line}: `{directive}` needs a value")]
+    MissingValue {
+        line: usize,
+        directive: &'static str,
+    },
+    #[cfg(feature = "std-fs")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Nameserver {
+    pub addr: IpAddr,
+    /// the interface on a link-local address, `eth0` for `fe80::1%eth0`
+    pub scope: Option<String>,
+}
+
+/// one word off an `options` line, `rotate` or `ndots:2`
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct ResolvOption {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ResolvConf {
+    /// in the order the resolver tries them. glibc only looks at the
+    /// first three but they are all kept
+    pub nameservers: Vec<Nameserver>,
+    /// `domain` and `search` override each other, whichever comes last in
+    /// the file wins and the other one is left empty
+    pub domain: Option<String>,
+    pub search: Vec<String>,
+    /// `sortlist` entries as written, `130.155.160.0/255.255.240.0`
+    pub sortlist: Vec<String>,
+    pub options: Vec<ResolvOption>,
+}
+
+impl ResolvConf {
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ResolvConfError> {
+        std::fs::read_to_string(path)?.parse()
+    }
+
+    /// the domains a short name gets tried with, the search list or else
+    /// the local domain
+    pub fn search_list(&self) -> Vec<&str> {
+        match &self.domain {
+            Some(domain) if self.search.is_empty() => vec![domain.as_str()],
+            _ => self.search.iter().map(String::as_str).collect(),
+        }
+    }
+
+    /// the value of option `name` the last time it was set. a flag that
+    /// is set without a value gives back `Some(None)`
+    pub fn option(&self, name: &str) -> Option<Option<&str>> {
+        self.options
+            .iter()
+            .rev()
+            .find(|o| o.name == name)
+            .map(|o| o.value.as_deref())
+    }
+
+    /// set option `name`, replacing it if it was already there
+    pub fn set_option(&mut self, name: &str, value: Option<&str>) {
+        self.options.retain(|o| o.name != name);
+        self.options.push(ResolvOption {
+            name: name.to_string(),
+            value: value.map(str::to_string),
+        });
+    }
+
+    fn number(&self, name: &str, default: u32) -> u32 {
+        self.option(name)
+            .flatten()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// how many dots a name needs before it is tried as is first
+    pub fn ndots(&self) -> u32 {
+        self.number("ndots", 1)
+    }
+
+    /// seconds to wait on a nameserver
+    pub fn timeout(&self) -> u32 {
+        self.number("timeout", 5)
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.number("attempts", 2)
+    }
+}
+
+impl FromStr for ResolvConf {
+    type Err = ResolvConfError;
+
+    fn from_str(s: &str) -> Result<Self, ResolvConfError> {
+        let mut conf = ResolvConf::default();
+        for (i, raw) in s.lines().enumerate() {
+            let line = i + 1;
+            let raw = raw.trim();
+            if raw.starts_with(['#', ';']) {
+                continue;
+            }
+            let mut words = raw.split_whitespace();
+            let Some(directive) = words.next() else {
+                continue;
+            };
+            let values = words.collect::<Vec<_>>();
+            let missing = |directive| ResolvConfError::MissingValue { line, directive };
+            match directive {
+                "nameserver" => {
+                    let value = *values.first().ok_or_else(|| missing("nameserver"))?;
+                    let (addr, scope) = match value.split_once('%') {
+                        Some((addr, scope)) => (addr, Some(scope.to_string())),
+                        None => (value, None),
+                    };
+                    let addr = addr
+                        .parse()
+                        .map_err(|_| ResolvConfError::InvalidNameserver {
+                            line,
+                            value: value.to_string(),
+                        })?;
+                    conf.nameservers.push(Nameserver { addr, scope });
+                }
+                "domain" => {
+                    let value = values.first().ok_or_else(|| missing("domain"))?;
+                    conf.domain = Some(value.to_string());
+                    conf.search.clear();
+                }
+                "search" => {
+                    if values.is_empty() {
+                        return Err(missing("search"));
+                    }
+                    conf.search = values.iter().map(|v| v.to_string()).collect();
+                    conf.domain = None;
+                }
+                "sortlist" => conf.sortlist = values.iter().map(|v| v.to_string()).collect(),
+                "options" => {
+                    for word in values {
+                        let (name, value) = match word.split_once(':') {
+                            Some((name, value)) => (name, Some(value)),
+                            None => (word, None),
+                        };
+                        conf.set_option(name, value);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(conf)
+    }
+}
+
+/// the file back out, one directive a line in the usual order
+impl fmt::Display for ResolvConf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ns in &self.nameservers {
+            match &ns.scope {
+                Some(scope) => writeln!(f, "nameserver {}%{scope}", ns.addr)?,
+                None => writeln!(f, "nameserver {}", ns.addr)?,
+            }
+        }
+        if !self.search.is_empty() {
+            writeln!(f, "search {}", self.search.join(" "))?;
+        } else if let Some(domain) = &self.domain {
+            writeln!(f, "domain {domain}")?;
+        }
+        if !self.sortlist.is_empty() {
+            writeln!(f, "sortlist {}", self.sortlist.join(" "))?;
+        }
+        if !self.options.is_empty() {
+            f.write_str("options")?;
+            for option in &self.options {
+                match &option.value {
+                    Some(value) => write!(f, " {}:{value}", option.name)?,
+                    None => write!(f, " {}", option.name)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_write_back() {
+        let conf: ResolvConf = "# generated\n\
+            nameserver 10.0.0.1\n\
+            ; old style comment\n\
+            nameserver fe80::1%eth0\n\
+            domain lan\n\
+            search lan example.com\n\
+            options ndots:2 timeout:1\n\
+            options rotate ndots:3\n\
+            lookup file bind\n"
+            .parse()
+            .unwrap();
+        assert_eq!(conf.nameservers.len(), 2);
+        assert_eq!(conf.nameservers[1].scope.as_deref(), Some("eth0"));
+        assert_eq!(conf.domain, None);
+        assert_eq!(conf.search_list(), ["lan", "example.com"]);
+        assert_eq!((conf.ndots(), conf.timeout(), conf.attempts()), (3, 1, 2));
+        assert_eq!(conf.option("rotate"), Some(None));
+        assert_eq!(conf.option("edns0"), None);
+        assert_eq!(
+            conf.to_string(),
+            "nameserver 10.0.0.1\n\
+             nameserver fe80::1%eth0\n\
+             search lan example.com\n\
+             options timeout:1 rotate ndots:3\n"
+        );
+        assert_eq!(conf.to_string().parse::<ResolvConf>().unwrap(), conf);
+
+        let conf: ResolvConf = "search a b\ndomain corp\n".parse().unwrap();
+        assert_eq!(conf.search_list(), ["corp"]);
+        assert_eq!(conf.to_string(), "domain corp\n");
+    }
+
+    #[test]
+    fn bad_lines() {
+        assert!(matches!(
+            "nameserver dns.example\n".parse::<ResolvConf>(),
+            Err(ResolvConfError::InvalidNameserver { line: 1, .. })
+        ));
+        assert!(matches!(
+            "\nsearch\n".parse::<ResolvConf>(),
+            Err(ResolvConfError::MissingValue {
+                line: 2,
+                directive: "search"
+            })
+        ));
+    }
+}
@@ -0,0 +1,171 @@
+//! names pointed somewhere else for now without touching the file, like
+//! a test harness sending `api.example` at a local stub, and written into
+//! a managed block once they should stick around
+
+use std::net::IpAddr;
+
+use crate::hosts_file::{strip_names, HostsFile};
+use crate::record::{Record, RecordError};
+
+/// a hosts file with overrides on top of it. a name the overrides have
+/// resolves there whatever the file says, the rest go to the file
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Resolver {
+    base: HostsFile,
+    /// kept as a file of its own so lookups work the same as in `base`
+    overlay: HostsFile,
+}
+
+impl Resolver {
+    /// the overrides are checked against the policy `base` has
+    pub fn new(base: HostsFile) -> Self {
+        let mut overlay = HostsFile::default();
+        overlay.set_policy(base.policy().cloned());
+        Resolver { base, overlay }
+    }
+
+    pub fn base(&self) -> &HostsFile {
+        &self.base
+    }
+
+    /// swap the file out from under the overrides, after it was read
+    /// again say. the overrides stay
+    pub fn set_base(&mut self, base: HostsFile) {
+        self.overlay.set_policy(base.policy().cloned());
+        self.base = base;
+    }
+
+    pub fn into_base(self) -> HostsFile {
+        self.base
+    }
+
+    /// every override, oldest first
+    pub fn overrides(&self) -> &[Record] {
+        self.overlay.records()
+    }
+
+    /// point `name` at `addr` until the override is taken back off
+    pub fn set(&mut self, name: &str, addr: IpAddr) -> Result<(), RecordError> {
+        self.overlay.set(name, addr)
+    }
+
+    /// add `record` as an override for all of its names. they come off any
+    /// older overrides, so the newest one wins
+    pub fn add_override(&mut self, record: Record) {
+        let names = record.names.iter().map(String::as_str).collect::<Vec<_>>();
+        self.overlay
+            .edit(|doc| strip_names(doc.lines_mut(), &names, None));
+        self.overlay.add_record(record);
+    }
+
+    /// stop overriding `name` so it goes back to what the file says.
+    /// gives back false when there was no override for it
+    pub fn remove_override(&mut self, name: &str) -> bool {
+        self.overlay.remove_by_name(name)
+    }
+
+    pub fn clear_overrides(&mut self) {
+        self.overlay = HostsFile::default();
+        self.overlay.set_policy(self.base.policy().cloned());
+    }
+
+    /// where `name` resolves, overrides first. names are matched without
+    /// caring about case like `HostsFile::lookup`
+    pub fn lookup(&self, name: &str) -> Option<IpAddr> {
+        self.overlay.lookup(name).or_else(|| self.base.lookup(name))
+    }
+
+    /// every name that resolves to `addr`, overrides first. names in the
+    /// file that are overridden somewhere else are left out
+    pub fn reverse_lookup(&self, addr: IpAddr) -> Vec<&str> {
+        let mut names = self.overlay.reverse_lookup(addr);
+        for name in self.base.reverse_lookup(addr) {
+            if !self.overlay.contains_name(name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// write the overrides into the block called `name` in the file and
+    /// clear them, lookups come out the same before and after. the
+    /// overridden names are taken off every other line so the block
+    /// wins, the block is added at the end when it isnt there yet
+    ///
+    /// this only changes the file in memory, write `base` out after
+    pub fn flush(&mut self, name: &str) -> usize {
+        let overrides = self.overlay.records().to_vec();
+        if overrides.is_empty() {
+            return 0;
+        }
+        let names = overrides
+            .iter()
+            .flat_map(|r| r.names.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+        self.base
+            .edit(|doc| strip_names(doc.lines_mut(), &names, None));
+        let mut records = self
+            .base
+            .managed_block(name)
+            .map(|block| block.records().to_vec())
+            .unwrap_or_default();
+        records.extend(overrides.iter().cloned());
+        self.base.set_managed_block(name, records);
+        self.clear_overrides();
+        overrides.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_win() {
+        let base: HostsFile = "127.0.0.1 localhost\n10.0.0.5 db db.lan\n".parse().unwrap();
+        let mut resolver = Resolver::new(base.clone());
+        let stub: IpAddr = "127.0.0.2".parse().unwrap();
+        resolver.set("DB", stub).unwrap();
+        resolver.add_override(Record::new(stub, vec!["api.example".into()]).unwrap());
+
+        assert_eq!(resolver.lookup("db"), Some(stub));
+        assert_eq!(resolver.lookup("db.lan"), "10.0.0.5".parse().ok());
+        assert_eq!(resolver.lookup("api.example"), Some(stub));
+        assert_eq!(resolver.reverse_lookup(stub), ["DB", "api.example"]);
+        assert_eq!(
+            resolver.reverse_lookup("10.0.0.5".parse().unwrap()),
+            ["db.lan"]
+        );
+        // nothing went into the file
+        assert_eq!(resolver.base(), &base);
+
+        assert!(resolver.remove_override("db"));
+        assert!(!resolver.remove_override("db"));
+        assert_eq!(resolver.lookup("db"), "10.0.0.5".parse().ok());
+    }
+
+    #[test]
+    fn flush_into_block() {
+        let base: HostsFile = "127.0.0.1 localhost\n10.0.0.5 db db.lan\n\
+            # BEGIN overrides\n10.0.0.9 cache\n# END overrides\n"
+            .parse()
+            .unwrap();
+        let mut resolver = Resolver::new(base);
+        resolver.set("db", "10.0.0.6".parse().unwrap()).unwrap();
+        resolver.set("queue", "10.0.0.7".parse().unwrap()).unwrap();
+        let before = ["db", "db.lan", "cache", "queue"].map(|n| resolver.lookup(n));
+
+        assert_eq!(resolver.flush("overrides"), 2);
+        assert!(resolver.overrides().is_empty());
+        assert_eq!(
+            ["db", "db.lan", "cache", "queue"].map(|n| resolver.lookup(n)),
+            before
+        );
+        assert_eq!(
+            resolver.base().to_string(),
+            "127.0.0.1 localhost\n10.0.0.5\tdb.lan\n\
+             # BEGIN overrides\n10.0.0.9\tcache\n10.0.0.6\tdb\n10.0.0.7\tqueue\n# END overrides\n"
+        );
+        assert_eq!(resolver.flush("overrides"), 0);
+    }
+}
@@ -0,0 +1,338 @@
+//! signed hosts files, so a fleet only applies blocklists that came from
+//! a publisher it trusts
+//!
+//! the signature rides along in the file as a comment block at the end,
+//! which every resolver skips over:
+//!
+//! ```text
+//! 0.0.0.0 ads.example
+//! # -----BEGIN HOSTS SIGNATURE-----
+//! # -----BEGIN SSH SIGNATURE-----
+//! # U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAg...
+//! # -----END SSH SIGNATURE-----
+//! # -----END HOSTS SIGNATURE-----
+//! ```
+//!
+//! it covers every byte of the file outside of the block, comments
+//! included since tags like `expires:` live in them. the signing is done
+//! by the system `ssh-keygen -Y`, the same way git signs commits with ssh
+//! keys, so use an ed25519 key and an allowed signers file in the format
+//! ssh-keygen(1) describes. there is no crypto in this crate
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use thiserror::Error;
+
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+
+const BEGIN: &str = "# -----BEGIN HOSTS SIGNATURE-----";
+const END: &str = "# -----END HOSTS SIGNATURE-----";
+
+/// what the signatures are made for, so a key someone also signs git
+/// commits with cant have one of those passed off as a hosts file
+pub const NAMESPACE: &str = "hosts-digger";
+
+#[derive(Error, Debug)]
+pub enum SigningError {
+    #[error("the file has no signature block")]
+    Unsigned,
+    #[error("the signature block is broken")]
+    Malformed,
+    #[error("signing failed: {0}")]
+    Sign(String),
+    #[error("the signature doesnt check out: {0}")]
+    BadSignature(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParserError),
+}
+
+/// `text` with a signature from the private key at `key` on the end. a
+/// signature that was already there gets replaced
+pub fn sign<P: AsRef<Path>>(text: &str, key: P) -> Result<String, SigningError> {
+    let mut body = match split(text) {
+        Ok((body, _)) => body,
+        Err(SigningError::Unsigned) => text.to_string(),
+        Err(e) => return Err(e),
+    };
+    // the block goes on a line of its own, so the newline is signed too
+    if !body.is_empty() && !body.ends_with('\n') {
+        body.push('\n');
+    }
+    let output = ssh_keygen(
+        Command::new("ssh-keygen")
+            .args(["-q", "-Y", "sign", "-n", NAMESPACE, "-f"])
+            .arg(key.as_ref()),
+        &body,
+    )?;
+    if !output.status.success() {
+        return Err(SigningError::Sign(stderr(&output)));
+    }
+
+    let mut out = body;
+    out.push_str(BEGIN);
+    out.push('\n');
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        out.push_str("# ");
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push_str(END);
+    out.push('\n');
+    Ok(out)
+}
+
+/// check the signature on `text` was made by `identity` as listed in the
+/// allowed signers file, and give back the text without it
+pub fn verify<P: AsRef<Path>>(
+    text: &str,
+    allowed_signers: P,
+    identity: &str,
+) -> Result<String, SigningError> {
+    let (body, signature) = split(text)?;
+
+    // ssh-keygen only takes the signature from a file
+    let dir = private_dir()?;
+    let sig_path = dir.join("signature");
+    let written = write_new(&sig_path, signature.as_bytes());
+    if let Err(e) = written {
+        let _ = fs::remove_dir_all(&dir);
+        return Err(e.into());
+    }
+    let output = ssh_keygen(
+        Command::new("ssh-keygen")
+            .args(["-q", "-Y", "verify", "-n", NAMESPACE, "-I", identity, "-f"])
+            .arg(allowed_signers.as_ref())
+            .arg("-s")
+            .arg(&sig_path),
+        &body,
+    );
+    let _ = fs::remove_dir_all(&dir);
+    let output = output?;
+    match output.status.success() {
+        true => Ok(body),
+        false => Err(SigningError::BadSignature(stderr(&output))),
+    }
+}
+
+impl HostsFile {
+    /// open the file at `path` only if it is signed by `identity`, the
+    /// signature block is left out of what gets parsed
+    pub fn open_verified<P, S>(
+        path: P,
+        allowed_signers: S,
+        identity: &str,
+    ) -> Result<Self, SigningError>
+    where
+        P: AsRef<Path>,
+        S: AsRef<Path>,
+    {
+        let text = fs::read_to_string(path)?;
+        Ok(verify(&text, allowed_signers, identity)?.parse()?)
+    }
+}
+
+/// the text outside the signature block, and the armored signature out
+/// of it with the `# ` taken back off
+fn split(text: &str) -> Result<(String, String), SigningError> {
+    let mut body = String::new();
+    let mut signature = None;
+    let mut inside = false;
+    for line in text.split_inclusive('\n') {
+        let bare = line.trim_end_matches(['\n', '\r']);
+        match (inside, bare) {
+            (false, BEGIN) if signature.is_none() => {
+                inside = true;
+                signature = Some(String::new());
+            }
+            (false, BEGIN) => return Err(SigningError::Malformed),
+            (true, END) => inside = false,
+            (true, line) => {
+                let line = line.strip_prefix("# ").ok_or(SigningError::Malformed)?;
+                let sig = signature.as_mut().expect("set on BEGIN");
+                sig.push_str(line);
+                sig.push('\n');
+            }
+            (false, _) => body.push_str(line),
+        }
+    }
+    match (inside, signature) {
+        (true, _) => Err(SigningError::Malformed),
+        (false, None) => Err(SigningError::Unsigned),
+        (false, Some(signature)) => Ok((body, signature)),
+    }
+}
+
+/// a new directory in the temp dir that only we can get into. one that
+/// is already there under the name, maybe put there by someone else, is
+/// never reused
+fn private_dir() -> io::Result<PathBuf> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    loop {
+        let dir = std::env::temp_dir().join(format!(
+            "hosts-digger-verify-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        builder.mode(0o700);
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// write a file that mustnt exist yet, so a symlink left in its place
+/// is never followed
+fn write_new(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path)?.write_all(contents)
+}
+
+/// run ssh-keygen with `input` on stdin. a failed run comes back as the
+/// output for the caller to pull stderr out of, only not being able to
+/// run it or feed it everything is an error here
+fn ssh_keygen(cmd: &mut Command, input: &str) -> Result<std::process::Output, SigningError> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| SigningError::Sign(format!("couldnt run ssh-keygen: {e}")))?;
+    let pipe = child.stdin.take();
+    // fed from another thread like `writer::run_with_stdin`, so one that
+    // gives up early still gets waited on and its stderr kept
+    let (written, output) = thread::scope(|s| {
+        let writer = s.spawn(move || match pipe {
+            Some(mut pipe) => pipe.write_all(input.as_bytes()),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output();
+        let written = writer
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("writing to ssh-keygen panicked")));
+        (written, output)
+    });
+    let output = output?;
+    if output.status.success() {
+        // it didnt see all of the text, so whatever it said is about
+        // something else
+        written?;
+    }
+    Ok(output)
+}
+
+fn stderr(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_block() {
+        let signed = format!("0.0.0.0 ads.example\n{BEGIN}\n# line one\n# line two\n{END}\n");
+        let (body, signature) = split(&signed).unwrap();
+        assert_eq!(body, "0.0.0.0 ads.example\n");
+        assert_eq!(signature, "line one\nline two\n");
+        assert!(matches!(
+            split("0.0.0.0 ads.example\n"),
+            Err(SigningError::Unsigned)
+        ));
+        assert!(matches!(
+            split(&format!("{BEGIN}\nnot a comment\n{END}\n")),
+            Err(SigningError::Malformed)
+        ));
+        assert!(matches!(
+            split(&format!("{BEGIN}\n# x\n")),
+            Err(SigningError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-signing-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let key = dir.join("publisher");
+        let made = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "publisher", "-f"])
+            .arg(&key)
+            .output();
+        match made {
+            Ok(made) => assert!(made.status.success(), "{made:?}"),
+            // no ssh-keygen on this machine is the only reason to skip
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                fs::remove_dir_all(&dir).unwrap();
+                return;
+            }
+            Err(e) => panic!("couldnt run ssh-keygen: {e}"),
+        }
+        let public = fs::read_to_string(dir.join("publisher.pub")).unwrap();
+        let signers = dir.join("allowed_signers");
+        fs::write(&signers, format!("lists@example.com {public}")).unwrap();
+
+        // ssh-keygen quits before reading any of this
+        let big = "0.0.0.0 ads.example\n".repeat(50_000);
+        match sign(&big, dir.join("missing")) {
+            Err(SigningError::Sign(msg)) => assert!(msg.contains("missing"), "{msg}"),
+            other => panic!("{other:?}"),
+        }
+
+        let text = "# blocklist\n0.0.0.0 ads.example\n";
+        let signed = sign(text, &key).unwrap();
+        assert!(signed.starts_with(text));
+        // signing again swaps the signature out instead of stacking them
+        let resigned = sign(&signed, &key).unwrap();
+        assert_eq!(resigned.matches(BEGIN).count(), 1);
+        assert_eq!(
+            verify(&signed, &signers, "lists@example.com").unwrap(),
+            text
+        );
+
+        let path = dir.join("hosts");
+        fs::write(&path, &signed).unwrap();
+        let hosts = HostsFile::open_verified(&path, &signers, "lists@example.com").unwrap();
+        assert_eq!(hosts.to_string(), text);
+
+        let no_newline = sign("0.0.0.0 ads.example", &key).unwrap();
+        assert!(verify(&no_newline, &signers, "lists@example.com").is_ok());
+
+        let tampered = signed.replace("ads.example", "ads.example evil.example");
+        assert!(matches!(
+            verify(&tampered, &signers, "lists@example.com"),
+            Err(SigningError::BadSignature(_))
+        ));
+        assert!(verify(&signed, &signers, "someone@else.com").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // the signature file mustnt already be there
+        let private = private_dir().unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&private).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        let existing = private.join("signature");
+        fs::write(&existing, "").unwrap();
+        let err = write_new(&existing, b"sig").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        fs::remove_dir_all(&private).unwrap();
+    }
+}
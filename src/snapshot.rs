@@ -0,0 +1,216 @@
+//! labelled copies of a hosts file kept in a state directory, so a bad
+//! edit can be undone later
+//!
+//! every snapshot is a plain copy named `<secs>.<nanos>-<label>.hosts`,
+//! the timestamp is when it was taken. saving under a label thats already
+//! used keeps both and restoring picks the newest
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use thiserror::Error;
+
+use crate::hosts_file::HostsFile;
+use crate::writer::{write_atomic, WriteOptions};
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("{0:?} cant be used as a snapshot label, stick to letters, digits, `.`, `-` and `_`")]
+    InvalidLabel(String),
+    #[error("there is no snapshot called {0:?}")]
+    NotFound(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Snapshot {
+    pub label: String,
+    pub taken: SystemTime,
+    /// where the copy lives
+    pub path: PathBuf,
+}
+
+/// a directory of snapshots
+#[derive(Clone, Debug)]
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+impl Default for SnapshotStore {
+    fn default() -> Self {
+        Self::new(Self::default_dir())
+    }
+}
+
+impl SnapshotStore {
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        SnapshotStore {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// `$HOSTS_DIGGER_STATE_DIR` when its set, otherwise `hosts-digger`
+    /// under the platforms per user state directory
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("HOSTS_DIGGER_STATE_DIR") {
+            return PathBuf::from(dir);
+        }
+        let base = if cfg!(windows) {
+            std::env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            std::env::var_os("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local/state")))
+        };
+        base.unwrap_or_else(std::env::temp_dir).join("hosts-digger")
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// copy the file at `hosts` into the store under `label`
+    pub fn save(&self, hosts: &Path, label: &str) -> Result<Snapshot, SnapshotError> {
+        check_label(label)?;
+        let contents = fs::read(hosts)?;
+        fs::create_dir_all(&self.dir)?;
+        let taken = SystemTime::now();
+        let since = taken.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let name = format!(
+            "{}.{:09}-{label}.hosts",
+            since.as_secs(),
+            since.subsec_nanos()
+        );
+        let path = self.dir.join(name);
+        fs::write(&path, contents)?;
+        Ok(Snapshot {
+            label: label.to_string(),
+            taken,
+            path,
+        })
+    }
+
+    /// put the newest snapshot called `label` back at `hosts`
+    pub fn restore(&self, label: &str, hosts: &Path) -> Result<Snapshot, SnapshotError> {
+        self.restore_with(label, hosts, &WriteOptions::default())
+    }
+
+    /// `restore`, writing the file with `options`
+    pub fn restore_with(
+        &self,
+        label: &str,
+        hosts: &Path,
+        options: &WriteOptions,
+    ) -> Result<Snapshot, SnapshotError> {
+        let snapshot = self
+            .list()?
+            .into_iter()
+            .rev()
+            .find(|s| s.label == label)
+            .ok_or_else(|| SnapshotError::NotFound(label.to_string()))?;
+        write_atomic(hosts, &fs::read(&snapshot.path)?, options)?;
+        Ok(snapshot)
+    }
+
+    /// every snapshot in the store, oldest first. a store that hasnt been
+    /// saved to yet is just empty
+    pub fn list(&self) -> io::Result<Vec<Snapshot>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut out = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if let Some(snapshot) = parse_name(&path) {
+                out.push(snapshot);
+            }
+        }
+        out.sort_by(|a, b| a.taken.cmp(&b.taken).then_with(|| a.label.cmp(&b.label)));
+        Ok(out)
+    }
+}
+
+/// snapshot the system hosts file into the default store
+pub fn save(label: &str) -> Result<Snapshot, SnapshotError> {
+    SnapshotStore::default().save(&HostsFile::system_path(), label)
+}
+
+/// put a snapshot from the default store back over the system hosts file
+pub fn restore(label: &str) -> Result<Snapshot, SnapshotError> {
+    SnapshotStore::default().restore(label, &HostsFile::system_path())
+}
+
+/// everything in the default store, oldest first
+pub fn list_snapshots() -> io::Result<Vec<Snapshot>> {
+    SnapshotStore::default().list()
+}
+
+fn check_label(label: &str) -> Result<(), SnapshotError> {
+    let ok = !label.is_empty()
+        && !label.starts_with('.')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    match ok {
+        true => Ok(()),
+        false => Err(SnapshotError::InvalidLabel(label.to_string())),
+    }
+}
+
+fn parse_name(path: &Path) -> Option<Snapshot> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".hosts")?;
+    let (stamp, label) = name.split_once('-')?;
+    let (secs, nanos) = stamp.split_once('.')?;
+    let since = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    Some(Snapshot {
+        label: label.to_string(),
+        taken: UNIX_EPOCH + since,
+        path: path.to_path_buf(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_list_restore() {
+        let dir =
+            std::env::temp_dir().join(format!("hosts-digger-snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let hosts = dir.join("hosts");
+        let store = SnapshotStore::new(dir.join("state"));
+        assert!(store.list().unwrap().is_empty());
+
+        fs::write(&hosts, "127.0.0.1 localhost\n").unwrap();
+        let first = store.save(&hosts, "before-vpn").unwrap();
+        fs::write(&hosts, "127.0.0.1 localhost\n10.8.0.1 vpn\n").unwrap();
+        store.save(&hosts, "before-vpn").unwrap();
+        fs::write(&hosts, "garbage\n").unwrap();
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0], first);
+        let restored = store.restore("before-vpn", &hosts).unwrap();
+        assert_eq!(restored, listed[1]);
+        assert_eq!(
+            fs::read_to_string(&hosts).unwrap(),
+            "127.0.0.1 localhost\n10.8.0.1 vpn\n"
+        );
+
+        assert!(matches!(
+            store.restore("nope", &hosts),
+            Err(SnapshotError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.save(&hosts, "../escape"),
+            Err(SnapshotError::InvalidLabel(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
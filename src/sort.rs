@@ -0,0 +1,137 @@
+//! putting records in a predictable order so generated files diff cleanly
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::dedupe::DedupePolicy;
+use crate::document::Line;
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use crate::record::Record;
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum SortOrder {
+    /// leave things the way they are
+    #[default]
+    Original,
+    /// by address, numerically, ipv4 before ipv6
+    Address,
+    /// by the first name on each line, ignoring case
+    Name,
+}
+
+impl SortOrder {
+    fn compare(&self, a: &Record, b: &Record) -> Ordering {
+        match self {
+            SortOrder::Original => Ordering::Equal,
+            SortOrder::Address => a
+                .addr()
+                .cmp(&b.addr())
+                .then_with(|| a.scope().cmp(&b.scope())),
+            SortOrder::Name => {
+                let a = a.primary_name().map(fold_name);
+                let b = b.primary_name().map(fold_name);
+                a.cmp(&b)
+            }
+        }
+    }
+}
+
+impl HostsFile {
+    /// reorder the record lines. comments and blank lines stay where they
+    /// are and the records get shuffled around them, ties keep their order
+    pub fn sort(&mut self, order: SortOrder) {
+        if order == SortOrder::Original {
+            return;
+        }
+        self.edit(|doc| {
+            let lines = doc.lines_mut();
+            let slots: Vec<usize> = (0..lines.len())
+                .filter(|&i| matches!(lines[i], Line::Record(_)))
+                .collect();
+            let mut records: Vec<Line> = slots
+                .iter()
+                .map(|&i| std::mem::replace(&mut lines[i], Line::Blank(String::new())))
+                .collect();
+            records.sort_by(|a, b| match (a, b) {
+                (Line::Record(a), Line::Record(b)) => order.compare(a.record(), b.record()),
+                _ => Ordering::Equal,
+            });
+            for (i, line) in slots.into_iter().zip(records) {
+                lines[i] = line;
+            }
+        })
+    }
+
+    /// one line per address, written out the standard way. duplicate
+    /// addresses get folded onto their first line, every record is
+    /// rewritten as `addr<tab>names`, blank lines are emptied out and
+    /// trailing whitespace comes off of comments
+    pub fn canonicalize(&mut self) {
+        self.dedupe(DedupePolicy::MergeNames);
+        self.edit(|doc| {
+            for line in doc.lines_mut() {
+                match line {
+                    Line::Record(line) => {
+                        let mut record = line.record().clone();
+                        let mut seen = HashSet::new();
+                        record
+                            .names
+                            .retain(|name| seen.insert(fold_name(name).into_owned()));
+                        line.set_record(record);
+                    }
+                    Line::Blank(text) => text.clear(),
+                    Line::Comment(text) => text.truncate(text.trim_end().len()),
+                    Line::Invalid(_) => {}
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS: &str = "# static\n\
+        fe80::1%eth0 router\n\
+        10.0.0.10   Zeta\n\
+        \n\
+        10.0.0.9 alpha\n\
+        127.0.0.1 localhost\n";
+
+    #[test]
+    fn sort_by_address() {
+        let mut hosts: HostsFile = HOSTS.parse().unwrap();
+        hosts.sort(SortOrder::Address);
+        assert_eq!(
+            hosts.to_string(),
+            "# static\n10.0.0.9 alpha\n10.0.0.10   Zeta\n\n127.0.0.1 localhost\nfe80::1%eth0 router\n"
+        );
+    }
+
+    #[test]
+    fn sort_by_name() {
+        let mut hosts: HostsFile = HOSTS.parse().unwrap();
+        hosts.sort(SortOrder::Name);
+        let names: Vec<_> = hosts
+            .records()
+            .iter()
+            .map(|r| r.names()[0].as_str())
+            .collect();
+        assert_eq!(names, ["alpha", "localhost", "router", "Zeta"]);
+
+        let mut unsorted: HostsFile = HOSTS.parse().unwrap();
+        unsorted.sort(SortOrder::Original);
+        assert_eq!(unsorted.to_string(), HOSTS);
+    }
+
+    #[test]
+    fn canonicalize_folds_and_normalizes() {
+        let mut hosts: HostsFile = "# hi   \n10.0.0.5   db  DB\n   \n10.0.0.5 cache # dupe\n"
+            .parse()
+            .unwrap();
+        hosts.canonicalize();
+        assert_eq!(hosts.to_string(), "# hi\n10.0.0.5\tdb cache\n\n");
+    }
+}
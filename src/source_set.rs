@@ -0,0 +1,183 @@
+//! putting one hosts file together out of several, the way every
+//! blocklist manager does. sources are read in order and later ones win
+//! when two of them disagree about a name, then the allowlist punches
+//! holes in the result and the denylist blocks whatever is left to block
+
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::dedupe::DedupePolicy;
+use crate::document::{Line, RecordLine};
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+use crate::record::{Record, RecordError};
+#[cfg(feature = "http")]
+use crate::remote::{self, RemoteCache, RemoteError};
+
+#[derive(Error, Debug)]
+pub enum SourceSetError {
+    #[error("{}: {source}", path.display())]
+    Local {
+        path: PathBuf,
+        #[source]
+        source: ParserError,
+    },
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Remote(#[from] RemoteError),
+    #[error(transparent)]
+    InvalidName(#[from] RecordError),
+}
+
+#[derive(Clone, Debug)]
+enum Input {
+    Path(PathBuf),
+    #[cfg(feature = "http")]
+    Url(String),
+    Records(Vec<Record>),
+}
+
+/// an ordered list of places records come from, see the module docs
+#[derive(Clone, Debug, Default)]
+pub struct SourceSet {
+    inputs: Vec<Input>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    #[cfg(feature = "http")]
+    cache: Option<PathBuf>,
+}
+
+impl SourceSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a hosts file on disk
+    pub fn path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.inputs.push(Input::Path(path.as_ref().to_path_buf()));
+        self
+    }
+
+    /// a hosts format list to download
+    #[cfg(feature = "http")]
+    pub fn url<S: Into<String>>(mut self, url: S) -> Self {
+        self.inputs.push(Input::Url(url.into()));
+        self
+    }
+
+    /// records that dont live in any file
+    pub fn records<I: IntoIterator<Item = Record>>(mut self, records: I) -> Self {
+        self.inputs
+            .push(Input::Records(records.into_iter().collect()));
+        self
+    }
+
+    /// a name that never makes it into the output, whatever the sources
+    /// say. globs work, see `HostsFile::subtract`
+    pub fn allow<S: Into<String>>(mut self, name: S) -> Self {
+        self.allow.push(name.into());
+        self
+    }
+
+    /// a name that gets blocked in the output, whatever the sources say
+    pub fn deny<S: Into<String>>(mut self, name: S) -> Self {
+        self.deny.push(name.into());
+        self
+    }
+
+    /// keep downloads in `dir` so unchanged lists arent fetched again
+    #[cfg(feature = "http")]
+    pub fn cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cache = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// read every source and put the result together. records from files
+    /// are tagged with where they came from
+    pub fn build(&self) -> Result<HostsFile, SourceSetError> {
+        let mut hosts = HostsFile::default();
+        for input in &self.inputs {
+            let records = self.read(input)?;
+            hosts.edit(|doc| {
+                for record in records {
+                    doc.push(Line::Record(RecordLine::new(record)));
+                }
+            });
+        }
+        // later sources win, same as merging them in one at a time
+        hosts.dedupe(DedupePolicy::KeepLast);
+        hosts.subtract(&self.allow);
+        hosts.add_blocked(&self.deny)?;
+        Ok(hosts)
+    }
+
+    fn read(&self, input: &Input) -> Result<Vec<Record>, SourceSetError> {
+        match input {
+            Input::Path(path) => {
+                let hosts = HostsFile::open(path)
+                    .map_err(|source| SourceSetError::Local {
+                        path: path.clone(),
+                        source,
+                    })?
+                    .with_source(path);
+                Ok(hosts.records().to_vec())
+            }
+            #[cfg(feature = "http")]
+            Input::Url(url) => {
+                let fetched = match &self.cache {
+                    Some(dir) => RemoteCache::new(dir).fetch(url)?,
+                    None => remote::fetch(url)?,
+                };
+                Ok(fetched.records)
+            }
+            Input::Records(records) => Ok(records.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn builds_from_sources() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-sources-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let base = dir.join("base");
+        let ads = dir.join("ads");
+        fs::write(&base, "127.0.0.1 localhost\n10.0.0.5 db\n").unwrap();
+        fs::write(
+            &ads,
+            "0.0.0.0 ads.example\n0.0.0.0 track.example\n0.0.0.0 cdn.example\n",
+        )
+        .unwrap();
+        let moved = Record::new("10.0.0.6".parse().unwrap(), vec!["db".into()]).unwrap();
+
+        let hosts = SourceSet::new()
+            .path(&base)
+            .path(&ads)
+            .records([moved])
+            .allow("cdn.example")
+            .deny("evil.example")
+            .build()
+            .unwrap();
+        assert_eq!(
+            hosts.to_string(),
+            "127.0.0.1\tlocalhost\n\
+             0.0.0.0\tads.example\n\
+             0.0.0.0\ttrack.example\n\
+             10.0.0.6\tdb\n\
+             0.0.0.0\tevil.example\n"
+        );
+        assert_eq!(
+            hosts.records()[1].source().unwrap().to_string(),
+            format!("{}:1", ads.display())
+        );
+
+        let missing = SourceSet::new().path(dir.join("nope")).build();
+        assert!(matches!(missing, Err(SourceSetError::Local { .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
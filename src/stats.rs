@@ -0,0 +1,150 @@
+//! numbers about a hosts file, for `hosts-digger stats` and for feeding
+//! monitoring
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::document::Line;
+use crate::hostname::fold_name;
+use crate::hosts_file::HostsFile;
+use crate::ip_class::{ip_class, IpClass};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Stats {
+    /// every physical line, blanks included
+    pub lines: usize,
+    pub records: usize,
+    pub comments: usize,
+    pub blanks: usize,
+    /// lines the parser couldnt make sense of
+    pub invalid: usize,
+    pub ipv4: usize,
+    pub ipv6: usize,
+    /// records per class of address, biggest first
+    pub by_class: Vec<(IpClass, usize)>,
+    /// every name on every record, aliases included
+    pub names: usize,
+    /// the names after the first one on each record
+    pub aliases: usize,
+    /// names that show up on more than one record, counted once each
+    pub duplicate_names: usize,
+    /// length in characters of the longest line
+    pub longest_line: usize,
+    /// 1-based line number of the longest line, the first one on a tie
+    pub longest_line_number: Option<usize>,
+}
+
+impl HostsFile {
+    /// count up what is in the file
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        for (i, line) in self.document().lines().iter().enumerate() {
+            stats.lines += 1;
+            match line {
+                Line::Record(_) => {}
+                Line::Comment(_) => stats.comments += 1,
+                Line::Blank(_) => stats.blanks += 1,
+                Line::Invalid(_) => stats.invalid += 1,
+            }
+            let length = line.to_string().chars().count();
+            if length > stats.longest_line {
+                stats.longest_line = length;
+                stats.longest_line_number = Some(i + 1);
+            }
+        }
+
+        let mut classes: Vec<(IpClass, usize)> = Vec::new();
+        let mut seen: HashMap<_, usize> = HashMap::new();
+        for record in self.records() {
+            stats.records += 1;
+            match record.addr().is_ipv4() {
+                true => stats.ipv4 += 1,
+                false => stats.ipv6 += 1,
+            }
+            let class = ip_class(record.addr());
+            match classes.iter_mut().find(|(c, _)| *c == class) {
+                Some((_, count)) => *count += 1,
+                None => classes.push((class, 1)),
+            }
+            stats.names += record.names().len();
+            stats.aliases += record.names().len().saturating_sub(1);
+            for name in record.names() {
+                *seen.entry(fold_name(name)).or_default() += 1;
+            }
+        }
+        // stable, so ties stay in the order they were first seen
+        classes.sort_by_key(|&(_, count)| Reverse(count));
+        stats.by_class = classes;
+        stats.duplicate_names = seen.values().filter(|&&n| n > 1).count();
+        stats
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "lines: {}", self.lines)?;
+        writeln!(
+            f,
+            "  records: {}, comments: {}, blank: {}, invalid: {}",
+            self.records, self.comments, self.blanks, self.invalid
+        )?;
+        writeln!(f, "addresses: {} ipv4, {} ipv6", self.ipv4, self.ipv6)?;
+        for (class, count) in &self.by_class {
+            writeln!(f, "  {class:?}: {count}")?;
+        }
+        writeln!(
+            f,
+            "names: {}, aliases: {}, duplicated: {}",
+            self.names, self.aliases, self.duplicate_names
+        )?;
+        if let Some(line) = self.longest_line_number {
+            writeln!(
+                f,
+                "longest line: {} characters on line {line}",
+                self.longest_line
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let hosts: HostsFile = "# blocklist\n\
+            127.0.0.1 localhost\n\
+            \n\
+            0.0.0.0 ads.example ads2.example\n\
+            0.0.0.0 Ads.example\n\
+            ::1 localhost ip6-localhost\n\
+            10.0.0.5 db\n\
+            what even\n"
+            .parse()
+            .unwrap();
+        let stats = hosts.stats();
+        assert_eq!(stats.lines, 8);
+        assert_eq!(
+            (stats.records, stats.comments, stats.blanks, stats.invalid),
+            (5, 1, 1, 1)
+        );
+        assert_eq!((stats.ipv4, stats.ipv6), (4, 1));
+        assert_eq!(
+            stats.by_class,
+            [
+                (IpClass::Loopback, 2),
+                (IpClass::Unspecified, 2),
+                (IpClass::Private, 1)
+            ]
+        );
+        assert_eq!((stats.names, stats.aliases), (7, 2));
+        // ads.example and localhost
+        assert_eq!(stats.duplicate_names, 2);
+        assert_eq!(stats.longest_line_number, Some(4));
+        assert_eq!(stats.longest_line, 32);
+        assert!(stats.to_string().contains("records: 5, comments: 1"));
+    }
+}
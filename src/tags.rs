@@ -0,0 +1,102 @@
+//! `key: value` tags in a records trailing comment, the way groups and
+//! expiry dates get stored without the file stopping being a hosts file
+//!
+//! ```text
+//! 10.0.0.5 db # primary group: myproject expires: 2025-07-01T00:00:00Z
+//! ```
+//!
+//! a tag is a word ending in `:` and the word after it. everything else
+//! in the comment is left alone
+
+use crate::record::{Record, RecordError};
+
+/// the value of `key` in `comment`
+fn get<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    let mut words = comment.split_whitespace();
+    while let Some(word) = words.next() {
+        if word.strip_suffix(':') == Some(key) {
+            return words.next();
+        }
+    }
+    None
+}
+
+/// `comment` with `key` taken out, and put back on the end as `value`
+/// when there is one
+fn set(comment: Option<&str>, key: &str, value: Option<&str>) -> Option<String> {
+    let mut words = Vec::new();
+    let mut rest = comment.unwrap_or_default().split_whitespace();
+    while let Some(word) = rest.next() {
+        match word.strip_suffix(':') == Some(key) {
+            true => {
+                rest.next();
+            }
+            false => words.push(word.to_string()),
+        }
+    }
+    if let Some(value) = value {
+        words.push(format!("{key}: {value}"));
+    }
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// keys cant have a `:` anywhere, values only cant end with one so
+/// timestamps fit
+fn check(word: &str, key: bool) -> Result<(), RecordError> {
+    let bad = word.is_empty()
+        || word.ends_with(':')
+        || word.contains(|c: char| c.is_whitespace() || c == '#' || (key && c == ':'));
+    match bad {
+        true => Err(RecordError::InvalidTag(word.to_string())),
+        false => Ok(()),
+    }
+}
+
+impl Record {
+    /// the value tagged `key` in the trailing comment, `myproject` out of
+    /// `# group: myproject`
+    pub fn tag(&self, key: &str) -> Option<&str> {
+        get(self.comment.as_deref()?, key)
+    }
+
+    /// set or clear a tag in the trailing comment, the rest of the comment
+    /// stays. keys and values are single words without `#`, keys cant
+    /// have a `:` in them either
+    pub fn set_tag(&mut self, key: &str, value: Option<&str>) -> Result<(), RecordError> {
+        check(key, true)?;
+        if let Some(value) = value {
+            check(value, false)?;
+        }
+        self.comment = set(self.comment.as_deref(), key, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_in_comments() {
+        let mut record: Record = "10.0.0.5 db # primary group: web".parse().unwrap();
+        assert_eq!(record.tag("group"), Some("web"));
+        assert_eq!(record.tag("primary"), None);
+
+        record.set_tag("group", Some("api")).unwrap();
+        record.set_tag("expires", Some("2025-07-01")).unwrap();
+        assert_eq!(
+            record.comment(),
+            Some("primary group: api expires: 2025-07-01")
+        );
+        record.set_tag("group", None).unwrap();
+        assert_eq!(record.comment(), Some("primary expires: 2025-07-01"));
+
+        assert!(record.set_tag("group", Some("my project")).is_err());
+        assert!(record.set_tag("a:b", Some("x")).is_err());
+        assert!(record.set_tag("at", Some("x:")).is_err());
+        record.set_tag("expires", None).unwrap();
+        record.set_comment(None).unwrap();
+        record.set_tag("expires", None).unwrap();
+        assert_eq!(record.comment(), None);
+    }
+}
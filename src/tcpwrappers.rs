@@ -0,0 +1,446 @@
+//! /etc/hosts.allow and /etc/hosts.deny, the tcp wrappers access control
+//! files. they sit next to /etc/hosts and get audited along with it
+//!
+//! ```text
+//! sshd, vsftpd : 192.168.1. .example.com EXCEPT bad.example.com : allow
+//! ALL : ALL : spawn (/bin/echo %d %c >> /var/log/denied) : deny
+//! ```
+//!
+//! each rule is `daemons : clients` followed by optional fields, see
+//! hosts_access(5) and hosts_options(5). without the options extension a
+//! third field is a shell command, that comes out here as an option whose
+//! keyword is the first word of the command
+
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::io;
+use std::net::Ipv4Addr;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::cidr::Cidr;
+
+#[derive(Error, Debug)]
+pub enum TcpWrappersError {
+    #[error("line {line}: a rule needs a daemon list and a client list")]
+    MissingClients { line: usize },
+    #[error("line {line}: empty pattern list")]
+    EmptyList { line: usize },
+    #[error("line {line}: {pattern:?} is not a valid pattern")]
+    InvalidPattern { line: usize, pattern: String },
+    #[cfg(feature = "std-fs")]
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// one entry in a daemon or client list
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Pattern {
+    All,
+    /// hosts without a dot in their name
+    Local,
+    /// user or host that couldnt be looked up
+    Unknown,
+    Known,
+    /// hosts whose name and address dont agree
+    Paranoid,
+    /// `.example.com`, anything ending in it
+    Suffix(String),
+    /// `192.168.`, addresses starting with it
+    Prefix(String),
+    /// `10.0.0.0/255.0.0.0`, `10.0.0.0/8` or `[fe80::]/10`
+    Network(Cidr),
+    /// `@group`, an nis netgroup
+    NetGroup(String),
+    /// `/etc/ssh.allow`, a file with more patterns in it
+    File(String),
+    /// anything with `*` or `?` in it
+    Wildcard(String),
+    /// `user@host` in client lists, `daemon@host` in daemon lists
+    At {
+        user: String,
+        host: Box<Pattern>,
+    },
+    /// a host name or address
+    Exact(String),
+}
+
+/// a list of patterns with everything in `except` cut out of it.
+/// `a EXCEPT b EXCEPT c` nests as a except (b except c)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternList {
+    pub patterns: Vec<Pattern>,
+    pub except: Option<Box<PatternList>>,
+}
+
+/// one option field, `keyword` or `keyword value` or `keyword = value`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RuleOption {
+    pub keyword: String,
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rule {
+    pub daemons: PatternList,
+    pub clients: PatternList,
+    pub options: Vec<RuleOption>,
+    /// 1-based line the rule starts on
+    pub line: usize,
+}
+
+impl Rule {
+    /// true when one of the options is a bare `allow`
+    pub fn allows(&self) -> bool {
+        self.has_option("allow")
+    }
+
+    /// true when one of the options is a bare `deny`
+    pub fn denies(&self) -> bool {
+        self.has_option("deny")
+    }
+
+    fn has_option(&self, keyword: &str) -> bool {
+        self.options
+            .iter()
+            .any(|o| o.value.is_none() && o.keyword.eq_ignore_ascii_case(keyword))
+    }
+}
+
+/// parse the text of a hosts.allow or hosts.deny file, comments and
+/// blank lines are skipped and a `\` at the end of a line carries it on
+pub fn parse(text: &str) -> Result<Vec<Rule>, TcpWrappersError> {
+    let mut rules = Vec::new();
+    let mut pending = String::new();
+    let mut start = 0;
+    for (i, raw) in text.lines().enumerate() {
+        if pending.is_empty() {
+            start = i + 1;
+        }
+        match raw.strip_suffix('\\') {
+            Some(rest) => {
+                pending.push_str(rest);
+                pending.push(' ');
+                continue;
+            }
+            None => pending.push_str(raw),
+        }
+        let line = std::mem::take(&mut pending);
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        rules.push(parse_rule(line, start)?);
+    }
+    let line = pending.trim();
+    if !line.is_empty() && !line.starts_with('#') {
+        rules.push(parse_rule(line, start)?);
+    }
+    Ok(rules)
+}
+
+#[cfg(feature = "std-fs")]
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Vec<Rule>, TcpWrappersError> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+fn parse_rule(line: &str, number: usize) -> Result<Rule, TcpWrappersError> {
+    let fields = split_fields(line);
+    if fields.len() < 2 {
+        return Err(TcpWrappersError::MissingClients { line: number });
+    }
+    let options = fields[2..]
+        .iter()
+        .map(|field| parse_option(field))
+        .collect();
+    Ok(Rule {
+        daemons: parse_list(&fields[0], number)?,
+        clients: parse_list(&fields[1], number)?,
+        options,
+        line: number,
+    })
+}
+
+/// split on the colons that arent backslash escaped or inside the
+/// brackets around an ipv6 address, `\:` comes out as `:`
+fn split_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut brackets = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("always one field");
+        match c {
+            '\\' if chars.peek() == Some(&':') => field.push(chars.next().unwrap()),
+            '[' => {
+                brackets = true;
+                field.push(c);
+            }
+            ']' => {
+                brackets = false;
+                field.push(c);
+            }
+            ':' if !brackets => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn parse_list(field: &str, line: usize) -> Result<PatternList, TcpWrappersError> {
+    let words = field
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>();
+    list_from(&words, line)
+}
+
+fn list_from(words: &[&str], line: usize) -> Result<PatternList, TcpWrappersError> {
+    let split = words.iter().position(|w| w.eq_ignore_ascii_case("EXCEPT"));
+    let (head, except) = match split {
+        Some(i) => (&words[..i], Some(&words[i + 1..])),
+        None => (words, None),
+    };
+    if head.is_empty() {
+        return Err(TcpWrappersError::EmptyList { line });
+    }
+    let patterns = head
+        .iter()
+        .map(|w| {
+            parse_pattern(w).ok_or_else(|| TcpWrappersError::InvalidPattern {
+                line,
+                pattern: w.to_string(),
+            })
+        })
+        .collect::<Result<_, _>>()?;
+    let except = match except {
+        Some(rest) => Some(Box::new(list_from(rest, line)?)),
+        None => None,
+    };
+    Ok(PatternList { patterns, except })
+}
+
+/// none when the word looks like one kind of pattern but doesnt hold up,
+/// a network with a bad mask say
+fn parse_pattern(word: &str) -> Option<Pattern> {
+    let wildcard = match word.to_ascii_uppercase().as_str() {
+        "ALL" => Some(Pattern::All),
+        "LOCAL" => Some(Pattern::Local),
+        "UNKNOWN" => Some(Pattern::Unknown),
+        "KNOWN" => Some(Pattern::Known),
+        "PARANOID" => Some(Pattern::Paranoid),
+        _ => None,
+    };
+    if wildcard.is_some() {
+        return wildcard;
+    }
+    if let Some(group) = word.strip_prefix('@') {
+        return (!group.is_empty()).then(|| Pattern::NetGroup(group.to_string()));
+    }
+    if word.starts_with('/') {
+        return Some(Pattern::File(word.to_string()));
+    }
+    if let Some((user, host)) = word.split_once('@') {
+        return Some(Pattern::At {
+            user: user.to_string(),
+            host: Box::new(parse_pattern(host)?),
+        });
+    }
+    if let Some(v6) = word.strip_prefix('[') {
+        let (addr, prefix) = match v6.split_once("]/") {
+            Some((addr, prefix)) => (addr, prefix),
+            None => (v6.strip_suffix(']')?, "128"),
+        };
+        return format!("{addr}/{prefix}")
+            .parse()
+            .ok()
+            .map(Pattern::Network);
+    }
+    if let Some((addr, mask)) = word.split_once('/') {
+        return network(addr, mask).map(Pattern::Network);
+    }
+    if word.starts_with('.') {
+        return Some(Pattern::Suffix(word.to_string()));
+    }
+    if word.ends_with('.') {
+        return Some(Pattern::Prefix(word.to_string()));
+    }
+    if word.contains(['*', '?']) {
+        return Some(Pattern::Wildcard(word.to_string()));
+    }
+    Some(Pattern::Exact(word.to_string()))
+}
+
+/// `net/mask` with a dotted mask, which has to be contiguous, or a
+/// prefix length
+fn network(addr: &str, mask: &str) -> Option<Cidr> {
+    let prefix = match mask.parse::<Ipv4Addr>() {
+        Ok(mask) => {
+            let bits = u32::from(mask);
+            if bits.leading_ones() + bits.trailing_zeros() != 32 {
+                return None;
+            }
+            bits.leading_ones().to_string()
+        }
+        Err(_) => mask.to_string(),
+    };
+    format!("{addr}/{prefix}").parse().ok()
+}
+
+fn parse_option(field: &str) -> RuleOption {
+    let end = field
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(field.len());
+    let (keyword, rest) = field.split_at(end);
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest).trim();
+    RuleOption {
+        keyword: keyword.to_string(),
+        value: (!rest.is_empty()).then(|| rest.to_string()),
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::All => f.write_str("ALL"),
+            Pattern::Local => f.write_str("LOCAL"),
+            Pattern::Unknown => f.write_str("UNKNOWN"),
+            Pattern::Known => f.write_str("KNOWN"),
+            Pattern::Paranoid => f.write_str("PARANOID"),
+            Pattern::Network(net) if net.addr().is_ipv6() => {
+                write!(f, "[{}]/{}", net.addr(), net.prefix())
+            }
+            // older tcpd only knows dotted masks for v4
+            Pattern::Network(net) => {
+                let mask = u32::MAX.checked_shl(32 - net.prefix() as u32).unwrap_or(0);
+                write!(f, "{}/{}", net.addr(), Ipv4Addr::from(mask))
+            }
+            Pattern::NetGroup(group) => write!(f, "@{group}"),
+            Pattern::At { user, host } => write!(f, "{user}@{host}"),
+            Pattern::Suffix(s)
+            | Pattern::Prefix(s)
+            | Pattern::File(s)
+            | Pattern::Wildcard(s)
+            | Pattern::Exact(s) => f.write_str(s),
+        }
+    }
+}
+
+impl fmt::Display for PatternList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, pattern) in self.patterns.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{pattern}")?;
+        }
+        if let Some(except) = &self.except {
+            write!(f, " EXCEPT {except}")?;
+        }
+        Ok(())
+    }
+}
+
+/// back out in the file format, colons in option values get escaped
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} : {}", self.daemons, self.clients)?;
+        for option in &self.options {
+            write!(f, " : {}", option.keyword)?;
+            if let Some(value) = &option.value {
+                write!(f, " {}", value.replace(':', "\\:"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_and_patterns() {
+        let rules = parse(
+            "# allow the lan in\n\
+             sshd, vsftpd : 192.168.1. .example.com EXCEPT bad.example.com\n\
+             \n\
+             in.tftpd : 10.0.0.0/255.0.0.0 [fe80::]/10 @trusted admin@*.lan \\\n\
+             \t: severity auth.info : allow\n\
+             ALL : ALL : spawn (/bin/echo %d\\: %c) : deny\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 3);
+
+        let ssh = &rules[0];
+        assert_eq!(ssh.line, 2);
+        assert_eq!(
+            ssh.daemons.patterns,
+            [
+                Pattern::Exact("sshd".into()),
+                Pattern::Exact("vsftpd".into())
+            ]
+        );
+        assert_eq!(
+            ssh.clients.patterns[0],
+            Pattern::Prefix("192.168.1.".into())
+        );
+        assert_eq!(
+            ssh.clients.patterns[1],
+            Pattern::Suffix(".example.com".into())
+        );
+        assert_eq!(
+            ssh.clients.except.as_deref().unwrap().patterns,
+            [Pattern::Exact("bad.example.com".into())]
+        );
+
+        let tftp = &rules[1];
+        assert_eq!(tftp.line, 4);
+        assert_eq!(
+            tftp.clients.patterns,
+            [
+                Pattern::Network("10.0.0.0/8".parse().unwrap()),
+                Pattern::Network("fe80::/10".parse().unwrap()),
+                Pattern::NetGroup("trusted".into()),
+                Pattern::At {
+                    user: "admin".into(),
+                    host: Box::new(Pattern::Wildcard("*.lan".into()))
+                },
+            ]
+        );
+        assert_eq!(tftp.options[0].value.as_deref(), Some("auth.info"));
+        assert!(tftp.allows() && !tftp.denies());
+
+        let all = &rules[2];
+        assert_eq!(all.daemons.patterns, [Pattern::All]);
+        assert_eq!(all.options[0].keyword, "spawn");
+        assert_eq!(all.options[0].value.as_deref(), Some("(/bin/echo %d: %c)"));
+        assert!(all.denies());
+        assert_eq!(
+            all.to_string(),
+            "ALL : ALL : spawn (/bin/echo %d\\: %c) : deny"
+        );
+        assert!(tftp
+            .to_string()
+            .starts_with("in.tftpd : 10.0.0.0/255.0.0.0, [fe80::]/10"));
+        assert_eq!(parse(&tftp.to_string()).unwrap()[0].clients, tftp.clients);
+    }
+
+    #[test]
+    fn bad_rules() {
+        assert!(matches!(
+            parse("sshd\n"),
+            Err(TcpWrappersError::MissingClients { line: 1 })
+        ));
+        assert!(matches!(
+            parse("# x\nsshd : EXCEPT foo\n"),
+            Err(TcpWrappersError::EmptyList { line: 2 })
+        ));
+        assert!(matches!(
+            parse("sshd : 10.0.0.0/255.0.255.0\n"),
+            Err(TcpWrappersError::InvalidPattern { .. })
+        ));
+    }
+}
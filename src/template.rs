@@ -0,0 +1,185 @@
+//! render hosts files out of a template kept in git. `{{ ... }}` gets
+//! swapped for one of
+//!
+//! - `{{ name }}` a variable set with `var`, or the environment variable
+//!   of the same name when there isnt one
+//! - `{{ env("NAME") }}` straight from the environment
+//! - `{{ ip_of("db") }}` whatever `db` resolves to in the hosts file given
+//!   to `resolve_from`
+//!
+//! and thats it, no loops or conditionals
+
+use std::collections::HashMap;
+use std::env;
+#[cfg(feature = "std-fs")]
+use std::fs;
+#[cfg(feature = "std-fs")]
+use std::io;
+#[cfg(feature = "std-fs")]
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+
+#[derive(Error, Debug)]
+pub enum TemplateError {
+    #[error("line {line}: {{{{ is never closed")]
+    Unclosed { line: usize },
+    #[error("line {line}: {name} is not set")]
+    UnknownVariable { line: usize, name: String },
+    #[error("line {line}: {name} does not resolve")]
+    UnknownHost { line: usize, name: String },
+    #[error("line {line}: dont know what to do with {expr:?}")]
+    BadExpression { line: usize, expr: String },
+    #[error("rendered template does not parse: {0}")]
+    Parse(#[from] ParserError),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HostsTemplate {
+    source: String,
+    vars: HashMap<String, String>,
+    hosts: Option<HostsFile>,
+}
+
+impl HostsTemplate {
+    pub fn new<S: Into<String>>(source: S) -> Self {
+        HostsTemplate {
+            source: source.into(),
+            ..Default::default()
+        }
+    }
+
+    #[cfg(feature = "std-fs")]
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(Self::new(fs::read_to_string(path)?))
+    }
+
+    /// set a variable, these win over the environment
+    pub fn var<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Self {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// where `ip_of` looks names up
+    pub fn resolve_from(mut self, hosts: HostsFile) -> Self {
+        self.hosts = Some(hosts);
+        self
+    }
+
+    /// the template with every placeholder filled in
+    pub fn render(&self) -> Result<String, TemplateError> {
+        let mut out = String::with_capacity(self.source.len());
+        let mut rest = self.source.as_str();
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let line = self.line_at(rest, start);
+            let after = &rest[start + 2..];
+            let end = after.find("}}").ok_or(TemplateError::Unclosed { line })?;
+            out.push_str(&self.eval(after[..end].trim(), line)?);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    /// render and parse the result in one go
+    pub fn render_hosts(&self) -> Result<HostsFile, TemplateError> {
+        Ok(self.render()?.parse()?)
+    }
+
+    /// which line of the source `rest[at]` is on
+    fn line_at(&self, rest: &str, at: usize) -> usize {
+        let consumed = self.source.len() - rest.len() + at;
+        self.source[..consumed].matches('\n').count() + 1
+    }
+
+    fn eval(&self, expr: &str, line: usize) -> Result<String, TemplateError> {
+        if let Some(arg) = call(expr, "env") {
+            return env::var(arg).map_err(|_| TemplateError::UnknownVariable {
+                line,
+                name: arg.to_string(),
+            });
+        }
+        if let Some(name) = call(expr, "ip_of") {
+            return self
+                .hosts
+                .as_ref()
+                .and_then(|hosts| hosts.lookup(name))
+                .map(|addr| addr.to_string())
+                .ok_or_else(|| TemplateError::UnknownHost {
+                    line,
+                    name: name.to_string(),
+                });
+        }
+        let is_ident = !expr.is_empty()
+            && expr
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-');
+        if !is_ident {
+            return Err(TemplateError::BadExpression {
+                line,
+                expr: expr.to_string(),
+            });
+        }
+        match self.vars.get(expr) {
+            Some(value) => Ok(value.clone()),
+            None => env::var(expr).map_err(|_| TemplateError::UnknownVariable {
+                line,
+                name: expr.to_string(),
+            }),
+        }
+    }
+}
+
+/// the quoted argument out of `func("arg")`
+fn call<'a>(expr: &'a str, func: &str) -> Option<&'a str> {
+    let args = expr.strip_prefix(func)?.trim_start().strip_prefix('(')?;
+    let arg = args.strip_suffix(')')?.trim();
+    arg.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_placeholders() {
+        let source = "# {{ env_name }} hosts\n\
+            {{ip_of(\"db\")}}\tdb.{{ env_name }}.internal\n\
+            {{ web_ip }} web # from {{ env(\"HOSTS_DIGGER_TEMPLATE_TEST\") }}\n";
+        std::env::set_var("HOSTS_DIGGER_TEMPLATE_TEST", "ci");
+        let hosts: HostsFile = "10.0.0.5 db\n".parse().unwrap();
+        let template = HostsTemplate::new(source)
+            .var("env_name", "staging")
+            .var("web_ip", "10.0.0.8")
+            .resolve_from(hosts);
+
+        assert_eq!(
+            template.render().unwrap(),
+            "# staging hosts\n10.0.0.5\tdb.staging.internal\n10.0.0.8 web # from ci\n"
+        );
+        let rendered = template.render_hosts().unwrap();
+        assert_eq!(
+            rendered.lookup("db.staging.internal"),
+            Some("10.0.0.5".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn render_errors() {
+        let err = |source: &str| HostsTemplate::new(source).render().unwrap_err().to_string();
+        assert_eq!(
+            err("a\n{{ nope_not_set_anywhere }}"),
+            "line 2: nope_not_set_anywhere is not set"
+        );
+        assert_eq!(err("{{ ip_of(\"db\") }}"), "line 1: db does not resolve");
+        assert_eq!(err("\n\n{{ web"), "line 3: {{ is never closed");
+        assert_eq!(
+            err("{{ 1 + 1 }}"),
+            "line 1: dont know what to do with \"1 + 1\""
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! random but valid records for the round trip tests
+//!
+//! this is a stand in and not `Arbitrary` or proptest, neither crate
+//! can be pulled into this build yet. once they can, `Arbitrary` for
+//! `Record` goes behind a feature and these tests move over to proptest
+//! so failures get shrunk
+
+use std::net::IpAddr;
+
+use crate::record::Record;
+
+/// xorshift, plenty random for generating test records
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    pub(crate) fn pick(&mut self, chars: &[char]) -> char {
+        chars[self.below(chars.len())]
+    }
+}
+
+fn arbitrary_name(rng: &mut Rng) -> String {
+    const EDGE: &[char] = &['a', 'z', 'q', '0', '9', '_', 'é', 'ß'];
+    const MIDDLE: &[char] = &['a', 'k', '7', '-', '_', 'é'];
+    let labels: Vec<String> = (0..1 + rng.below(3))
+        .map(|_| {
+            let mut label: String = rng.pick(EDGE).to_string();
+            let middle = rng.below(6);
+            for _ in 0..middle {
+                label.push(rng.pick(MIDDLE));
+            }
+            if middle > 0 {
+                label.push(rng.pick(EDGE));
+            }
+            label
+        })
+        .collect();
+    labels.join(".")
+}
+
+pub(crate) fn arbitrary_record(rng: &mut Rng) -> Record {
+    let names = (0..1 + rng.below(4)).map(|_| arbitrary_name(rng)).collect();
+    let mut record = match rng.below(3) {
+        0 => Record::new(IpAddr::from((rng.next() as u32).to_be_bytes()), names),
+        1 => Record::new(IpAddr::from((rng.next() as u128).to_be_bytes()), names),
+        _ => {
+            let addr = IpAddr::from((0xfe80_u128 << 112 | rng.next() as u128).to_be_bytes());
+            Record::scoped(addr, format!("eth{}", rng.below(4)), names)
+        }
+    }
+    .unwrap();
+    if rng.below(3) == 0 {
+        let comment = ["owned by ops", "see #42", "a # b", "ünïcode"][rng.below(4)];
+        record.set_comment(Some(comment.into())).unwrap();
+    }
+    record
+}
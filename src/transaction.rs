@@ -0,0 +1,126 @@
+//! all or nothing edits. changes are made to a copy of the file and only
+//! land, on disk and in the HostsFile, once `commit` has written them out
+
+use std::fs;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+
+use crate::hosts_file::HostsFile;
+use crate::writer::{backup_path, write_atomic, WriteOptions};
+
+/// a set of staged edits to a HostsFile, see `HostsFile::transaction`
+///
+/// edit it like the HostsFile it derefs to. dropping it without calling
+/// `commit` throws every staged change away
+#[derive(Debug)]
+pub struct Transaction<'a> {
+    hosts: &'a mut HostsFile,
+    staged: HostsFile,
+    path: PathBuf,
+}
+
+impl HostsFile {
+    /// start staging edits that get written to `path` on commit
+    pub fn transaction<P: AsRef<Path>>(&mut self, path: P) -> Transaction<'_> {
+        Transaction {
+            staged: self.clone(),
+            hosts: self,
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl Transaction<'_> {
+    /// write the staged file out and apply it to the HostsFile
+    ///
+    /// whatever was at the path first gets copied to its backup. if the
+    /// write fails the backup is put back and the HostsFile is left as
+    /// it was before the transaction
+    pub fn commit(self) -> io::Result<()> {
+        let backup = backup_path(&self.path);
+        let backed_up = match fs::copy(&self.path, &backup) {
+            Ok(_) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+
+        if let Err(e) = self.staged.write_to(&self.path) {
+            if backed_up {
+                let previous = fs::read(&backup)?;
+                write_atomic(&self.path, &previous, &WriteOptions::default())?;
+            }
+            return Err(e);
+        }
+        *self.hosts = self.staged;
+        Ok(())
+    }
+
+    /// throw the staged changes away, same as dropping the transaction
+    pub fn rollback(self) {}
+}
+
+impl Deref for Transaction<'_> {
+    type Target = HostsFile;
+
+    fn deref(&self) -> &HostsFile {
+        &self.staged
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut HostsFile {
+        &mut self.staged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::ValidationPolicy;
+    use crate::record::Record;
+
+    #[test]
+    fn commit_and_discard() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-tx-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+        let mut hosts = HostsFile::open(&path).unwrap();
+
+        {
+            let mut tx = hosts.transaction(&path);
+            tx.set("db", "10.0.0.5".parse().unwrap()).unwrap();
+            assert!(tx.lookup("db").is_some());
+        }
+        assert_eq!(hosts.lookup("db"), None);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "127.0.0.1 localhost\n");
+
+        let mut tx = hosts.transaction(&path);
+        tx.set("db", "10.0.0.5".parse().unwrap()).unwrap();
+        tx.set("cache", "10.0.0.7".parse().unwrap()).unwrap();
+        tx.commit().unwrap();
+        assert_eq!(hosts.lookup("cache"), Some("10.0.0.7".parse().unwrap()));
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "127.0.0.1 localhost\n10.0.0.5\tdb\n10.0.0.7\tcache\n"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "127.0.0.1 localhost\n"
+        );
+
+        // a commit that cant be written leaves everything where it was
+        hosts.set_policy(Some(
+            ValidationPolicy::new().allow_network("10.0.0.0/8".parse().unwrap()),
+        ));
+        let before = fs::read_to_string(&path).unwrap();
+        let mut tx = hosts.transaction(&path);
+        tx.add_record(Record::new("8.8.8.8".parse().unwrap(), vec!["dns".into()]).unwrap());
+        assert!(tx.commit().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), before);
+        assert_eq!(hosts.lookup("dns"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! asking the system resolver what it actually returns for the names in
+//! a hosts file, for CI checks that a dev machine is set up right
+
+use std::fmt;
+use std::net::{IpAddr, ToSocketAddrs};
+
+use crate::record::Record;
+
+/// what the system resolver said about a name
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Resolution {
+    /// the hosts file address was one of the answers
+    Matches,
+    /// the name resolves, just not to the hosts file address
+    Different(Vec<IpAddr>),
+    /// the name doesnt resolve at all
+    NotFound,
+}
+
+/// one name off of a record and how it resolved
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NameCheck {
+    pub name: String,
+    /// the address the record has for it
+    pub expected: IpAddr,
+    pub resolution: Resolution,
+}
+
+impl NameCheck {
+    pub fn is_ok(&self) -> bool {
+        self.resolution == Resolution::Matches
+    }
+}
+
+impl fmt::Display for NameCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.resolution {
+            Resolution::Matches => write!(f, "{} resolves to {}", self.name, self.expected),
+            Resolution::Different(addrs) => {
+                let addrs: Vec<String> = addrs.iter().map(IpAddr::to_string).collect();
+                write!(
+                    f,
+                    "{} should be {} but resolves to {}",
+                    self.name,
+                    self.expected,
+                    addrs.join(", ")
+                )
+            }
+            Resolution::NotFound => write!(f, "{} does not resolve", self.name),
+        }
+    }
+}
+
+/// look every name on `record` up with the system resolver, this blocks
+/// on whatever the resolver does, dns included
+pub fn resolves(record: &Record) -> Vec<NameCheck> {
+    let expected = record.addr();
+    record
+        .names()
+        .iter()
+        .map(|name| NameCheck {
+            name: name.clone(),
+            expected,
+            resolution: resolve(name, expected),
+        })
+        .collect()
+}
+
+/// `resolves` for every record, in order
+pub fn resolves_all<'a, I>(records: I) -> Vec<NameCheck>
+where
+    I: IntoIterator<Item = &'a Record>,
+{
+    records.into_iter().flat_map(resolves).collect()
+}
+
+fn resolve(name: &str, expected: IpAddr) -> Resolution {
+    let Ok(addrs) = (name, 0).to_socket_addrs() else {
+        return Resolution::NotFound;
+    };
+    let mut found: Vec<IpAddr> = Vec::new();
+    for addr in addrs {
+        if !found.contains(&addr.ip()) {
+            found.push(addr.ip());
+        }
+    }
+    if found.contains(&expected) {
+        Resolution::Matches
+    } else if found.is_empty() {
+        Resolution::NotFound
+    } else {
+        Resolution::Different(found)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_against_the_system() {
+        // every machine we run tests on has localhost in its hosts file
+        let record: Record = "127.0.0.1 localhost hosts-digger-nope.invalid"
+            .parse()
+            .unwrap();
+        let checks = resolves(&record);
+        assert!(checks[0].is_ok(), "{}", checks[0]);
+        assert_eq!(checks[1].resolution, Resolution::NotFound);
+        assert_eq!(
+            checks[1].to_string(),
+            "hosts-digger-nope.invalid does not resolve"
+        );
+
+        let wrong: Record = "10.9.9.9 localhost".parse().unwrap();
+        let checks = resolves_all([&wrong]);
+        assert!(matches!(&checks[0].resolution, Resolution::Different(addrs) if !addrs.is_empty()));
+        assert!(checks[0]
+            .to_string()
+            .starts_with("localhost should be 10.9.9.9 but resolves to "));
+    }
+}
@@ -0,0 +1,514 @@
+//! keep an eye on a hosts file and hand back a fresh parse whenever it
+//! changes, including editors that save by renaming a temp file over the
+//! original
+//!
+//! on linux the watcher sleeps on inotify until something happens to the
+//! file. there is no kqueue or windows backend yet, those platforms and
+//! a linux without inotify to spare fall back to checking the file
+//! metadata on an interval
+//!
+//! `poll_records` and `record_events` go one further and say which names
+//! changed, for caches that only want to drop what is stale
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::diff::{diff, Change, Mapping};
+use crate::hosts_file::HostsFile;
+use crate::parser::ParserError;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug)]
+pub enum WatchEvent {
+    /// the file changed, here is what it looks like now
+    Changed(Box<HostsFile>),
+    /// the file went away. if it comes back you get a `Changed`
+    Removed,
+}
+
+/// one name that resolves differently than it did, worked out with the
+/// same diff as `HostsDiff` so moving lines or editing comments doesnt
+/// show up
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum RecordEvent {
+    Added(Mapping),
+    Removed(Mapping),
+    Modified(Change),
+}
+
+impl RecordEvent {
+    /// the name that changed
+    pub fn name(&self) -> &str {
+        match self {
+            RecordEvent::Added(m) | RecordEvent::Removed(m) => &m.name,
+            RecordEvent::Modified(c) => &c.name,
+        }
+    }
+}
+
+/// enough of the metadata to notice a write or a rename over the file
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path) -> io::Result<Option<Stamp>> {
+        match fs::metadata(path) {
+            Ok(meta) => Ok(Some(Stamp {
+                modified: meta.modified().ok(),
+                len: meta.len(),
+                #[cfg(unix)]
+                ino: std::os::unix::fs::MetadataExt::ino(&meta),
+            })),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// watches a hosts file for changes. use `poll` to check once or iterate
+/// it to block until the next change
+#[derive(Debug)]
+pub struct HostsWatcher {
+    path: PathBuf,
+    interval: Duration,
+    stamp: Option<Stamp>,
+    current: Option<HostsFile>,
+    /// none where there is nothing better than sleeping
+    notifier: Option<sys::Notifier>,
+}
+
+/// how many times `poll` reads the file again when it changed while it
+/// was being read, before leaving it for the next poll
+const MAX_REREADS: usize = 5;
+
+impl HostsWatcher {
+    /// start watching `path`, whatever is there right now counts as the
+    /// starting point so only later edits show up as events
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, ParserError> {
+        let path = path.as_ref().to_path_buf();
+        let stamp = Stamp::of(&path)?;
+        let current = match stamp {
+            Some(_) => Some(HostsFile::open(&path)?),
+            None => None,
+        };
+        Ok(HostsWatcher {
+            notifier: sys::Notifier::new(&path).ok(),
+            path,
+            interval: DEFAULT_INTERVAL,
+            stamp,
+            current,
+        })
+    }
+
+    /// watch this machine's hosts file
+    pub fn system() -> Result<Self, ParserError> {
+        Self::new(HostsFile::system_path())
+    }
+
+    /// how long to sleep between checks when iterating without inotify, a
+    /// second by default
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// the last version of the file we saw, `None` if it doesnt exist
+    pub fn current(&self) -> Option<&HostsFile> {
+        self.current.as_ref()
+    }
+
+    /// check the file once without blocking. touching the file without
+    /// changing what it says doesnt count as a change
+    ///
+    /// a file that changes again while it is being read, because whatever
+    /// writes it does so in place and isnt done yet, is read again
+    pub fn poll(&mut self) -> Option<Result<WatchEvent, ParserError>> {
+        for _ in 0..MAX_REREADS {
+            let stamp = match Stamp::of(&self.path) {
+                Ok(stamp) => stamp,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if stamp == self.stamp {
+                return None;
+            }
+            if stamp.is_none() {
+                self.stamp = stamp;
+                return self.current.take().map(|_| Ok(WatchEvent::Removed));
+            }
+            let hosts = HostsFile::open(&self.path);
+            match Stamp::of(&self.path) {
+                Ok(after) if after == stamp => {}
+                // half written, or gone again
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e.into())),
+            }
+            self.stamp = stamp;
+            let hosts = match hosts {
+                Ok(hosts) => hosts,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.current.as_ref() == Some(&hosts) {
+                return None;
+            }
+            self.current = Some(hosts.clone());
+            return Some(Ok(WatchEvent::Changed(Box::new(hosts))));
+        }
+        None
+    }
+
+    /// block until something might have happened to the file
+    fn wait(&mut self) {
+        if let Some(notifier) = &mut self.notifier {
+            match notifier.wait() {
+                Ok(()) => return,
+                // the directory went away or the queue broke, sleep from
+                // here on
+                Err(_) => self.notifier = None,
+            }
+        }
+        thread::sleep(self.interval);
+    }
+
+    /// `poll`, but with what changed name by name. a change that doesnt
+    /// touch any name, like an edited comment, gives back `None`. the file
+    /// going away removes every name in it
+    pub fn poll_records(&mut self) -> Option<Result<Vec<RecordEvent>, ParserError>> {
+        let empty = HostsFile::default();
+        let before = self.current.clone().unwrap_or_default();
+        let d = match self.poll()? {
+            Ok(WatchEvent::Changed(after)) => diff(&before, &after),
+            Ok(WatchEvent::Removed) => diff(&before, &empty),
+            Err(e) => return Some(Err(e)),
+        };
+        let events = d
+            .added
+            .into_iter()
+            .map(RecordEvent::Added)
+            .chain(d.removed.into_iter().map(RecordEvent::Removed))
+            .chain(d.changed.into_iter().map(RecordEvent::Modified))
+            .collect::<Vec<_>>();
+        (!events.is_empty()).then_some(Ok(events))
+    }
+
+    /// block on the file and hand back record events one at a time
+    pub fn record_events(self) -> RecordEvents {
+        RecordEvents {
+            watcher: self,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+/// iterator from `HostsWatcher::record_events`, never returns `None`
+#[derive(Debug)]
+pub struct RecordEvents {
+    watcher: HostsWatcher,
+    pending: VecDeque<RecordEvent>,
+}
+
+impl Iterator for RecordEvents {
+    type Item = Result<RecordEvent, ParserError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            match self.watcher.poll_records() {
+                Some(Ok(events)) => self.pending.extend(events),
+                Some(Err(e)) => return Some(Err(e)),
+                None => self.watcher.wait(),
+            }
+        }
+    }
+}
+
+impl Iterator for HostsWatcher {
+    type Item = Result<WatchEvent, ParserError>;
+
+    /// blocks until the file changes, never returns `None`
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.poll() {
+                return Some(event);
+            }
+            self.wait();
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::ffi::{c_char, c_int, CString, OsString};
+    use std::fs::{self, File};
+    use std::io::{self, Read};
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::FromRawFd;
+    use std::path::Path;
+
+    // the same on every linux arch we build for
+    const IN_CLOEXEC: c_int = 0o2000000;
+    const IN_MODIFY: u32 = 0x2;
+    const IN_CLOSE_WRITE: u32 = 0x8;
+    const IN_MOVED_FROM: u32 = 0x40;
+    const IN_MOVED_TO: u32 = 0x80;
+    const IN_CREATE: u32 = 0x100;
+    const IN_DELETE: u32 = 0x200;
+    const IN_DELETE_SELF: u32 = 0x400;
+    const IN_MOVE_SELF: u32 = 0x800;
+    const IN_Q_OVERFLOW: u32 = 0x4000;
+    const IN_IGNORED: u32 = 0x8000;
+
+    const MASK: u32 = IN_MODIFY
+        | IN_CLOSE_WRITE
+        | IN_MOVED_FROM
+        | IN_MOVED_TO
+        | IN_CREATE
+        | IN_DELETE
+        | IN_DELETE_SELF
+        | IN_MOVE_SELF;
+
+    /// wd, mask, cookie and name length ahead of the name
+    const EVENT_HEADER: usize = 16;
+
+    extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+    }
+
+    /// an inotify watch on the directories the file is named in, the one
+    /// in the path and the real one when that is a symlink. directories
+    /// and not the file so a rename over it still gets seen
+    #[derive(Debug)]
+    pub(super) struct Notifier {
+        inotify: File,
+        /// watch descriptor and the file name it is about
+        watches: Vec<(c_int, OsString)>,
+    }
+
+    impl Notifier {
+        pub(super) fn new(path: &Path) -> io::Result<Notifier> {
+            // SAFETY: plain syscall, the fd is owned by the File right after
+            let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: a fresh fd nothing else has
+            let inotify = unsafe { File::from_raw_fd(fd) };
+            let mut notifier = Notifier {
+                inotify,
+                watches: Vec::new(),
+            };
+            notifier.watch(path)?;
+            if let Ok(real) = fs::canonicalize(path) {
+                if real != path {
+                    notifier.watch(&real)?;
+                }
+            }
+            Ok(notifier)
+        }
+
+        fn watch(&mut self, path: &Path) -> io::Result<()> {
+            let name = path
+                .file_name()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            let dir = CString::new(dir.as_os_str().as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+            // SAFETY: the fd is ours and dir is nul terminated
+            let wd = unsafe { inotify_add_watch(self.fd(), dir.as_ptr(), MASK) };
+            if wd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            self.watches.push((wd, name.to_os_string()));
+            Ok(())
+        }
+
+        fn fd(&self) -> c_int {
+            std::os::unix::io::AsRawFd::as_raw_fd(&self.inotify)
+        }
+
+        /// block until there is an event about the file. an error means the
+        /// watch is no good anymore
+        pub(super) fn wait(&mut self) -> io::Result<()> {
+            let mut buf = [0u8; 4096];
+            loop {
+                let len = match self.inotify.read(&mut buf) {
+                    Ok(len) => len,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                };
+                let mut ours = false;
+                let mut i = 0;
+                while i + EVENT_HEADER <= len {
+                    let field = |at: usize| {
+                        u32::from_ne_bytes(buf[i + at..i + at + 4].try_into().expect("4 bytes"))
+                    };
+                    let (wd, mask, name_len) = (field(0) as c_int, field(4), field(12) as usize);
+                    let name = &buf[i + EVENT_HEADER..(i + EVENT_HEADER + name_len).min(len)];
+                    // the name is padded out with nuls
+                    let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                    i += EVENT_HEADER + name_len;
+
+                    if mask & IN_Q_OVERFLOW != 0 {
+                        // lost track, anything could have happened
+                        ours = true;
+                        continue;
+                    }
+                    if mask & (IN_IGNORED | IN_DELETE_SELF | IN_MOVE_SELF) != 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            "the watched directory went away",
+                        ));
+                    }
+                    ours |= self
+                        .watches
+                        .iter()
+                        .any(|(w, n)| *w == wd && n.as_bytes() == name);
+                }
+                if ours {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// nothing to wait on, the watcher sleeps for its interval instead
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod sys {
+    use std::io;
+    use std::path::Path;
+
+    #[derive(Debug)]
+    pub(super) enum Notifier {}
+
+    impl Notifier {
+        pub(super) fn new(_: &Path) -> io::Result<Notifier> {
+            Err(io::ErrorKind::Unsupported.into())
+        }
+
+        pub(super) fn wait(&mut self) -> io::Result<()> {
+            match *self {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_up_edits_and_removal() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let mut watcher = HostsWatcher::new(&path)
+            .unwrap()
+            .interval(Duration::from_millis(10));
+        #[cfg(target_os = "linux")]
+        assert!(watcher.notifier.is_some());
+        assert!(watcher.poll().is_none());
+
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.5 db\n").unwrap();
+        match watcher.next() {
+            Some(Ok(WatchEvent::Changed(hosts))) => {
+                assert_eq!(hosts.lookup("db"), Some("10.0.0.5".parse().unwrap()))
+            }
+            other => panic!("expected a change, got {other:?}"),
+        }
+        assert!(watcher.poll().is_none());
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(watcher.poll(), Some(Ok(WatchEvent::Removed))));
+        assert!(watcher.current().is_none());
+
+        fs::write(&path, "::1 localhost\n").unwrap();
+        assert!(matches!(watcher.poll(), Some(Ok(WatchEvent::Changed(_)))));
+
+        // a rename over the file while the watcher is already waiting
+        let writer = {
+            let dir = dir.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                fs::write(dir.join("hosts.tmp"), "10.0.0.9 queue\n").unwrap();
+                fs::rename(dir.join("hosts.tmp"), dir.join("hosts")).unwrap();
+            })
+        };
+        match watcher.next() {
+            Some(Ok(WatchEvent::Changed(hosts))) => assert!(hosts.contains_name("queue")),
+            other => panic!("expected a change, got {other:?}"),
+        }
+        writer.join().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn record_deltas() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-deltas-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.5 db\n10.0.0.6 cache\n").unwrap();
+        let mut watcher = HostsWatcher::new(&path).unwrap();
+
+        // a comment and some spacing dont change any name
+        fs::write(
+            &path,
+            "# hi\n127.0.0.1  localhost\n10.0.0.5 db\n10.0.0.6 cache\n",
+        )
+        .unwrap();
+        assert!(watcher.poll_records().is_none());
+        assert!(watcher.current().is_some());
+
+        fs::write(&path, "127.0.0.1 localhost\n10.0.0.7 db\n10.0.0.8 queue\n").unwrap();
+        let events = watcher.poll_records().unwrap().unwrap();
+        let ip = |s: &str| s.parse().unwrap();
+        assert_eq!(
+            events,
+            [
+                RecordEvent::Added(Mapping {
+                    name: "queue".into(),
+                    addr: ip("10.0.0.8")
+                }),
+                RecordEvent::Removed(Mapping {
+                    name: "cache".into(),
+                    addr: ip("10.0.0.6")
+                }),
+                RecordEvent::Modified(Change {
+                    name: "db".into(),
+                    old: ip("10.0.0.5"),
+                    new: ip("10.0.0.7")
+                }),
+            ]
+        );
+
+        fs::remove_file(&path).unwrap();
+        let mut events = watcher.interval(Duration::from_millis(10)).record_events();
+        let names = (0..3)
+            .map(|_| events.next().unwrap().unwrap())
+            .inspect(|e| assert!(matches!(e, RecordEvent::Removed(_))))
+            .map(|e| e.name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["localhost", "db", "queue"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
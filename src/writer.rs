@@ -0,0 +1,411 @@
+use crate::record::Record;
+use std::fmt;
+#[cfg(feature = "std-fs")]
+use std::fs::{self, File, OpenOptions};
+#[cfg(feature = "std-fs")]
+use std::io::{self, Write};
+#[cfg(feature = "std-fs")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std-fs")]
+use std::process::{Command, Stdio};
+#[cfg(feature = "std-fs")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std-fs")]
+use std::thread;
+
+/// width of a tab stop, what every terminal and editor defaults to
+const TAB_WIDTH: usize = 8;
+
+/// how the address column gets separated from the names when writing
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Alignment {
+    /// a single tab after the address, like most distros ship
+    Tab,
+    /// enough tabs to line every name up on the same tab stop
+    #[default]
+    Tabs,
+    /// pad the address column out to this many characters with spaces,
+    /// there is always at least one space between the columns
+    Spaces(usize),
+}
+
+/// write `records` out in hosts syntax, one record per line
+pub(crate) fn write_records<W: fmt::Write>(
+    out: &mut W,
+    records: &[Record],
+    align: Alignment,
+) -> fmt::Result {
+    let widest = widest_addr(records.iter());
+    for record in records {
+        out.write_str(&record_line(record, align, widest))?;
+        out.write_char('\n')?;
+    }
+    Ok(())
+}
+
+/// the longest address field out of `records`
+pub(crate) fn widest_addr<'a>(records: impl Iterator<Item = &'a Record>) -> usize {
+    records.map(|r| r.addr_field().len()).max().unwrap_or(0)
+}
+
+/// a single record as a line of text without the newline, `widest` is
+/// how long the longest address it needs to line up with is
+pub(crate) fn record_line(record: &Record, align: Alignment, widest: usize) -> String {
+    let addr = record.addr_field();
+    let mut out = addr.clone();
+    match align {
+        Alignment::Tab => out.push('\t'),
+        Alignment::Tabs => {
+            // the first tab stop past the widest address
+            let names_col = (widest / TAB_WIDTH + 1) * TAB_WIDTH;
+            let tabs = names_col
+                .saturating_sub(addr.len())
+                .div_ceil(TAB_WIDTH)
+                .max(1);
+            out.extend(std::iter::repeat_n('\t', tabs));
+        }
+        Alignment::Spaces(width) => {
+            let pad = width.saturating_sub(addr.len()).max(1);
+            out.extend(std::iter::repeat_n(' ', pad));
+        }
+    }
+    out.push_str(&record.names_field());
+    if let Some(comment) = record.comment() {
+        out.push_str(" # ");
+        out.push_str(comment);
+    }
+    out
+}
+
+#[cfg(feature = "std-fs")]
+/// knobs for how a hosts file lands on disk
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteOptions {
+    /// copy whatever is at the path to `<path>.bak` before replacing it
+    pub backup: bool,
+    /// what to do when we arent allowed to write the file ourselves
+    pub escalation: Escalation,
+}
+
+#[cfg(feature = "std-fs")]
+impl WriteOptions {
+    pub fn escalate(mut self, escalation: Escalation) -> Self {
+        self.escalation = escalation;
+        self
+    }
+}
+
+#[cfg(feature = "std-fs")]
+/// how to get the file written when the write fails with
+/// `PermissionDenied`, /etc/hosts usually needs root
+///
+/// the escalated write pipes the contents through `tee`, which isnt atomic
+/// like the normal path is
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Escalation {
+    /// give up with a `PermissionDenied` that says what to do about it
+    #[default]
+    None,
+    /// `sudo tee`, which may prompt for a password on the terminal
+    Sudo,
+    /// `pkexec tee`, which pops up a polkit prompt on desktops
+    PolicyKit,
+}
+
+#[cfg(feature = "std-fs")]
+impl Escalation {
+    /// the command that writes its stdin to `path` with more privileges
+    fn command(self, path: &Path) -> Option<Command> {
+        let program = match self {
+            Escalation::None => return None,
+            Escalation::Sudo => "sudo",
+            Escalation::PolicyKit => "pkexec",
+        };
+        let mut cmd = Command::new(program);
+        cmd.arg("tee").arg(path);
+        Some(cmd)
+    }
+}
+
+#[cfg(feature = "std-fs")]
+/// where the backup for `path` goes
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+#[cfg(feature = "std-fs")]
+/// replace the file at `path` with `contents` without ever leaving a half
+/// written file behind
+///
+/// the new contents go to a temp file next to the original, get synced to
+/// disk, and are then renamed over it. a crash at any point leaves either
+/// the old file or the new one, never a mix of the two
+///
+/// without permission to do that the write goes through
+/// `options.escalation` instead
+pub(crate) fn write_atomic(path: &Path, contents: &[u8], options: &WriteOptions) -> io::Result<()> {
+    match replace_file(path, contents, options) {
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => match options.escalation {
+            Escalation::None => Err(permission_denied(path)),
+            escalation => write_escalated(escalation, path, contents, options.backup),
+        },
+        result => result,
+    }
+}
+
+#[cfg(feature = "std-fs")]
+fn permission_denied(path: &Path) -> io::Error {
+    #[cfg(windows)]
+    let fix = "run it from an Administrator prompt";
+    #[cfg(not(windows))]
+    let fix = "run it as root or write with Escalation::Sudo or Escalation::PolicyKit";
+    io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        format!("not allowed to write {}, {fix}", path.display()),
+    )
+}
+
+#[cfg(feature = "std-fs")]
+fn write_escalated(
+    escalation: Escalation,
+    path: &Path,
+    contents: &[u8],
+    backup: bool,
+) -> io::Result<()> {
+    let write = |target: &Path, bytes: &[u8]| match escalation.command(target) {
+        Some(mut cmd) => run_with_stdin(&mut cmd, bytes),
+        None => Err(permission_denied(path)),
+    };
+    if backup && path.exists() {
+        write(&backup_path(path), &fs::read(path)?)?;
+    }
+    write(path, contents)
+}
+
+#[cfg(feature = "std-fs")]
+fn run_with_stdin(cmd: &mut Command, stdin: &[u8]) -> io::Result<()> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let pipe = child.stdin.take();
+    // fed from another thread so a child that fills up stderr before it
+    // reads everything cant wedge us, and it always gets waited on
+    let (written, output) = thread::scope(|s| {
+        let writer = s.spawn(move || match pipe {
+            Some(mut pipe) => pipe.write_all(stdin),
+            None => Ok(()),
+        });
+        let output = child.wait_with_output();
+        let written = writer
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("writing to the child panicked")));
+        (written, output)
+    });
+    let output = output?;
+    if output.status.success() {
+        return written;
+    }
+    let mut msg = format!("{:?} failed with {}", cmd.get_program(), output.status);
+    if let Err(e) = written {
+        msg.push_str(&format!(", writing to it failed with {e}"));
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        msg.push_str(": ");
+        msg.push_str(stderr.trim());
+    }
+    Err(io::Error::new(io::ErrorKind::PermissionDenied, msg))
+}
+
+#[cfg(feature = "std-fs")]
+fn replace_file(path: &Path, contents: &[u8], options: &WriteOptions) -> io::Result<()> {
+    // write through symlinks instead of replacing them
+    let path = match fs::canonicalize(path) {
+        Ok(path) => path,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => path.to_path_buf(),
+        Err(e) => return Err(e),
+    };
+    let existing = match fs::metadata(&path) {
+        Ok(meta) => Some(meta),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    if options.backup && existing.is_some() {
+        fs::copy(&path, backup_path(&path))?;
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // the count keeps two threads writing the same file off each others tmp
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = dir.join(tmp_name);
+
+    let result = (|| {
+        let mut file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+        file.write_all(contents)?;
+        if let Some(meta) = &existing {
+            // keep the 0644 or whatever the original had
+            file.set_permissions(meta.permissions())?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, &path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+        return result;
+    }
+
+    // make the rename itself durable
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{arbitrary_record, Rng};
+
+    fn records() -> Vec<Record> {
+        vec![
+            Record::new("127.0.0.1".parse().unwrap(), vec!["localhost".into()]).unwrap(),
+            Record::new("::1".parse().unwrap(), vec!["ip6-localhost".into()]).unwrap(),
+            Record::new("192.168.10.42".parse().unwrap(), vec!["core".into()]).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..200 {
+            let records: Vec<Record> = (0..rng.below(8))
+                .map(|_| arbitrary_record(&mut rng))
+                .collect();
+            for align in [Alignment::Tab, Alignment::Tabs, Alignment::Spaces(20)] {
+                let mut out = String::new();
+                write_records(&mut out, &records, align).unwrap();
+                let parsed: Vec<Record> = crate::parse_str(&out).unwrap().records().to_vec();
+                #[cfg(not(feature = "idna"))]
+                assert_eq!(parsed, records, "{out}");
+                // punycode goes out, so only the folded names match
+                #[cfg(feature = "idna")]
+                assert_eq!(parsed.len(), records.len(), "{out}");
+            }
+        }
+    }
+
+    #[test]
+    fn tabs_align_names() {
+        let mut out = String::new();
+        write_records(&mut out, &records(), Alignment::Tabs).unwrap();
+        assert_eq!(
+            out,
+            "127.0.0.1\tlocalhost\n::1\t\tip6-localhost\n192.168.10.42\tcore\n"
+        );
+    }
+
+    #[test]
+    fn comments_written_back() {
+        let mut records = records();
+        records.truncate(1);
+        records[0].set_comment(Some("loopback".into())).unwrap();
+        let mut out = String::new();
+        write_records(&mut out, &records, Alignment::Tab).unwrap();
+        assert_eq!(out, "127.0.0.1\tlocalhost # loopback\n");
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn atomic_write_with_backup() {
+        let dir = std::env::temp_dir().join(format!("hosts-digger-atomic-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hosts");
+        fs::write(&path, "127.0.0.1 localhost\n").unwrap();
+
+        let options = WriteOptions {
+            backup: true,
+            ..Default::default()
+        };
+        write_atomic(&path, b"10.0.0.5 db\n", &options).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "10.0.0.5 db\n");
+        assert_eq!(
+            fs::read_to_string(backup_path(&path)).unwrap(),
+            "127.0.0.1 localhost\n"
+        );
+        // nothing but the file and its backup left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn spaces_pad_column() {
+        let mut out = String::new();
+        write_records(&mut out, &records()[..2], Alignment::Spaces(12)).unwrap();
+        assert_eq!(out, "127.0.0.1   localhost\n::1         ip6-localhost\n");
+    }
+
+    #[cfg(feature = "std-fs")]
+    #[test]
+    fn escalation_commands() {
+        let path = Path::new("/etc/hosts");
+        let args = |e: Escalation| {
+            e.command(path).map(|cmd| {
+                let mut args = vec![cmd.get_program().to_string_lossy().into_owned()];
+                args.extend(cmd.get_args().map(|a| a.to_string_lossy().into_owned()));
+                args
+            })
+        };
+        assert_eq!(args(Escalation::None), None);
+        assert_eq!(
+            args(Escalation::Sudo).unwrap(),
+            ["sudo", "tee", "/etc/hosts"]
+        );
+        assert_eq!(
+            args(Escalation::PolicyKit).unwrap(),
+            ["pkexec", "tee", "/etc/hosts"]
+        );
+        assert_eq!(
+            WriteOptions::default()
+                .escalate(Escalation::Sudo)
+                .escalation,
+            Escalation::Sudo
+        );
+
+        let err = permission_denied(path);
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(err
+            .to_string()
+            .starts_with("not allowed to write /etc/hosts, "));
+    }
+
+    #[cfg(all(feature = "std-fs", unix))]
+    #[test]
+    fn failed_escalation_waits_and_keeps_stderr() {
+        // exits without reading, so the write fails partway through too
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo not today >&2; exit 3"]);
+        let err = run_with_stdin(&mut cmd, &vec![b'x'; 1 << 20]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        let msg = err.to_string();
+        assert!(msg.contains("exit status: 3"), "{msg}");
+        assert!(msg.ends_with(": not today"), "{msg}");
+
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "cat >/dev/null"]);
+        run_with_stdin(&mut cmd, b"127.0.0.1 localhost\n").unwrap();
+    }
+}